@@ -0,0 +1,105 @@
+//! `knot adr`: architecture decision records, kept as numbered notes in one folder (`adr.dir`,
+//! `ADRs` by default) with a `status:` in their frontmatter.
+//!
+//! New records come from `.knot/templates/adr.md` when the vault has one, else the built-in
+//! Nygard-style template below; `{{number}}` and `{{title}}` are filled in along with the usual
+//! snippet variables. Superseding links the two records both ways with `[[wikilinks]]`.
+
+use anyhow::{bail, Context, Result};
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{frontmatter, links, numbering, snippets};
+
+pub const TEMPLATE: &str = ".knot/templates/adr.md";
+
+const BUILTIN: &str = "---\nstatus: proposed\ndate: {{date}}\n---\n# {{number}}. {{title}}\n\n## Context\n\n## Decision\n\n## Consequences\n";
+
+pub struct Record {
+    pub path: PathBuf,
+    pub number: u64,
+    pub title: String,
+    pub status: String,
+}
+
+/// Writes the next numbered record titled `title` into `dir` and returns its path.
+pub fn create(root: &Path, dir: &Path, title: &str, digits: usize) -> Result<PathBuf> {
+    if title.trim().is_empty() || title.contains(['/', '\\']) { bail!("invalid ADR title \"{}\"", title); }
+    fs::create_dir_all(dir)?;
+    let stem = numbering::stem(dir, title.trim(), digits);
+    let number = numbering::number(&stem).unwrap_or_default();
+    let template = fs::read_to_string(root.join(TEMPLATE)).unwrap_or_else(|_| BUILTIN.to_string());
+    let body = template.replace("{{number}}", &number.to_string()).replace("{{title}}", title.trim());
+    let path = dir.join(format!("{}.md", stem));
+    if path.exists() { bail!("{} already exists", path.display()); }
+    fs::write(&path, snippets::render(&body))?;
+    Ok(path)
+}
+
+/// Every numbered note in `dir`, lowest number first.
+pub fn list(dir: &Path) -> Vec<Record> {
+    let mut records: Vec<Record> = fs::read_dir(dir).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "md"))
+        .filter_map(|path| {
+            let number = numbering::number(&links::link_name(&path))?;
+            let text = fs::read_to_string(&path).ok()?;
+            let (yaml, body) = frontmatter::split(&text);
+            let status = yaml.and_then(|y| frontmatter::value(y, "status")).unwrap_or_else(|| "-".into());
+            let title = body.lines().find_map(|l| l.strip_prefix("# "))
+                .map(|h| heading_title(h).to_string())
+                .unwrap_or_else(|| links::link_name(&path));
+            Some(Record { path, number, title, status })
+        })
+        .collect();
+    records.sort_by_key(|r| r.number);
+    records
+}
+
+/// Marks record `old` superseded by `new` (each given by number or file name), `new` accepted,
+/// and links them. Returns the two paths.
+pub fn supersede(dir: &Path, old: &str, new: &str) -> Result<(PathBuf, PathBuf)> {
+    let records = list(dir);
+    let find = |query: &str| {
+        let query = query.trim().trim_end_matches(".md");
+        records.iter().find(|r| query.parse::<u64>().ok() == Some(r.number) || links::link_name(&r.path) == query)
+            .map(|r| r.path.clone())
+            .with_context(|| format!("no ADR \"{}\" in {}", query, dir.display()))
+    };
+    let (old, new) = (find(old)?, find(new)?);
+    if old == new { bail!("an ADR can't supersede itself"); }
+    let (old_name, new_name) = (links::link_name(&old), links::link_name(&new));
+    update(&old, &[("status", "superseded".into()), ("superseded_by", new_name.clone())], &format!("Superseded by [[{}]]", new_name))?;
+    update(&new, &[("status", "accepted".into()), ("supersedes", old_name.clone())], &format!("Supersedes [[{}]]", old_name))?;
+    Ok((old, new))
+}
+
+/// Sets frontmatter `values` on the note at `path` and adds `line` under its title (or at the top).
+fn update(path: &Path, values: &[(&str, String)], line: &str) -> Result<()> {
+    let text = fs::read_to_string(path)?;
+    let (yaml, body) = frontmatter::split(&text);
+    let yaml = values.iter().fold(yaml.unwrap_or("").to_string(), |y, (k, v)| frontmatter::set_value(&y, k, v));
+    let body = if body.contains(line) {
+        body.to_string()
+    } else {
+        let mut offset = 0;
+        let title_end = body.split_inclusive('\n').find_map(|l| {
+            offset += l.len();
+            l.starts_with("# ").then_some(offset)
+        });
+        match title_end {
+            Some(end) => format!("{}\n\n{}\n{}", body[..end].trim_end_matches('\n'), line, &body[end..]),
+            None => format!("{}\n\n{}", line, body),
+        }
+    };
+    fs::write(path, frontmatter::join(Some(&yaml), &body))?;
+    Ok(())
+}
+
+/// `Use Postgres` from an `# 7. Use Postgres` heading.
+fn heading_title(heading: &str) -> &str {
+    let digits = heading.chars().take_while(char::is_ascii_digit).count();
+    match heading[digits..].strip_prefix(['.', ':']) {
+        Some(rest) if digits > 0 => rest.trim(),
+        _ => heading.trim(),
+    }
+}
@@ -0,0 +1,86 @@
+//! Adding files to a note's attachment folder, and finding attachments no note links to any more,
+//! so deleted notes don't leave their images behind.
+
+use anyhow::{Context, Result};
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+use crate::{html, links, trash::TRASH_DIR, vault::relative};
+
+pub struct Orphan {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Files under any attachment folder that none of `notes`, given with their text, reference,
+/// sorted by path.
+///
+/// A markdown link or an HTML `src`/`href` counts when it resolves to the file; a wikilink/embed
+/// counts when it names the file by vault-relative path or, like Obsidian's shortest-path links,
+/// by bare file name.
+pub fn orphans(root: &Path, attachment_dirs: &[String], notes: &[(PathBuf, String)]) -> Vec<Orphan> {
+    let mut paths = HashSet::new();
+    let mut names = HashSet::new();
+    for (note, text) in notes {
+        let note_dir = note.parent().unwrap_or(root).strip_prefix(root).unwrap_or(Path::new("")).to_path_buf();
+        for dest in links::md_destinations(text).into_iter().chain(html::local_references(text)) {
+            let resolved = match dest.strip_prefix('/') { Some(abs) => PathBuf::from(abs), None => links::normalize(&note_dir.join(&dest)) };
+            paths.insert(resolved.to_string_lossy().replace('\\', "/"));
+        }
+        for target in links::wiki_targets(text) {
+            if target.contains('/') { paths.insert(target.trim_start_matches('/').to_string()); } else { names.insert(target); }
+        }
+    }
+
+    let in_attachments = |rel: &str| rel.split('/').rev().skip(1).any(|c| attachment_dirs.iter().any(|d| d.trim_matches('/') == c));
+    let mut out: Vec<Orphan> = WalkDir::new(root).min_depth(1).into_iter()
+        .filter_entry(|e| { let name = e.file_name().to_string_lossy(); name != ".git" && name != TRASH_DIR })
+        .flatten()
+        .filter(|e| e.file_type().is_file() && !e.file_name().to_string_lossy().starts_with('.'))
+        .filter(|e| {
+            let rel = relative(root, e.path());
+            in_attachments(&rel) && !paths.contains(&rel) && !names.contains(e.file_name().to_string_lossy().as_ref())
+        })
+        .map(|e| Orphan { size: e.metadata().map(|m| m.len()).unwrap_or(0), path: e.into_path() })
+        .collect();
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    out
+}
+
+/// The file a paste refers to, when the pasted text is a single path: what terminals type when
+/// a file is dropped on them, quoted, backslash-escaped or as a `file://` URL.
+pub fn pasted_file(text: &str) -> Option<PathBuf> {
+    let text = text.trim();
+    if text.is_empty() || text.contains('\n') { return None; }
+    let unquoted = ['\'', '"'].iter().find_map(|q| text.strip_prefix(*q)?.strip_suffix(*q)).unwrap_or(text);
+    let path = match unquoted.strip_prefix("file://") {
+        Some(url) => url.replace("%20", " "),
+        None => unquoted.replace("\\ ", " "),
+    };
+    let path = PathBuf::from(path);
+    (path.is_absolute() && path.is_file()).then_some(path)
+}
+
+/// Copies `src` into the `dir_name` attachment folder beside `note`, renaming it if the name is
+/// taken, and returns the link to it relative to the note.
+pub fn attach(note: &Path, src: &Path, dir_name: &str) -> Result<String> {
+    let folder = note.parent().context("note has no folder")?.join(dir_name);
+    fs::create_dir_all(&folder)?;
+    let name = src.file_name().context("pasted path has no file name")?;
+    let mut file = folder.join(name);
+    for n in 2.. {
+        if !file.exists() { break; }
+        let ext = src.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        file = folder.join(format!("{} {}{}", links::link_name(src), n, ext));
+    }
+    fs::copy(src, &file)?;
+    Ok(format!("{}/{}", dir_name, file.file_name().unwrap_or_default().to_string_lossy()).replace(' ', "%20"))
+}
+
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 { size /= 1024.0; unit += 1; }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
@@ -0,0 +1,187 @@
+//! Audio attachments (voice memos and the like): finding the ones a note links to, reading their
+//! duration, and playing them with `audio.player`.
+//!
+//! Durations come from container headers (MP3 with or without a Xing/Info frame, M4A/MP4, WAV,
+//! FLAC, Ogg Vorbis/Opus) without decoding any audio; other formats show without one.
+
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use walkdir::WalkDir;
+
+use crate::{editor, links};
+
+pub const DEFAULT_PLAYER: &str = "mpv --no-video";
+
+const EXTENSIONS: &[&str] = &["mp3", "m4a", "aac", "mp4", "wav", "flac", "ogg", "oga", "opus", "webm", "amr", "3gp"];
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Existing audio files `note` links to or embeds, in the order they appear. Markdown links
+/// resolve against the note's folder; bare `[[memo.m4a]]` names are looked up across the vault.
+pub fn attachments(root: &Path, note: &Path) -> Vec<PathBuf> {
+    let text = fs::read_to_string(note).unwrap_or_default();
+    let rel = note.strip_prefix(root).unwrap_or(note);
+    let mut found: Vec<PathBuf> = Vec::new();
+    for dest in links::md_destinations(&text) {
+        let Some(path) = links::resolve(&links::Target::Path(dest), rel, &[]).map(|p| root.join(p)) else { continue };
+        if is_audio(&path) && path.is_file() && !found.contains(&path) { found.push(path); }
+    }
+    for name in links::wiki_targets(&text).into_iter().filter(|t| is_audio(Path::new(t))) {
+        let path = if name.contains('/') {
+            Some(root.join(name.trim_start_matches('/'))).filter(|p| p.is_file())
+        } else {
+            WalkDir::new(root).into_iter()
+                .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+                .flatten()
+                .find(|e| e.file_type().is_file() && e.file_name().to_string_lossy() == name.as_str())
+                .map(|e| e.into_path())
+        };
+        if let Some(path) = path.filter(|p| !found.contains(p)) { found.push(path); }
+    }
+    found
+}
+
+/// Starts `player` (an `editor`-style command line; the file is appended unless it has `{file}`)
+/// on `path` in the background, with its output discarded so it can't draw over the TUI.
+pub fn play(player: &str, path: &Path) -> Result<()> {
+    let mut cmd = editor::command(player, path, 1);
+    cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+        .spawn().with_context(|| format!("couldn't start `{}`", player))?;
+    Ok(())
+}
+
+/// `1:05` or `1:02:05`.
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 { format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60) } else { format!("{}:{:02}", secs / 60, secs % 60) }
+}
+
+/// Playing time read from the file's headers.
+pub fn duration(path: &Path) -> Option<Duration> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut head = Vec::new();
+    (&mut file).take(64 * 1024).read_to_end(&mut head).ok()?;
+    let seconds = match head.get(..4)? {
+        b"RIFF" if head.get(8..12) == Some(&b"WAVE"[..]) => wav(&head),
+        b"fLaC" => flac(&head),
+        b"OggS" => ogg(&mut file, &head, len),
+        _ if head.get(4..8) == Some(&b"ftyp"[..]) => mp4(&mut file, len),
+        _ => mp3(&head, len),
+    }?;
+    (seconds.is_finite() && seconds > 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+fn be(bytes: &[u8]) -> u64 { bytes.iter().fold(0, |n, &b| n << 8 | b as u64) }
+fn le(bytes: &[u8]) -> u64 { bytes.iter().rev().fold(0, |n, &b| n << 8 | b as u64) }
+
+/// Chunks after the `WAVE` tag: the `fmt ` chunk's byte rate and the `data` chunk's size.
+fn wav(head: &[u8]) -> Option<f64> {
+    let (mut i, mut byte_rate) = (12, None);
+    while let Some(id) = head.get(i..i + 4) {
+        let size = le(head.get(i + 4..i + 8)?);
+        match id {
+            b"fmt " => byte_rate = Some(le(head.get(i + 16..i + 20)?)),
+            b"data" => return Some(size as f64 / byte_rate.filter(|&r| r > 0)? as f64),
+            _ => {}
+        }
+        i += 8 + size as usize + (size % 2) as usize;
+    }
+    None
+}
+
+/// The STREAMINFO block, always first: 20 bits of sample rate and 36 of total samples.
+fn flac(head: &[u8]) -> Option<f64> {
+    let info = head.get(8..8 + 34)?;
+    let rate = be(&info[10..13]) >> 4;
+    let samples = be(&info[13..18]) & 0xF_FFFF_FFFF;
+    (rate > 0).then(|| samples as f64 / rate as f64)
+}
+
+/// The last page's granule position over the sample rate from the first page's codec header.
+fn ogg(file: &mut File, head: &[u8], len: u64) -> Option<f64> {
+    let (rate, pre_skip) = if let Some(i) = find(head, b"OpusHead") {
+        (48_000, le(head.get(i + 10..i + 12)?))
+    } else {
+        let i = find(head, b"\x01vorbis")?;
+        (le(head.get(i + 12..i + 16)?), 0)
+    };
+    let start = len.saturating_sub(64 * 1024);
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let page = tail.windows(4).rposition(|w| w == b"OggS")?;
+    let granule = le(tail.get(page + 6..page + 14)?);
+    (rate > 0).then(|| granule.saturating_sub(pre_skip) as f64 / rate as f64)
+}
+
+/// `moov/mvhd`: duration in units of its timescale. The `moov` box may sit at the end of the
+/// file, so boxes are walked by seeking rather than read whole.
+fn mp4(file: &mut File, len: u64) -> Option<f64> {
+    let (mut pos, mut end) = (0, len);
+    loop {
+        if pos + 8 > end { return None; }
+        file.seek(SeekFrom::Start(pos)).ok()?;
+        let mut header = [0; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let (mut size, kind) = (be(&header[..4]), [header[4], header[5], header[6], header[7]]);
+        let mut body = pos + 8;
+        if size == 1 {
+            file.read_exact(&mut header[8..16]).ok()?;
+            size = be(&header[8..16]);
+            body += 8;
+        } else if size == 0 {
+            size = end - pos;
+        }
+        match &kind {
+            b"moov" => { end = pos + size; pos = body; }
+            b"mvhd" => {
+                let mut mvhd = [0; 32];
+                file.read_exact(&mut mvhd).ok()?;
+                let (scale, duration) = if mvhd[0] == 1 { (be(&mvhd[20..24]), be(&mvhd[24..32])) } else { (be(&mvhd[12..16]), be(&mvhd[16..20])) };
+                return (scale > 0).then(|| duration as f64 / scale as f64);
+            }
+            _ if size < 8 => return None,
+            _ => pos += size,
+        }
+    }
+}
+
+/// MPEG audio layer III: the frame count from a Xing/Info header when there is one, otherwise
+/// the size over the first frame's bitrate (exact for constant-bitrate files).
+fn mp3(head: &[u8], len: u64) -> Option<f64> {
+    let mut start = 0;
+    if head.starts_with(b"ID3") {
+        // Syncsafe size: 7 bits per byte.
+        start = 10 + head.get(6..10)?.iter().fold(0usize, |n, &b| n << 7 | (b & 0x7F) as usize);
+    }
+    let frame = start + head.get(start..)?.windows(2).position(|w| w[0] == 0xFF && w[1] & 0xE6 == 0xE2)?;
+    let h = head.get(frame..frame + 4)?;
+    let mpeg1 = h[1] & 0x18 == 0x18;
+    let version_rates: [u64; 3] = match h[1] & 0x18 { 0x18 => [44_100, 48_000, 32_000], 0x10 => [22_050, 24_000, 16_000], _ => [11_025, 12_000, 8_000] };
+    let rate = *version_rates.get((h[2] >> 2 & 3) as usize)?;
+    const V1: [u64; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+    const V2: [u64; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    let kbps = *(if mpeg1 { &V1 } else { &V2 }).get((h[2] >> 4) as usize)?;
+    let samples_per_frame = if mpeg1 { 1152 } else { 576 };
+    let mono = h[3] >> 6 == 3;
+    let side_info = match (mpeg1, mono) { (true, false) => 32, (true, true) | (false, false) => 17, (false, true) => 9 };
+    let xing = frame + 4 + side_info;
+    if matches!(head.get(xing..xing + 4), Some(b"Xing" | b"Info")) && head.get(xing + 7)? & 1 == 1 {
+        let frames = be(head.get(xing + 8..xing + 12)?);
+        return Some((frames * samples_per_frame) as f64 / rate as f64);
+    }
+    (kbps > 0).then(|| (len - frame as u64) as f64 * 8.0 / (kbps * 1000) as f64)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
@@ -0,0 +1,221 @@
+//! Whole-vault backups: `knot backup` packs the vault, history and nested category repositories
+//! included, into one `knot-<vault>-<date>-<time>.tar.gz` in `backup.dir`, optionally sealed with
+//! age as `.tar.gz.age`, and rotates older ones out. `knot restore` unpacks such an archive into
+//! a fresh vault on another machine. Run `knot backup` from cron for a schedule; rotation keeps
+//! the newest archive of each recent day and week.
+//!
+//! ```toml
+//! [backup]
+//! dir = "/mnt/nas/knot"
+//! encrypt = true
+//! keep_daily = 7
+//! keep_weekly = 4
+//! ```
+//!
+//! Every archive carries a manifest listing each file with its size and git object hash;
+//! restoring checks the unpacked files against it before the vault is put in place.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Local, NaiveDateTime};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{crypt::{self, Crypt}, git};
+
+/// The manifest's name in the archive, next to the vault's own files.
+const MANIFEST: &str = "knot-backup.manifest";
+const STAMP: &str = "%Y%m%d-%H%M%S";
+
+pub struct Made {
+    pub path: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+    /// Older archives rotation deleted.
+    pub rotated: Vec<PathBuf>,
+}
+
+struct Entry {
+    hash: String,
+    size: u64,
+    rel: String,
+}
+
+/// Backs up the vault at `root` into `dir`, encrypted when `encrypt` is set: to `crypt`'s
+/// identity, or with a passphrase without one. Then rotates the vault's archives there.
+pub fn create(root: &Path, dir: &Path, encrypt: bool, crypt: Option<&Crypt>, keep_daily: usize, keep_weekly: usize) -> Result<Made> {
+    if !root.is_dir() { bail!("no vault at {}", root.display()); }
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let dir = dir.canonicalize()?;
+    let root = root.canonicalize()?;
+    // A backup folder inside the vault would be packed into every later backup.
+    let inside = dir.strip_prefix(&root).ok().map(|p| p.to_string_lossy().replace('\\', "/"));
+
+    let mut rels: Vec<String> = walkdir::WalkDir::new(&root).into_iter()
+        .filter_entry(|e| e.path() != dir)
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.path().strip_prefix(&root).ok().map(|p| p.to_string_lossy().replace('\\', "/")))
+        .collect();
+    rels.sort();
+    let entries = hash_files(&root, rels)?;
+
+    let scratch = std::env::temp_dir().join(format!("knot-backup-{}", std::process::id()));
+    crypt::create_private_dir(&scratch)?;
+    let result = (|| -> Result<Made> {
+        fs::write(scratch.join(MANIFEST), manifest(&root, &entries))?;
+        let name = format!("knot-{}-{}.tar.gz", vault_name(&root), Local::now().format(STAMP));
+        let tarball = scratch.join(&name);
+        let mut tar = Command::new("tar");
+        tar.arg("-czf").arg(&tarball);
+        if let Some(d) = &inside { tar.arg(format!("--exclude=./{}", d)); }
+        tar.arg("-C").arg(&root).arg(".").arg("-C").arg(&scratch).arg(MANIFEST);
+        run_tar(tar)?;
+
+        let path = if encrypt { dir.join(format!("{}.{}", name, crypt::EXTENSION)) } else { dir.join(&name) };
+        let part = path.with_extension("part");
+        if encrypt { crypt::encrypt_file(crypt, &tarball, &part)?; } else { fs::copy(&tarball, &part)?; }
+        fs::rename(&part, &path)?;
+        let bytes = fs::metadata(&path)?.len();
+        let rotated = rotate(&dir, &vault_name(&root), &path, keep_daily, keep_weekly)?;
+        Ok(Made { path, files: entries.len(), bytes, rotated })
+    })();
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+/// Unpacks the archive at `archive` into `into`, which must not have files yet, after checking
+/// every file against the manifest. Returns how many files it restored.
+pub fn restore(archive: &Path, into: &Path, crypt: Option<&Crypt>) -> Result<usize> {
+    if !archive.is_file() { bail!("no backup at {}", archive.display()); }
+    if fs::read_dir(into).is_ok_and(|mut e| e.next().is_some()) {
+        bail!("{} already has files; restore into a new folder with --into", into.display());
+    }
+    let name = into.file_name().context("restore needs a folder to restore into")?.to_string_lossy().to_string();
+    let parent = into.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+    // Unpacked beside the target, so the vault only appears once it's complete and checked.
+    let staging = parent.join(format!(".{}.restoring", name));
+    if staging.exists() { fs::remove_dir_all(&staging)?; }
+    crypt::create_private_dir(&staging)?;
+
+    let result = (|| -> Result<usize> {
+        let tarball = if crypt::is_encrypted(archive) {
+            let plain = staging.join("archive.tar.gz");
+            crypt::decrypt_file(crypt, archive, &plain)?;
+            plain
+        } else {
+            archive.to_path_buf()
+        };
+        let vault = staging.join("vault");
+        fs::create_dir(&vault)?;
+        let mut tar = Command::new("tar");
+        tar.arg("-xzf").arg(&tarball).arg("-C").arg(&vault);
+        run_tar(tar)?;
+
+        let text = fs::read_to_string(vault.join(MANIFEST)).context("the archive has no manifest; was it made by `knot backup`?")?;
+        let expected = parse_manifest(&text);
+        let listed = text.lines().find_map(|l| l.strip_prefix("# files ")).and_then(|n| n.trim().parse().ok());
+        if listed != Some(expected.len()) { bail!("the archive's manifest is incomplete; the archive is damaged"); }
+        let actual = hash_files(&vault, expected.iter().map(|e| e.rel.clone()).collect())?;
+        let bad: Vec<&str> = expected.iter().zip(&actual)
+            .filter(|(want, got)| want.hash != got.hash || want.size != got.size)
+            .map(|(want, _)| want.rel.as_str())
+            .collect();
+        if !bad.is_empty() {
+            let (n, verb) = if bad.len() == 1 { ("1 file".to_string(), "doesn't") } else { (format!("{} files", bad.len()), "don't") };
+            bail!("{} {} match the manifest, e.g. {}; the archive is damaged", n, verb, bad[0]);
+        }
+        fs::remove_file(vault.join(MANIFEST))?;
+        if into.exists() { fs::remove_dir(into)?; }
+        fs::rename(&vault, into)?;
+        Ok(expected.len())
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Size and git object hash of each of the vault-relative `rels`, in order.
+fn hash_files(root: &Path, rels: Vec<String>) -> Result<Vec<Entry>> {
+    let mut entries = Vec::with_capacity(rels.len());
+    for chunk in rels.chunks(200) {
+        let mut args = vec!["hash-object", "--no-filters", "--"];
+        args.extend(chunk.iter().map(String::as_str));
+        let out = git::run(root, &args).map_err(|e| anyhow::anyhow!("hashing files: {}", e))?;
+        for (rel, hash) in chunk.iter().zip(out.lines()) {
+            let size = fs::metadata(root.join(rel)).map(|m| m.len()).unwrap_or(0);
+            entries.push(Entry { hash: hash.to_string(), size, rel: rel.clone() });
+        }
+    }
+    if entries.len() != rels.len() { bail!("couldn't hash every file"); }
+    Ok(entries)
+}
+
+fn manifest(root: &Path, entries: &[Entry]) -> String {
+    let mut out = format!(
+        "# knot backup\n# created {}\n# vault {}\n# files {}\n",
+        Local::now().to_rfc3339(), root.display(), entries.len(),
+    );
+    for e in entries { out.push_str(&format!("{} {} {}\n", e.hash, e.size, e.rel)); }
+    out
+}
+
+fn parse_manifest(text: &str) -> Vec<Entry> {
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut parts = l.splitn(3, ' ');
+            let hash = parts.next()?.to_string();
+            let size = parts.next()?.parse().ok()?;
+            Some(Entry { hash, size, rel: parts.next()?.to_string() })
+        })
+        .collect()
+}
+
+fn run_tar(mut tar: Command) -> Result<()> {
+    let out = tar.output().map_err(|e| if e.kind() == std::io::ErrorKind::NotFound { anyhow::anyhow!("tar is not installed or not on PATH") } else { e.into() })?;
+    if !out.status.success() { bail!("tar failed: {}", String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(())
+}
+
+/// The vault's folder name as it goes in archive names, e.g. `knot_vault` for `~/.knot_vault`.
+fn vault_name(root: &Path) -> String {
+    let name = root.file_name().unwrap_or_default().to_string_lossy();
+    let name: String = name.trim_start_matches('.').chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if name.is_empty() { "vault".into() } else { name }
+}
+
+/// When the archive `file_name` of vault `vault` was made, or `None` for anything else.
+fn made_at(file_name: &str, vault: &str) -> Option<NaiveDateTime> {
+    let rest = file_name.strip_prefix(&format!("knot-{}-", vault))?;
+    let stamp = rest.strip_suffix(".tar.gz").or_else(|| rest.strip_suffix(".tar.gz.age"))?;
+    NaiveDateTime::parse_from_str(stamp, STAMP).ok()
+}
+
+/// Deletes the vault's archives in `dir` that are neither the newest of one of the last
+/// `keep_daily` days nor of one of the last `keep_weekly` weeks that have any. `latest`, just
+/// made, always stays; with both at zero nothing is rotated.
+fn rotate(dir: &Path, vault: &str, latest: &Path, keep_daily: usize, keep_weekly: usize) -> Result<Vec<PathBuf>> {
+    if keep_daily == 0 && keep_weekly == 0 { return Ok(Vec::new()); }
+    let mut archives: Vec<(NaiveDateTime, PathBuf)> = fs::read_dir(dir)?.flatten()
+        .filter_map(|e| Some((made_at(&e.file_name().to_string_lossy(), vault)?, e.path())))
+        .collect();
+    archives.sort_by(|a, b| b.cmp(a));
+    let (mut days, mut weeks) = (BTreeSet::new(), BTreeSet::new());
+    let mut rotated = Vec::new();
+    for (at, path) in archives {
+        let day = at.date();
+        let week = (at.iso_week().year(), at.iso_week().week());
+        let mut keep = path == latest;
+        if !days.contains(&day) && days.len() < keep_daily { days.insert(day); keep = true; }
+        if !weeks.contains(&week) && weeks.len() < keep_weekly { weeks.insert(week); keep = true; }
+        if !keep {
+            fs::remove_file(&path)?;
+            rotated.push(path);
+        }
+    }
+    Ok(rotated)
+}
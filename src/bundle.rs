@@ -0,0 +1,115 @@
+//! Portable single-file snapshot of knot's config directory (settings, themes, snippets and
+//! anything else kept next to `config.toml`) and the vault's templates, for
+//! `knot config export` / `knot config import`.
+//!
+//! The format is plain text so it diffs well when checked into a vault: each file starts with a
+//! `===== knot: <relative path> =====` marker line followed by its contents verbatim. Files from
+//! the vault's `.knot/templates` are marked `===== knot vault: <vault-relative path> =====`, and
+//! note templates `===== knot templates: <path in templates.dir> =====`, so they land in the
+//! importing vault's own `templates.dir`. A file that doesn't end in
+//! a newline gets one in the bundle, and ` (no newline at end)` in its marker so import drops it.
+
+use anyhow::{bail, Context, Result};
+use std::{fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+const MARKER_START: &str = "===== knot: ";
+const VAULT_MARKER_START: &str = "===== knot vault: ";
+const TEMPLATES_MARKER_START: &str = "===== knot templates: ";
+const MARKER_END: &str = " =====";
+const NO_NEWLINE: &str = " (no newline at end)";
+
+/// The vault folder, besides `templates.dir`, that travels with the config.
+const VAULT_TEMPLATES: &str = ".knot/templates";
+
+/// Where a bundled file's path is relative to.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Place { Config, Vault, Templates }
+
+/// One file of a bundle.
+pub struct File {
+    pub place: Place,
+    pub rel: String,
+    pub text: String,
+}
+
+/// Bundles every text file under `dir`, then under the vault's `.knot/templates` and its
+/// `templates_dir`, each sorted by path.
+pub fn export(dir: &Path, vault: &Path, templates_dir: &str) -> Result<String> {
+    let mut out = String::new();
+    bundle_files(&mut out, MARKER_START, dir, dir)?;
+    bundle_files(&mut out, VAULT_MARKER_START, vault, &vault.join(VAULT_TEMPLATES))?;
+    // Already bundled when it's kept inside `.knot/templates`.
+    if !Path::new(templates_dir).starts_with(VAULT_TEMPLATES) {
+        let templates = vault.join(templates_dir);
+        bundle_files(&mut out, TEMPLATES_MARKER_START, &templates, &templates)?;
+    }
+    Ok(out)
+}
+
+/// Appends the files under `from` to `out`, marked with their paths relative to `base`.
+fn bundle_files(out: &mut String, marker: &str, base: &Path, from: &Path) -> Result<()> {
+    let mut entries: Vec<PathBuf> = WalkDir::new(from).min_depth(1).into_iter().flatten()
+        .filter(|e| e.file_type().is_file()).map(|e| e.into_path()).collect();
+    entries.sort();
+    for path in entries {
+        let rel = path.strip_prefix(base)?.to_string_lossy().replace('\\', "/");
+        // Backups left behind by `import` are not part of the setup.
+        if rel.ends_with(".bak") { continue; }
+        let Ok(text) = fs::read_to_string(&path) else { eprintln!("skipping non-text file {}", rel); continue };
+        let newline = text.ends_with('\n');
+        out.push_str(&format!("{}{}{}{}\n", marker, rel, if newline { "" } else { NO_NEWLINE }, MARKER_END));
+        out.push_str(&text);
+        if !newline { out.push('\n'); }
+    }
+    Ok(())
+}
+
+/// Splits a bundle back into its files.
+pub fn parse(bundle: &str) -> Result<Vec<File>> {
+    let mut files: Vec<File> = Vec::new();
+    // Whether the file being read had no final newline of its own.
+    let mut trim = false;
+    for line in bundle.split_inclusive('\n') {
+        let header = line.trim_end();
+        let marker = [(MARKER_START, Place::Config), (VAULT_MARKER_START, Place::Vault), (TEMPLATES_MARKER_START, Place::Templates)].into_iter()
+            .find_map(|(start, place)| Some((place, header.strip_prefix(start)?.strip_suffix(MARKER_END)?)));
+        match (marker, files.last_mut()) {
+            (Some((place, rel)), last) => {
+                if trim { if let Some(file) = last { file.text.pop(); } }
+                let (rel, no_newline) = match rel.strip_suffix(NO_NEWLINE) { Some(rel) => (rel, true), None => (rel, false) };
+                if rel.split('/').any(|c| c == ".." || c.is_empty()) || rel.starts_with('/') { bail!("refusing unsafe path `{}` in bundle", rel); }
+                files.push(File { place, rel: rel.to_string(), text: String::new() });
+                trim = no_newline;
+            }
+            (None, Some(file)) => file.text.push_str(line),
+            (None, None) if line.trim().is_empty() => {}
+            (None, None) => bail!("not a knot config bundle"),
+        }
+    }
+    if trim { if let Some(file) = files.last_mut() { file.text.pop(); } }
+    Ok(files)
+}
+
+/// Writes the bundle's files into `dir`, and its templates into the vault at `vault` and its
+/// `templates_dir`. Files that already exist with different contents are kept as `<name>.bak`.
+/// Returns the paths written.
+pub fn import(dir: &Path, vault: &Path, templates_dir: &str, bundle: &str) -> Result<Vec<PathBuf>> {
+    let files = parse(bundle)?;
+    if let Some(file) = files.iter().find(|f| f.place == Place::Vault && !f.rel.starts_with(&format!("{}/", VAULT_TEMPLATES))) {
+        bail!("refusing `{}` in bundle: only {} is taken from the vault", file.rel, VAULT_TEMPLATES);
+    }
+    let mut written = Vec::new();
+    for File { place, rel, text } in files {
+        let dest = match place { Place::Config => dir.join(&rel), Place::Vault => vault.join(&rel), Place::Templates => vault.join(templates_dir).join(&rel) };
+        if let Some(parent) = dest.parent() { fs::create_dir_all(parent)?; }
+        match fs::read_to_string(&dest) {
+            Ok(old) if old == text => continue,
+            Ok(_) => { fs::rename(&dest, dest.with_file_name(format!("{}.bak", dest.file_name().unwrap_or_default().to_string_lossy())))?; }
+            Err(_) => {}
+        }
+        fs::write(&dest, text).with_context(|| format!("writing {}", dest.display()))?;
+        written.push(dest);
+    }
+    Ok(written)
+}
@@ -0,0 +1,222 @@
+//! Agenda from an iCalendar (`.ics`) source, for creating meeting notes from upcoming events.
+//!
+//! `calendar.source` is a local file or an `http(s)://`/`webcal://` URL serving one (CalDAV
+//! servers and hosted calendars publish these as "secret address" or export links), fetched with
+//! `curl`. Simple recurrences are expanded: `FREQ=DAILY/WEEKLY/MONTHLY/YEARLY` with `INTERVAL`,
+//! `COUNT`, `UNTIL`, weekly `BYDAY` and `EXDATE`. Times with a `TZID` are taken as local time.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use std::{fs, path::Path, process::{Command, Stdio}};
+
+use crate::{config::CalendarConfig, snippets};
+
+/// Template for meeting notes; `{{title}}`, `{{start}}`, `{{end}}`, `{{attendees}}`,
+/// `{{location}}` and `{{description}}` are filled from the event.
+pub const TEMPLATE: &str = ".knot/templates/meeting.md";
+
+const BUILTIN: &str = "---\nevent: \"{{title}}\"\nstart: {{start}}\nend: {{end}}\nattendees: [{{attendees}}]\n---\n# {{title}}\n\n**When:** {{start}}–{{end}}  \n**Where:** {{location}}  \n**Attendees:** {{attendees}}\n\n{{description}}\n\n## Agenda\n\n## Notes\n\n## Action items\n\n- [ ] \n";
+
+#[derive(Clone)]
+pub struct Event {
+    pub summary: String,
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+    pub all_day: bool,
+    pub location: String,
+    pub description: String,
+    pub attendees: Vec<String>,
+}
+
+impl Event {
+    /// `2024-09-03 14:00 Weekly sync`, as listed in the picker.
+    pub fn label(&self) -> String {
+        if self.all_day { format!("{} (all day) {}", self.start.format("%a %Y-%m-%d"), self.summary) } else { format!("{} {}", self.start.format("%a %Y-%m-%d %H:%M"), self.summary) }
+    }
+
+    /// File name for the event's meeting note.
+    pub fn note_name(&self) -> String {
+        let title: String = self.summary.chars().map(|c| if matches!(c, '/' | '\\' | ':') { '-' } else { c }).collect();
+        format!("{} {}.md", self.start.format("%Y-%m-%d"), title.trim())
+    }
+
+    /// The meeting note's contents, from the vault's template or the built-in one.
+    pub fn note(&self, root: &Path) -> String {
+        let template = fs::read_to_string(root.join(TEMPLATE)).unwrap_or_else(|_| BUILTIN.to_string());
+        let time = |t: &DateTime<Local>| if self.all_day { t.format("%Y-%m-%d").to_string() } else { t.format("%Y-%m-%d %H:%M").to_string() };
+        let end = self.end.map(|e| if e.date_naive() == self.start.date_naive() && !self.all_day { e.format("%H:%M").to_string() } else { time(&e) }).unwrap_or_default();
+        snippets::render(&template
+            .replace("{{title}}", &self.summary)
+            .replace("{{start}}", &time(&self.start))
+            .replace("{{end}}", &end)
+            .replace("{{attendees}}", &self.attendees.join(", "))
+            .replace("{{location}}", &self.location)
+            .replace("{{description}}", self.description.trim()))
+    }
+}
+
+/// Events starting between the beginning of today and `config.days_ahead` days from now, in order.
+pub fn upcoming(config: &CalendarConfig) -> Result<Vec<Event>> {
+    let Some(source) = &config.source else { bail!("no calendar.source configured") };
+    let text = if source.contains("://") { fetch(source)? } else {
+        fs::read_to_string(crate::config::expand_home(source)).with_context(|| format!("reading {}", source))?
+    };
+    let from = Local::now().date_naive().and_hms_opt(0, 0, 0).and_then(|d| Local.from_local_datetime(&d).earliest()).unwrap_or_else(Local::now);
+    let to = from + Duration::days(config.days_ahead as i64 + 1);
+    let mut events: Vec<Event> = parse(&text).into_iter().flat_map(|e| occurrences(e, from, to)).collect();
+    events.sort_by_key(|e| e.start);
+    Ok(events)
+}
+
+fn fetch(url: &str) -> Result<String> {
+    let url = url.replacen("webcal://", "https://", 1);
+    let out = Command::new("curl").args(["-fsSL", "--max-time", "15", "--", &url])
+        .stdin(Stdio::null()).output().context("curl is not installed")?;
+    if !out.status.success() { bail!("fetching the calendar failed: {}", String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// A `VEVENT` with its recurrence rule, before expansion.
+struct Raw {
+    event: Event,
+    rrule: Option<String>,
+    exdates: Vec<NaiveDateTime>,
+    /// Events without a readable `DTSTART` are dropped.
+    has_start: bool,
+}
+
+fn parse(text: &str) -> Vec<Raw> {
+    // Unfold: a line starting with a space or tab continues the previous one.
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(rest) if !lines.is_empty() => lines.last_mut().expect("checked").push_str(rest),
+            _ => lines.push(line.trim_end_matches('\r').to_string()),
+        }
+    }
+    let mut events = Vec::new();
+    let mut current: Option<Raw> = None;
+    for line in &lines {
+        if line == "BEGIN:VEVENT" {
+            current = Some(Raw {
+                event: Event { summary: String::new(), start: Local::now(), end: None, all_day: false, location: String::new(), description: String::new(), attendees: Vec::new() },
+                rrule: None,
+                exdates: Vec::new(),
+                has_start: false,
+            });
+            continue;
+        }
+        let Some(raw) = current.as_mut() else { continue };
+        if line == "END:VEVENT" {
+            let mut raw = current.take().expect("checked");
+            if raw.has_start {
+                if raw.event.summary.is_empty() { raw.event.summary = "(no title)".into(); }
+                events.push(raw);
+            }
+            continue;
+        }
+        let Some((head, value)) = line.split_once(':') else { continue };
+        let mut params = head.split(';');
+        let name = params.next().unwrap_or("").to_ascii_uppercase();
+        let params: Vec<&str> = params.collect();
+        let e = &mut raw.event;
+        match name.as_str() {
+            "SUMMARY" => e.summary = unescape(value),
+            "LOCATION" => e.location = unescape(value),
+            "DESCRIPTION" => e.description = unescape(value),
+            "DTSTART" => {
+                if let Some((t, all_day)) = parse_time(value) {
+                    e.start = t;
+                    e.all_day = all_day;
+                    raw.has_start = true;
+                }
+            }
+            "DTEND" => e.end = parse_time(value).map(|(t, _)| t),
+            "RRULE" => raw.rrule = Some(value.to_ascii_uppercase()),
+            "EXDATE" => raw.exdates.extend(value.split(',').filter_map(parse_time).map(|(t, _)| t.naive_local())),
+            "ATTENDEE" | "ORGANIZER" => {
+                let cn = params.iter().find_map(|p| p.strip_prefix("CN=")).map(|n| n.trim_matches('"').to_string());
+                let who = cn.unwrap_or_else(|| value.trim_start_matches("mailto:").trim_start_matches("MAILTO:").to_string());
+                if !who.is_empty() && !e.attendees.contains(&who) { e.attendees.push(who); }
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+/// `20240903T140000Z` (UTC), `20240903T140000` (local) or `20240903` (all day).
+fn parse_time(value: &str) -> Option<(DateTime<Local>, bool)> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let t = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&t).with_timezone(&Local), false));
+    }
+    if let Ok(t) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some((Local.from_local_datetime(&t).earliest()?, false));
+    }
+    let d = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some((Local.from_local_datetime(&d.and_hms_opt(0, 0, 0)?).earliest()?, true))
+}
+
+/// The occurrences of `raw` starting in `[from, to)`.
+fn occurrences(raw: Raw, from: DateTime<Local>, to: DateTime<Local>) -> Vec<Event> {
+    let Raw { event, rrule, exdates, .. } = raw;
+    let Some(rule) = rrule else {
+        return if event.start >= from && event.start < to { vec![event] } else { Vec::new() };
+    };
+    let part = |key: &str| rule.split(';').find_map(|p| p.strip_prefix(&format!("{}=", key)).map(str::to_string));
+    let interval = part("INTERVAL").and_then(|i| i.parse::<i64>().ok()).unwrap_or(1).max(1);
+    let count = part("COUNT").and_then(|c| c.parse::<usize>().ok());
+    let until = part("UNTIL").and_then(|u| parse_time(&u)).map(|(t, _)| t);
+    let by_day: Vec<Weekday> = part("BYDAY").map(|d| d.split(',').filter_map(|d| weekday(d.trim_start_matches(|c: char| c == '-' || c == '+' || c.is_ascii_digit()))).collect()).unwrap_or_default();
+    let freq = part("FREQ").unwrap_or_default();
+    let duration = event.end.map(|e| e - event.start);
+
+    let start = event.start.naive_local();
+    let mut found = Vec::new();
+    let mut emitted = 0;
+    // Walk period by period (day, week, month, year) from the first occurrence.
+    for period in 0..20_000 {
+        let candidates: Vec<NaiveDateTime> = match freq.as_str() {
+            "DAILY" => vec![start + Duration::days(period * interval)],
+            "WEEKLY" if !by_day.is_empty() => {
+                let week_start = start - Duration::days(start.weekday().num_days_from_monday() as i64) + Duration::weeks(period * interval);
+                let mut days: Vec<NaiveDateTime> = by_day.iter().map(|d| week_start + Duration::days(d.num_days_from_monday() as i64)).filter(|d| *d >= start).collect();
+                days.sort();
+                days
+            }
+            "WEEKLY" => vec![start + Duration::weeks(period * interval)],
+            "MONTHLY" => add_months(start, period * interval).into_iter().collect(),
+            "YEARLY" => add_months(start, period * interval * 12).into_iter().collect(),
+            _ => return if event.start >= from && event.start < to { vec![event] } else { Vec::new() },
+        };
+        for at in candidates {
+            let Some(at_local) = Local.from_local_datetime(&at).earliest() else { continue };
+            if until.is_some_and(|u| at_local > u) || count.is_some_and(|c| emitted >= c) || at_local >= to { return found; }
+            emitted += 1;
+            if at_local >= from && !exdates.contains(&at) {
+                found.push(Event { start: at_local, end: duration.map(|d| at_local + d), ..event.clone() });
+            }
+        }
+    }
+    found
+}
+
+fn add_months(t: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let total = t.year() as i64 * 12 + t.month0() as i64 + months;
+    let date = NaiveDate::from_ymd_opt((total / 12) as i32, (total % 12) as u32 + 1, t.day())?;
+    Some(date.and_time(t.time()))
+}
+
+fn weekday(code: &str) -> Option<Weekday> {
+    Some(match code {
+        "MO" => Weekday::Mon, "TU" => Weekday::Tue, "WE" => Weekday::Wed, "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri, "SA" => Weekday::Sat, "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("\\n", "\n").replace("\\N", "\n").replace("\\,", ",").replace("\\;", ";").replace("\\\\", "\\")
+}
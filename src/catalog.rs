@@ -0,0 +1,71 @@
+//! Generated `_index.md` notes: one per category, linking every note in it grouped by folder, so
+//! exports and other markdown tools get an entry point into each category.
+//!
+//! `knot index` writes them; after that the startup maintenance pass keeps the existing ones
+//! current. A file is only rewritten when its listing changed, so it never dirties the vault
+//! needlessly.
+
+use anyhow::Result;
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use crate::vault;
+
+pub const NAME: &str = "_index.md";
+
+const HEADER: &str = "<!-- Generated by `knot index` and kept up to date by knot; edits are overwritten. -->";
+
+/// The index note for `category`, listing the notes of `notes` under it.
+pub fn render(root: &Path, category: &str, notes: &[PathBuf]) -> String {
+    let dir = root.join(category);
+    // Notes directly in the category sort first under the empty folder name.
+    let mut folders: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for note in notes.iter().filter(|n| n.starts_with(&dir) && n.file_name().is_some_and(|f| f != NAME)) {
+        let rel = vault::relative(&dir, note);
+        let folder = rel.rsplit_once('/').map_or("", |(folder, _)| folder).to_string();
+        folders.entry(folder).or_default().push(rel);
+    }
+    let mut out = format!("{}\n# {}\n", HEADER, category);
+    if folders.is_empty() { out.push_str("\nNo notes yet.\n"); }
+    for (folder, mut rels) in folders {
+        rels.sort_by_key(|r| r.to_lowercase());
+        if !folder.is_empty() { out.push_str(&format!("\n## {}\n", folder)); }
+        out.push('\n');
+        for rel in rels {
+            let title = Path::new(&rel).file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            out.push_str(&format!("- [{}]({})\n", title, rel.replace(' ', "%20")));
+        }
+    }
+    out
+}
+
+/// Every category: the vault's visible top-level folders that hold notes. `notes` is sorted.
+pub fn categories(root: &Path, notes: &[PathBuf]) -> Vec<String> {
+    let mut cats: Vec<String> = notes.iter()
+        .filter_map(|n| vault::relative(root, n).split_once('/').map(|(cat, _)| cat.to_string()))
+        .filter(|cat| !cat.starts_with('.'))
+        .collect();
+    cats.dedup();
+    cats
+}
+
+/// Writes the index notes of `categories`, returning the ones that changed.
+pub fn write(root: &Path, categories: &[String], notes: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for category in categories {
+        let path = root.join(category).join(NAME);
+        let text = render(root, category, notes);
+        if fs::read_to_string(&path).is_ok_and(|old| old == text) { continue; }
+        fs::write(&path, text)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Regenerates the index notes that already exist, for the maintenance pass.
+pub fn refresh(root: &Path, notes: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let existing: Vec<String> = notes.iter()
+        .filter(|n| n.file_name().is_some_and(|f| f == NAME))
+        .filter_map(|n| vault::relative(root, n).strip_suffix(&format!("/{}", NAME)).filter(|c| !c.contains('/')).map(str::to_string))
+        .collect();
+    write(root, &existing, notes)
+}
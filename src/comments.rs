@@ -0,0 +1,77 @@
+//! Review comments on a note, kept beside it in a hidden sidecar (`.<note>.comments`) so the
+//! note's own text is never touched and the comments sync along with it. Open ones show in a
+//! margin next to the preview; `K` adds them and resolves them.
+//!
+//! ```text
+//! - [ ] 2026-10-15 14:30 · Dana
+//!   Is this still the plan after the offsite?
+//! - [x] 2026-10-14 09:12 · Sam
+//!   Typo in the second heading.
+//! ```
+
+use std::{fs, io, path::{Path, PathBuf}};
+
+#[derive(Debug, Clone)]
+pub struct Comment {
+    /// When it was written, as `YYYY-MM-DD HH:MM`.
+    pub at: String,
+    pub by: String,
+    pub text: String,
+    pub resolved: bool,
+}
+
+/// Where the comments on `note` are kept.
+pub fn sidecar(note: &Path) -> PathBuf {
+    let name = note.file_name().unwrap_or_default().to_string_lossy();
+    note.with_file_name(format!(".{}.comments", name))
+}
+
+/// The comments on `note`, oldest first.
+pub fn load(note: &Path) -> Vec<Comment> {
+    let text = fs::read_to_string(sidecar(note)).unwrap_or_default();
+    let mut comments: Vec<Comment> = Vec::new();
+    for line in text.lines() {
+        let head = line.strip_prefix("- [ ] ").map(|h| (h, false)).or_else(|| line.strip_prefix("- [x] ").map(|h| (h, true)));
+        if let Some((head, resolved)) = head {
+            let (at, by) = head.split_once(" · ").unwrap_or((head, ""));
+            comments.push(Comment { at: at.to_string(), by: by.to_string(), text: String::new(), resolved });
+        } else if let (Some(body), Some(last)) = (line.strip_prefix("  "), comments.last_mut()) {
+            if !last.text.is_empty() { last.text.push('\n'); }
+            last.text.push_str(body);
+        }
+    }
+    comments
+}
+
+fn save(note: &Path, comments: &[Comment]) -> io::Result<()> {
+    let mut out = String::new();
+    for c in comments {
+        out.push_str(&format!("- [{}] {} · {}\n", if c.resolved { 'x' } else { ' ' }, c.at, c.by));
+        for line in c.text.lines() { out.push_str(&format!("  {}\n", line)); }
+    }
+    fs::write(sidecar(note), out)
+}
+
+pub fn add(note: &Path, by: &str, text: &str) -> io::Result<()> {
+    let mut comments = load(note);
+    let at = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+    comments.push(Comment { at, by: by.to_string(), text: text.trim().to_string(), resolved: false });
+    save(note, &comments)
+}
+
+/// Resolves comment `idx`, or reopens it if it was resolved; returns whether it is resolved now.
+pub fn toggle(note: &Path, idx: usize) -> io::Result<bool> {
+    let mut comments = load(note);
+    let Some(comment) = comments.get_mut(idx) else { return Ok(false) };
+    comment.resolved = !comment.resolved;
+    let resolved = comment.resolved;
+    save(note, &comments)?;
+    Ok(resolved)
+}
+
+/// Moves the comments on `old` along with the note, renamed or moved to `new`.
+pub fn follow(old: &Path, new: &Path) -> io::Result<()> {
+    let from = sidecar(old);
+    if !from.exists() { return Ok(()); }
+    fs::rename(from, sidecar(new))
+}
@@ -0,0 +1,192 @@
+//! Shell completion. The scripts `knot completions <shell>` prints are thin: on every Tab they
+//! hand the words typed so far to the hidden `knot __complete`, which walks the clap definitions
+//! to find what's being completed. Subcommands, flags and their values come from clap; note,
+//! folder and category arguments are listed from the vault, so they're always current.
+
+use clap::{Arg, ArgAction, Command};
+use std::path::{Path, PathBuf};
+
+use crate::vault::{self, Ignore};
+
+/// Printed alone when the word is a path outside the vault, for the shell's own file completion.
+pub const FILES: &str = ":files";
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// The script that hooks `knot __complete` into `shell`.
+pub fn script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+    }
+}
+
+const BASH: &str = r#"# knot completion for bash: add `source <(knot completions bash)` to ~/.bashrc
+_knot() {
+    local IFS=$'\n' out
+    out=$(knot __complete -- "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null)
+    if [[ $out == ":files" ]]; then
+        compopt -o default
+        COMPREPLY=()
+        return
+    fi
+    COMPREPLY=($out)
+    # Folders are completed into, not past.
+    [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == */ ]] && compopt -o nospace
+}
+complete -F _knot knot
+"#;
+
+const ZSH: &str = r#"# knot completion for zsh: add `source <(knot completions zsh)` to ~/.zshrc, after compinit
+_knot() {
+    local -a items folders
+    items=("${(@f)$(knot __complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+    if [[ ${items[1]} == ":files" ]]; then
+        _files
+        return
+    fi
+    items=(${items:#})
+    folders=(${(M)items:#*/})
+    compadd -S '' -- $folders
+    compadd -- ${items:#*/}
+}
+compdef _knot knot
+"#;
+
+const FISH: &str = r#"# knot completion for fish: knot completions fish > ~/.config/fish/completions/knot.fish
+function __knot_complete
+    set -l words (commandline -opc)
+    set -e words[1]
+    set -l out (knot __complete -- $words (commandline -ct) 2>/dev/null)
+    if test "$out" = ":files"
+        __fish_complete_path (commandline -ct)
+    else
+        printf '%s\n' $out
+    end
+end
+complete -c knot -f -a '(__knot_complete)'
+"#;
+
+/// What the word being completed can be.
+pub enum Candidates {
+    Words(Vec<String>),
+    /// Vault-relative note paths.
+    Notes,
+    /// Vault-relative folders, with a trailing slash.
+    Folders,
+    /// Notes and folders, for commands that take either.
+    NotesAndFolders,
+    Categories,
+    /// Paths outside the vault, left to the shell.
+    Files,
+}
+
+/// The outcome of reading the command line: what to offer, for which typed prefix, and the
+/// `--vault` given on it, if any.
+pub struct Completion {
+    pub candidates: Candidates,
+    pub prefix: String,
+    pub vault: Option<PathBuf>,
+}
+
+/// Reads `words`, the arguments after `knot` with the one being completed last, against `cli`.
+pub fn complete(cli: &mut Command, words: &[String]) -> Completion {
+    cli.build();
+    let (prefix, done) = match words.split_last() {
+        Some((last, done)) => (last.clone(), done),
+        None => (String::new(), &[][..]),
+    };
+    let mut cmd: &Command = cli;
+    let mut positionals = 0;
+    let mut pending: Option<&Arg> = None;
+    let mut vault = None;
+    for word in done {
+        if let Some(arg) = pending.take() {
+            if arg.get_id() == "vault" { vault = Some(PathBuf::from(word)); }
+            continue;
+        }
+        if let Some(long) = word.strip_prefix("--").filter(|l| !l.is_empty()) {
+            let (name, value) = long.split_once('=').map_or((long, None), |(n, v)| (n, Some(v)));
+            let Some(arg) = cmd.get_arguments().find(|a| a.get_long() == Some(name)) else { continue };
+            match value {
+                Some(v) if arg.get_id() == "vault" => vault = Some(PathBuf::from(v)),
+                None if takes_value(arg) => pending = Some(arg),
+                _ => {}
+            }
+        } else if word.len() > 1 && word.starts_with('-') && word != "--" {
+            // In a cluster like `-xm`, only the last flag can take the next word as its value.
+            let short = word.chars().last();
+            let arg = cmd.get_arguments().find(|a| a.get_short() == short);
+            if arg.is_some_and(takes_value) && word.len() == 2 { pending = arg; }
+        } else if let Some(sub) = cmd.find_subcommand(word).filter(|_| positionals == 0) {
+            cmd = sub;
+        } else if word != "--" {
+            positionals += 1;
+        }
+    }
+    let candidates = if let Some(arg) = pending {
+        values(cmd, arg)
+    } else if prefix.starts_with('-') {
+        Candidates::Words(flags(cmd))
+    } else if cmd.has_subcommands() && positionals == 0 {
+        Candidates::Words(cmd.get_subcommands().filter(|s| !s.is_hide_set()).map(|s| s.get_name().to_string()).collect())
+    } else {
+        let args: Vec<&Arg> = cmd.get_positionals().collect();
+        let arg = args.get(positionals).or_else(|| args.last().filter(|a| matches!(a.get_action(), ArgAction::Append)));
+        arg.map_or(Candidates::Words(Vec::new()), |arg| values(cmd, arg))
+    };
+    Completion { candidates, prefix, vault }
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+/// Every flag `cmd` takes, its own and the global ones, long forms only.
+fn flags(cmd: &Command) -> Vec<String> {
+    cmd.get_arguments()
+        .filter(|a| !a.is_hide_set())
+        .filter_map(|a| a.get_long().map(|l| format!("--{}", l)))
+        .collect()
+}
+
+/// The values `arg` of `cmd` takes. Arguments are recognized by name, as the commands in
+/// `main.rs` use them.
+fn values(cmd: &Command, arg: &Arg) -> Candidates {
+    let choices = arg.get_possible_values();
+    if !choices.is_empty() {
+        return Candidates::Words(choices.iter().filter(|v| !v.is_hide_set()).map(|v| v.get_name().to_string()).collect());
+    }
+    match (cmd.get_name(), arg.get_id().as_str()) {
+        // A new note's name is a folder plus a title that doesn't exist yet.
+        ("new", "note") => Candidates::Folders,
+        ("capture", "name") | (_, "note") => Candidates::Notes,
+        (_, "paths") => Candidates::NotesAndFolders,
+        (_, "folder") => Candidates::Folders,
+        (_, "category" | "categories") => Candidates::Categories,
+        (_, "path" | "file" | "output" | "vault" | "archive" | "dir" | "into") => Candidates::Files,
+        _ => Candidates::Words(Vec::new()),
+    }
+}
+
+/// The vault's notes, folders or categories for `candidates`, vault-relative.
+pub fn vault_items(root: &Path, ignore: &Ignore, candidates: &Candidates) -> Vec<String> {
+    let notes = || vault::walk_notes(root, ignore).iter().map(|n| vault::relative(root, n)).collect::<Vec<_>>();
+    let folders = |top: bool| vault::walk_dirs(root, ignore).iter()
+        .filter(|d| !top || d.parent() == Some(root))
+        .map(|d| vault::relative(root, d))
+        .collect::<Vec<_>>();
+    match candidates {
+        Candidates::Notes => notes(),
+        Candidates::Folders => folders(false).into_iter().map(|d| format!("{}/", d)).collect(),
+        Candidates::NotesAndFolders => folders(false).into_iter().map(|d| format!("{}/", d)).chain(notes()).collect(),
+        Candidates::Categories => folders(true),
+        Candidates::Words(_) | Candidates::Files => Vec::new(),
+    }
+}
@@ -0,0 +1,508 @@
+//! User configuration loaded from `~/.config/knot/config.toml`.
+//!
+//! Only the subset of TOML knot actually needs is understood: `[section]` headers,
+//! `key = value` pairs with strings, integers, booleans and single-level arrays, and `#` comments.
+
+use anyhow::{bail, Context, Result};
+use ratatui::style::Color;
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, time::Duration};
+
+use crate::snippets::Snippets;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value { Str(String), Int(i64), Bool(bool), Array(Vec<Value>) }
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> { if let Value::Str(s) = self { Some(s) } else { None } }
+    pub fn as_int(&self) -> Option<i64> { if let Value::Int(i) = self { Some(*i) } else { None } }
+    pub fn as_bool(&self) -> Option<bool> { if let Value::Bool(b) = self { Some(*b) } else { None } }
+    /// String items of an array; non-string items are skipped.
+    pub fn as_str_list(&self) -> Option<Vec<String>> {
+        if let Value::Array(items) = self { Some(items.iter().filter_map(|v| v.as_str().map(String::from)).collect()) } else { None }
+    }
+}
+
+/// Flat view of a TOML document: every key is stored under its dotted path (`format.wrap_width`).
+#[derive(Debug, Default, Clone)]
+pub struct Table(pub BTreeMap<String, Value>);
+
+impl Table {
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        let mut section = String::new();
+        let mut lines = src.lines().enumerate();
+        while let Some((n, raw)) = lines.next() {
+            let line = strip_comment(raw).trim().to_string();
+            if line.is_empty() { continue; }
+            if line.starts_with('[') {
+                if !line.ends_with(']') { bail!("line {}: unterminated section header", n + 1); }
+                section = line[1..line.len() - 1].trim().replace('"', "");
+                continue;
+            }
+            let Some((key, rest)) = line.split_once('=') else { bail!("line {}: expected `key = value`", n + 1) };
+            let key = key.trim().trim_matches('"').trim_matches('\'');
+            let mut rest = rest.trim().to_string();
+            // Arrays and triple-quoted strings may span several lines.
+            while (rest.starts_with('[') && !balanced(&rest)) || (rest.starts_with("\"\"\"") && (rest.len() < 6 || !rest[3..].contains("\"\"\""))) {
+                let Some((_, next)) = lines.next() else { bail!("line {}: unterminated value", n + 1) };
+                rest.push('\n');
+                rest.push_str(if rest.starts_with("\"\"\"") { next } else { strip_comment(next) });
+            }
+            let value = parse_value(rest.trim()).with_context(|| format!("line {}: invalid value for `{}`", n + 1, key))?;
+            let full = if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) };
+            map.insert(full, value);
+        }
+        Ok(Table(map))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> { self.0.get(key) }
+
+    /// Keys directly below `section`, e.g. the snippet triggers in `[snippets]`.
+    pub fn section<'a>(&'a self, section: &'a str) -> impl Iterator<Item = (&'a str, &'a Value)> + 'a {
+        let prefix = format!("{}.", section);
+        self.0.iter().filter_map(move |(k, v)| k.strip_prefix(&prefix).map(|rest| (rest, v)))
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = None;
+    for (i, c) in line.char_indices() {
+        match (c, in_str) {
+            ('"' | '\'', None) => in_str = Some(c),
+            (q, Some(open)) if q == open => in_str = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn balanced(s: &str) -> bool {
+    let mut depth = 0i32;
+    let mut in_str = None;
+    for c in s.chars() {
+        match (c, in_str) {
+            ('"' | '\'', None) => in_str = Some(c),
+            (q, Some(open)) if q == open => in_str = None,
+            ('[', None) => depth += 1,
+            (']', None) => depth -= 1,
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+fn parse_value(s: &str) -> Result<Value> {
+    if let Some(body) = s.strip_prefix("\"\"\"").and_then(|r| r.strip_suffix("\"\"\"")) {
+        return Ok(Value::Str(unescape(body.strip_prefix('\n').unwrap_or(body))?));
+    }
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') { return Ok(Value::Str(unescape(&s[1..s.len() - 1])?)); }
+    if s.len() >= 2 && s.starts_with('\'') && s.ends_with('\'') { return Ok(Value::Str(s[1..s.len() - 1].to_string())); }
+    if s == "true" { return Ok(Value::Bool(true)); }
+    if s == "false" { return Ok(Value::Bool(false)); }
+    if let Some(inner) = s.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+        return split_items(inner).iter().map(|item| parse_value(item)).collect::<Result<Vec<_>>>().map(Value::Array);
+    }
+    s.replace('_', "").parse::<i64>().map(Value::Int).map_err(|_| anyhow::anyhow!("unsupported value `{}`", s))
+}
+
+fn split_items(inner: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut cur = String::new();
+    let mut depth = 0;
+    let mut in_str = None;
+    for c in inner.chars() {
+        match (c, in_str) {
+            ('"' | '\'', None) => in_str = Some(c),
+            (q, Some(open)) if q == open => in_str = None,
+            ('[', None) => depth += 1,
+            (']', None) => depth -= 1,
+            (',', None) if depth == 0 => { items.push(std::mem::take(&mut cur)); continue; }
+            _ => {}
+        }
+        cur.push(c);
+    }
+    items.push(cur);
+    items.into_iter().map(|i| i.trim().to_string()).filter(|i| !i.is_empty()).collect()
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' { out.push(c); continue; }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            other => bail!("unknown escape `\\{}`", other.map(String::from).unwrap_or_default()),
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatConfig {
+    /// Run the formatter on a note after every editor session.
+    pub on_save: bool,
+    /// Wrap prose lines longer than this; `0` leaves line lengths alone.
+    pub wrap_width: usize,
+}
+
+/// The built-in color schemes, for `theme.name` and switching from the command palette.
+pub const THEMES: [&str; 4] = ["dark", "light", "solarized", "gruvbox"];
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// The built-in scheme the colors start from.
+    pub name: String,
+    /// Category tab colors, cycled in tab order.
+    pub palette: Vec<Color>,
+    /// Border of the focused pane.
+    pub accent: Color,
+    /// Background of the selected row in lists.
+    pub highlight: Color,
+    /// Secondary text and quiet borders.
+    pub dim: Color,
+}
+
+impl Theme {
+    /// Built-in scheme `name`, one of `THEMES`.
+    pub fn named(name: &str) -> Option<Self> {
+        let rgb = |hex: u32| Color::Rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8);
+        let (palette, accent, highlight, dim) = match name.trim().to_ascii_lowercase().as_str() {
+            "dark" => (vec![Color::Cyan, Color::Magenta, Color::Green, Color::Yellow, Color::Blue], Color::Yellow, rgb(0x282828), Color::DarkGray),
+            "light" => (vec![rgb(0x005f87), rgb(0x870087), rgb(0x008700), rgb(0xaf5f00), rgb(0x0000af)], rgb(0xaf5f00), rgb(0xe0e0e0), rgb(0x808080)),
+            "solarized" => (vec![rgb(0x2aa198), rgb(0xd33682), rgb(0x859900), rgb(0xb58900), rgb(0x268bd2)], rgb(0xcb4b16), rgb(0x073642), rgb(0x586e75)),
+            "gruvbox" => (vec![rgb(0x8ec07c), rgb(0xd3869b), rgb(0xb8bb26), rgb(0xfabd2f), rgb(0x83a598)], rgb(0xfe8019), rgb(0x3c3836), rgb(0x928374)),
+            _ => return None,
+        };
+        Some(Self { name: name.trim().to_ascii_lowercase(), palette, accent, highlight, dim })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// Remote to push to; `None` pushes to the branch's upstream.
+    pub remote: Option<String>,
+    /// Only commit locally when false.
+    pub push: bool,
+    /// Commit message; `{{now}}` becomes the sync timestamp.
+    pub message: String,
+    /// Let `S` edit the commit message, pre-filled from `message`, before syncing.
+    pub ask_message: bool,
+    /// Pull in the background when knot starts.
+    pub pull_on_start: bool,
+    /// Commit (and push) automatically once edits have settled for `debounce`.
+    pub auto: bool,
+    /// Quiet period after the last change before an automatic sync, so a burst of saves
+    /// becomes one commit.
+    pub debounce: Duration,
+    /// Also commit (and push) on a fixed timer, whenever there is something to sync.
+    pub interval: Option<Duration>,
+    /// `[category_remotes]`: category → URL of the repository it syncs with instead of the
+    /// vault's; see `nested`.
+    pub categories: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotifyConfig {
+    /// Desktop notifications for finished syncs and other background work.
+    pub enabled: bool,
+    /// Only notify about background work that took at least this long.
+    pub min_duration: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarConfig {
+    /// `.ics` file or URL to read events from; the agenda is off without one.
+    pub source: Option<String>,
+    /// Vault-relative folder meeting notes are created in.
+    pub folder: String,
+    /// How many days past today the agenda lists.
+    pub days_ahead: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpiryConfig {
+    /// Move expired notes into `archive_dir` at startup instead of only flagging them.
+    pub auto_archive: bool,
+    /// Vault-relative folder expired notes move to, keeping their path below it.
+    pub archive_dir: String,
+    /// `[expire_after_days]`: vault-relative folder → days after a note's last change. A note's
+    /// own `expires:` date wins over its folder's policy.
+    pub folders: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub vault_path: PathBuf,
+    /// Editor command line, e.g. `"code --wait"` or `"vim +{line} {file}"`; see `editor::resolve`
+    /// for what happens when unset.
+    pub editor: Option<String>,
+    pub theme: Theme,
+    /// `[category_colors]`: category → its tab color, pinned whatever the theme's palette. Set in
+    /// the vault's `.knot/config.toml`, everyone sharing the vault sees the same.
+    pub category_colors: BTreeMap<String, Color>,
+    /// `[category_icons]`: category → an emoji shown before its name.
+    pub category_icons: BTreeMap<String, String>,
+    /// How many keys the footer hints at in Normal mode.
+    pub hints: crate::keymap::Hints,
+    /// `[keys]`: Normal-mode keys rebound from their defaults; see `keymap`.
+    pub keys: crate::keymap::Keymap,
+    pub sync: SyncConfig,
+    pub notify: NotifyConfig,
+    pub format: FormatConfig,
+    pub snippets: Snippets,
+    /// Polling interval for re-reading the vault, for mounts where change notification is unreliable.
+    /// Zero disables polling.
+    pub refresh_interval: Duration,
+    pub index: IndexConfig,
+    /// Trash entries older than this are purged at startup; `None` keeps them until purged by hand.
+    pub trash_retention: Option<Duration>,
+    pub expiry: ExpiryConfig,
+    /// strftime pattern for daily notes, relative to the vault, e.g. `Journal/%Y/%Y-%m-%d.md`.
+    pub journal_path: String,
+    /// Save a text snapshot of pages captured into the read-later queue, not just their link.
+    pub reading_snapshot: bool,
+    /// Replace bare URLs in captured and pasted text with `[Title](url)` links.
+    pub unfurl_links: bool,
+    /// How long to wait for each page title before keeping the bare URL.
+    pub unfurl_timeout: Duration,
+    /// Vault-relative note `knot capture` appends to when given no note name.
+    pub capture_inbox: String,
+    /// strftime pattern stamped on each `knot capture` entry; empty leaves entries unstamped.
+    pub capture_stamp: String,
+    pub calendar: CalendarConfig,
+    /// `[virtual_folders]`: name → glob over vault-relative paths, shown in every Folders pane.
+    pub virtual_folders: Vec<(String, String)>,
+    /// `[numbered_folders]`: vault-relative folder → digits in the sequence number new notes there
+    /// are prefixed with, e.g. `ADRs = 4` for `0007-title.md`.
+    pub numbered_folders: Vec<(String, usize)>,
+    /// Vault-relative folder `knot adr` keeps decision records in.
+    pub adr_dir: String,
+    /// Vault-relative folder the templates `N` and `knot new --template` offer are kept in.
+    pub templates_dir: String,
+    /// Command that plays audio attachments; `{file}` is replaced, or the path appended.
+    pub audio_player: String,
+    pub encryption: EncryptionConfig,
+    pub backup: BackupConfig,
+    /// `[contexts.<name>]`: named setups switched to with `x`; see `context`.
+    pub contexts: Vec<crate::context::Context>,
+    /// Remember secrets (the sync token, an unlocked age identity, note passphrases) in the OS
+    /// keyring. Turn off on shared machines to be asked every session instead.
+    pub keyring: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct EncryptionConfig {
+    /// age identity file that decrypts `.age` notes (and, its recipient, encrypts them); may
+    /// itself be passphrase-encrypted. `None` turns encryption off.
+    pub identity: Option<PathBuf>,
+    /// Vault-relative folders whose new notes are created encrypted.
+    pub folders: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// Where `knot backup` writes archives; kept outside the vault so they don't back up (or
+    /// sync) each other.
+    pub dir: PathBuf,
+    /// Encrypt archives with age without being asked to with `--encrypt`.
+    pub encrypt: bool,
+    /// Newest archive of each of this many days is kept by rotation.
+    pub keep_daily: usize,
+    /// Newest archive of each of this many weeks, on top of the daily ones.
+    pub keep_weekly: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexConfig {
+    /// Extra gitignore-style globs excluded from search and indexing.
+    pub ignore: Vec<String>,
+    /// Folder names holding attachments rather than notes, skipped wherever they appear.
+    pub attachment_dirs: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            vault_path: dirs::home_dir().unwrap_or_default().join(".knot_vault"),
+            editor: None,
+            theme: Theme::named("dark").expect("built in"),
+            category_colors: BTreeMap::new(),
+            category_icons: BTreeMap::new(),
+            hints: crate::keymap::Hints::Minimal,
+            keys: crate::keymap::Keymap::default(),
+            sync: SyncConfig { remote: None, push: true, message: "Manual Sync: {{now}}".into(), ask_message: false, pull_on_start: false, auto: false, debounce: Duration::from_secs(30), interval: None, categories: Vec::new() },
+            notify: NotifyConfig { enabled: false, min_duration: Duration::ZERO },
+            format: FormatConfig { on_save: false, wrap_width: 0 },
+            snippets: Snippets::default(),
+            refresh_interval: Duration::ZERO,
+            index: IndexConfig { ignore: Vec::new(), attachment_dirs: vec!["attachments".into(), "assets".into(), "_resources".into()] },
+            trash_retention: None,
+            journal_path: crate::journal::DEFAULT_PATH.into(),
+            reading_snapshot: false,
+            unfurl_links: false,
+            unfurl_timeout: Duration::from_secs(5),
+            capture_inbox: "Inbox/capture.md".into(),
+            capture_stamp: "%Y-%m-%d %H:%M".into(),
+            calendar: CalendarConfig { source: None, folder: "Meetings".into(), days_ahead: 7 },
+            expiry: ExpiryConfig { auto_archive: false, archive_dir: "Archive".into(), folders: Vec::new() },
+            virtual_folders: Vec::new(),
+            numbered_folders: Vec::new(),
+            adr_dir: "ADRs".into(),
+            templates_dir: crate::template::DEFAULT_DIR.into(),
+            audio_player: crate::audio::DEFAULT_PLAYER.into(),
+            contexts: Vec::new(),
+            encryption: EncryptionConfig { identity: None, folders: Vec::new() },
+            backup: BackupConfig { dir: dirs::data_dir().unwrap_or_default().join("knot").join("backups"), encrypt: false, keep_daily: 7, keep_weekly: 4 },
+            keyring: true,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> { dirs::config_dir().map(|d| d.join("knot").join("config.toml")) }
+
+    /// Loads the user config, falling back to defaults when the file does not exist.
+    /// `KNOT_VAULT` (which `--vault` sets) overrides `vault.path`.
+    pub fn load() -> Result<Self> {
+        let mut cfg = match Self::path() {
+            Some(p) if p.exists() => Self::load_from(&p)?,
+            _ => Self::default(),
+        };
+        if let Some(vault) = std::env::var(VAULT_ENV).ok().filter(|v| !v.trim().is_empty()) {
+            cfg.vault_path = std::path::absolute(expand_home(vault.trim()))?;
+        }
+        Ok(cfg)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let mut cfg = Self::default();
+        cfg.apply(&read_table(path)?);
+        Ok(cfg)
+    }
+
+    /// Layers the vault's own `.knot/config.toml` over the global settings, so each vault can
+    /// have its own editor, sync and ignore rules. `vault.path` is meaningless there and ignored.
+    pub fn with_vault_overrides(mut self) -> Result<Self> {
+        let path = self.vault_path.join(".knot").join("config.toml");
+        if path.exists() {
+            let vault_path = self.vault_path.clone();
+            self.apply(&read_table(&path)?);
+            self.vault_path = vault_path;
+        }
+        Ok(self)
+    }
+
+    /// Tab color of `category`: the pinned one, or else one from the palette picked by its name,
+    /// so it keeps its color as other categories come and go.
+    pub fn category_color(&self, category: &str) -> Color {
+        if let Some(color) = self.category_colors.get(category) { return *color; }
+        // FNV-1a: stable across runs and builds, unlike std's hasher.
+        let hash = category.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        self.theme.palette[(hash % self.theme.palette.len() as u64) as usize]
+    }
+
+    /// `category` with its icon, if it has one.
+    pub fn category_label(&self, category: &str) -> String {
+        match self.category_icons.get(category) {
+            Some(icon) => format!("{} {}", icon, category),
+            None => category.to_string(),
+        }
+    }
+
+    fn apply(&mut self, t: &Table) {
+        if let Some(p) = t.get("vault.path").and_then(Value::as_str) { self.vault_path = expand_home(p); }
+        if let Some(cmd) = t.get("editor.command").and_then(Value::as_str).filter(|c| !c.trim().is_empty()) { self.editor = Some(cmd.to_string()); }
+        // The scheme first, so the colors set next to it adjust it.
+        if let Some(theme) = t.get("theme.name").and_then(Value::as_str).and_then(Theme::named) { self.theme = theme; }
+        if let Some(colors) = t.get("theme.palette").and_then(Value::as_str_list) {
+            let palette: Vec<Color> = colors.iter().filter_map(|c| c.parse().ok()).collect();
+            if !palette.is_empty() { self.theme.palette = palette; }
+        }
+        if let Some(c) = t.get("theme.accent").and_then(Value::as_str).and_then(|c| c.parse().ok()) { self.theme.accent = c; }
+        if let Some(c) = t.get("theme.highlight").and_then(Value::as_str).and_then(|c| c.parse().ok()) { self.theme.highlight = c; }
+        if let Some(c) = t.get("theme.dim").and_then(Value::as_str).and_then(|c| c.parse().ok()) { self.theme.dim = c; }
+        if let Some(h) = t.get("ui.hints").and_then(Value::as_str).and_then(crate::keymap::Hints::parse) { self.hints = h; }
+        crate::keymap::parse(t, &mut self.keys);
+        if let Some(r) = t.get("sync.remote").and_then(Value::as_str) { self.sync.remote = Some(r.to_string()); }
+        if let Some(b) = t.get("sync.push").and_then(Value::as_bool) { self.sync.push = b; }
+        if let Some(m) = t.get("sync.message").and_then(Value::as_str) { self.sync.message = m.to_string(); }
+        if let Some(b) = t.get("sync.ask_message").and_then(Value::as_bool) { self.sync.ask_message = b; }
+        if let Some(b) = t.get("sync.pull_on_start").and_then(Value::as_bool) { self.sync.pull_on_start = b; }
+        if let Some(b) = t.get("sync.auto").and_then(Value::as_bool) { self.sync.auto = b; }
+        if let Some(mins) = t.get("sync.interval_mins").and_then(Value::as_int) { self.sync.interval = (mins > 0).then(|| Duration::from_secs(mins as u64 * 60)); }
+        if let Some(secs) = t.get("sync.debounce_secs").and_then(Value::as_int) { self.sync.debounce = Duration::from_secs(secs.max(0) as u64); }
+        if let Some(b) = t.get("notifications.enabled").and_then(Value::as_bool) { self.notify.enabled = b; }
+        if let Some(secs) = t.get("notifications.min_secs").and_then(Value::as_int) { self.notify.min_duration = Duration::from_secs(secs.max(0) as u64); }
+        if let Some(b) = t.get("format.on_save").and_then(Value::as_bool) { self.format.on_save = b; }
+        if let Some(w) = t.get("format.wrap_width").and_then(Value::as_int) { self.format.wrap_width = w.max(0) as usize; }
+        if let Some(secs) = t.get("refresh.interval_secs").and_then(Value::as_int) { self.refresh_interval = Duration::from_secs(secs.max(0) as u64); }
+        if let Some(globs) = t.get("index.ignore").and_then(Value::as_str_list) { self.index.ignore = globs; }
+        if let Some(dirs) = t.get("index.attachment_dirs").and_then(Value::as_str_list) { self.index.attachment_dirs = dirs; }
+        if let Some(days) = t.get("trash.purge_after_days").and_then(Value::as_int) { self.trash_retention = (days > 0).then(|| Duration::from_secs(days as u64 * 86_400)); }
+        if let Some(p) = t.get("journal.path").and_then(Value::as_str) { self.journal_path = p.trim_start_matches('/').to_string(); }
+        if let Some(b) = t.get("reading.snapshot").and_then(Value::as_bool) { self.reading_snapshot = b; }
+        if let Some(b) = t.get("capture.unfurl_links").and_then(Value::as_bool) { self.unfurl_links = b; }
+        if let Some(p) = t.get("capture.inbox").and_then(Value::as_str).filter(|p| !p.trim().is_empty()) { self.capture_inbox = p.trim_matches('/').to_string(); }
+        if let Some(f) = t.get("capture.timestamp").and_then(Value::as_str) { self.capture_stamp = f.to_string(); }
+        if let Some(secs) = t.get("capture.unfurl_timeout_secs").and_then(Value::as_int) { self.unfurl_timeout = Duration::from_secs(secs.max(1) as u64); }
+        if let Some(src) = t.get("calendar.source").and_then(Value::as_str).filter(|s| !s.trim().is_empty()) { self.calendar.source = Some(src.to_string()); }
+        if let Some(d) = t.get("calendar.folder").and_then(Value::as_str) { self.calendar.folder = d.trim_matches('/').to_string(); }
+        if let Some(days) = t.get("calendar.days_ahead").and_then(Value::as_int) { self.calendar.days_ahead = days.max(0) as u64; }
+        if let Some(b) = t.get("expiry.auto_archive").and_then(Value::as_bool) { self.expiry.auto_archive = b; }
+        if let Some(d) = t.get("expiry.archive_dir").and_then(Value::as_str) { self.expiry.archive_dir = d.trim_matches('/').to_string(); }
+        for (folder, days) in t.section("expire_after_days") {
+            if let Some(days) = days.as_int() { self.expiry.folders.push((folder.trim_matches('/').to_string(), days.max(0) as u64)); }
+        }
+        crate::context::parse(t, &mut self.contexts);
+        for (name, glob) in t.section("virtual_folders") {
+            if let Some(glob) = glob.as_str() { self.virtual_folders.push((name.to_string(), glob.to_string())); }
+        }
+        if let Some(p) = t.get("audio.player").and_then(Value::as_str).filter(|p| !p.trim().is_empty()) { self.audio_player = p.to_string(); }
+        if let Some(p) = t.get("encryption.identity").and_then(Value::as_str).filter(|p| !p.trim().is_empty()) { self.encryption.identity = Some(expand_home(p)); }
+        if let Some(dirs) = t.get("encryption.folders").and_then(Value::as_str_list) { self.encryption.folders = dirs.iter().map(|d| d.trim_matches('/').to_string()).collect(); }
+        if let Some(d) = t.get("backup.dir").and_then(Value::as_str).filter(|d| !d.trim().is_empty()) { self.backup.dir = expand_home(d); }
+        if let Some(b) = t.get("backup.encrypt").and_then(Value::as_bool) { self.backup.encrypt = b; }
+        if let Some(n) = t.get("backup.keep_daily").and_then(Value::as_int) { self.backup.keep_daily = n.max(0) as usize; }
+        if let Some(n) = t.get("backup.keep_weekly").and_then(Value::as_int) { self.backup.keep_weekly = n.max(0) as usize; }
+        if let Some(b) = t.get("secrets.keyring").and_then(Value::as_bool) { self.keyring = b; }
+        if let Some(d) = t.get("adr.dir").and_then(Value::as_str) { self.adr_dir = d.trim_matches('/').to_string(); }
+        if let Some(d) = t.get("templates.dir").and_then(Value::as_str).filter(|d| !d.trim_matches('/').is_empty()) { self.templates_dir = d.trim_matches('/').to_string(); }
+        for (category, url) in t.section("category_remotes") {
+            if let Some(url) = url.as_str().map(str::trim).filter(|u| !u.is_empty()) { self.sync.categories.push((category.trim_matches('/').to_string(), url.to_string())); }
+        }
+        for (category, color) in t.section("category_colors") {
+            if let Some(color) = color.as_str().and_then(|c| c.trim().parse().ok()) { self.category_colors.insert(category.trim_matches('/').to_string(), color); }
+        }
+        for (category, icon) in t.section("category_icons") {
+            if let Some(icon) = icon.as_str().map(str::trim).filter(|i| !i.is_empty()) { self.category_icons.insert(category.trim_matches('/').to_string(), icon.to_string()); }
+        }
+        for (folder, digits) in t.section("numbered_folders") {
+            if let Some(digits) = digits.as_int() { self.numbered_folders.push((folder.trim_matches('/').to_string(), digits.clamp(1, 9) as usize)); }
+        }
+        for (trigger, body) in t.section("snippets") {
+            if let Some(body) = body.as_str() { self.snippets.insert(trigger, body); }
+        }
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table> {
+    let src = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    Table::parse(&src).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Environment variable naming the vault, taking precedence over `vault.path`.
+pub const VAULT_ENV: &str = "KNOT_VAULT";
+
+/// `~/notes` → `$HOME/notes`; other paths are taken as written.
+pub fn expand_home(p: &str) -> PathBuf {
+    match p.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None if p == "~" => dirs::home_dir().unwrap_or_default(),
+        None => PathBuf::from(p),
+    }
+}
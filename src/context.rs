@@ -0,0 +1,137 @@
+//! Named workspace contexts (`[contexts.<name>]` in the config): a vault, where to browse in it,
+//! which filters are on, how notes are sorted and how the panes are laid out, switched to
+//! together with `x` so one keystroke sets knot up for a task.
+//!
+//! ```toml
+//! [contexts.Writing]
+//! category = "Drafts"
+//! sort = "name"
+//! layout = "wide-preview"
+//!
+//! [contexts."Support triage"]
+//! vault = "~/work-notes"
+//! tag = "ticket"
+//! author = "Dana"
+//! age = "week"
+//! layout = "wide-notes"
+//! ```
+
+use std::path::PathBuf;
+
+use crate::config::{self, Table};
+
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub name: String,
+    /// Another vault to open; `None` stays in the current one.
+    pub vault: Option<PathBuf>,
+    pub category: Option<String>,
+    /// Folder in the category, or a virtual folder by name.
+    pub folder: Option<String>,
+    /// Tag filter, as with Ctrl-T.
+    pub tag: Option<String>,
+    /// Author filter, as with `@`.
+    pub author: Option<String>,
+    /// Age filter, as with `f`: `all`, `today`, `week` or `stale`.
+    pub age: Option<String>,
+    pub sort: Sort,
+    pub layout: Layout,
+}
+
+/// Order of the Notes pane.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sort {
+    #[default]
+    Modified,
+    Name,
+}
+
+/// How the three panes share the width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    #[default]
+    Standard,
+    /// Most of the screen for the preview, for reading and writing.
+    WidePreview,
+    /// A wide Notes pane, for sorting through many notes.
+    WideNotes,
+}
+
+impl Sort {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "modified" | "recent" => Some(Sort::Modified),
+            "name" => Some(Sort::Name),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self { Sort::Modified => "modified", Sort::Name => "name" }
+    }
+}
+
+impl Layout {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "standard" | "default" => Some(Layout::Standard),
+            "wide-preview" => Some(Layout::WidePreview),
+            "wide-notes" => Some(Layout::WideNotes),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self { Layout::Standard => "standard", Layout::WidePreview => "wide-preview", Layout::WideNotes => "wide-notes" }
+    }
+
+    /// Percentages of the width for the Folders, Notes and Preview panes.
+    pub fn widths(self) -> [u16; 3] {
+        match self {
+            Layout::Standard => [20, 30, 50],
+            Layout::WidePreview => [15, 20, 65],
+            Layout::WideNotes => [15, 50, 35],
+        }
+    }
+}
+
+/// The contexts defined in `t`, merged into `contexts` by name.
+pub fn parse(t: &Table, contexts: &mut Vec<Context>) {
+    for (key, value) in t.section("contexts") {
+        let Some((name, field)) = key.rsplit_once('.') else { continue };
+        let i = match contexts.iter().position(|c| c.name == name) {
+            Some(i) => i,
+            None => { contexts.push(Context { name: name.to_string(), ..Context::default() }); contexts.len() - 1 }
+        };
+        let ctx = &mut contexts[i];
+        let text = value.as_str().map(str::trim).filter(|s| !s.is_empty());
+        match (field, text) {
+            ("vault", Some(v)) => ctx.vault = Some(config::expand_home(v)),
+            ("category", Some(c)) => ctx.category = Some(c.trim_matches('/').to_string()),
+            ("folder", Some(f)) => ctx.folder = Some(f.trim_matches('/').to_string()),
+            ("tag", Some(tag)) => ctx.tag = Some(tag.trim_start_matches('#').to_string()),
+            ("author", Some(a)) => ctx.author = Some(a.trim_start_matches('@').to_string()),
+            ("age", Some(a)) => ctx.age = Some(a.to_string()),
+            ("sort", Some(s)) => ctx.sort = Sort::parse(s).unwrap_or_default(),
+            ("layout", Some(l)) => ctx.layout = Layout::parse(l).unwrap_or_default(),
+            _ => {}
+        }
+    }
+}
+
+/// One line describing `ctx` for the picker.
+pub fn describe(ctx: &Context) -> String {
+    let mut parts = Vec::new();
+    if let Some(v) = &ctx.vault { parts.push(v.display().to_string()); }
+    match (&ctx.category, &ctx.folder) {
+        (Some(c), Some(f)) => parts.push(format!("{}/{}", c, f)),
+        (Some(c), None) => parts.push(c.clone()),
+        _ => {}
+    }
+    if let Some(t) = &ctx.tag { parts.push(format!("#{}", t)); }
+    if let Some(a) = &ctx.author { parts.push(format!("@{}", a)); }
+    if let Some(a) = &ctx.age { parts.push(a.clone()); }
+    if ctx.sort != Sort::default() { parts.push(format!("by {}", ctx.sort.name())); }
+    if ctx.layout != Layout::default() { parts.push(ctx.layout.name().to_string()); }
+    format!("{}  {}", ctx.name, parts.join(" · "))
+}
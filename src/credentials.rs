@@ -0,0 +1,110 @@
+//! Sync credentials kept in the OS keyring (see `secrets`).
+//!
+//! knot registers itself as the vault repository's git credential helper, so HTTP remotes get
+//! their username and token from the keyring on every push and pull, from knot or from plain git.
+//! Protocol: <https://git-scm.com/docs/git-credential#IOFMT>.
+
+use anyhow::{bail, Context, Result};
+use crossterm::{event::{self, Event, KeyCode, KeyEventKind}, terminal};
+use std::{collections::BTreeMap, env, io::{self, BufRead, Write}, path::Path};
+
+use crate::{git, secrets};
+
+/// Keyring account for a remote, e.g. `https://github.com`.
+fn account(protocol: &str, host: &str) -> String { format!("git:{}://{}", protocol, host) }
+
+/// Reads the `key=value` block git sends a credential helper.
+fn read_request(input: impl BufRead) -> BTreeMap<String, String> {
+    input.lines().map_while(Result::ok).take_while(|l| !l.is_empty())
+        .filter_map(|l| l.split_once('=').map(|(k, v)| (k.to_string(), v.to_string())))
+        .collect()
+}
+
+/// `knot credential get|store|erase`, as invoked by git. With `keyring` off (`secrets.keyring`)
+/// nothing is looked up or saved, though erasing still works.
+pub fn helper(action: &str, keyring: bool) -> Result<()> {
+    let req = read_request(io::stdin().lock());
+    let (Some(protocol), Some(host)) = (req.get("protocol"), req.get("host")) else { return Ok(()) };
+    let account = account(protocol, host);
+    match action {
+        "get" | "store" if !keyring => {}
+        "get" => {
+            // Stored as the same key=value block so username and token travel together.
+            if let Some(stored) = secrets::get(&account)? { println!("{}", stored); }
+        }
+        "store" => {
+            if let (Some(user), Some(pass)) = (req.get("username"), req.get("password")) {
+                secrets::set(&account, &format!("username={}\npassword={}", user, pass))?;
+            }
+        }
+        "erase" => secrets::delete(&account)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Guided setup: asks for a username and token for the vault's push remote, stores them in the
+/// keyring and points the vault repository's credential helper at knot.
+pub fn setup(root: &Path) -> Result<()> {
+    let url = git::run(root, &["remote", "get-url", "--push", "origin"]).context("the vault has no `origin` remote yet")?;
+    let Some((protocol, host)) = http_remote(root) else {
+        bail!("{} is not an HTTP(S) remote; SSH remotes authenticate with your SSH agent instead", url.trim());
+    };
+    println!("Storing credentials for {}://{} in the system keyring.", protocol, host);
+    let user = prompt("Username: ", false)?;
+    let token = prompt("Token or password (hidden): ", true)?;
+    store(root, &user, &token)?;
+    println!("Done. Sync will now authenticate from the keyring.");
+    Ok(())
+}
+
+/// Protocol and host of the vault's push remote, when it is an HTTP(S) one.
+pub fn http_remote(root: &Path) -> Option<(String, String)> {
+    let url = git::run(root, &["remote", "get-url", "--push", "origin"]).ok()?;
+    let (protocol, rest) = url.trim().split_once("://").filter(|(p, _)| p.starts_with("http"))?;
+    let host = rest.split('/').next().unwrap_or(rest);
+    let host = host.rsplit_once('@').map_or(host, |(_, h)| h);
+    Some((protocol.to_string(), host.to_string()))
+}
+
+/// Saves `user` and `token` for the vault's HTTP(S) remote in the keyring and makes knot the
+/// repository's credential helper.
+pub fn store(root: &Path, user: &str, token: &str) -> Result<()> {
+    if user.is_empty() || token.is_empty() { bail!("username and token are both required"); }
+    let (protocol, host) = http_remote(root).context("the vault's remote is not an HTTP(S) one")?;
+    secrets::set(&account(&protocol, &host), &format!("username={}\npassword={}", user, token))?;
+
+    let exe = env::current_exe()?.to_string_lossy().replace('\'', "'\\''");
+    // The empty entry resets helpers inherited from global config so the keyring wins.
+    git::run(root, &["config", "--local", "--replace-all", "credential.helper", ""])?;
+    git::run(root, &["config", "--local", "--add", "credential.helper", &format!("!'{}' credential", exe)])?;
+    Ok(())
+}
+
+pub fn prompt(label: &str, hidden: bool) -> Result<String> {
+    print!("{}", label);
+    io::stdout().flush()?;
+    if !hidden {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        return Ok(line.trim().to_string());
+    }
+    terminal::enable_raw_mode()?;
+    let mut secret = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(k)) if k.kind == KeyEventKind::Press => match k.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(anyhow::anyhow!("cancelled")),
+                KeyCode::Backspace => { secret.pop(); }
+                KeyCode::Char(c) => secret.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    println!();
+    result.map(|_| secret)
+}
@@ -0,0 +1,209 @@
+//! Encryption at rest with [age](https://age-encryption.org): notes saved as `name.md.age`
+//! (ASCII-armored, so git still treats them as text) are only ever decrypted in memory for the
+//! preview, or into a private temporary file while the editor has them open.
+//!
+//! Enabled by `encryption.identity`, an age identity file. Keeping that file itself encrypted
+//! with a passphrase (`age -p -o key.age key.txt`) gives passphrase protection: knot asks for it
+//! once at startup and holds the unlocked identity in a private temporary file until it exits;
+//! with `secrets.keyring` on, the unlocked identity is remembered in the OS keyring so later
+//! sessions don't ask.
+//! New notes in `encryption.folders` are created encrypted; `knot encrypt` converts existing ones.
+//!
+//! Encrypted notes are not indexed, so search, tags and tasks don't see their contents.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{config::EncryptionConfig, secrets};
+
+pub const EXTENSION: &str = "age";
+
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == EXTENSION)
+}
+
+/// `Private/plan.md` for `Private/plan.md.age`.
+pub fn plain_name(path: &Path) -> PathBuf {
+    if is_encrypted(path) { path.with_extension("") } else { path.to_path_buf() }
+}
+
+pub struct Crypt {
+    identity: PathBuf,
+    /// Private directory holding the unlocked identity and notes being edited; removed on drop.
+    scratch: PathBuf,
+}
+
+impl Crypt {
+    /// `None` when encryption isn't configured. A passphrase-protected identity is unlocked here,
+    /// from the keyring when `keyring` allows and it's there, else with age asking on the
+    /// terminal, so call this before the TUI takes the screen.
+    pub fn load(config: &EncryptionConfig, keyring: bool) -> Result<Option<Self>> {
+        Self::open(config, keyring, true)
+    }
+
+    /// Like [`load`](Self::load), but fails instead of asking for the identity's passphrase,
+    /// for when the TUI has the terminal.
+    pub fn load_without_asking(config: &EncryptionConfig, keyring: bool) -> Result<Option<Self>> {
+        Self::open(config, keyring, false)
+    }
+
+    fn open(config: &EncryptionConfig, keyring: bool, ask: bool) -> Result<Option<Self>> {
+        let Some(identity) = &config.identity else { return Ok(None) };
+        if !identity.is_file() { bail!("age identity {} not found", identity.display()); }
+        // Numbered, since a context switch has the next vault's open before this one's drops.
+        static OPENED: AtomicUsize = AtomicUsize::new(0);
+        let scratch = std::env::temp_dir().join(format!("knot-{}-{}", std::process::id(), OPENED.fetch_add(1, Ordering::Relaxed)));
+        create_private_dir(&scratch)?;
+        let mut crypt = Self { identity: identity.clone(), scratch };
+        let header = fs::read(identity)?;
+        if header.starts_with(b"age-encryption.org/") || header.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----") {
+            let account = format!("age-identity:{}", identity.display());
+            let remembered = if keyring { secrets::get(&account).ok().flatten().filter(|k| k.contains("AGE-SECRET-KEY-")) } else { None };
+            let key = match remembered {
+                Some(key) => key,
+                None if !ask => bail!("{} needs its passphrase; start knot in this vault to unlock it", identity.display()),
+                None => {
+                    println!("Unlocking {}", identity.display());
+                    let out = Command::new("age").arg("-d").arg(identity).stderr(Stdio::inherit()).output().map_err(not_installed)?;
+                    if !out.status.success() { bail!("couldn't unlock the age identity"); }
+                    let key = String::from_utf8_lossy(&out.stdout).into_owned();
+                    if keyring {
+                        if let Err(e) = secrets::set(&account, &key) { eprintln!("Couldn't remember the identity in the keyring: {:#}", e); }
+                    }
+                    key
+                }
+            };
+            let unlocked = crypt.scratch.join("identity.txt");
+            fs::write(&unlocked, key)?;
+            crypt.identity = unlocked;
+        }
+        Ok(Some(crypt))
+    }
+
+    pub fn decrypt(&self, path: &Path) -> Result<String> {
+        let out = Command::new("age").arg("-d").arg("-i").arg(&self.identity).arg(path)
+            .stdin(Stdio::null()).output().map_err(not_installed)?;
+        if !out.status.success() { bail!("decrypting {}: {}", path.display(), String::from_utf8_lossy(&out.stderr).trim()); }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    /// Encrypts `text` to the identity's own recipient and writes it to `path`. The ciphertext is
+    /// written next to it first, so a failure leaves the old file intact.
+    pub fn encrypt(&self, text: &str, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("age.tmp");
+        let mut child = Command::new("age").args(["-e", "-a", "-i"]).arg(&self.identity).arg("-o").arg(&tmp)
+            .stdin(Stdio::piped()).stderr(Stdio::piped()).spawn().map_err(not_installed)?;
+        child.stdin.take().context("age stdin")?.write_all(text.as_bytes())?;
+        let out = child.wait_with_output()?;
+        if !out.status.success() {
+            let _ = fs::remove_file(&tmp);
+            bail!("encrypting {}: {}", path.display(), String::from_utf8_lossy(&out.stderr).trim());
+        }
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Decrypts `path` into the scratch directory for an editor, keeping its name so editors
+    /// pick the right syntax.
+    pub fn open_for_editing(&self, path: &Path) -> Result<PathBuf> {
+        let text = if path.exists() { self.decrypt(path)? } else { String::new() };
+        let copy = self.scratch.join(plain_name(path).file_name().context("note has no name")?);
+        fs::write(&copy, text)?;
+        Ok(copy)
+    }
+
+    /// Re-encrypts an edited copy from [`open_for_editing`](Self::open_for_editing) over `path`
+    /// when it changed, and deletes the copy. Returns whether it changed.
+    pub fn finish_editing(&self, copy: &Path, path: &Path) -> Result<bool> {
+        let text = fs::read_to_string(copy)?;
+        let changed = !path.exists() || self.decrypt(path)? != text;
+        if changed { self.encrypt(&text, path)?; }
+        fs::remove_file(copy)?;
+        Ok(changed)
+    }
+
+    /// Replaces the plaintext note at `path` with `path.age`.
+    pub fn encrypt_note(&self, path: &Path) -> Result<PathBuf> {
+        let target = PathBuf::from(format!("{}.{}", path.display(), EXTENSION));
+        if target.exists() { bail!("{} already exists", target.display()); }
+        self.encrypt(&fs::read_to_string(path)?, &target)?;
+        fs::remove_file(path)?;
+        Ok(target)
+    }
+
+    /// Replaces the encrypted note at `path` with its plaintext.
+    pub fn decrypt_note(&self, path: &Path) -> Result<PathBuf> {
+        let target = plain_name(path);
+        if target.exists() { bail!("{} already exists", target.display()); }
+        fs::write(&target, self.decrypt(path)?)?;
+        fs::remove_file(path)?;
+        Ok(target)
+    }
+}
+
+impl Drop for Crypt {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.scratch);
+    }
+}
+
+/// Whether new notes in the vault-relative folder `rel` are created encrypted.
+pub fn encrypts_folder(config: &EncryptionConfig, rel: &str) -> bool {
+    config.identity.is_some() && config.folders.iter().any(|f| rel == f || rel.starts_with(&format!("{}/", f)))
+}
+
+/// Encrypts the file `src` into `dst`, unarmored since archives aren't for git to diff: to
+/// `crypt`'s identity or, without one, with a passphrase age asks for on the terminal.
+pub fn encrypt_file(crypt: Option<&Crypt>, src: &Path, dst: &Path) -> Result<()> {
+    let mut cmd = Command::new("age");
+    match crypt {
+        Some(crypt) => cmd.arg("-e").arg("-i").arg(&crypt.identity),
+        None => cmd.arg("-p"),
+    };
+    run_on_file(cmd, src, dst)
+}
+
+/// Reverses [`encrypt_file`], asking for the passphrase when `src` was encrypted with one.
+pub fn decrypt_file(crypt: Option<&Crypt>, src: &Path, dst: &Path) -> Result<()> {
+    let mut head = vec![0; 512];
+    let n = io::Read::read(&mut fs::File::open(src)?, &mut head)?;
+    let passphrase = String::from_utf8_lossy(&head[..n]).contains("\n-> scrypt ");
+    let mut cmd = Command::new("age");
+    cmd.arg("-d");
+    match crypt {
+        Some(crypt) if !passphrase => { cmd.arg("-i").arg(&crypt.identity); }
+        None if !passphrase => bail!("{} is encrypted to an age identity; set encryption.identity to it", src.display()),
+        _ => {}
+    }
+    run_on_file(cmd, src, dst)
+}
+
+/// Runs age on `src` into `dst`, leaving the terminal to it for passphrase prompts.
+fn run_on_file(mut cmd: Command, src: &Path, dst: &Path) -> Result<()> {
+    let status = cmd.arg("-o").arg(dst).arg(src).status().map_err(not_installed)?;
+    if !status.success() {
+        let _ = fs::remove_file(dst);
+        bail!("age couldn't process {}", src.display());
+    }
+    Ok(())
+}
+
+pub fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+fn not_installed(e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound { anyhow::anyhow!("age is not installed or not on PATH") } else { e.into() }
+}
@@ -0,0 +1,53 @@
+//! Resolving and launching the external editor.
+//!
+//! The command comes from `editor.command`, then `$VISUAL`, then `$EDITOR`, then helix if it is
+//! installed (knot's historical default) and finally the platform's stock editor. `{file}` and
+//! `{line}` in the command are substituted; without `{file}` the path is appended, so plain
+//! `code --wait` and templated `vim +{line} {file}` both work.
+
+use std::{env, path::Path, process::Command};
+
+/// The editor command line to use, before templating.
+pub fn resolve(configured: Option<&str>) -> String {
+    // Each source is skipped when empty, so `EDITOR=` or `command = ""` falls through to the next.
+    let set = |c: &String| !c.trim().is_empty();
+    configured.map(String::from).filter(set)
+        .or_else(|| env::var("VISUAL").ok().filter(set))
+        .or_else(|| env::var("EDITOR").ok().filter(set))
+        .unwrap_or_else(|| {
+            if on_path("helix") { "helix".into() } else if cfg!(windows) { "notepad".into() } else { "vi".into() }
+        })
+}
+
+/// Builds the process that opens `path` at `line` (1-based).
+pub fn command(cmdline: &str, path: &Path, line: usize) -> Command {
+    let file = path.to_string_lossy();
+    let mut words = split_args(cmdline);
+    if !words.iter().any(|w| w.contains("{file}")) { words.push("{file}".into()); }
+    let mut words = words.into_iter().map(|w| w.replace("{file}", &file).replace("{line}", &line.to_string()));
+    let mut cmd = Command::new(words.next().unwrap_or_default());
+    cmd.args(words);
+    cmd
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// Whitespace-separated words, honouring single and double quotes so paths with spaces survive.
+fn split_args(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut cur = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+    for c in s.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => { quote = Some(c); in_word = true; }
+            (q, Some(open)) if q == open => quote = None,
+            (c, None) if c.is_whitespace() => { if in_word { words.push(std::mem::take(&mut cur)); in_word = false; } }
+            _ => { cur.push(c); in_word = true; }
+        }
+    }
+    if in_word { words.push(cur); }
+    words
+}
@@ -0,0 +1,329 @@
+//! `knot import eml`: turns saved email messages (`.eml`, RFC 5322/MIME) into notes.
+//!
+//! The subject becomes the title, sender, recipients and date go into frontmatter, the body is
+//! kept as markdown (the plain-text part when there is one, otherwise the HTML part converted),
+//! and attachments are written next to the note under its attachment folder and linked at the end.
+//! MIME is parsed here directly: multipart nesting, base64 and quoted-printable bodies, encoded
+//! header words and the common charsets.
+
+use anyhow::{bail, Context, Result};
+use chrono::DateTime;
+use std::{fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+use crate::html;
+
+/// One MIME entity: unfolded headers and the raw (still transfer-encoded) body.
+struct Part {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+struct Attachment {
+    name: String,
+    data: Vec<u8>,
+}
+
+/// Imports `path` (an `.eml` file or a folder searched for them) into `dir`, saving attachments
+/// under `dir/<attachments>/<note name>/`. Returns each new note with its attachment count.
+pub fn import(path: &Path, dir: &Path, attachments: &str) -> Result<Vec<(PathBuf, usize)>> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let mut found: Vec<PathBuf> = WalkDir::new(path).into_iter().flatten()
+            .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|x| x.eq_ignore_ascii_case("eml")))
+            .map(|e| e.into_path())
+            .collect();
+        found.sort();
+        found
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if files.is_empty() { bail!("no .eml files in {}", path.display()); }
+    fs::create_dir_all(dir)?;
+    files.iter().map(|file| {
+        let raw = fs::read(file).with_context(|| format!("reading {}", file.display()))?;
+        import_message(&raw, dir, attachments).with_context(|| format!("importing {}", file.display()))
+    }).collect()
+}
+
+fn import_message(raw: &[u8], dir: &Path, attachments_dir: &str) -> Result<(PathBuf, usize)> {
+    let message = parse(raw);
+    let header = |name: &str| message.header(name).map(decode_words);
+    let subject = header("subject").filter(|s| !s.trim().is_empty()).unwrap_or_else(|| "(no subject)".into());
+    let date = message.header("date").and_then(|d| DateTime::parse_from_rfc2822(d.trim()).ok());
+
+    let mut text = None;
+    let mut html_body = None;
+    let mut attachments = Vec::new();
+    collect(&message, &mut text, &mut html_body, &mut attachments);
+    if text.is_none() && html_body.is_none() && attachments.is_empty() && !message.body.is_empty() {
+        bail!("not an email message");
+    }
+    let body = text.or_else(|| html_body.map(|h| html::to_markdown(html::main_content(&h)))).unwrap_or_default();
+
+    let stem = match date {
+        Some(d) => format!("{} {}", d.format("%Y-%m-%d"), file_safe(&subject)),
+        None => file_safe(&subject),
+    };
+    let mut note = dir.join(format!("{}.md", stem));
+    for n in 2.. {
+        if !note.exists() { break; }
+        note = dir.join(format!("{} {}.md", stem, n));
+    }
+    let name = crate::links::link_name(&note);
+
+    let mut out = format!("---\ntitle: \"{}\"\n", subject.replace('"', "'"));
+    for (key, field) in [("from", "from"), ("to", "to"), ("cc", "cc"), ("message_id", "message-id")] {
+        if let Some(value) = header(field) { out.push_str(&format!("{}: \"{}\"\n", key, value.replace('"', "'"))); }
+    }
+    match (date, message.header("date")) {
+        (Some(d), _) => out.push_str(&format!("date: {}\n", d.format("%Y-%m-%d %H:%M"))),
+        (None, Some(raw)) => out.push_str(&format!("date: \"{}\"\n", raw.trim())),
+        (None, None) => {}
+    }
+    out.push_str(&format!("---\n# {}\n\n{}\n", subject, body.trim()));
+
+    if !attachments.is_empty() {
+        let folder = dir.join(attachments_dir).join(&name);
+        fs::create_dir_all(&folder)?;
+        out.push_str("\n## Attachments\n\n");
+        for a in &attachments {
+            let mut file = folder.join(&a.name);
+            for n in 2.. {
+                if !file.exists() { break; }
+                let p = Path::new(&a.name);
+                let ext = p.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+                file = folder.join(format!("{} {}{}", crate::links::link_name(p), n, ext));
+            }
+            fs::write(&file, &a.data)?;
+            let rel = format!("{}/{}/{}", attachments_dir, name, file.file_name().unwrap_or_default().to_string_lossy());
+            out.push_str(&format!("- [{}]({})\n", a.name, rel.replace(' ', "%20")));
+        }
+    }
+    fs::write(&note, out)?;
+    Ok((note, attachments.len()))
+}
+
+/// Walks the MIME tree: the first plain-text and HTML bodies, and every attachment.
+fn collect(part: &Part, text: &mut Option<String>, html_body: &mut Option<String>, attachments: &mut Vec<Attachment>) {
+    let (mime, params) = part.content_type();
+    if let Some(boundary) = mime.starts_with("multipart/").then(|| param(&params, "boundary")).flatten() {
+        for child in split_multipart(&part.body, &boundary) { collect(&parse(child), text, html_body, attachments); }
+        return;
+    }
+    let disposition = part.header("content-disposition").map(parse_params).unwrap_or_default();
+    let filename = param(&disposition.1, "filename").or_else(|| param(&params, "name")).map(|n| decode_words(&n));
+    let is_attachment = disposition.0 == "attachment" || (filename.is_some() && !mime.starts_with("text/"));
+    let data = part.decoded_body();
+    if !is_attachment && mime == "text/plain" && text.is_none() {
+        *text = Some(decode_charset(&data, param(&params, "charset").as_deref()));
+    } else if !is_attachment && mime == "text/html" && html_body.is_none() {
+        *html_body = Some(decode_charset(&data, param(&params, "charset").as_deref()));
+    } else if is_attachment || filename.is_some() || mime == "message/rfc822" {
+        let ext = if mime == "message/rfc822" { "eml" } else { "bin" };
+        let name = filename.map(|n| file_safe(&n)).filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("attachment-{}.{}", attachments.len() + 1, ext));
+        attachments.push(Attachment { name, data });
+    }
+}
+
+fn parse(raw: &[u8]) -> Part {
+    // A part without headers starts straight with the blank line.
+    let leading = [&b"\r\n"[..], b"\n"].into_iter().find(|nl| raw.starts_with(nl)).map(|nl| (0, nl.len()));
+    let split = leading.or_else(|| find(raw, b"\r\n\r\n").map(|i| (i, i + 4))
+        .into_iter().chain(find(raw, b"\n\n").map(|i| (i, i + 2)))
+        .min_by_key(|(i, _)| *i));
+    let (head, body) = match split {
+        Some((end, start)) => (&raw[..end], raw[start..].to_vec()),
+        None => (raw, Vec::new()),
+    };
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() { value.push(' '); value.push_str(line.trim()); }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    Part { headers, body }
+}
+
+impl Part {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Lowercase MIME type and parameters; `text/plain` when missing, as RFC 2045 says.
+    fn content_type(&self) -> (String, Vec<(String, String)>) {
+        self.header("content-type").map(parse_params).unwrap_or_else(|| ("text/plain".into(), Vec::new()))
+    }
+
+    fn decoded_body(&self) -> Vec<u8> {
+        match self.header("content-transfer-encoding").map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+            Some("base64") => base64(&self.body),
+            Some("quoted-printable") => quoted_printable(&self.body),
+            _ => self.body.clone(),
+        }
+    }
+}
+
+/// `value; key=val; key="quoted val"` → (lowercase value, [(lowercase key, val)]).
+fn parse_params(header: &str) -> (String, Vec<(String, String)>) {
+    let mut pieces = header.split(';');
+    let value = pieces.next().unwrap_or("").trim().to_ascii_lowercase();
+    let params = pieces.filter_map(|p| {
+        let (k, v) = p.split_once('=')?;
+        let k = k.trim().to_ascii_lowercase();
+        let v = v.trim().trim_matches('"');
+        // RFC 2231 `filename*=utf-8''caf%C3%A9.pdf`.
+        match k.strip_suffix('*') {
+            Some(k) => {
+                let encoded = v.splitn(3, '\'').nth(2).unwrap_or(v);
+                Some((k.to_string(), String::from_utf8_lossy(&percent_decode(encoded)).into_owned()))
+            }
+            None => Some((k, v.to_string())),
+        }
+    }).collect();
+    (value, params)
+}
+
+fn param(params: &[(String, String)], key: &str) -> Option<String> {
+    params.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+}
+
+/// The parts of a multipart body, without the preamble and epilogue.
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut pos = 0;
+    while pos < body.len() {
+        let end = body[pos..].iter().position(|&b| b == b'\n').map_or(body.len(), |i| pos + i + 1);
+        let line = String::from_utf8_lossy(&body[pos..end]);
+        let line = line.trim_end();
+        if line.starts_with(&delimiter) {
+            if let Some(s) = start { parts.push(trim_line_end(&body[s..pos])); }
+            if line[delimiter.len()..].starts_with("--") { return parts; }
+            start = Some(end);
+        }
+        pos = end;
+    }
+    if let Some(s) = start { parts.push(&body[s..]); }
+    parts
+}
+
+/// Drops the line break that belongs to the following boundary line.
+fn trim_line_end(part: &[u8]) -> &[u8] {
+    let part = part.strip_suffix(b"\n").unwrap_or(part);
+    part.strip_suffix(b"\r").unwrap_or(part)
+}
+
+/// Decodes RFC 2047 `=?charset?B|Q?...?=` words in a header value.
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let Some(word) = encoded_word(&rest[start..]) else {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            after_word = false;
+            continue;
+        };
+        let between = &rest[..start];
+        // Whitespace between two encoded words is not part of the text.
+        if !(after_word && between.trim().is_empty()) { out.push_str(between); }
+        out.push_str(&word.0);
+        rest = &rest[start + word.1..];
+        after_word = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The decoded text of the encoded word at the start of `s` and its length.
+fn encoded_word(s: &str) -> Option<(String, usize)> {
+    let mut fields = s[2..].splitn(3, '?');
+    let (charset, encoding, rest) = (fields.next()?, fields.next()?, fields.next()?);
+    let end = rest.find("?=")?;
+    let payload = &rest[..end];
+    let bytes = match encoding.to_ascii_lowercase().as_str() {
+        "b" => base64(payload.as_bytes()),
+        "q" => quoted_printable(payload.replace('_', " ").as_bytes()),
+        _ => return None,
+    };
+    Some((decode_charset(&bytes, Some(charset)), 2 + charset.len() + 1 + encoding.len() + 1 + end + 2))
+}
+
+/// UTF-8 and ASCII as is; Latin-1 and Windows-1252 byte by byte; anything else best effort.
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset.map(|c| c.trim().to_ascii_lowercase()).as_deref() {
+        Some("iso-8859-1" | "latin1" | "windows-1252" | "cp1252") => bytes.iter().map(|&b| b as char).collect(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn base64(input: &[u8]) -> Vec<u8> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for v in input.iter().filter_map(|&c| value(c)) {
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    out
+}
+
+fn quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'=' if input[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'=' if input[i + 1..].starts_with(b"\n") => i += 2,
+            b'=' if input.get(i + 1..i + 3).and_then(hex_byte).is_some() => {
+                out.extend(input.get(i + 1..i + 3).and_then(hex_byte));
+                i += 3;
+            }
+            b => { out.push(b); i += 1; }
+        }
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes.get(i + 1..i + 3).filter(|_| bytes[i] == b'%').and_then(hex_byte) {
+            Some(b) => { out.push(b); i += 3; }
+            None => { out.push(bytes[i]); i += 1; }
+        }
+    }
+    out
+}
+
+fn hex_byte(pair: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// A subject or attachment name usable as a file name.
+fn file_safe(name: &str) -> String {
+    let cleaned: String = name.chars().map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '-' } else { c }).collect();
+    cleaned.trim().trim_matches('.').chars().take(100).collect()
+}
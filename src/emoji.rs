@@ -0,0 +1,117 @@
+//! `:shortcode:` emoji rendering and completion, using the GitHub names for the common set.
+
+/// Sorted by shortcode so lookups can binary-search.
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"), ("-1", "👎"), ("100", "💯"), ("alarm_clock", "⏰"), ("angry", "😠"), ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"), ("arrow_right", "➡️"), ("arrow_up", "⬆️"), ("art", "🎨"), ("baby", "👶"), ("balloon", "🎈"),
+    ("bangbang", "‼️"), ("battery", "🔋"), ("beer", "🍺"), ("bell", "🔔"), ("bike", "🚲"), ("birthday", "🎂"),
+    ("blush", "😊"), ("bomb", "💣"), ("book", "📖"), ("bookmark", "🔖"), ("books", "📚"), ("boom", "💥"),
+    ("bowtie", "👔"), ("brain", "🧠"), ("briefcase", "💼"), ("broken_heart", "💔"), ("bug", "🐛"), ("bulb", "💡"),
+    ("calendar", "📆"), ("camera", "📷"), ("car", "🚗"), ("cat", "🐱"), ("chart_with_upwards_trend", "📈"),
+    ("check", "✔️"), ("checkered_flag", "🏁"), ("clap", "👏"), ("clipboard", "📋"), ("clock", "🕐"), ("cloud", "☁️"),
+    ("coffee", "☕"), ("computer", "💻"), ("confused", "😕"), ("construction", "🚧"), ("cool", "🆒"), ("crown", "👑"),
+    ("cry", "😢"), ("dart", "🎯"), ("date", "📅"), ("dog", "🐶"), ("dollar", "💵"), ("door", "🚪"), ("email", "📧"),
+    ("envelope", "✉️"), ("exclamation", "❗"), ("eyes", "👀"), ("facepalm", "🤦"), ("file_folder", "📁"), ("fire", "🔥"),
+    ("flashlight", "🔦"), ("floppy_disk", "💾"), ("flower", "🌸"), ("gear", "⚙️"), ("ghost", "👻"), ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"), ("grimacing", "😬"), ("grin", "😁"), ("grinning", "😀"), ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"), ("heart", "❤️"), ("heavy_check_mark", "✔️"), ("heavy_minus_sign", "➖"),
+    ("heavy_plus_sign", "➕"), ("hourglass", "⌛"), ("house", "🏠"), ("hugs", "🤗"), ("inbox_tray", "📥"),
+    ("information_source", "ℹ️"), ("joy", "😂"), ("key", "🔑"), ("keyboard", "⌨️"), ("kiss", "💋"), ("label", "🏷️"),
+    ("laughing", "😆"), ("link", "🔗"), ("lock", "🔒"), ("loudspeaker", "📢"), ("mag", "🔍"), ("mailbox", "📫"),
+    ("memo", "📝"), ("microphone", "🎤"), ("money_with_wings", "💸"), ("moon", "🌙"), ("muscle", "💪"),
+    ("musical_note", "🎵"), ("neutral_face", "😐"), ("new", "🆕"), ("no_entry", "⛔"), ("notebook", "📓"),
+    ("ok", "🆗"), ("ok_hand", "👌"), ("outbox_tray", "📤"), ("package", "📦"), ("page_facing_up", "📄"),
+    ("paperclip", "📎"), ("partying_face", "🥳"), ("pencil", "📝"), ("pencil2", "✏️"), ("phone", "☎️"), ("pin", "📌"),
+    ("pizza", "🍕"), ("point_down", "👇"), ("point_left", "👈"), ("point_right", "👉"), ("point_up", "☝️"),
+    ("pray", "🙏"), ("pushpin", "📌"), ("question", "❓"), ("rainbow", "🌈"), ("raised_hands", "🙌"), ("recycle", "♻️"),
+    ("red_circle", "🔴"), ("relaxed", "☺️"), ("robot", "🤖"), ("rocket", "🚀"), ("rofl", "🤣"), ("rotating_light", "🚨"),
+    ("scissors", "✂️"), ("scream", "😱"), ("see_no_evil", "🙈"), ("seedling", "🌱"), ("shield", "🛡️"),
+    ("shopping_cart", "🛒"), ("shrug", "🤷"), ("skull", "💀"), ("sleeping", "😴"), ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"), ("smiley", "😃"), ("smirk", "😏"), ("snowflake", "❄️"), ("sob", "😭"), ("sparkles", "✨"),
+    ("speech_balloon", "💬"), ("star", "⭐"), ("star2", "🌟"), ("stop_sign", "🛑"), ("stopwatch", "⏱️"),
+    ("sunglasses", "😎"), ("sunny", "☀️"), ("sweat_smile", "😅"), ("tada", "🎉"), ("thinking", "🤔"),
+    ("thought_balloon", "💭"), ("thumbsdown", "👎"), ("thumbsup", "👍"), ("tick", "✔️"), ("timer_clock", "⏲️"),
+    ("tired_face", "😫"), ("trophy", "🏆"), ("truck", "🚚"), ("tv", "📺"), ("umbrella", "☂️"), ("unlock", "🔓"),
+    ("upside_down_face", "🙃"), ("v", "✌️"), ("warning", "⚠️"), ("wave", "👋"), ("white_check_mark", "✅"),
+    ("wink", "😉"), ("world_map", "🗺️"), ("wrench", "🔧"), ("x", "❌"), ("yum", "😋"), ("zap", "⚡"), ("zzz", "💤"),
+];
+
+pub fn lookup(code: &str) -> Option<&'static str> {
+    EMOJI.binary_search_by(|(name, _)| name.cmp(&code)).ok().map(|i| EMOJI[i].1)
+}
+
+/// Shortcodes starting with `prefix`, best (shortest) matches first.
+pub fn complete(prefix: &str, limit: usize) -> Vec<(&'static str, &'static str)> {
+    let mut hits: Vec<_> = EMOJI.iter().copied().filter(|(name, _)| name.starts_with(prefix)).collect();
+    hits.sort_by_key(|(name, _)| name.len());
+    hits.truncate(limit);
+    hits
+}
+
+/// Replaces known `:shortcode:`s in a single line, leaving `inline code` spans alone.
+fn render_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut in_code = false;
+    while let Some(pos) = rest.find([':', '`']) {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with('`') {
+            in_code = !in_code;
+            out.push('`');
+            rest = &rest[1..];
+            continue;
+        }
+        let candidate = rest[1..].find(':').map(|end| &rest[1..end + 1]);
+        match candidate.filter(|c| !in_code && !c.is_empty()).and_then(lookup) {
+            Some(emoji) => {
+                out.push_str(emoji);
+                rest = &rest[candidate.unwrap().len() + 2..];
+            }
+            None => {
+                out.push(':');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Renders shortcodes throughout a note, skipping fenced code blocks.
+pub fn render(text: &str) -> String {
+    let mut in_fence = false;
+    text.lines().map(|line| {
+        if line.trim_start().starts_with("```") { in_fence = !in_fence; return line.to_string(); }
+        if in_fence { line.to_string() } else { render_line(line) }
+    }).collect::<Vec<_>>().join("\n")
+}
+
+/// The unfinished `:code` the user is typing at the end of `buf`, if any.
+pub fn pending_shortcode(buf: &str) -> Option<&str> {
+    let start = buf.rfind(':')?;
+    let code = &buf[start + 1..];
+    let preceded_ok = buf[..start].chars().last().is_none_or(char::is_whitespace);
+    (preceded_ok && !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'))).then_some(code)
+}
+
+/// Turns a just-closed `:code:` at the end of `buf` into its emoji.
+pub fn expand_closed(buf: &mut String) {
+    if !buf.ends_with(':') { return; }
+    let Some(code) = pending_shortcode(&buf[..buf.len() - 1]) else { return };
+    if let Some(emoji) = lookup(code) {
+        let start = buf.len() - code.len() - 2;
+        buf.truncate(start);
+        buf.push_str(emoji);
+    }
+}
+
+/// Completes the pending `:code` at the end of `buf` to the best matching emoji.
+pub fn complete_pending(buf: &mut String) {
+    let Some(code) = pending_shortcode(buf) else { return };
+    if let Some((_, emoji)) = complete(code, 1).first() {
+        let start = buf.len() - code.len() - 1;
+        buf.truncate(start);
+        buf.push_str(emoji);
+    }
+}
@@ -0,0 +1,38 @@
+//! Expiry for time-bound notes: an `expires: YYYY-MM-DD` frontmatter date, or a per-folder
+//! `[expire_after_days]` policy counted from the note's last modification.
+
+use chrono::{DateTime, Local, NaiveDate};
+use std::{fs, path::{Path, PathBuf}, time::Duration};
+
+use crate::{config::ExpiryConfig, frontmatter, vault};
+
+/// The last day `note` is current, if it ever expires.
+pub fn expires_on(root: &Path, note: &Path, config: &ExpiryConfig) -> Option<NaiveDate> {
+    let text = fs::read_to_string(note).ok()?;
+    if let Some(date) = frontmatter::split(&text).0.and_then(|y| frontmatter::value(y, "expires")) {
+        return NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok();
+    }
+    let rel = vault::relative(root, note);
+    // The most specific folder policy applies.
+    let (_, days) = config.folders.iter()
+        .filter(|(folder, _)| rel.starts_with(&format!("{}/", folder)))
+        .max_by_key(|(folder, _)| folder.len())?;
+    let modified = fs::metadata(note).and_then(|m| m.modified()).ok()?;
+    Some((DateTime::<Local>::from(modified) + Duration::from_secs(days * 86_400)).date_naive())
+}
+
+/// Notes past their expiry date, skipping those already in the archive folder.
+pub fn expired(root: &Path, notes: &[PathBuf], config: &ExpiryConfig) -> Vec<PathBuf> {
+    let today = Local::now().date_naive();
+    let archive = root.join(&config.archive_dir);
+    notes.iter()
+        .filter(|n| !n.starts_with(&archive))
+        .filter(|n| expires_on(root, n, config).is_some_and(|d| d < today))
+        .cloned()
+        .collect()
+}
+
+/// Where an expired note goes when archived: the same relative path under the archive folder.
+pub fn archive_path(root: &Path, note: &Path, config: &ExpiryConfig) -> PathBuf {
+    root.join(&config.archive_dir).join(note.strip_prefix(root).unwrap_or(note))
+}
@@ -0,0 +1,77 @@
+//! YAML frontmatter handling for the handful of keys knot cares about (`tags`, dates, status).
+//! This is line-based rather than a YAML parser: it understands `key: value`, inline lists
+//! (`tags: [a, b]`) and block lists (`tags:` followed by `- a` lines).
+
+/// Splits a note into its frontmatter (without the `---` fences) and body.
+pub fn split(text: &str) -> (Option<&str>, &str) {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else { return (None, text) };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// Reassembles a note from frontmatter and body.
+pub fn join(yaml: Option<&str>, body: &str) -> String {
+    match yaml {
+        Some(y) if !y.trim().is_empty() => format!("---\n{}\n---\n{}", y.trim_end(), body),
+        _ => body.to_string(),
+    }
+}
+
+/// A scalar key's value, unquoted; `None` when missing or empty.
+pub fn value(yaml: &str, key: &str) -> Option<String> {
+    let line = yaml.lines().find(|l| l.split_once(':').is_some_and(|(k, _)| k.trim() == key && !l.starts_with(' ')))?;
+    let value = line.split_once(':')?.1.trim().trim_matches(|c| c == '"' || c == '\'');
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Values of a list-valued key, accepting inline, block and comma-separated forms.
+pub fn list(yaml: &str, key: &str) -> Vec<String> {
+    let lines: Vec<&str> = yaml.lines().collect();
+    let Some(pos) = lines.iter().position(|l| l.split_once(':').is_some_and(|(k, _)| k.trim() == key && !l.starts_with(' '))) else { return Vec::new() };
+    let value = lines[pos].split_once(':').map(|(_, v)| v.trim()).unwrap_or("");
+    let clean = |s: &str| s.trim().trim_matches(|c| c == '"' || c == '\'').trim_start_matches('#').to_string();
+    if value.is_empty() {
+        return lines[pos + 1..].iter().take_while(|l| l.trim_start().starts_with("- "))
+            .map(|l| clean(&l.trim_start()[2..])).filter(|s| !s.is_empty()).collect();
+    }
+    value.trim_start_matches('[').trim_end_matches(']').split(',').map(clean).filter(|s| !s.is_empty()).collect()
+}
+
+/// Replaces a list-valued key (in whatever form it was written) with an inline list.
+pub fn set_list(yaml: &str, key: &str, values: &[String]) -> String {
+    let mut out = Vec::new();
+    let mut lines = yaml.lines().peekable();
+    let mut found = false;
+    while let Some(l) = lines.next() {
+        if l.split_once(':').is_some_and(|(k, _)| k.trim() == key && !l.starts_with(' ')) {
+            found = true;
+            while lines.peek().is_some_and(|n| n.trim_start().starts_with("- ")) { lines.next(); }
+            out.push(format!("{}: [{}]", key, values.join(", ")));
+        } else {
+            out.push(l.to_string());
+        }
+    }
+    if !found { out.push(format!("{}: [{}]", key, values.join(", "))); }
+    out.join("\n")
+}
+
+/// Sets a scalar key, replacing its line or appending one.
+pub fn set_value(yaml: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut out: Vec<String> = yaml.lines().map(|l| {
+        if l.split_once(':').is_some_and(|(k, _)| k.trim() == key && !l.starts_with(' ')) {
+            found = true;
+            format!("{}: {}", key, value)
+        } else {
+            l.to_string()
+        }
+    }).collect();
+    if !found { out.push(format!("{}: {}", key, value)); }
+    out.join("\n")
+}
@@ -0,0 +1,32 @@
+//! Subsequence fuzzy matching used by the completion and picker popups.
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence, or `None` if it doesn't match.
+/// Consecutive runs and matches at word starts (after `/`, `-`, `_`, space) score higher; gaps cost a little.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() { return Some(0); }
+    let cand: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut ci = 0;
+    let mut prev_match: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let start = ci;
+        while ci < cand.len() && !cand[ci].to_lowercase().eq(std::iter::once(q)) { ci += 1; }
+        if ci == cand.len() { return None; }
+        score += 10;
+        if prev_match == Some(ci.wrapping_sub(1)) { score += 15; }
+        if ci == 0 || matches!(cand[ci - 1], '/' | '-' | '_' | ' ' | '.') { score += 20; }
+        score -= (ci - start) as i64;
+        prev_match = Some(ci);
+        ci += 1;
+    }
+    Some(score - cand.len() as i64 / 4)
+}
+
+/// Indices of `candidates` matching `query`, best first, at most `limit` of them.
+pub fn rank<S: AsRef<str>>(query: &str, candidates: &[S], limit: usize) -> Vec<usize> {
+    let mut hits: Vec<(i64, usize)> = candidates.iter().enumerate()
+        .filter_map(|(i, c)| score(query, c.as_ref()).map(|s| (s, i)))
+        .collect();
+    hits.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    hits.into_iter().take(limit).map(|(_, i)| i).collect()
+}
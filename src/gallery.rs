@@ -0,0 +1,99 @@
+//! Contact sheet for image-heavy folders: every image under the browsed folder with its pixel
+//! size, opened in the system's image viewer.
+//!
+//! Sizes are read from the file header (PNG, GIF, JPEG, BMP, WebP) rather than by decoding the
+//! image, so large screenshot archives open quickly. Other formats show without dimensions.
+
+use anyhow::{Context, Result};
+use std::{fs::File, io::Read, path::{Path, PathBuf}, process::{Command, Stdio}};
+use walkdir::WalkDir;
+
+use crate::trash::TRASH_DIR;
+
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "svg", "avif", "heic", "tif", "tiff"];
+
+pub struct Image {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Width and height in pixels, when the header could be read.
+    pub size: Option<(u32, u32)>,
+}
+
+/// Images in `dir` and its subfolders, sorted by path.
+pub fn images(dir: &Path) -> Vec<Image> {
+    let mut found: Vec<Image> = WalkDir::new(dir).min_depth(1).into_iter()
+        .filter_entry(|e| { let name = e.file_name().to_string_lossy(); e.depth() == 0 || !name.starts_with('.') && name != TRASH_DIR })
+        .flatten()
+        .filter(|e| e.file_type().is_file() && is_image(e.path()))
+        .map(|e| Image {
+            path: e.path().to_path_buf(),
+            bytes: e.metadata().map(|m| m.len()).unwrap_or(0),
+            size: dimensions(e.path()),
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Opens `path` with the platform's default application, without waiting for it.
+pub fn open(path: &Path) -> Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        Command::new("xdg-open")
+    };
+    cmd.arg(path).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+        .spawn().context("no image viewer found (xdg-open, open or start)")?;
+    Ok(())
+}
+
+/// Pixel size from the image header.
+fn dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut head = Vec::with_capacity(64 * 1024);
+    // JPEG puts the size in a frame header after any metadata segments; 64 KiB covers nearly all.
+    File::open(path).ok()?.take(64 * 1024).read_to_end(&mut head).ok()?;
+    let be16 = |i: usize| Some(u16::from_be_bytes(head.get(i..i + 2)?.try_into().ok()?) as u32);
+    let le16 = |i: usize| Some(u16::from_le_bytes(head.get(i..i + 2)?.try_into().ok()?) as u32);
+    let be32 = |i: usize| Some(u32::from_be_bytes(head.get(i..i + 4)?.try_into().ok()?));
+    let le32 = |i: usize| Some(u32::from_le_bytes(head.get(i..i + 4)?.try_into().ok()?));
+    match head.get(..4)? {
+        [0x89, b'P', b'N', b'G'] => Some((be32(16)?, be32(20)?)),
+        [b'G', b'I', b'F', b'8'] => Some((le16(6)?, le16(8)?)),
+        // Top-down bitmaps store a negative height.
+        [b'B', b'M', ..] => Some((le32(18)?, (le32(22)? as i32).unsigned_abs())),
+        [0xFF, 0xD8, ..] => {
+            let mut i = 2;
+            while head.get(i) == Some(&0xFF) {
+                let marker = *head.get(i + 1)?;
+                // SOF0..SOF15, except DHT (C4), JPG (C8) and DAC (CC), carry the frame size.
+                if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                    return Some((be16(i + 7)?, be16(i + 5)?));
+                }
+                i += 2 + be16(i + 2)? as usize;
+            }
+            None
+        }
+        [b'R', b'I', b'F', b'F'] if head.get(8..12) == Some(&b"WEBP"[..]) => match head.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = le32(21)?;
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => {
+                let le24 = |i: usize| Some(le32(i)? & 0xFF_FFFF);
+                Some((le24(24)? + 1, le24(27)? + 1))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
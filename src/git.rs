@@ -0,0 +1,288 @@
+//! Wrappers over the `git` CLI: vault setup, the read-only views (blame, history, diffs) and
+//! the error classification sync uses. knot has no git library of its own, so `git` must be on
+//! PATH; without it these return `GitError::NotInstalled`.
+//!
+//! Failures come back as a typed `GitError` so the UI can say *why* (no remote, bad credentials,
+//! missing identity, ...) instead of echoing raw stderr.
+
+use anyhow::Result;
+use std::{collections::BTreeMap, fmt, io, path::Path, process::Command};
+
+use crate::vault::relative;
+
+#[derive(Debug)]
+pub enum GitError {
+    /// `git` is not installed or not on PATH.
+    NotInstalled,
+    /// No remote to push to or pull from.
+    NoRemote,
+    /// The remote refused our credentials, or would have had to prompt for them.
+    Auth(String),
+    /// Committing needs `user.name` / `user.email`.
+    Identity,
+    /// The remote has commits we don't; a pull is needed first.
+    Rejected,
+    /// Local and remote both have new commits, so a fast-forward pull is impossible.
+    Diverged,
+    /// Rebasing local commits onto the remote's stopped on conflicts in these files, left for the
+    /// user to resolve.
+    Conflict(Vec<String>),
+    Failed { command: String, message: String },
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GitError::NotInstalled => write!(f, "git is not installed or not on PATH; knot needs it for history and sync"),
+            GitError::NoRemote => write!(f, "no remote configured; run `knot remote` to connect one"),
+            GitError::Auth(msg) => write!(f, "authentication failed: {}", msg),
+            GitError::Identity => write!(f, "git needs user.name and user.email to commit"),
+            GitError::Rejected => write!(f, "remote has newer commits; pull first"),
+            GitError::Diverged => write!(f, "local and remote have diverged; merge them with git"),
+            GitError::Conflict(files) => write!(f, "your changes conflict with the remote in {}; resolve them to finish the sync", files.join(", ")),
+            GitError::Failed { command, message } => write!(f, "git {}: {}", command, message),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+impl GitError {
+    pub fn spawn(err: io::Error, command: &str) -> Self {
+        if err.kind() == io::ErrorKind::NotFound { GitError::NotInstalled } else { GitError::Failed { command: command.into(), message: err.to_string() } }
+    }
+
+    /// Turns the stderr of a failed `git <command>` into the most specific error it matches.
+    pub fn classify(command: &str, stderr: &str) -> Self {
+        let has = |needle: &str| stderr.contains(needle);
+        let key_line = stderr.lines().find(|l| l.starts_with("fatal:") || l.starts_with("error:"))
+            .or_else(|| stderr.lines().rfind(|l| !l.trim().is_empty()))
+            .unwrap_or("").trim().to_string();
+        if has("No configured push destination") || has("does not appear to be a git repository") || has("No such remote") || has("no upstream") || has("no tracking information") {
+            GitError::NoRemote
+        } else if has("Authentication failed") || has("Permission denied") || has("could not read Username") || has("terminal prompts disabled") || has("Host key verification failed") {
+            GitError::Auth(key_line)
+        } else if has("Please tell me who you are") || has("unable to auto-detect email") {
+            GitError::Identity
+        } else if has("[rejected]") || has("non-fast-forward") || has("fetch first") {
+            GitError::Rejected
+        } else if has("Not possible to fast-forward") || has("divergent branches") {
+            GitError::Diverged
+        } else {
+            GitError::Failed { command: command.into(), message: key_line }
+        }
+    }
+}
+
+/// Runs git in `root` and returns stdout.
+pub fn run(root: &Path, args: &[&str]) -> Result<String, GitError> {
+    let command = args.first().copied().unwrap_or("");
+    let out = Command::new("git").args(args).current_dir(root).output().map_err(|e| GitError::spawn(e, command))?;
+    if !out.status.success() {
+        return Err(GitError::classify(command, &String::from_utf8_lossy(&out.stderr)));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Makes `root` a git repository if it isn't one yet.
+pub fn init(root: &Path) -> Result<(), GitError> {
+    if root.join(".git").exists() { return Ok(()); }
+    run(root, &["init", "--quiet"]).map(drop)
+}
+
+pub struct BlameLine {
+    pub hash: String,
+    /// Author time as a unix timestamp; 0 for lines that are not committed yet.
+    pub time: i64,
+    pub summary: String,
+    pub text: String,
+}
+
+impl BlameLine {
+    pub fn is_uncommitted(&self) -> bool { self.hash.chars().all(|c| c == '0') }
+}
+
+pub fn blame(root: &Path, path: &Path) -> Result<Vec<BlameLine>> {
+    let rel = relative(root, path);
+    let out = run(root, &["blame", "--line-porcelain", "--", &rel])?;
+    let mut lines = Vec::new();
+    let (mut hash, mut time, mut summary) = (String::new(), 0, String::new());
+    for line in out.lines() {
+        if let Some(text) = line.strip_prefix('\t') {
+            lines.push(BlameLine { hash: hash.clone(), time, summary: summary.clone(), text: text.to_string() });
+        } else if let Some(t) = line.strip_prefix("author-time ") {
+            time = t.parse().unwrap_or(0);
+        } else if let Some(s) = line.strip_prefix("summary ") {
+            summary = s.to_string();
+        } else if line.len() >= 40 && line[..40].chars().all(|c| c.is_ascii_hexdigit()) {
+            hash = line[..40].to_string();
+        }
+    }
+    Ok(lines)
+}
+
+/// Who last committed each file in the history of the repository at `root`, by path relative
+/// to it.
+pub fn last_authors(root: &Path) -> Result<BTreeMap<String, String>, GitError> {
+    let out = run(root, &["-c", "core.quotePath=false", "log", "--no-renames", "--name-only", "--format=%x1f%an"])?;
+    let mut authors = BTreeMap::new();
+    let mut author = "";
+    for line in out.lines().filter(|l| !l.is_empty()) {
+        match line.strip_prefix('\x1f') {
+            Some(name) => author = name,
+            // Newest first, so the first commit seen for a file is its last.
+            None => { authors.entry(line.to_string()).or_insert_with(|| author.to_string()); }
+        }
+    }
+    Ok(authors)
+}
+
+/// `user.name` from git's config for the repository at `root`, if it is set.
+pub fn user_name(root: &Path) -> Option<String> {
+    run(root, &["config", "user.name"]).ok().map(|n| n.trim().to_string()).filter(|n| !n.is_empty())
+}
+
+pub struct Revision {
+    pub hash: String,
+    pub time: i64,
+    pub summary: String,
+    /// Vault-relative path of the file at this revision (differs from today's across renames).
+    pub path: String,
+}
+
+/// Commits touching `path`, newest first, following renames.
+pub fn file_history(root: &Path, path: &Path) -> Result<Vec<Revision>> {
+    let rel = relative(root, path);
+    let out = run(root, &["log", "--follow", "--name-only", "--format=%x1e%H%x1f%at%x1f%s", "--", &rel])?;
+    Ok(out.split('\x1e').filter_map(|record| {
+        let mut lines = record.lines();
+        let mut fields = lines.next()?.split('\x1f');
+        let (hash, time, summary) = (fields.next()?, fields.next()?, fields.next().unwrap_or(""));
+        let path = lines.find(|l| !l.trim().is_empty()).unwrap_or(&rel).to_string();
+        Some(Revision { hash: hash.to_string(), time: time.parse().unwrap_or(0), summary: summary.to_string(), path })
+    }).collect())
+}
+
+/// Contents of `rel` as of commit `hash`.
+pub fn show_file(root: &Path, hash: &str, rel: &str) -> Result<String> {
+    Ok(run(root, &["show", &format!("{}:{}", hash, rel)])?)
+}
+
+/// Puts `path` back the way it was at `rev` and commits just that file as "Restore <file> to
+/// <hash>", leaving any other uncommitted changes alone. `false` when it already matches.
+pub fn restore(root: &Path, path: &Path, rev: &Revision) -> Result<bool> {
+    let rel = relative(root, path);
+    std::fs::write(path, show_file(root, &rev.hash, &rev.path)?)?;
+    run(root, &["add", "--", &rel])?;
+    if run(root, &["diff", "--cached", "--quiet", "--", &rel]).is_ok() { return Ok(false); }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    run(root, &["commit", "--quiet", "-m", &format!("Restore {} to {}", name, &rev.hash[..7.min(rev.hash.len())]), "--", &rel])?;
+    Ok(true)
+}
+
+/// Uncommitted changes to `path` as a unified diff against HEAD, or `None` when the file isn't
+/// in HEAD at all (new since the last commit, or nothing committed yet).
+pub fn diff_head(root: &Path, path: &Path) -> Result<Option<String>> {
+    let rel = relative(root, path);
+    if run(root, &["cat-file", "-e", &format!("HEAD:{}", rel)]).is_err() { return Ok(None); }
+    Ok(Some(run(root, &["diff", "--no-color", "--no-ext-diff", "HEAD", "--", &rel])?))
+}
+
+/// The last commit touching `path` made more than `days` days ago, as (hash, commit time); `None`
+/// when the note didn't exist yet.
+pub fn commit_before(root: &Path, path: &Path, days: u32) -> Result<Option<(String, i64)>> {
+    if run(root, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() { return Ok(None); }
+    let before = format!("--before={} days ago", days);
+    let out = run(root, &["log", "-1", "--format=%H %ct", &before, "HEAD", "--", &relative(root, path)])?;
+    Ok(out.split_once(' ').map(|(hash, time)| (hash.to_string(), time.trim().parse().unwrap_or(0))))
+}
+
+/// The note's changes since `rev`, uncommitted ones included, as a whole-file word diff in git's
+/// porcelain format: lines of ` ` kept, `-` removed and `+` added words, with `~` ending a line.
+pub fn word_diff(root: &Path, path: &Path, rev: &str) -> Result<String> {
+    Ok(run(root, &["diff", "--no-color", "--no-ext-diff", "--word-diff=porcelain", "--unified=1000000", rev, "--", &relative(root, path)])?)
+}
+
+/// Whether the vault has uncommitted changes or commits its upstream doesn't have yet.
+pub fn has_unsynced(root: &Path) -> bool {
+    // No upstream configured means nothing to compare against, not unsynced work.
+    dirty_count(root).is_ok_and(|n| n > 0) || ahead_behind(root).is_some_and(|(ahead, _)| ahead > 0)
+}
+
+/// Commits every pending change, so a bulk edit that follows can be undone on its own. Returns
+/// false when there was nothing to commit or the vault isn't a repository.
+pub fn checkpoint(root: &Path, message: &str) -> Result<bool, GitError> {
+    if !root.join(".git").exists() || dirty_count(root)? == 0 { return Ok(false); }
+    run(root, &["add", "-A"])?;
+    run(root, &["commit", "--quiet", "-m", message])?;
+    Ok(true)
+}
+
+/// Number of files with uncommitted changes, untracked ones included.
+pub fn dirty_count(root: &Path) -> Result<usize, GitError> {
+    Ok(run(root, &["status", "--porcelain"])?.lines().filter(|l| !l.trim().is_empty()).count())
+}
+
+/// Commits HEAD has that its upstream doesn't, and vice versa; `None` without an upstream.
+pub fn ahead_behind(root: &Path) -> Option<(usize, usize)> {
+    let out = run(root, &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"]).ok()?;
+    let mut counts = out.split_whitespace().map(|n| n.parse().unwrap_or(0));
+    Some((counts.next()?, counts.next()?))
+}
+
+/// Which version of a conflicted file to keep.
+#[derive(Clone, Copy)]
+pub enum Side { Mine, Theirs }
+
+/// Vault-relative files a stopped rebase or merge left with unresolved conflicts.
+pub fn conflicted(root: &Path) -> Vec<String> {
+    run(root, &["diff", "--name-only", "--diff-filter=U"]).unwrap_or_default().lines().map(String::from).collect()
+}
+
+/// Whether the conflicts come from a rebase (sync's own way of bringing in remote commits) rather
+/// than a merge. Matters because a rebase replays *our* commits onto theirs, swapping git's
+/// `--ours` and `--theirs`.
+pub fn rebasing(root: &Path) -> bool {
+    ["rebase-merge", "rebase-apply"].iter().any(|dir| {
+        run(root, &["rev-parse", "--git-path", dir]).is_ok_and(|p| root.join(p.trim()).exists())
+    })
+}
+
+/// Resolves `rel` by taking one side whole. A side that deleted the file deletes it.
+pub fn take_side(root: &Path, rel: &str, side: Side) -> Result<(), GitError> {
+    let flag = match (side, rebasing(root)) {
+        (Side::Mine, false) | (Side::Theirs, true) => "--ours",
+        (Side::Mine, true) | (Side::Theirs, false) => "--theirs",
+    };
+    match run(root, &["checkout", flag, "--", rel]) {
+        Ok(_) => run(root, &["add", "--", rel]).map(drop),
+        // That side has no version of the file.
+        Err(_) => run(root, &["rm", "--quiet", "--", rel]).map(drop),
+    }
+}
+
+/// Marks `rel` resolved if no conflict markers are left in it (after editing it by hand).
+pub fn mark_resolved(root: &Path, rel: &str) -> Result<bool, GitError> {
+    let text = std::fs::read_to_string(root.join(rel)).unwrap_or_default();
+    if text.lines().any(|l| l.starts_with("<<<<<<< ") || l.starts_with(">>>>>>> ") || l == "=======") { return Ok(false); }
+    run(root, &["add", "--", rel]).map(|_| true)
+}
+
+/// Carries on once every conflict is resolved: the next commit of a rebase, or the merge commit.
+/// Returns the conflicts the rebase stopped on next, if any.
+pub fn continue_after_conflicts(root: &Path) -> Result<Vec<String>, GitError> {
+    let args: &[&str] = if rebasing(root) { &["rebase", "--continue"] } else { &["commit", "--no-edit"] };
+    // Keep the commit messages as they are instead of opening an editor over the TUI.
+    let out = Command::new("git").args(args).current_dir(root).env("GIT_EDITOR", "true")
+        .output().map_err(|e| GitError::spawn(e, args[0]))?;
+    let conflicts = conflicted(root);
+    if !out.status.success() && conflicts.is_empty() {
+        return Err(GitError::classify(args[0], &String::from_utf8_lossy(&out.stderr)));
+    }
+    Ok(conflicts)
+}
+
+/// Gives up on the rebase or merge, putting the vault back as it was before it started.
+pub fn abort_after_conflicts(root: &Path) -> Result<(), GitError> {
+    run(root, &[if rebasing(root) { "rebase" } else { "merge" }, "--abort"]).map(drop)
+}
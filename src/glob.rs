@@ -0,0 +1,62 @@
+//! Minimal glob matching for ignore rules and path-based virtual folders.
+//!
+//! Paths are vault-relative and `/`-separated. `*` and `?` stay within one path segment,
+//! `**` spans any number of segments, and `[abc]` / `[a-z]` / `[!x]` match character classes.
+
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = path.chars().collect();
+    match_from(&p, &s)
+}
+
+fn match_from(p: &[char], s: &[char]) -> bool {
+    match p.first() {
+        None => s.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            let mut rest = &p[2..];
+            if rest.first() == Some(&'/') {
+                // `a/**/b` must also match `a/b`, so only segment boundaries are candidates.
+                rest = &rest[1..];
+                (0..=s.len()).any(|i| (i == 0 || s[i - 1] == '/') && match_from(rest, &s[i..]))
+            } else {
+                (0..=s.len()).any(|i| match_from(rest, &s[i..]))
+            }
+        }
+        Some('*') => {
+            for i in 0..=s.len() {
+                if match_from(&p[1..], &s[i..]) { return true; }
+                if i < s.len() && s[i] == '/' { break; }
+            }
+            false
+        }
+        Some('?') => !s.is_empty() && s[0] != '/' && match_from(&p[1..], &s[1..]),
+        Some('[') => match (s.first(), class_end(p)) {
+            (Some(&c), Some(end)) if c != '/' => class_matches(&p[1..end], c) && match_from(&p[end + 1..], &s[1..]),
+            (Some(&c), None) => c == '[' && match_from(&p[1..], &s[1..]),
+            _ => false,
+        },
+        Some(&c) => s.first() == Some(&c) && match_from(&p[1..], &s[1..]),
+    }
+}
+
+fn class_end(p: &[char]) -> Option<usize> {
+    // A `]` right after the opening bracket (or `[!`) is a literal member.
+    let start = if p.get(1) == Some(&'!') { 3 } else { 2 };
+    p.iter().skip(start).position(|c| *c == ']').map(|i| i + start)
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() { Some('!') => (true, &class[1..]), _ => (false, class) };
+    let mut hit = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            hit |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            hit |= class[i] == c;
+            i += 1;
+        }
+    }
+    hit != negated
+}
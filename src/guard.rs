@@ -0,0 +1,117 @@
+//! Guards a note open in the external editor against changes written to it meanwhile by
+//! something else: a `knot sync` from cron, `knot capture`, a file-sync client. A thread polls
+//! the note while the editor runs; once it exits, any version seen on disk that the next one
+//! wasn't written over is reported as a [`Clash`], to be merged instead of silently lost.
+//!
+//! A version counts as written over when the next one is closer to the version before it, as an
+//! editor's save is to what the editor last loaded or saved. The check is by content alone, so it
+//! can't tell whose save came last, and saving a note back exactly as it was also counts.
+
+use anyhow::Result;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use crate::{crypt, git::GitError};
+
+const POLL: Duration = Duration::from_millis(500);
+
+/// A note being watched while it's open in the editor.
+pub struct Guard {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    watcher: JoinHandle<Vec<(String, SystemTime)>>,
+}
+
+/// A version of the note that existed during the edit but was overwritten.
+pub struct Clash {
+    pub path: PathBuf,
+    /// The version the overwritten one was changed from.
+    pub base: String,
+    pub lost: String,
+    /// When the overwritten version was seen.
+    pub seen: SystemTime,
+    /// [`word_diff`] from the note as it is now to the overwritten version.
+    pub diff: Result<String, String>,
+}
+
+/// Starts watching `path`, taking its current content as the starting point.
+pub fn watch(path: &Path) -> Guard {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (file, flag) = (path.to_path_buf(), stop.clone());
+    let watcher = thread::spawn(move || {
+        let mut versions: Vec<(String, SystemTime)> = fs::read_to_string(&file).ok().map(|t| (t, SystemTime::now())).into_iter().collect();
+        while !flag.load(Ordering::Relaxed) {
+            thread::sleep(POLL);
+            // Missing or half-written files show up again on the next poll.
+            let Ok(text) = fs::read_to_string(&file) else { continue };
+            if versions.last().is_none_or(|(last, _)| *last != text) { versions.push((text, SystemTime::now())); }
+        }
+        versions
+    });
+    Guard { path: path.to_path_buf(), stop, watcher }
+}
+
+impl Guard {
+    /// Stops watching once the editor has exited. Returns the latest version that was written
+    /// over.
+    pub fn finish(self) -> Option<Clash> {
+        self.stop.store(true, Ordering::Relaxed);
+        let mut versions = self.watcher.join().ok()?;
+        let current = fs::read_to_string(&self.path).ok()?;
+        if versions.last().is_some_and(|(last, _)| *last != current) { versions.push((current.clone(), SystemTime::now())); }
+        let lost = versions.windows(3).rev().find(|v| span(&v[0].0, &v[2].0) < span(&v[1].0, &v[2].0))?;
+        let diff = word_diff(&current, &lost[1].0).map_err(|e| format!("{:#}", e));
+        Some(Clash { path: self.path, base: lost[0].0.clone(), lost: lost[1].0.clone(), seen: lost[1].1, diff })
+    }
+}
+
+/// How far apart two versions are: the length of the stretch between their common start and end.
+fn span(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let start = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let end = a[start..].iter().rev().zip(b[start..].iter().rev()).take_while(|(x, y)| x == y).count();
+    (a.len() - start - end).max(b.len() - start - end)
+}
+
+/// Three-way merge: `theirs`' changes from `base` applied to `ours`, with conflict markers where
+/// both changed the same lines. Returns the text and whether it has any.
+pub fn merge(ours: &str, base: &str, theirs: &str) -> Result<(String, bool)> {
+    let dir = scratch()?;
+    for (name, text) in [("ours", ours), ("base", base), ("theirs", theirs)] { fs::write(dir.join(name), text)?; }
+    let out = Command::new("git").args(["merge-file", "-p", "-L", "now", "-L", "before", "-L", "overwritten", "ours", "base", "theirs"])
+        .current_dir(&dir).output().map_err(|e| GitError::spawn(e, "merge-file"))?;
+    let _ = fs::remove_dir_all(&dir);
+    // The exit code is the number of conflicts; errors are negative, i.e. above 127.
+    match out.status.code() {
+        Some(n) if (0..128).contains(&n) => Ok((String::from_utf8_lossy(&out.stdout).into_owned(), n > 0)),
+        _ => Err(GitError::classify("merge-file", &String::from_utf8_lossy(&out.stderr)).into()),
+    }
+}
+
+/// `git diff --word-diff=porcelain` from `old` to `new`, whole texts as one hunk.
+fn word_diff(old: &str, new: &str) -> Result<String> {
+    let dir = scratch()?;
+    fs::write(dir.join("old"), old)?;
+    fs::write(dir.join("new"), new)?;
+    let out = Command::new("git").args(["diff", "--no-index", "--no-color", "--no-ext-diff", "--word-diff=porcelain", "--unified=1000000", "old", "new"])
+        .current_dir(&dir).output().map_err(|e| GitError::spawn(e, "diff"))?;
+    let _ = fs::remove_dir_all(&dir);
+    // 1 means the files differ.
+    match out.status.code() {
+        Some(0 | 1) => Ok(String::from_utf8_lossy(&out.stdout).into_owned()),
+        _ => Err(GitError::classify("diff", &String::from_utf8_lossy(&out.stderr)).into()),
+    }
+}
+
+/// A private folder for the texts git compares, since notes can be private.
+fn scratch() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("knot-{}-guard", std::process::id()));
+    crypt::create_private_dir(&dir)?;
+    Ok(dir)
+}
@@ -0,0 +1,288 @@
+//! HTML → markdown for pasted or piped browser copies and read-later snapshots.
+//!
+//! A small tag-stream converter rather than a DOM: it keeps headings, paragraphs, links, images,
+//! emphasis, code, quotes and (nested, numbered) lists, drops scripts, styles and page chrome, and
+//! flattens everything else to its text. Good enough for the kind of HTML people copy.
+
+/// Whether `text` is HTML rather than plain text or markdown: it starts with a tag and closes one.
+pub fn looks_like_html(text: &str) -> bool {
+    let t = text.trim_start().to_ascii_lowercase();
+    t.starts_with("<!doctype html") || t.starts_with("<html")
+        || (t.starts_with('<') && t[1..].starts_with(|c: char| c.is_ascii_alphabetic()) && t.contains("</"))
+}
+
+/// The document's `<title>`, if it has a non-empty one.
+pub fn title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let open_end = start + lower[start..].find('>')? + 1;
+    let close = open_end + lower[open_end..].find("</title>")?;
+    let title = unescape(&collapse(&html[open_end..close]));
+    (!title.is_empty()).then_some(title)
+}
+
+/// The part of a page worth keeping: its `<article>`, else `<main>`, else `<body>`.
+pub fn main_content(html: &str) -> &str {
+    // Indices into `lower` are valid in `html` too: ASCII lowercasing keeps byte offsets.
+    let lower = html.to_ascii_lowercase();
+    ["article", "main", "body"].iter().find_map(|tag| {
+        let start = lower.find(&format!("<{}", tag)).filter(|&s| lower[s + tag.len() + 1..].starts_with(['>', ' ', '\n', '\t']))?;
+        let end = lower.rfind(&format!("</{}>", tag)).filter(|&e| e > start).unwrap_or(lower.len());
+        Some(&html[start..end])
+    }).unwrap_or(html)
+}
+
+/// Converts an HTML fragment or document to markdown.
+pub fn to_markdown(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut md = Writer::default();
+    let mut skip_until: Option<String> = None;
+    let mut pre: Option<String> = None;
+    let mut hrefs: Vec<String> = Vec::new();
+    // One entry per open list: the next number for `<ol>`, `None` for `<ul>`.
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut i = 0;
+    while i < html.len() {
+        let rel = html[i..].find('<').unwrap_or(html.len() - i);
+        let text = &html[i..i + rel];
+        match (&skip_until, &mut pre) {
+            (Some(_), _) => {}
+            (None, Some(code)) => code.push_str(text),
+            (None, None) => md.text(text),
+        }
+        let tag_start = i + rel;
+        if tag_start >= html.len() { break; }
+        if lower[tag_start..].starts_with("<!--") {
+            i = lower[tag_start..].find("-->").map_or(html.len(), |e| tag_start + e + 3);
+            continue;
+        }
+        let Some(tag_len) = html[tag_start..].find('>') else { break };
+        let raw = &html[tag_start + 1..tag_start + tag_len];
+        let tag = &lower[tag_start + 1..tag_start + tag_len];
+        i = tag_start + tag_len + 1;
+        let closing = tag.starts_with('/');
+        let name: String = tag.trim_start_matches('/').chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        if let Some(until) = &skip_until {
+            if closing && &name == until { skip_until = None; }
+            continue;
+        }
+        if let Some(code) = &pre {
+            if closing && name == "pre" {
+                md.block(&format!("```\n{}\n```", unescape(code.trim_matches('\n'))));
+                pre = None;
+            }
+            continue;
+        }
+        match (name.as_str(), closing) {
+            ("script" | "style" | "nav" | "header" | "footer" | "aside" | "form" | "noscript" | "svg" | "head" | "template", false) if !tag.ends_with('/') => {
+                skip_until = Some(name);
+            }
+            ("pre", false) => { md.end_block(); pre = Some(String::new()); }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                md.end_block();
+                md.prefix = format!("{} ", "#".repeat(name[1..].parse().unwrap_or(1)));
+            }
+            ("ul", false) => { md.end_block(); lists.push(None); }
+            ("ol", false) => {
+                md.end_block();
+                lists.push(Some(attr(raw, tag, "start").and_then(|s| s.parse().ok()).unwrap_or(1)));
+            }
+            ("ul" | "ol", true) => { md.end_block(); lists.pop(); if lists.is_empty() { md.gap(); } }
+            ("li", false) => {
+                md.end_block();
+                // Children sit under their parent's text: past `- ` or `1. `.
+                let indent: String = lists[..lists.len().saturating_sub(1)].iter().map(|l| if l.is_some() { "   " } else { "  " }).collect();
+                let marker = match lists.last_mut() {
+                    Some(Some(n)) => { *n += 1; format!("{}. ", *n - 1) }
+                    _ => "- ".to_string(),
+                };
+                md.prefix = format!("{}{}", indent, marker);
+                md.in_list = true;
+            }
+            ("blockquote", false) => { md.end_block(); md.quote += 1; }
+            ("blockquote", true) => { md.end_block(); md.quote = md.quote.saturating_sub(1); }
+            ("p" | "div" | "section" | "tr" | "dt" | "dd" | "figcaption" | "table" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "li", _) => md.end_block(),
+            ("br", _) => md.line_break(),
+            ("hr", _) => md.block("---"),
+            ("strong" | "b", _) => md.inline("**"),
+            ("em" | "i", _) => md.inline("*"),
+            ("code", _) => md.inline("`"),
+            ("del" | "s", _) => md.inline("~~"),
+            ("a", false) => {
+                hrefs.push(attr(raw, tag, "href").unwrap_or_default());
+                md.inline("[");
+            }
+            ("a", true) => {
+                let href = hrefs.pop().unwrap_or_default();
+                if href.is_empty() || href.starts_with("javascript:") { md.unwrap_link(); } else { md.inline(&format!("]({})", href)); }
+            }
+            ("img", _) => {
+                if let Some(src) = attr(raw, tag, "src") {
+                    md.inline(&format!("![{}]({})", attr(raw, tag, "alt").unwrap_or_default(), src));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(code) = pre { md.block(&format!("```\n{}\n```", unescape(code.trim_matches('\n')))); }
+    md.finish()
+}
+
+/// Accumulates markdown one block at a time, collapsing HTML whitespace inside blocks.
+#[derive(Default)]
+struct Writer {
+    out: String,
+    /// Text of the block being built, whitespace not yet collapsed.
+    current: String,
+    /// Heading or list marker for the current block.
+    prefix: String,
+    quote: usize,
+    /// The current block is a list item.
+    in_list: bool,
+    /// The previous block was a list item, so another item follows it without a blank line.
+    last_was_item: bool,
+}
+
+impl Writer {
+    fn text(&mut self, text: &str) { self.current.push_str(text); }
+
+    fn inline(&mut self, markup: &str) { self.current.push_str(markup); }
+
+    /// Drops the `[` of a link without a usable target, keeping its text.
+    fn unwrap_link(&mut self) {
+        if let Some(open) = self.current.rfind('[') { self.current.remove(open); }
+    }
+
+    /// `<br>`: a NUL survives whitespace collapsing and becomes a newline when the block ends.
+    fn line_break(&mut self) { self.current.push('\u{0}'); }
+
+    fn end_block(&mut self) {
+        let text = unescape(&collapse(&self.current)).replace(" \u{0} ", "\n").replace('\u{0}', "\n");
+        let text = text.trim_matches('\n').to_string();
+        self.current.clear();
+        let prefix = std::mem::take(&mut self.prefix);
+        let item = std::mem::take(&mut self.in_list);
+        if text.trim().is_empty() { return; }
+        let quote = "> ".repeat(self.quote);
+        let body: Vec<String> = text.lines().enumerate()
+            .map(|(n, l)| format!("{}{}{}", quote, if n == 0 { prefix.clone() } else { " ".repeat(prefix.len()) }, l.trim()))
+            .collect();
+        if !(item && self.last_was_item) { self.gap(); }
+        self.out.push_str(&body.join("\n"));
+        self.out.push('\n');
+        self.last_was_item = item;
+    }
+
+    fn block(&mut self, text: &str) {
+        self.end_block();
+        self.gap();
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.last_was_item = false;
+    }
+
+    /// Ends a list: the next block is a new paragraph.
+    fn gap(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") { self.out.push('\n'); }
+        self.last_was_item = false;
+    }
+
+    fn finish(mut self) -> String {
+        self.end_block();
+        self.out.trim().to_string()
+    }
+}
+
+/// Local files the HTML tags in `text` point at with `src` or `href`, like `<img src="a.png">` in
+/// a note: without `#anchor` and with `%20` decoded, as [`crate::links::md_destinations`] has them.
+pub fn local_references(text: &str) -> Vec<String> {
+    let lower = text.to_ascii_lowercase();
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(open) = lower[from..].find('<') {
+        let start = from + open + 1;
+        let Some(len) = lower[start..].find('>') else { break };
+        let (raw, tag) = (&text[start..start + len], &lower[start..start + len]);
+        from = start + len;
+        if !tag.starts_with(|c: char| c.is_ascii_alphabetic()) { continue; }
+        for value in ["src", "href"].into_iter().filter_map(|name| attr(raw, tag, name)) {
+            let path = value.split('#').next().unwrap_or_default();
+            if path.is_empty() || path.contains("://") || path.starts_with("mailto:") || path.starts_with("data:") { continue; }
+            found.push(path.replace("%20", " "));
+        }
+    }
+    found
+}
+
+/// An attribute's value from a tag's source (`raw`, original case) found via its lowercase `tag`.
+fn attr(raw: &str, tag: &str, name: &str) -> Option<String> {
+    let mut from = 0;
+    while let Some(pos) = tag[from..].find(name) {
+        let at = from + pos;
+        from = at + name.len();
+        if !tag[..at].ends_with(char::is_whitespace) { continue; }
+        let rest = tag[from..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else { continue };
+        let value_start = tag.len() - rest.trim_start().len();
+        let value = &raw[value_start..];
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => &value[1..value[1..].find(q).map_or(value.len(), |e| e + 1)],
+            _ => &value[..value.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(value.len())],
+        };
+        return Some(unescape(value));
+    }
+    None
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decodes the named entities browsers commonly emit plus every numeric one.
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.char_indices().take(12).find(|&(_, c)| c == ';').map(|(i, _)| i) else { out.push('&'); rest = &rest[1..]; continue };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'), "lt" => Some('<'), "gt" => Some('>'), "quot" => Some('"'), "apos" => Some('\''),
+            "nbsp" => Some(' '), "mdash" => Some('—'), "ndash" => Some('–'), "hellip" => Some('…'),
+            "lsquo" | "rsquo" => Some('\''), "ldquo" | "rdquo" => Some('"'), "copy" => Some('©'), "middot" => Some('·'),
+            _ => entity.strip_prefix('#').and_then(|n| match n.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => n.parse().ok(),
+            }).and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => { out.push(c); rest = &rest[semi + 1..]; }
+            None => { out.push('&'); rest = &rest[1..]; }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Pasted or piped text ready to go into a note: HTML is converted, anything else kept as is.
+pub fn convert_if_html(text: &str) -> String {
+    if looks_like_html(text) { to_markdown(main_content(text)) } else { text.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{local_references, unescape};
+
+    #[test]
+    fn unescape_keeps_non_ascii_after_a_bare_ampersand() {
+        assert_eq!(unescape("&ab 日本語"), "&ab 日本語");
+        assert_eq!(unescape("Tom &amp; 日本語 &#x65E5;"), "Tom & 日本語 日");
+    }
+
+    #[test]
+    fn local_references_skip_urls_and_anchors() {
+        let note = "<img src=\"assets/a%20b.png\" alt=x> <a href='doc.pdf#p2'>d</a> <a href=\"https://x.org\">x</a> 1 < 2";
+        assert_eq!(local_references(note), ["assets/a b.png", "doc.pdf"]);
+    }
+}
@@ -0,0 +1,102 @@
+//! What syncs brought in from other devices and people: the notes that commits from the remote
+//! added or changed, queued in `.knot/incoming` until looked at. `n` lists them, and so does the
+//! end of a sync that brought any, so catching up on a shared vault is a list to work through.
+
+use std::{fs, path::Path};
+
+use crate::{git::{self, GitError}, status, vault};
+
+const FILE: &str = "incoming";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind { Added, Modified, Deleted }
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// Relative to the repository the commits are in until the sync job places it in the vault.
+    pub path: String,
+    pub kind: Kind,
+    /// Author of the latest commit that touched it.
+    pub by: String,
+}
+
+impl Kind {
+    fn code(self) -> char {
+        match self { Kind::Added => 'A', Kind::Modified => 'M', Kind::Deleted => 'D' }
+    }
+
+    fn from_code(c: &str) -> Option<Self> {
+        match c { "A" => Some(Kind::Added), "M" => Some(Kind::Modified), "D" => Some(Kind::Deleted), _ => None }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self { Kind::Added => "✚", Kind::Modified => "✎", Kind::Deleted => "✖" }
+    }
+}
+
+/// The notes commits `from..to` in `repo` touched, newest first, each as it ended up: added when
+/// one of them created it, deleted when the latest removed it.
+pub fn between(repo: &Path, from: &str, to: &str) -> Result<Vec<Change>, GitError> {
+    let range = format!("{}..{}", from, to);
+    let log = git::run(repo, &["-c", "core.quotePath=false", "log", "--no-renames", "--name-status", "--format=%x1f%an", &range])?;
+    let mut changes: Vec<Change> = Vec::new();
+    let mut author = "";
+    for line in log.lines() {
+        if let Some(name) = line.strip_prefix('\u{1f}') { author = name; continue; }
+        let Some((code, path)) = line.split_once('\t') else { continue };
+        let Some(kind) = Kind::from_code(code) else { continue };
+        if !vault::is_note(Path::new(path)) { continue; }
+        match changes.iter_mut().find(|c| c.path == path) {
+            // Older than what's there already: only the note's creation still matters.
+            Some(newer) => if kind == Kind::Added && newer.kind == Kind::Modified { newer.kind = Kind::Added },
+            None => changes.push(Change { path: path.to_string(), kind, by: author.to_string() }),
+        }
+    }
+    Ok(changes)
+}
+
+/// The queue, newest first.
+pub fn load(root: &Path) -> Vec<Change> {
+    fs::read_to_string(root.join(".knot").join(FILE)).unwrap_or_default().lines()
+        .filter_map(|l| {
+            let mut parts = l.splitn(3, '\t');
+            let kind = Kind::from_code(parts.next()?)?;
+            let path = parts.next()?.to_string();
+            Some(Change { path, kind, by: parts.next().unwrap_or_default().to_string() })
+        })
+        .collect()
+}
+
+fn save(root: &Path, queue: &[Change]) -> std::io::Result<()> {
+    let text: String = queue.iter().map(|c| format!("{}\t{}\t{}\n", c.kind.code(), c.path, c.by)).collect();
+    status::write_local(root, FILE, &text)
+}
+
+/// Adds vault-relative `changes` to the front of the queue. A note that was queued as added
+/// stays that way; one deleted since leaves it.
+pub fn record(root: &Path, changes: &[Change]) -> std::io::Result<()> {
+    if changes.is_empty() { return Ok(()); }
+    let mut queue = load(root);
+    let mut fresh = Vec::new();
+    for change in changes {
+        let before = queue.iter().position(|c| c.path == change.path).map(|i| queue.remove(i));
+        if change.kind == Kind::Deleted { continue; }
+        let added = before.is_some_and(|b| b.kind == Kind::Added);
+        fresh.push(Change { kind: if added { Kind::Added } else { change.kind }, ..change.clone() });
+    }
+    fresh.append(&mut queue);
+    save(root, &fresh)
+}
+
+/// Takes vault-relative `path` off the queue, once it has been looked at.
+pub fn dismiss(root: &Path, path: &str) -> std::io::Result<()> {
+    let mut queue = load(root);
+    let len = queue.len();
+    queue.retain(|c| c.path != path);
+    if queue.len() == len { return Ok(()); }
+    save(root, &queue)
+}
+
+pub fn clear(root: &Path) -> std::io::Result<()> {
+    save(root, &[])
+}
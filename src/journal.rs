@@ -0,0 +1,92 @@
+//! Daily journal notes with a rotating prompt.
+//!
+//! Today's note lives at the configured `journal.path` pattern (strftime, default
+//! `Journal/%Y-%m-%d.md`) and is created from `.knot/templates/daily.md` or the built-in template. `{{prompt}}` is filled with one question from `.knot/prompts.txt`, one per line,
+//! chosen by date so every day of the cycle asks the next question. Lines containing `{{prompt}}`
+//! are dropped when there is no prompts file.
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use std::{fmt::Write, fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+use crate::snippets;
+
+pub const DEFAULT_PATH: &str = "Journal/%Y-%m-%d.md";
+pub const TEMPLATE: &str = ".knot/templates/daily.md";
+pub const PROMPTS: &str = ".knot/prompts.txt";
+
+const BUILTIN: &str = "# {{date}}\n\n## {{prompt}}\n\n\n## Notes\n\n";
+
+/// Questions from the prompts file, skipping blanks and `#` comments.
+pub fn prompts(root: &Path) -> Vec<String> {
+    fs::read_to_string(root.join(PROMPTS)).unwrap_or_default().lines()
+        .map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(String::from).collect()
+}
+
+/// The prompt for `date`: consecutive days walk through the list in order.
+pub fn prompt_for(prompts: &[String], date: NaiveDate) -> Option<&str> {
+    if prompts.is_empty() { return None; }
+    Some(&prompts[date.num_days_from_ce() as usize % prompts.len()])
+}
+
+/// Returns today's journal note, creating it (and its folders) from the template first if needed.
+pub fn today(root: &Path, pattern: &str) -> Result<PathBuf> {
+    let date = Local::now().date_naive();
+    let mut rel = String::new();
+    write!(rel, "{}", date.format(pattern)).map_err(|_| anyhow!("invalid journal.path pattern \"{}\"", pattern))?;
+    let path = root.join(rel);
+    if path.exists() { return Ok(path); }
+    let template = fs::read_to_string(root.join(TEMPLATE)).unwrap_or_else(|_| BUILTIN.to_string());
+    let prompts = prompts(root);
+    let body = match prompt_for(&prompts, date) {
+        Some(prompt) => template.replace("{{prompt}}", prompt),
+        None => template.split_inclusive('\n').filter(|l| !l.contains("{{prompt}}")).collect(),
+    };
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    fs::write(&path, snippets::render(&body))?;
+    Ok(path)
+}
+
+/// The prompt a note answers: the first prompts-file question that appears as one of its headings.
+pub fn prompt_in<'a>(text: &str, prompts: &'a [String]) -> Option<&'a str> {
+    text.lines().filter_map(heading).find_map(|h| prompts.iter().find(|p| p.as_str() == h)).map(String::as_str)
+}
+
+/// The lines under the `prompt` heading, up to the next heading.
+pub fn answer(text: &str, prompt: &str) -> Option<String> {
+    let body: Vec<&str> = text.lines().skip_while(|l| heading(l) != Some(prompt)).skip(1)
+        .take_while(|l| heading(l).is_none()).collect();
+    let body = body.join("\n");
+    (!body.trim().is_empty()).then(|| body.trim().to_string())
+}
+
+/// The folder every journal note lives under: the pattern up to its first date field.
+fn journal_dir(root: &Path, pattern: &str) -> PathBuf {
+    let fixed = &pattern[..pattern.find('%').unwrap_or(pattern.len())];
+    root.join(&fixed[..fixed.rfind('/').unwrap_or(0)])
+}
+
+/// Every journal note's answer to `prompt`, newest first, as markdown with one section per day.
+pub fn past_answers(root: &Path, pattern: &str, prompt: &str) -> String {
+    let mut days: Vec<PathBuf> = WalkDir::new(journal_dir(root, pattern)).into_iter().flatten()
+        .map(|e| e.into_path()).filter(|p| p.extension().is_some_and(|e| e == "md")).collect();
+    // Date-named files sort chronologically by name, whatever folders the pattern adds.
+    days.sort_by_key(|p| p.file_name().map(|n| n.to_os_string()));
+    days.reverse();
+    let mut out = format!("# {}\n", prompt);
+    let mut count = 0;
+    for day in days {
+        let Ok(text) = fs::read_to_string(&day) else { continue };
+        if let Some(answer) = answer(&text, prompt) {
+            out.push_str(&format!("\n## {}\n\n{}\n", day.file_stem().unwrap_or_default().to_string_lossy(), answer));
+            count += 1;
+        }
+    }
+    if count == 0 { out.push_str("\nNo answers yet.\n"); }
+    out
+}
+
+fn heading(line: &str) -> Option<&str> {
+    line.starts_with('#').then(|| line.trim_start_matches('#').trim())
+}
@@ -0,0 +1,666 @@
+//! The Normal-mode keys as data. Every [`Action`] has default keys that the `[keys]` config
+//! section can replace; the event loop in `main.rs` dispatches on actions, and the footer's hint
+//! bar lists the bindings below with whatever keys they ended up on.
+//!
+//! A binding can be a sequence of keys, like `gg`, and most motions take a count typed before
+//! them, like `5j`; [`Pending`] keeps track of both while they're being typed.
+//!
+//! ```toml
+//! [keys]
+//! sync = "ctrl-s"
+//! # Arrows only, freeing h/j/k/l.
+//! left = "left"
+//! right = "right"
+//! up = "up"
+//! down = "down"
+//! journal = ["d", "ctrl-j"]
+//! top = ["g g", "home"]
+//! ```
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::{Duration, Instant};
+
+use crate::config::{Table, Value};
+
+/// How much of the keymap the footer shows in Normal mode (`ui.hints`). `?` always expands it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hints { Off, Minimal, Full }
+
+impl Hints {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(Hints::Off),
+            "minimal" => Some(Hints::Minimal),
+            "full" | "all" => Some(Hints::Full),
+            _ => None,
+        }
+    }
+}
+
+/// What a Normal-mode key does. Several depend on where the focus is and what state the browser
+/// is in: `Back` cancels whatever is pending, `Left`/`Right` step through time in the preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Sync,
+    Focus,
+    Left,
+    Right,
+    Up,
+    Down,
+    Top,
+    Bottom,
+    HalfPageUp,
+    HalfPageDown,
+    Back,
+    Open,
+    NewCategory,
+    NewFolder,
+    NewNote,
+    RawPreview,
+    MoreKeys,
+    AgeFilter,
+    Blame,
+    Changes,
+    TimeTravel,
+    WordChanges,
+    Answers,
+    Rename,
+    Share,
+    QuickEdit,
+    Contexts,
+    Journal,
+    Lock,
+    Archive,
+    Unarchive,
+    Merge,
+    Jump,
+    TagFilter,
+    Move,
+    Paste,
+    MoveTo,
+    Mark,
+    MarkRange,
+    MarkAll,
+    Outline,
+    Tag,
+    Search,
+    Append,
+    Delete,
+    Orphans,
+    Trash,
+    ReadingList,
+    Tasks,
+    Images,
+    Audio,
+    History,
+    Agenda,
+    ReadLater,
+    Format,
+    PrevLink,
+    NextLink,
+    Palette,
+    Incoming,
+    AuthorFilter,
+    Themes,
+    Comments,
+}
+
+/// Every action with its name in `[keys]` and its default keys; a sequence is written with
+/// spaces between its keys.
+const ACTIONS: &[(Action, &str, &[&str])] = &[
+    (Action::Quit, "quit", &["q"]),
+    (Action::Sync, "sync", &["S"]),
+    (Action::Focus, "focus", &["tab"]),
+    (Action::Left, "left", &["h", "left"]),
+    (Action::Right, "right", &["l", "right"]),
+    (Action::Up, "up", &["k", "up"]),
+    (Action::Down, "down", &["j", "down"]),
+    (Action::Top, "top", &["g g", "home"]),
+    (Action::Bottom, "bottom", &["G", "end"]),
+    (Action::HalfPageUp, "half_page_up", &["ctrl-u", "pageup"]),
+    (Action::HalfPageDown, "half_page_down", &["ctrl-d", "pagedown"]),
+    (Action::Back, "back", &["esc"]),
+    (Action::Open, "open", &["enter"]),
+    (Action::NewCategory, "new_category", &["C"]),
+    (Action::NewFolder, "new_folder", &["F"]),
+    (Action::NewNote, "new_note", &["N"]),
+    (Action::RawPreview, "raw_preview", &["v"]),
+    (Action::MoreKeys, "more_keys", &["?"]),
+    (Action::AgeFilter, "age_filter", &["f"]),
+    (Action::Blame, "blame", &["b"]),
+    (Action::Changes, "changes", &["g"]),
+    (Action::TimeTravel, "time_travel", &["t"]),
+    (Action::WordChanges, "word_changes", &["W"]),
+    (Action::Answers, "answers", &["R"]),
+    (Action::Rename, "rename", &["r"]),
+    (Action::Share, "share", &["Q"]),
+    (Action::QuickEdit, "quick_edit", &["e"]),
+    (Action::Contexts, "contexts", &["x"]),
+    (Action::Journal, "journal", &["d"]),
+    (Action::Lock, "lock", &["X"]),
+    (Action::Archive, "archive", &["A"]),
+    (Action::Unarchive, "unarchive", &["U"]),
+    (Action::Merge, "merge", &["M"]),
+    (Action::Jump, "jump", &["ctrl-p"]),
+    (Action::TagFilter, "tag_filter", &["ctrl-t"]),
+    (Action::AuthorFilter, "author_filter", &["@"]),
+    (Action::Themes, "themes", &[]),
+    (Action::Comments, "comments", &["K"]),
+    (Action::Move, "move", &["m"]),
+    (Action::Paste, "paste", &["p"]),
+    (Action::MoveTo, "move_to", &["P"]),
+    (Action::Mark, "mark", &["space"]),
+    (Action::MarkRange, "mark_range", &["V"]),
+    (Action::MarkAll, "mark_all", &["*"]),
+    (Action::Outline, "outline", &["o"]),
+    (Action::Tag, "tag", &["#"]),
+    (Action::Search, "search", &["/"]),
+    (Action::Append, "append", &["a"]),
+    (Action::Delete, "delete", &["D"]),
+    (Action::Orphans, "orphans", &["ctrl-g"]),
+    (Action::Trash, "trash", &["T"]),
+    (Action::ReadingList, "reading_list", &["L"]),
+    (Action::Tasks, "tasks", &["w"]),
+    (Action::Images, "images", &["i"]),
+    (Action::Audio, "audio", &["O"]),
+    (Action::History, "history", &["H"]),
+    (Action::Agenda, "agenda", &["c"]),
+    (Action::ReadLater, "read_later", &["u"]),
+    (Action::Format, "format", &["="]),
+    (Action::PrevLink, "prev_link", &["["]),
+    (Action::NextLink, "next_link", &["]"]),
+    (Action::Palette, "palette", &[":", "ctrl-k"]),
+    (Action::Incoming, "incoming", &["n"]),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(a, _, _)| *a == self).map_or("?", |(_, name, _)| name)
+    }
+}
+
+/// A key with the modifiers that matter: Ctrl and Alt, and Shift on keys without a case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    mods: KeyModifiers,
+}
+
+impl Key {
+    /// `S`, `ctrl-s`, `alt-enter`, `shift-tab`, `f5`, `space`, `left`…
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut rest = s.trim();
+        let mut mods = KeyModifiers::NONE;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let (m, len) = match () {
+                _ if lower.starts_with("ctrl-") => (KeyModifiers::CONTROL, 5),
+                _ if lower.starts_with("alt-") => (KeyModifiers::ALT, 4),
+                _ if lower.starts_with("shift-") => (KeyModifiers::SHIFT, 6),
+                _ if lower.starts_with('^') && lower.len() > 1 => (KeyModifiers::CONTROL, 1),
+                _ => break,
+            };
+            mods |= m;
+            rest = &rest[len..];
+        }
+        let lower = rest.to_ascii_lowercase();
+        let code = match lower.as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if mods.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok_and(|n| (1..=24).contains(&n)) => KeyCode::F(f[1..].parse().ok()?),
+            _ => {
+                let mut chars = rest.chars();
+                let c = chars.next().filter(|_| chars.next().is_none())?;
+                // Terminals send Ctrl-S as Ctrl plus a lowercase `s`.
+                KeyCode::Char(match () {
+                    _ if mods.contains(KeyModifiers::SHIFT) => c.to_ascii_uppercase(),
+                    _ if mods.contains(KeyModifiers::CONTROL) => c.to_ascii_lowercase(),
+                    _ => c,
+                })
+            }
+        };
+        Some(Self::new(code, mods))
+    }
+
+    /// Shift is part of a character (`S`), so it only counts on keys that have no case.
+    fn new(code: KeyCode, mods: KeyModifiers) -> Self {
+        let mut mods = mods & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) { mods.remove(KeyModifiers::SHIFT); }
+        Self { code, mods }
+    }
+
+    pub fn of(event: &KeyEvent) -> Self { Self::new(event.code, event.modifiers) }
+
+    /// The key as the footer shows it: `S`, `^S`, `ENTER`, `←`…
+    pub fn label(self) -> String {
+        let base = match self.code {
+            KeyCode::Char(' ') => "SPACE".to_string(),
+            KeyCode::Char(c) if self.mods.contains(KeyModifiers::CONTROL) => c.to_ascii_uppercase().to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "ENTER".into(),
+            KeyCode::Esc => "ESC".into(),
+            KeyCode::Tab => "TAB".into(),
+            KeyCode::BackTab => "S-TAB".into(),
+            KeyCode::Backspace => "BKSP".into(),
+            KeyCode::Delete => "DEL".into(),
+            KeyCode::Insert => "INS".into(),
+            KeyCode::Left => "←".into(),
+            KeyCode::Right => "→".into(),
+            KeyCode::Up => "↑".into(),
+            KeyCode::Down => "↓".into(),
+            KeyCode::Home => "HOME".into(),
+            KeyCode::End => "END".into(),
+            KeyCode::PageUp => "PGUP".into(),
+            KeyCode::PageDown => "PGDN".into(),
+            KeyCode::F(n) => format!("F{}", n),
+            other => format!("{:?}", other),
+        };
+        let mut label = String::new();
+        if self.mods.contains(KeyModifiers::CONTROL) { label.push('^'); }
+        if self.mods.contains(KeyModifiers::ALT) { label.push_str("M-"); }
+        if self.mods.contains(KeyModifiers::SHIFT) { label.push_str("S-"); }
+        label + &base
+    }
+}
+
+/// One or more keys pressed in turn.
+type Sequence = Vec<Key>;
+
+/// `g g` → [g, g]; `None` if any key is unknown.
+fn parse_sequence(s: &str) -> Option<Sequence> {
+    let keys: Option<Sequence> = s.split_whitespace().map(Key::parse).collect();
+    keys.filter(|k| !k.is_empty())
+}
+
+fn sequence_label(keys: &[Key]) -> String {
+    keys.iter().map(|k| k.label()).collect()
+}
+
+/// Which keys trigger which actions, the defaults with `[keys]` applied.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Every action's key sequences, in [`ACTIONS`] order.
+    keys: Vec<(Action, Vec<Sequence>)>,
+    /// Actions the config rebound. One of them wins a sequence it shares with a default binding.
+    rebound: Vec<Action>,
+    /// `[keys]` entries that couldn't be used.
+    errors: Vec<String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = ACTIONS.iter().map(|(action, _, keys)| (*action, keys.iter().filter_map(|k| parse_sequence(k)).collect())).collect();
+        Self { keys, rebound: Vec::new(), errors: Vec::new() }
+    }
+}
+
+impl Keymap {
+    fn bound(&self, keys: &[Key]) -> Vec<Action> {
+        self.keys.iter().filter(|(_, seqs)| seqs.iter().any(|s| s == keys)).map(|(a, _)| *a).collect()
+    }
+
+    /// Of the actions sharing a sequence, the first one the config rebound, else the first.
+    fn winner(&self, actions: &[Action]) -> Option<Action> {
+        actions.iter().find(|a| self.rebound.contains(a)).or(actions.first()).copied()
+    }
+
+    /// The action `keys` complete, and whether they're also the start of a longer sequence.
+    fn lookup(&self, keys: &[Key]) -> (Option<Action>, bool) {
+        let longer = self.keys.iter().flat_map(|(_, seqs)| seqs).any(|s| s.len() > keys.len() && s.starts_with(keys));
+        (self.winner(&self.bound(keys)), longer)
+    }
+
+    /// The first sequence that triggers `action`, as the footer shows it; `None` when it's
+    /// unbound or loses all its sequences to other actions.
+    pub fn label(&self, action: Action) -> Option<String> {
+        let (_, seqs) = self.keys.iter().find(|(a, _)| *a == action)?;
+        seqs.iter().find(|s| self.winner(&self.bound(s)) == Some(action)).map(|s| sequence_label(s))
+    }
+
+    /// [`Keymap::label`] for messages that name the key to press: the action's name when it's
+    /// unbound, so they still point at something.
+    pub fn key(&self, action: Action) -> String {
+        self.label(action).unwrap_or_else(|| action.name().to_string())
+    }
+
+    /// What's wrong with the `[keys]` section: entries that couldn't be used, and sequences bound
+    /// to more than one action, with the one that wins.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = self.errors.clone();
+        let mut seen: Vec<&Sequence> = Vec::new();
+        for (_, seqs) in &self.keys {
+            for seq in seqs {
+                if seen.contains(&seq) { continue; }
+                seen.push(seq);
+                let actions = self.bound(seq);
+                let Some(winner) = self.winner(&actions).filter(|_| actions.len() > 1) else { continue };
+                let names: Vec<&str> = actions.iter().map(|a| a.name()).collect();
+                problems.push(format!("{} is bound to {} ({} wins)", sequence_label(seq), names.join(" and "), winner.name()));
+            }
+        }
+        problems
+    }
+}
+
+/// Applies the `[keys]` section of `t`: each action named there gets exactly the keys given,
+/// one as a string or several as an array; an empty array unbinds it.
+pub fn parse(t: &Table, keymap: &mut Keymap) {
+    for (name, value) in t.section("keys") {
+        let Some(&(action, _, _)) = ACTIONS.iter().find(|(_, n, _)| *n == name) else {
+            keymap.errors.push(format!("unknown action `{}`", name));
+            continue;
+        };
+        let specs = match value {
+            Value::Str(s) => vec![s.clone()],
+            Value::Array(_) => value.as_str_list().unwrap_or_default(),
+            _ => { keymap.errors.push(format!("`{}` takes a key or a list of keys", name)); continue; }
+        };
+        let mut seqs = Vec::new();
+        for spec in specs {
+            match parse_sequence(&spec) {
+                Some(seq) => seqs.push(seq),
+                None => keymap.errors.push(format!("unknown key `{}` for {}", spec, name)),
+            }
+        }
+        if let Some(entry) = keymap.keys.iter_mut().find(|(a, _)| *a == action) { entry.1 = seqs; }
+        if !keymap.rebound.contains(&action) { keymap.rebound.push(action); }
+    }
+}
+
+/// How long a key that both completes a binding and starts a longer one (`g`, `gg`) waits for
+/// the rest before it counts on its own.
+pub const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(600);
+
+/// Counts above this are taken as typos.
+const MAX_COUNT: usize = 9999;
+
+/// Keys typed towards a binding, vim style: a count (`5j`) and the start of a sequence (the
+/// first `g` of `gg`).
+#[derive(Default)]
+pub struct Pending {
+    keys: Sequence,
+    count: Option<usize>,
+    /// When the last key of an unfinished sequence came.
+    at: Option<Instant>,
+}
+
+impl Pending {
+    /// Takes a key press, returning what it completes: usually one action or none, but two when
+    /// it doesn't continue the sequence before it, which then counts on its own. Each action
+    /// comes with the count typed before it, if any.
+    pub fn feed(&mut self, keymap: &Keymap, event: &KeyEvent) -> Vec<(Action, Option<usize>)> {
+        let key = Key::of(event);
+        if key.code == KeyCode::Esc && (!self.keys.is_empty() || self.count.is_some()) {
+            *self = Self::default();
+            return Vec::new();
+        }
+        if let (true, KeyCode::Char(c @ '0'..='9')) = (self.keys.is_empty() && key.mods.is_empty(), key.code) {
+            // 0 only continues a count, and bound digits keep their binding.
+            if (c != '0' || self.count.is_some()) && keymap.lookup(&[key]) == (None, false) {
+                let digit = c as usize - '0' as usize;
+                self.count = Some((self.count.unwrap_or(0) * 10 + digit).min(MAX_COUNT));
+                return Vec::new();
+            }
+        }
+        let mut done = Vec::new();
+        self.keys.push(key);
+        loop {
+            match keymap.lookup(&self.keys) {
+                (_, true) => { self.at = Some(Instant::now()); break; }
+                (Some(action), false) => { done.push((action, self.count.take())); self.keys.clear(); break; }
+                (None, false) if self.keys.len() == 1 => { *self = Self::default(); break; }
+                (None, false) => {
+                    // The last key goes nowhere from here: what came before stands on its own, and
+                    // the last key starts afresh.
+                    let last = self.keys.pop().expect("more than one key");
+                    match keymap.lookup(&self.keys).0 {
+                        Some(action) => done.push((action, self.count.take())),
+                        None => self.count = None,
+                    }
+                    self.keys = vec![last];
+                }
+            }
+        }
+        done
+    }
+
+    /// When the unfinished sequence gives up waiting, for the event loop's timeout.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.at.filter(|_| !self.keys.is_empty()).map(|at| at + SEQUENCE_TIMEOUT)
+    }
+
+    /// After [`Pending::deadline`]: the action the keys typed so far complete on their own.
+    pub fn expire(&mut self, keymap: &Keymap) -> Option<(Action, Option<usize>)> {
+        if self.deadline().is_none_or(|d| Instant::now() < d) { return None; }
+        let action = keymap.lookup(&self.keys).0;
+        let count = self.count.take();
+        *self = Self::default();
+        action.map(|a| (a, count))
+    }
+
+    /// The count and keys typed so far, for the footer.
+    pub fn typed(&self) -> String {
+        let count = self.count.map(|n| n.to_string()).unwrap_or_default();
+        count + &sequence_label(&self.keys)
+    }
+}
+
+/// Where a binding applies: a focused pane, or a state that changes what keys do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Any,
+    Categories,
+    Folders,
+    Notes,
+    /// Notes cut with `m` wait to be pasted.
+    Moving,
+    /// Notes are marked for a batch action.
+    Marked,
+    /// A note waits for `M` on the note to merge it into.
+    Merging,
+    /// The preview shows something other than the note.
+    Preview,
+    /// The preview steps through the note's past: time travel and word changes.
+    Stepping,
+    /// A preview link is selected.
+    Link,
+    /// The Notes pane is filtered by tag or author.
+    Filtered,
+}
+
+impl Scope {
+    /// States that take over the footer: only their keys are hinted at, under this title.
+    fn title(self) -> Option<&'static str> {
+        match self {
+            Scope::Moving => Some("Moving:"),
+            Scope::Marked => Some("Marked:"),
+            Scope::Merging => Some("Merging:"),
+            _ => None,
+        }
+    }
+}
+
+pub struct Binding {
+    /// Hinted at together, their keys joined with `/`.
+    pub actions: &'static [Action],
+    pub text: &'static str,
+    pub scope: Scope,
+    /// Shown with `ui.hints = "minimal"`, not only when expanded.
+    pub essential: bool,
+}
+
+const fn bind(actions: &'static [Action], text: &'static str, scope: Scope, essential: bool) -> Binding {
+    Binding { actions, text, scope, essential }
+}
+
+use Action::*;
+
+/// Every Normal-mode hint, most useful first within each scope.
+pub const NORMAL: &[Binding] = &[
+    bind(&[Paste], "Paste into this folder", Scope::Moving, true),
+    bind(&[MoveTo], "Pick destination", Scope::Moving, true),
+    bind(&[Back], "Cancel", Scope::Moving, true),
+    bind(&[Mark, MarkRange], "Mark", Scope::Marked, true),
+    bind(&[Move], "Move", Scope::Marked, true),
+    bind(&[Tag], "Tag", Scope::Marked, true),
+    bind(&[Delete], "Trash", Scope::Marked, true),
+    bind(&[Back], "Clear", Scope::Marked, true),
+    bind(&[Merge], "Merge into this note", Scope::Merging, true),
+    bind(&[Back], "Cancel", Scope::Merging, true),
+    bind(&[Left, Right], "Older/Newer", Scope::Stepping, true),
+    bind(&[Back], "Back to the note", Scope::Preview, true),
+    bind(&[Open], "Follow link", Scope::Link, true),
+    bind(&[PrevLink, NextLink], "Previous/Next link", Scope::Link, true),
+    bind(&[Back], "Done with links", Scope::Link, true),
+    bind(&[MarkAll], "Mark all", Scope::Filtered, true),
+    bind(&[Back], "Clear filter", Scope::Filtered, true),
+    bind(&[Left, Right], "Switch category", Scope::Categories, true),
+    bind(&[Archive], "Archive category", Scope::Categories, false),
+    bind(&[Rename], "Rename", Scope::Categories, false),
+    bind(&[Down, Up], "Choose folder", Scope::Folders, true),
+    bind(&[Top, Bottom], "First/Last", Scope::Folders, false),
+    bind(&[HalfPageDown, HalfPageUp], "Half page", Scope::Folders, false),
+    bind(&[Rename], "Rename", Scope::Folders, false),
+    bind(&[Open], "Edit", Scope::Notes, true),
+    bind(&[QuickEdit], "Quick edit", Scope::Notes, true),
+    bind(&[Append], "Append", Scope::Notes, true),
+    bind(&[Format], "Format", Scope::Notes, true),
+    bind(&[Outline], "Outline", Scope::Notes, false),
+    bind(&[Comments], "Comments", Scope::Notes, false),
+    bind(&[Rename], "Rename", Scope::Notes, false),
+    bind(&[Move], "Move", Scope::Notes, false),
+    bind(&[Mark, MarkRange], "Mark", Scope::Notes, false),
+    bind(&[MarkAll], "Mark all", Scope::Notes, false),
+    bind(&[Merge], "Merge", Scope::Notes, false),
+    bind(&[History], "History", Scope::Notes, false),
+    bind(&[Blame], "Blame", Scope::Notes, false),
+    bind(&[Changes], "Changes", Scope::Notes, false),
+    bind(&[TimeTravel], "Time travel", Scope::Notes, false),
+    bind(&[WordChanges], "Word changes", Scope::Notes, false),
+    bind(&[Answers], "Past answers", Scope::Notes, false),
+    bind(&[Lock], "Lock", Scope::Notes, false),
+    bind(&[Share], "Share", Scope::Notes, false),
+    bind(&[Top, Bottom], "First/Last", Scope::Notes, false),
+    bind(&[HalfPageDown, HalfPageUp], "Half page", Scope::Notes, false),
+    bind(&[Focus], "Focus", Scope::Any, true),
+    bind(&[Palette], "Commands", Scope::Any, true),
+    bind(&[Sync], "Sync to Cloud", Scope::Any, true),
+    bind(&[NewCategory, NewFolder, NewNote], "New", Scope::Any, true),
+    bind(&[Delete], "Delete", Scope::Any, true),
+    bind(&[Down, Up], "Up/Down", Scope::Any, false),
+    bind(&[Left, Right], "Category", Scope::Any, false),
+    bind(&[Search], "Search", Scope::Any, false),
+    bind(&[Jump], "Jump to note", Scope::Any, false),
+    bind(&[TagFilter], "Filter by tag", Scope::Any, false),
+    bind(&[AuthorFilter], "Filter by author", Scope::Any, false),
+    bind(&[Contexts], "Contexts", Scope::Any, false),
+    bind(&[AgeFilter], "Age filter", Scope::Any, false),
+    bind(&[MoveTo], "Move to…", Scope::Any, false),
+    bind(&[Journal], "Today's journal", Scope::Any, false),
+    bind(&[ReadLater], "Read later", Scope::Any, false),
+    bind(&[ReadingList], "Reading list", Scope::Any, false),
+    bind(&[Tasks], "Tasks", Scope::Any, false),
+    bind(&[Agenda], "Agenda", Scope::Any, false),
+    bind(&[Incoming], "What's new", Scope::Any, false),
+    bind(&[Images], "Images", Scope::Any, false),
+    bind(&[Audio], "Audio", Scope::Any, false),
+    bind(&[RawPreview], "Raw preview", Scope::Any, false),
+    bind(&[PrevLink, NextLink], "Links", Scope::Any, false),
+    bind(&[Trash], "Trash", Scope::Any, false),
+    bind(&[Orphans], "Orphaned attachments", Scope::Any, false),
+    bind(&[Unarchive], "Unarchive", Scope::Any, false),
+    bind(&[Quit], "Quit", Scope::Any, false),
+];
+
+/// The hints for the `active` scopes (the focused pane's and the states that apply, `Any`
+/// implied), in order, as their keys in `keymap` and what they do, with the title of a state
+/// that takes over the footer. Keys are only listed under their first scope, and bindings whose
+/// actions are all unbound not at all.
+pub fn hints(keymap: &Keymap, active: &[Scope], full: bool) -> (Option<&'static str>, Vec<(String, &'static str)>) {
+    let title = active.iter().find_map(|s| s.title());
+    let mut scopes: Vec<Scope> = active.to_vec();
+    // States that take over go first; with everything expanded the rest follows them.
+    scopes.sort_by_key(|s| s.title().is_none());
+    if title.is_some() && !full { scopes.retain(|s| s.title().is_some()); } else { scopes.push(Scope::Any); }
+    let mut out: Vec<(String, &'static str)> = Vec::new();
+    for scope in scopes {
+        for b in NORMAL.iter().filter(|b| b.scope == scope && (full || b.essential)) {
+            let labels: Vec<String> = b.actions.iter().filter_map(|a| keymap.label(*a)).collect();
+            if labels.is_empty() { continue; }
+            let keys = labels.join("/");
+            if !out.iter().any(|(k, _)| *k == keys) { out.push((keys, b.text)); }
+        }
+    }
+    (title, out)
+}
+
+/// What the command palette offers, in order: each action with its description and the pane it
+/// acts on. Those for a note are only offered while one is selected, and run as if the Notes pane
+/// had the focus; likewise for folders and categories.
+pub const PALETTE: &[(Action, &str, Scope)] = &[
+    (NewNote, "New note", Scope::Any),
+    (Sync, "Sync to Cloud", Scope::Any),
+    (Search, "Search all notes", Scope::Any),
+    (Jump, "Jump to note", Scope::Any),
+    (Journal, "Today's journal", Scope::Any),
+    (Incoming, "What's new from the remote", Scope::Any),
+    (Open, "Edit note in the editor", Scope::Notes),
+    (QuickEdit, "Quick edit note", Scope::Notes),
+    (Append, "Append to note", Scope::Notes),
+    (Comments, "Comment on note", Scope::Notes),
+    (Rename, "Rename note", Scope::Notes),
+    (Move, "Move note", Scope::Notes),
+    (Merge, "Merge note into another", Scope::Notes),
+    (Format, "Format note", Scope::Notes),
+    (Outline, "Outline of note", Scope::Notes),
+    (Delete, "Delete note", Scope::Notes),
+    (History, "History of note", Scope::Notes),
+    (Blame, "Blame note", Scope::Notes),
+    (Changes, "Changes since last commit", Scope::Notes),
+    (TimeTravel, "Time travel through note", Scope::Notes),
+    (WordChanges, "Word changes in note", Scope::Notes),
+    (Answers, "Past answers in note", Scope::Notes),
+    (Lock, "Lock or unlock note", Scope::Notes),
+    (Share, "Share note on the network", Scope::Notes),
+    (MarkAll, "Mark all notes", Scope::Notes),
+    (NewFolder, "New folder", Scope::Any),
+    (Rename, "Rename folder", Scope::Folders),
+    (Delete, "Delete folder", Scope::Folders),
+    (NewCategory, "New category", Scope::Any),
+    (Rename, "Rename category", Scope::Categories),
+    (Archive, "Archive category", Scope::Categories),
+    (Unarchive, "Unarchive a category", Scope::Any),
+    (Contexts, "Switch context", Scope::Any),
+    (TagFilter, "Filter by tag", Scope::Any),
+    (AuthorFilter, "Filter by last author", Scope::Any),
+    (AgeFilter, "Change age filter", Scope::Any),
+    (RawPreview, "Toggle raw preview", Scope::Any),
+    (Themes, "Switch color theme", Scope::Any),
+    (MoveTo, "Move marked notes to…", Scope::Any),
+    (ReadLater, "Read later", Scope::Any),
+    (ReadingList, "Reading list", Scope::Any),
+    (Tasks, "Tasks", Scope::Any),
+    (Agenda, "Agenda", Scope::Any),
+    (Images, "Images", Scope::Any),
+    (Audio, "Audio", Scope::Any),
+    (Trash, "Trash", Scope::Any),
+    (Orphans, "Orphaned attachments", Scope::Any),
+    (MoreKeys, "Show more or fewer keys", Scope::Any),
+    (Quit, "Quit", Scope::Any),
+];
@@ -0,0 +1,204 @@
+//! `[[wikilink]]` and relative markdown link helpers: completion and rewriting on rename/move.
+
+use std::path::{Component, Path, PathBuf};
+
+/// The query typed after an unclosed `[[` at the end of `buf`, if the user is writing a link.
+pub fn pending_wikilink(buf: &str) -> Option<&str> {
+    let open = buf.rfind("[[")?;
+    let query = &buf[open + 2..];
+    (!query.contains("]]") && !query.contains('\n')).then_some(query)
+}
+
+/// Replaces the unclosed `[[query` at the end of `buf` with a complete link to `target`.
+pub fn complete_wikilink(buf: &mut String, target: &str) {
+    if let Some(open) = buf.rfind("[[") {
+        buf.truncate(open);
+        buf.push_str(&format!("[[{}]]", target));
+    }
+}
+
+/// The name a note is linked by: its file stem.
+pub fn link_name(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Lexically resolves `.` and `..` components without touching the filesystem.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => { out.pop(); }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Path to `to` as seen from directory `from_dir` (both relative to the same root).
+pub fn relative_path(from_dir: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from_dir.components().collect();
+    let to_parts: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to_parts).take_while(|(a, b)| a == b).count();
+    let mut out = PathBuf::new();
+    for _ in common..from.len() { out.push(".."); }
+    for c in &to_parts[common..] { out.push(c); }
+    out
+}
+
+/// A link as written in a note.
+#[derive(Clone)]
+pub enum Target {
+    /// `[[name]]` or `[[dir/name]]`, without alias or heading.
+    Wiki(String),
+    /// A markdown link destination, which may be a URL.
+    Path(String),
+}
+
+/// The note a link points at, relative to the vault: the existing note it resolves to, or where a
+/// new note for it belongs. `None` for URLs. `note` is the vault-relative note containing the link;
+/// `notes` are the vault's notes, vault-relative.
+pub fn resolve(target: &Target, note: &Path, notes: &[PathBuf]) -> Option<PathBuf> {
+    let note_dir = note.parent().unwrap_or(Path::new(""));
+    match target {
+        Target::Wiki(name) => {
+            let name = name.trim_start_matches('/').trim_end_matches(".md");
+            let found = notes.iter().find(|p| {
+                let noext = p.with_extension("").to_string_lossy().replace('\\', "/");
+                if name.contains('/') { noext.eq_ignore_ascii_case(name) } else { link_name(p).eq_ignore_ascii_case(name) }
+            });
+            // Unresolved links create their note next to the linking one, like Obsidian does.
+            found.cloned().or_else(|| Some(if name.contains('/') { PathBuf::from(format!("{}.md", name)) } else { note_dir.join(format!("{}.md", name)) }))
+        }
+        Target::Path(dest) => {
+            let dest = dest.trim_start_matches('<').trim_end_matches('>');
+            let path = dest[..dest.find('#').unwrap_or(dest.len())].replace("%20", " ");
+            if path.is_empty() || path.contains("://") || path.starts_with("mailto:") { return None; }
+            Some(match path.strip_prefix('/') { Some(abs) => PathBuf::from(abs), None => normalize(&note_dir.join(path)) })
+        }
+    }
+}
+
+/// Template for notes created by following a link to a note that doesn't exist yet.
+pub const STUB_TEMPLATE: &str = ".knot/templates/stub.md";
+
+/// Contents for a new note at `path`: the vault's stub template, or just a title heading.
+/// `{{title}}` is the link name; the snippet date placeholders work too.
+pub fn stub(root: &Path, path: &Path) -> String {
+    let template = std::fs::read_to_string(root.join(STUB_TEMPLATE)).unwrap_or_else(|_| "# {{title}}\n\n".to_string());
+    crate::snippets::render(&template.replace("{{title}}", &link_name(path)))
+}
+
+/// Where a note moved from and to, both vault-relative.
+pub struct Move<'a> { pub old: &'a Path, pub new: &'a Path }
+
+impl Move<'_> {
+    fn stem(p: &Path) -> String { link_name(p) }
+
+    fn matches_wiki(&self, target: &str) -> bool {
+        let target = target.trim().trim_end_matches(".md");
+        let old_noext = self.old.with_extension("").to_string_lossy().replace('\\', "/");
+        target.eq_ignore_ascii_case(&Self::stem(self.old)) || target.eq_ignore_ascii_case(&old_noext)
+    }
+
+    fn wiki_replacement(&self, target: &str) -> String {
+        if target.contains('/') {
+            let new = self.new.with_extension("").to_string_lossy().replace('\\', "/");
+            if target.trim().ends_with(".md") { format!("{}.md", new) } else { new }
+        } else {
+            Self::stem(self.new)
+        }
+    }
+}
+
+/// Rewrites `[[wikilinks]]` and relative `[text](path.md)` links inside a note at vault-relative
+/// `note` that point at `mv.old`, returning the new text and how many links changed.
+pub fn rewrite_links(text: &str, note: &Path, mv: &Move) -> (String, usize) {
+    let note_dir = note.parent().unwrap_or(Path::new(""));
+    let mut count = 0;
+    let text = rewrite_wikilinks(text, &mut count, mv);
+    let text = rewrite_md_links(&text, &mut count, |dest| {
+        let resolved = if let Some(abs) = dest.strip_prefix('/') { PathBuf::from(abs) } else { normalize(&note_dir.join(dest)) };
+        (resolved == mv.old).then(|| relative_path(note_dir, mv.new))
+    });
+    (text, count)
+}
+
+/// Fixes the relative markdown links inside a note that itself moved from `old_dir` to `new_dir`.
+pub fn rebase_own_links(text: &str, old_dir: &Path, new_dir: &Path) -> (String, usize) {
+    let mut count = 0;
+    let text = rewrite_md_links(text, &mut count, |dest| {
+        if dest.starts_with('/') { return None; }
+        let target = normalize(&old_dir.join(dest));
+        let rebased = relative_path(new_dir, &target);
+        (rebased != normalize(Path::new(dest))).then_some(rebased)
+    });
+    (text, count)
+}
+
+/// Targets of every `[[wikilink]]` and `![[embed]]` in `text`, without `|alias` or `#heading`.
+pub fn wiki_targets(text: &str) -> Vec<String> {
+    text.split("[[").skip(1).filter_map(|rest| {
+        let inner = &rest[..rest.find("]]")?];
+        Some(inner[..inner.find(['|', '#']).unwrap_or(inner.len())].trim().to_string())
+    }).collect()
+}
+
+/// Local markdown link and image destinations in `text`, percent-decoded and without `#anchor`.
+pub fn md_destinations(text: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    rewrite_md_links(text, &mut 0, |dest| { found.push(dest.to_string()); None });
+    found
+}
+
+fn rewrite_wikilinks(text: &str, count: &mut usize, mv: &Move) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find("[[") {
+        out.push_str(&rest[..open + 2]);
+        rest = &rest[open + 2..];
+        let Some(close) = rest.find("]]") else { break };
+        let inner = &rest[..close];
+        let split = inner.find(['|', '#']).unwrap_or(inner.len());
+        let (target, suffix) = inner.split_at(split);
+        if mv.matches_wiki(target) {
+            out.push_str(&mv.wiki_replacement(target));
+            out.push_str(suffix);
+            *count += 1;
+        } else {
+            out.push_str(inner);
+        }
+        rest = &rest[close..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Calls `rewrite` with every local link destination (minus `#anchor`, percent-decoded);
+/// a returned path replaces the destination.
+fn rewrite_md_links(text: &str, count: &mut usize, mut rewrite: impl FnMut(&str) -> Option<PathBuf>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("](") {
+        out.push_str(&rest[..pos + 2]);
+        rest = &rest[pos + 2..];
+        let (bracketed, body) = match rest.strip_prefix('<') { Some(b) => (true, b), None => (false, rest) };
+        let end = if bracketed { body.find('>') } else { body.find([')', ' ', '\t', '\n']) };
+        let Some(end) = end else { continue };
+        let dest = &body[..end];
+        let (path_part, anchor) = dest.split_at(dest.find('#').unwrap_or(dest.len()));
+        let is_local = !path_part.is_empty() && !path_part.contains("://") && !path_part.starts_with("mailto:");
+        let encoded = path_part.contains("%20");
+        if let Some(new) = is_local.then(|| rewrite(&path_part.replace("%20", " "))).flatten() {
+            let mut new = new.to_string_lossy().replace('\\', "/");
+            if encoded || (!bracketed && new.contains(' ')) { new = new.replace(' ', "%20"); }
+            if bracketed { out.push('<'); }
+            out.push_str(&new);
+            out.push_str(anchor);
+            *count += 1;
+            rest = &body[end..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
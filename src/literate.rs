@@ -0,0 +1,112 @@
+//! Literate export: the fenced code blocks of one language pulled out of a note as a runnable
+//! script, or as a notebook in jupytext's "percent" format (`# %%` cells, prose as
+//! `# %% [markdown]` cells) that Jupyter opens with the jupytext extension.
+
+use anyhow::{bail, Result};
+
+use crate::frontmatter;
+
+enum Chunk {
+    Prose(String),
+    Code(String),
+}
+
+/// Extension for scripts in `lang`, recorded in the notebook header.
+fn extension(lang: &str) -> &'static str {
+    match canonical(lang).as_str() {
+        "python" => "py", "r" => "R", "julia" => "jl", "bash" => "sh", "javascript" => "js",
+        "typescript" => "ts", "rust" => "rs", "go" => "go", "ruby" => "rb", "sql" => "sql", "lua" => "lua",
+        _ => "txt",
+    }
+}
+
+/// The `lang` code blocks in `note` joined into a script; with `notebook`, a jupytext percent
+/// notebook with the prose between them as markdown cells.
+pub fn export(note: &str, lang: &str, notebook: bool) -> Result<String> {
+    let chunks = split(frontmatter::split(note).1, lang);
+    if !chunks.iter().any(|c| matches!(c, Chunk::Code(_))) { bail!("no ```{} code blocks in this note", lang); }
+    let comment = comment_prefix(lang);
+    if !notebook {
+        let code: Vec<&str> = chunks.iter().filter_map(|c| match c { Chunk::Code(code) => Some(code.trim_end()), Chunk::Prose(_) => None }).collect();
+        return Ok(code.join("\n\n") + "\n");
+    }
+    let Some(comment) = comment else { bail!("don't know how to write comments in {}, which notebooks need", lang) };
+    let mut out = format!("{c} ---\n{c} jupyter:\n{c}   jupytext:\n{c}     text_representation:\n{c}       extension: .{}\n{c}       format_name: percent\n", extension(lang), c = comment);
+    if canonical(lang) == "python" {
+        out.push_str(&format!("{c}   kernelspec:\n{c}     display_name: Python 3\n{c}     language: python\n{c}     name: python3\n", c = comment));
+    }
+    out.push_str(&format!("{} ---\n", comment));
+    for chunk in &chunks {
+        match chunk {
+            Chunk::Prose(text) => {
+                let lines: Vec<String> = text.trim().lines().map(|l| if l.is_empty() { comment.to_string() } else { format!("{} {}", comment, l) }).collect();
+                out.push_str(&format!("\n{} %% [markdown]\n{}\n", comment, lines.join("\n")));
+            }
+            Chunk::Code(code) => out.push_str(&format!("\n{} %%\n{}\n", comment, code.trim_end())),
+        }
+    }
+    Ok(out)
+}
+
+/// Prose and matching code blocks in order; blocks in other languages stay in the prose as they are.
+fn split(body: &str, lang: &str) -> Vec<Chunk> {
+    let want = canonical(lang);
+    let mut chunks = Vec::new();
+    let mut prose = String::new();
+    let mut code: Option<String> = None;
+    // Fence characters and length of the open block, and whether it's one we extract.
+    let mut fence: Option<(String, bool)> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        let marker: String = trimmed.chars().take_while(|&c| c == '`' || c == '~').collect();
+        let is_fence = marker.len() >= 3 && marker.chars().all(|c| c == marker.chars().next().unwrap_or('`'));
+        match &fence {
+            None if is_fence => {
+                let info = trimmed[marker.len()..].trim().split([' ', '{', ',']).next().unwrap_or("").to_string();
+                let extract = !info.is_empty() && canonical(&info) == want;
+                if extract {
+                    if !prose.trim().is_empty() { chunks.push(Chunk::Prose(std::mem::take(&mut prose))); }
+                    prose.clear();
+                    code = Some(String::new());
+                } else {
+                    prose.push_str(line);
+                    prose.push('\n');
+                }
+                fence = Some((marker, extract));
+            }
+            Some((open, extract)) if is_fence && marker.starts_with(open.as_str()) && trimmed[marker.len()..].trim().is_empty() => {
+                if *extract { chunks.push(Chunk::Code(code.take().unwrap_or_default())); } else { prose.push_str(line); prose.push('\n'); }
+                fence = None;
+            }
+            Some((_, true)) => { if let Some(c) = code.as_mut() { c.push_str(line); c.push('\n'); } }
+            _ => { prose.push_str(line); prose.push('\n'); }
+        }
+    }
+    // An unclosed block runs to the end of the note, as markdown renders it.
+    if let Some(c) = code { chunks.push(Chunk::Code(c)); }
+    if !prose.trim().is_empty() { chunks.push(Chunk::Prose(prose)); }
+    chunks
+}
+
+fn canonical(lang: &str) -> String {
+    let lang = lang.to_ascii_lowercase();
+    match lang.as_str() {
+        "py" | "python3" | "ipython" => "python".into(),
+        "sh" | "shell" | "zsh" => "bash".into(),
+        "js" | "node" => "javascript".into(),
+        "ts" => "typescript".into(),
+        "jl" => "julia".into(),
+        "rs" => "rust".into(),
+        "rb" => "ruby".into(),
+        _ => lang,
+    }
+}
+
+fn comment_prefix(lang: &str) -> Option<&'static str> {
+    match canonical(lang).as_str() {
+        "python" | "r" | "julia" | "bash" | "ruby" => Some("#"),
+        "javascript" | "typescript" | "rust" | "go" => Some("//"),
+        "sql" | "lua" => Some("--"),
+        _ => None,
+    }
+}
@@ -0,0 +1,87 @@
+//! Per-note password protection: a locked note is stored as `name.md.locked`, encrypted with its
+//! own passphrase (AES-256 with a PBKDF2-derived key, via the `openssl` CLI), independently of
+//! any vault-wide `encryption` setup.
+//!
+//! The passphrase reaches openssl through its environment, never its arguments, so it doesn't
+//! show in the process list. A marker line ahead of the text tells a wrong passphrase from a
+//! damaged file. Like `.age` notes, locked notes are not indexed.
+//!
+//! With `secrets.keyring` on, a note's passphrase is remembered in the OS keyring once entered.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+pub const EXTENSION: &str = "locked";
+
+const MAGIC: &str = "knot-locked-note\n";
+const PASS_VAR: &str = "KNOT_LOCK_PASSPHRASE";
+
+pub fn is_locked(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == EXTENSION)
+}
+
+/// `Journal/diary.md` for `Journal/diary.md.locked`.
+pub fn plain_name(path: &Path) -> PathBuf {
+    if is_locked(path) { path.with_extension("") } else { path.to_path_buf() }
+}
+
+fn openssl(decrypt: bool, passphrase: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let mut cmd = Command::new("openssl");
+    cmd.args(["enc", "-aes-256-cbc", "-pbkdf2", "-iter", "200000", "-a", "-A", "-pass"]).arg(format!("env:{}", PASS_VAR));
+    if decrypt { cmd.arg("-d"); }
+    let mut child = cmd.env(PASS_VAR, passphrase)
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| if e.kind() == std::io::ErrorKind::NotFound { anyhow::anyhow!("openssl is not installed or not on PATH") } else { e.into() })?;
+    child.stdin.take().context("openssl stdin")?.write_all(input)?;
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        if decrypt { bail!("wrong passphrase"); }
+        bail!("openssl: {}", String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(out.stdout)
+}
+
+/// The keyring account a locked note's passphrase is remembered under.
+pub fn keyring_account(path: &Path) -> String {
+    format!("lock:{}", path.display())
+}
+
+/// The text of the locked note at `path`.
+pub fn read(path: &Path, passphrase: &str) -> Result<String> {
+    let plain = openssl(true, passphrase, fs::read_to_string(path)?.trim().as_bytes())?;
+    let text = String::from_utf8(plain).ok().context("wrong passphrase")?;
+    text.strip_prefix(MAGIC).map(str::to_string).context("wrong passphrase")
+}
+
+/// Encrypts `text` into `path`, through a temporary file so a failure leaves the old one intact.
+pub fn write(path: &Path, passphrase: &str, text: &str) -> Result<()> {
+    let cipher = openssl(false, passphrase, format!("{}{}", MAGIC, text).as_bytes())?;
+    let tmp = path.with_extension("locked.tmp");
+    fs::write(&tmp, [cipher.as_slice(), b"\n"].concat())?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Replaces the plaintext note at `path` with `path.locked`.
+pub fn lock(path: &Path, passphrase: &str) -> Result<PathBuf> {
+    let target = PathBuf::from(format!("{}.{}", path.display(), EXTENSION));
+    if target.exists() { bail!("{} already exists", target.display()); }
+    write(&target, passphrase, &fs::read_to_string(path)?)?;
+    fs::remove_file(path)?;
+    Ok(target)
+}
+
+/// Replaces the locked note at `path` with its plaintext.
+pub fn unlock(path: &Path, passphrase: &str) -> Result<PathBuf> {
+    let target = plain_name(path);
+    if target.exists() { bail!("{} already exists", target.display()); }
+    fs::write(&target, read(path, passphrase)?)?;
+    fs::remove_file(path)?;
+    Ok(target)
+}
@@ -1,318 +1,3395 @@
 use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{
-    backend::CrosstermBackend,
-    prelude::*,
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap, Tabs},
-    Terminal,
-};
-use std::{fs, path::{PathBuf}, process::Command, io::{self, Write}};
+use ratatui::{backend::CrosstermBackend, text::Text, widgets::ListState, Terminal};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, path::{Path, PathBuf}, io, time::{Duration, Instant, SystemTime}};
 use chrono::Local;
 
-#[derive(PartialEq, Clone, Copy)]
-enum Focus { Categories, Subfolders, Files }
+mod adr;
+mod attachments;
+mod audio;
+mod backup;
+mod bundle;
+mod calendar;
+mod catalog;
+mod comments;
+mod completions;
+mod context;
+mod config;
+mod credentials;
+mod crypt;
+mod editor;
+mod eml;
+mod emoji;
+mod expiry;
+mod frontmatter;
+mod gallery;
+mod fuzzy;
+mod git;
+mod glob;
+mod guard;
+mod html;
+mod incoming;
+mod journal;
+mod json;
+mod keymap;
+mod links;
+mod lock;
+mod literate;
+mod markdown;
+mod nested;
+mod notify;
+mod numbering;
+mod opml;
+mod outline;
+mod pdf;
+mod picker;
+mod plaintext;
+mod profile;
+mod project;
+mod qr;
+mod reading;
+mod remote;
+mod render;
+mod search;
+mod secrets;
+mod session;
+mod share;
+mod snippets;
+mod starter;
+mod status;
+mod sync;
+mod tags;
+mod tasks;
+mod template;
+mod textarea;
+mod trash;
+mod tutorial;
+mod ui;
+mod vault;
+
+use attachments::Orphan;
+use config::Config;
+use keymap::Action;
+use picker::{Picker, PickerKind};
+use profile::Profiler;
+use sync::{SyncJob, SyncKind};
+use vault::{Ignore, NoteIndex};
+
+#[derive(Parser)]
+#[command(name = "knot", version, about = "Terminal Markdown note manager")]
+struct Cli {
+    /// Time refreshes, preview renders and git calls; shows a debug HUD and appends to the profile log
+    #[arg(long)]
+    profile: bool,
+    /// Vault folder to use instead of vault.path (also read from KNOT_VAULT)
+    #[arg(long, global = true, value_name = "PATH")]
+    vault: Option<PathBuf>,
+    /// Start in this context from `[contexts.<name>]`
+    #[arg(long, value_name = "NAME")]
+    context: Option<String>,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Move settings between machines
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print vault path, note count, pending changes and last sync, then exit
+    Status {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Keep sync credentials in the OS keyring
+    Credential {
+        #[command(subcommand)]
+        action: CredentialAction,
+    },
+    /// Create a project category pre-filled from the vault's template set (.knot/templates/project/)
+    NewProject {
+        name: String,
+        /// Create it as a folder inside this category instead
+        #[arg(long = "in", value_name = "CATEGORY")]
+        category: Option<String>,
+    },
+    /// List the vault's notes, one vault-relative path per line
+    List {
+        /// Only notes under this folder, relative to the vault
+        folder: Option<String>,
+        /// One JSON object per note, with its modification time, word count and size
+        #[arg(long)]
+        json: bool,
+    },
+    /// Search every note (and PDF) and print the matches as path:line:snippet, for fzf, vim's
+    /// quickfix list and scripts; exits 1 when nothing matches
+    Search {
+        /// Text to find; smart case, like the TUI's search
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Print absolute paths instead of vault-relative ones
+        #[arg(long)]
+        absolute: bool,
+        /// One JSON object per match instead, with the note's modification time and word count
+        #[arg(long)]
+        json: bool,
+        /// Stop after this many matches
+        #[arg(long, default_value_t = 1000)]
+        limit: usize,
+    },
+    /// Print a note
+    Cat {
+        /// Note path relative to the vault (the .md is optional), or a markdown file
+        note: String,
+    },
+    /// Create a note, e.g. `knot new Work/standup`, and print its path
+    New {
+        /// Category/folder path and title, relative to the vault
+        note: String,
+        /// Start from <NAME>.md in the templates folder (.templates/ unless templates.dir is set)
+        #[arg(long, value_name = "NAME")]
+        template: Option<String>,
+    },
+    /// Commit and push every change (or only pull), printing git's progress; for scripts and cron
+    Sync {
+        /// Only fast-forward to the remote
+        #[arg(long)]
+        pull: bool,
+        /// Commit message instead of sync.message
+        #[arg(short, long)]
+        message: Option<String>,
+        /// Print the outcome as a JSON object, failures included
+        #[arg(long)]
+        json: bool,
+    },
+    /// Append text piped on stdin to a note, timestamped; HTML is converted to markdown
+    Capture {
+        /// Note name, e.g. `Inbox` or `Work/meeting` (default: capture.inbox)
+        name: Option<String>,
+        /// Capture this text instead of reading stdin
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Convert files from other tools into notes
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    /// Write notes out in formats other tools read
+    Export {
+        #[command(subcommand)]
+        target: ExportTarget,
+    },
+    /// Add a URL to the read-later queue (the Reading category)
+    ReadLater {
+        url: String,
+        /// Use this title instead of the page's own
+        #[arg(long)]
+        title: Option<String>,
+        /// Also save the page's main text as markdown
+        #[arg(long)]
+        snapshot: bool,
+    },
+    /// Architecture decision records: numbered notes with a status, in the `adr.dir` folder
+    Adr {
+        #[command(subcommand)]
+        action: AdrAction,
+    },
+    /// Encrypt notes with the age identity in encryption.identity, replacing each with name.md.age
+    Encrypt {
+        /// Notes or folders, relative to the vault
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Turn encrypted notes back into plaintext ones
+    Decrypt {
+        /// Notes or folders, relative to the vault
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Pack the whole vault, history included, into one archive in backup.dir and rotate older ones
+    /// out; run it from cron for scheduled backups
+    Backup {
+        /// Encrypt the archive with age: to encryption.identity's recipient, or with a passphrase
+        /// when there's no identity (default: backup.encrypt)
+        #[arg(long)]
+        encrypt: bool,
+        /// Write the archive here instead of backup.dir
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Keep the newest archive of each of this many days (default: backup.keep_daily)
+        #[arg(long, value_name = "N")]
+        keep_daily: Option<usize>,
+        /// Keep the newest archive of each of this many weeks (default: backup.keep_weekly)
+        #[arg(long, value_name = "N")]
+        keep_weekly: Option<usize>,
+    },
+    /// Rebuild a vault from a `knot backup` archive, checking every file against its manifest
+    Restore {
+        archive: PathBuf,
+        /// Folder to restore into, which must be empty or new (default: the configured vault)
+        #[arg(long, value_name = "DIR")]
+        into: Option<PathBuf>,
+    },
+    /// Set up a new vault with categories, templates and settings for a way of taking notes
+    Init {
+        #[arg(long, value_enum, default_value = "blank")]
+        template: starter::Starter,
+    },
+    /// Write an _index.md into each category linking its notes by folder; knot keeps them current
+    Index {
+        /// Only these categories (default: every category)
+        categories: Vec<String>,
+    },
+    /// Learn the keys: lesson notes in a Tutorial category, with prompts that follow along as you press them
+    Tutorial,
+    /// Connect the vault to a git remote: clone an existing vault repository or add a new one
+    Remote {
+        /// Give this category its own repository instead, synced apart from the vault's
+        #[arg(long, value_name = "NAME")]
+        category: Option<String>,
+    },
+    /// One-line sync state for shell prompts and tmux status bars
+    PromptSegment {
+        #[arg(long, value_enum, default_value = "ansi")]
+        style: status::SegmentStyle,
+    },
+    /// Print a completion script for subcommands, flags, notes, folders and categories, e.g.
+    /// `source <(knot completions bash)`
+    Completions {
+        #[arg(value_enum)]
+        shell: completions::Shell,
+    },
+    /// Called by the completion scripts with the words typed so far
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+        words: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    /// Email messages: an .eml file, or a folder searched for them
+    Eml {
+        path: PathBuf,
+        /// Folder to put the notes in, relative to the vault
+        #[arg(long = "in", value_name = "FOLDER", default_value = "Mail")]
+        folder: String,
+    },
+    /// An OPML outline, as one note of nested bullets
+    Opml {
+        file: PathBuf,
+        /// Folder to put the note in, relative to the vault (default: the vault root)
+        #[arg(long = "in", value_name = "FOLDER", default_value = "")]
+        folder: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportTarget {
+    /// A folder's notes and their headings and bullets as an OPML outline
+    Opml {
+        /// Folder relative to the vault (default: the whole vault)
+        #[arg(default_value = "")]
+        folder: String,
+        /// Write here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// A note as wrapped plain text for email, links and footnotes as numbered references
+    Text {
+        /// Note path relative to the vault, or a markdown file
+        note: String,
+        /// Wrap prose at this many columns (0: don't wrap)
+        #[arg(long, default_value_t = 72)]
+        width: usize,
+        /// Write here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// A note's code blocks in one language as a runnable script, or a jupytext notebook
+    Code {
+        /// Note path relative to the vault, or a markdown file
+        note: String,
+        /// Language of the code blocks to extract
+        #[arg(long, default_value = "python")]
+        lang: String,
+        /// Keep the prose as markdown cells, in jupytext's percent format
+        #[arg(long)]
+        notebook: bool,
+        /// Write here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdrAction {
+    /// Create the next numbered record from .knot/templates/adr.md (or a built-in template)
+    New {
+        title: String,
+    },
+    /// List records with their number and status
+    List,
+    /// Mark a record superseded by another, both given by number or file name, and link the two
+    Supersede {
+        old: String,
+        new: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CredentialAction {
+    /// Store a username and token for the vault's HTTP remote and use them for every sync
+    Setup,
+    /// git credential helper protocol, called by git itself
+    #[command(hide = true)]
+    Get,
+    #[command(hide = true)]
+    Store,
+    #[command(hide = true)]
+    Erase,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Write the whole config directory and the vault's templates as one portable file (stdout when no file is given)
+    Export { file: Option<PathBuf> },
+    /// Restore a file written by `export` into the config directory and vault; replaced files are kept as `.bak`
+    Import { file: PathBuf },
+}
+
+/// `knot new`: the CLI counterpart of `N`, with the same numbering and encryption per folder.
+fn new_note(note: &str, template: Option<&str>) -> Result<()> {
+    let config = Config::load()?.with_vault_overrides()?;
+    let root = config.vault_path.clone();
+    let note = note.trim_matches('/').trim_end_matches(".md");
+    let (folder, title) = note.rsplit_once('/').unwrap_or(("", note));
+    if title.trim().is_empty() { anyhow::bail!("the note needs a name"); }
+    let dir = root.join(folder);
+    let stem = match config.numbered_folders.iter().find(|(f, _)| f == folder) {
+        Some((_, digits)) => numbering::stem(&dir, title, *digits),
+        None => title.to_string(),
+    };
+    let encrypted = crypt::encrypts_folder(&config.encryption, folder);
+    let path = dir.join(if encrypted { format!("{}.md.{}", stem, crypt::EXTENSION) } else { format!("{}.md", stem) });
+    if path.exists() { anyhow::bail!("{} already exists", vault::relative(&root, &path)); }
+    let template = match template {
+        Some(name) => {
+            let path = root.join(&config.templates_dir).join(format!("{}.md", name.trim_end_matches(".md")));
+            if !path.is_file() { anyhow::bail!("no template {}", vault::relative(&root, &path)); }
+            Some(path)
+        }
+        None => None,
+    };
+    let category = folder.split('/').next().unwrap_or_default();
+    let body = template::render(template.as_deref(), title, category);
+    fs::create_dir_all(&dir)?;
+    if encrypted {
+        crypt::Crypt::load(&config.encryption, config.keyring)?.context("encryption.identity is not set")?.encrypt(&body, &path)?;
+    } else {
+        fs::write(&path, body)?;
+    }
+    println!("{}", vault::relative(&root, &path));
+    Ok(())
+}
+
+/// `knot sync`: the same steps as `S` in the TUI, waited for. git's output goes to stderr when
+/// it's a terminal, so cron only reports the outcome.
+fn sync_now(pull: bool, message: Option<String>, json: bool) -> Result<()> {
+    let config = Config::load()?.with_vault_overrides()?;
+    let root = config.vault_path.clone();
+    let stamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let job = if pull { SyncJob::pull(root.clone(), &config.sync) } else { SyncJob::push(root.clone(), &config.sync, stamp, message) };
+    let verbose = io::IsTerminal::is_terminal(&io::stderr());
+    let mut job = job.wait(|line| if verbose { eprintln!("{}", line) });
+    let finished = job.finished.take().context("the sync stopped unexpectedly")?;
+    if finished.is_ok() && !pull { status::record_sync(&root, &job.stamp)?; }
+    incoming::record(&root, &job.incoming)?;
+    if json {
+        let conflicts = match &finished { Err(git::GitError::Conflict(files)) => files.clone(), _ => Vec::new() };
+        println!("{}", json::Json::object([
+            ("ok", finished.is_ok().into()),
+            ("action", if pull { "pull" } else { "push" }.into()),
+            ("stamp", (!pull).then(|| job.stamp.clone()).into()),
+            ("integrated", job.integrated.clone().into()),
+            ("incoming", job.incoming.iter().map(|c| c.path.clone()).collect::<Vec<_>>().into()),
+            ("conflicts", conflicts.into()),
+            ("error", finished.as_ref().err().map(|e| e.to_string()).into()),
+            ("category", finished.as_ref().err().and(job.category.clone()).into()),
+        ]));
+        if finished.is_err() { std::process::exit(1); }
+        return Ok(());
+    }
+    if !job.incoming.is_empty() {
+        let n = job.incoming.len();
+        println!("{} note{} new or changed on the remote · `n` in knot lists them", n, if n == 1 { "" } else { "s" });
+    }
+    match finished {
+        Err(git::GitError::Conflict(files)) => {
+            let files: Vec<String> = files.iter().map(|f| job.category.as_ref().map_or(f.clone(), |c| format!("{}/{}", c, f))).collect();
+            anyhow::bail!("sync stopped: {} conflict with the remote; open knot to resolve them", files.join(", "))
+        }
+        Err(e) => match &job.category {
+            Some(category) => anyhow::bail!("{} (syncing {})", e, category),
+            None => Err(e.into()),
+        },
+        Ok(()) if pull => { println!("Vault up to date"); Ok(()) }
+        Ok(()) => {
+            match &job.integrated {
+                Some(summary) => println!("Synced · {}", summary),
+                None => println!("Synced"),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `knot encrypt` / `knot decrypt`: converts the notes at or under each vault-relative path.
+fn convert_notes(paths: &[PathBuf], encrypt: bool) -> Result<()> {
+    let config = Config::load()?.with_vault_overrides()?;
+    let crypt = crypt::Crypt::load(&config.encryption, config.keyring)?.context("set encryption.identity to an age identity file first")?;
+    let root = config.vault_path;
+    for path in paths {
+        let path = root.join(path);
+        if !path.exists() { anyhow::bail!("{} not found", path.display()); }
+        let files: Vec<PathBuf> = walkdir::WalkDir::new(&path).into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .filter(|f| if encrypt { vault::is_note(f) } else { crypt::is_encrypted(f) })
+            .collect();
+        for file in files {
+            let done = if encrypt { crypt.encrypt_note(&file)? } else { crypt.decrypt_note(&file)? };
+            println!("{}", vault::relative(&root, &done));
+        }
+    }
+    Ok(())
+}
+
+/// Runs a non-interactive subcommand instead of the TUI.
+fn run_command(command: Commands) -> Result<()> {
+    let action = match command {
+        Commands::Config { action } => action,
+        Commands::Status { json } => {
+            let status = status::gather(&Config::load()?.with_vault_overrides()?);
+            if json { println!("{}", status.json()); } else { print!("{}", status.human()); }
+            return Ok(());
+        }
+        Commands::Credential { action } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            return match action {
+                CredentialAction::Setup if !config.keyring => anyhow::bail!("secrets.keyring is off, so there's nowhere to keep the credentials"),
+                CredentialAction::Setup => credentials::setup(&config.vault_path),
+                CredentialAction::Get => credentials::helper("get", config.keyring),
+                CredentialAction::Store => credentials::helper("store", config.keyring),
+                CredentialAction::Erase => credentials::helper("erase", config.keyring),
+            };
+        }
+        Commands::NewProject { name, category } => {
+            let root = Config::load()?.with_vault_overrides()?.vault_path;
+            for path in project::scaffold(&root, &name, category.as_deref())? {
+                println!("created {}", vault::relative(&root, &path));
+            }
+            return Ok(());
+        }
+        Commands::List { folder, json } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let root = &config.vault_path;
+            let ignore = Ignore::load(root, &config.index.attachment_dirs, &config.index.ignore);
+            let prefix = folder.map(|f| format!("{}/", f.trim_matches('/'))).filter(|f| f != "/");
+            for note in vault::walk_notes(root, &ignore) {
+                let rel = vault::relative(root, &note);
+                if !prefix.as_ref().is_none_or(|p| rel.starts_with(p.as_str())) { continue; }
+                if json {
+                    println!("{}", json::Json::object([("path", rel.into())].into_iter().chain(note_fields(&note))));
+                } else {
+                    println!("{}", rel);
+                }
+            }
+            return Ok(());
+        }
+        Commands::Index { categories } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let root = &config.vault_path;
+            let ignore = Ignore::load(root, &config.index.attachment_dirs, &config.index.ignore);
+            let notes = vault::walk_notes(root, &ignore);
+            let categories = if categories.is_empty() { catalog::categories(root, &notes) } else { categories };
+            for category in &categories {
+                if !root.join(category).is_dir() { anyhow::bail!("no category {}", category); }
+            }
+            let written = catalog::write(root, &categories, &notes)?;
+            for path in &written { println!("wrote {}", vault::relative(root, path)); }
+            if written.is_empty() { println!("Indexes already up to date"); }
+            return Ok(());
+        }
+        Commands::Search { query, absolute, json, limit } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let root = &config.vault_path;
+            let ignore = Ignore::load(root, &config.index.attachment_dirs, &config.index.ignore);
+            let mut notes = vault::walk_notes(root, &ignore);
+            notes.extend(pdf::walk(root));
+            let hits = search::search(&notes, &query.join(" "), limit);
+            for hit in &hits {
+                let path = if absolute { hit.path.display().to_string() } else { vault::relative(root, &hit.path) };
+                if json {
+                    let fields = [("path", path.into()), ("line", hit.line.into()), ("snippet", hit.snippet.as_str().into())];
+                    println!("{}", json::Json::object(fields.into_iter().chain(note_fields(&hit.path))));
+                } else {
+                    println!("{}:{}:{}", path, hit.line, hit.snippet);
+                }
+            }
+            // Like grep, so scripts can test for a match.
+            if hits.is_empty() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Cat { note } => {
+            print!("{}", read_note_arg(&note)?);
+            return Ok(());
+        }
+        Commands::New { note, template } => return new_note(&note, template.as_deref()),
+        Commands::Sync { pull, message, json } => return sync_now(pull, message, json),
+        Commands::Capture { name, message } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let root = config.vault_path;
+            let input = match message {
+                Some(text) => text,
+                None => {
+                    let mut input = String::new();
+                    io::Read::read_to_string(&mut io::stdin(), &mut input)?;
+                    input
+                }
+            };
+            if input.trim().is_empty() { anyhow::bail!("nothing to capture"); }
+            let mut markdown = html::convert_if_html(&input);
+            if config.unfurl_links { markdown = reading::unfurl(&markdown, config.unfurl_timeout); }
+            let name = name.unwrap_or(config.capture_inbox);
+            let plain = root.join(format!("{}.md", name.trim_end_matches(".md")));
+            let encrypted = PathBuf::from(format!("{}.{}", plain.display(), crypt::EXTENSION));
+            if PathBuf::from(format!("{}.{}", plain.display(), lock::EXTENSION)).exists() { anyhow::bail!("{} is locked; append to it in knot instead", vault::relative(&root, &plain)); }
+            let folder = plain.parent().map(|d| vault::relative(&root, d)).unwrap_or_default();
+            let path = if encrypted.exists() || (!plain.exists() && crypt::encrypts_folder(&config.encryption, &folder)) { encrypted } else { plain };
+            if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+            let crypt = if crypt::is_encrypted(&path) { Some(crypt::Crypt::load(&config.encryption, config.keyring)?.context("encryption.identity is not set")?) } else { None };
+            let mut note = match (&crypt, path.exists()) {
+                (Some(crypt), true) => crypt.decrypt(&path)?,
+                _ => fs::read_to_string(&path).unwrap_or_default(),
+            };
+            if !note.is_empty() { note.push_str(if note.ends_with('\n') { "\n" } else { "\n\n" }); }
+            let markdown = markdown.trim_start_matches('\n').trim_end();
+            if !config.capture_stamp.is_empty() {
+                // A one-liner shares the stamp's line; anything longer goes below it.
+                note.push_str(&format!("**{}**{}", Local::now().format(&config.capture_stamp), if markdown.contains('\n') { "\n\n" } else { " " }));
+            }
+            note.push_str(markdown);
+            note.push('\n');
+            match &crypt {
+                Some(crypt) => crypt.encrypt(&note, &path)?,
+                None => fs::write(&path, note)?,
+            }
+            println!("saved {}", vault::relative(&root, &path));
+            return Ok(());
+        }
+        Commands::Import { source: ImportSource::Eml { path, folder } } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let root = &config.vault_path;
+            let attachments = config.index.attachment_dirs.first().map_or("attachments", String::as_str);
+            for (note, count) in eml::import(&path, &root.join(folder.trim_matches('/')), attachments)? {
+                match count {
+                    0 => println!("imported {}", vault::relative(root, &note)),
+                    n => println!("imported {} ({} attachment{})", vault::relative(root, &note), n, if n == 1 { "" } else { "s" }),
+                }
+            }
+            return Ok(());
+        }
+        Commands::Import { source: ImportSource::Opml { file, folder } } => {
+            let root = Config::load()?.with_vault_overrides()?.vault_path;
+            let note = opml::import(&file, &root.join(folder.trim_matches('/')))?;
+            println!("imported {}", vault::relative(&root, &note));
+            return Ok(());
+        }
+        Commands::Export { target: ExportTarget::Opml { folder, output } } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let ignore = Ignore::load(&config.vault_path, &config.index.attachment_dirs, &config.index.ignore);
+            write_export(output, &opml::export(&config.vault_path, folder.trim_matches('/'), &ignore)?)?;
+            return Ok(());
+        }
+        Commands::Export { target: ExportTarget::Text { note, width, output } } => {
+            let text = read_note_arg(&note)?;
+            write_export(output, &plaintext::render(&text, width))?;
+            return Ok(());
+        }
+        Commands::Export { target: ExportTarget::Code { note, lang, notebook, output } } => {
+            let text = read_note_arg(&note)?;
+            write_export(output, &literate::export(&text, &lang, notebook)?)?;
+            return Ok(());
+        }
+        Commands::ReadLater { url, title, snapshot } => {
+            let root = Config::load()?.with_vault_overrides()?.vault_path;
+            let path = reading::capture(&root, &url, title.as_deref(), snapshot)?;
+            println!("queued {}", vault::relative(&root, &path));
+            return Ok(());
+        }
+        Commands::Adr { action } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let root = config.vault_path;
+            let dir = root.join(&config.adr_dir);
+            match action {
+                AdrAction::New { title } => {
+                    let digits = config.numbered_folders.iter().find(|(folder, _)| *folder == config.adr_dir).map_or(4, |(_, d)| *d);
+                    println!("created {}", vault::relative(&root, &adr::create(&root, &dir, &title, digits)?));
+                }
+                AdrAction::List => {
+                    for record in adr::list(&dir) {
+                        println!("{:>4}  {:<12} {}", record.number, record.status, record.title);
+                    }
+                }
+                AdrAction::Supersede { old, new } => {
+                    let (old, new) = adr::supersede(&dir, &old, &new)?;
+                    println!("{} is superseded by {}", links::link_name(&old), links::link_name(&new));
+                }
+            }
+            return Ok(());
+        }
+        Commands::Encrypt { paths } => return convert_notes(&paths, true),
+        Commands::Decrypt { paths } => return convert_notes(&paths, false),
+        Commands::Backup { encrypt, dir, keep_daily, keep_weekly } => {
+            let config = Config::load()?.with_vault_overrides()?;
+            let encrypt = encrypt || config.backup.encrypt;
+            let crypt = if encrypt { crypt::Crypt::load(&config.encryption, config.keyring)? } else { None };
+            let made = backup::create(
+                &config.vault_path,
+                &dir.unwrap_or(config.backup.dir),
+                encrypt,
+                crypt.as_ref(),
+                keep_daily.unwrap_or(config.backup.keep_daily),
+                keep_weekly.unwrap_or(config.backup.keep_weekly),
+            )?;
+            println!("Backed up {} files to {} ({} KB)", made.files, made.path.display(), made.bytes.div_ceil(1024));
+            for path in &made.rotated { println!("rotated out {}", path.display()); }
+            return Ok(());
+        }
+        Commands::Restore { archive, into } => {
+            let config = Config::load()?;
+            let crypt = if crypt::is_encrypted(&archive) { crypt::Crypt::load(&config.encryption, config.keyring)? } else { None };
+            let into = into.unwrap_or_else(|| config.vault_path.clone());
+            let files = backup::restore(&archive, &into, crypt.as_ref())?;
+            println!("Restored {} files into {}, each checked against the backup's manifest", files, into.display());
+            if into != config.vault_path { println!("Set vault.path = \"{}\" in the config to open it", into.display()); }
+            return Ok(());
+        }
+        Commands::Init { template } => {
+            let config = Config::load()?;
+            let root = config.vault_path;
+            let (written, kept) = starter::init(&root, &config.templates_dir, template)?;
+            for path in &written { println!("created {}", vault::relative(&root, path)); }
+            for path in &kept { println!("kept existing {}", vault::relative(&root, path)); }
+            println!("Vault ready at {}", root.display());
+            return Ok(());
+        }
+        Commands::Tutorial => unreachable!("the tutorial runs in the TUI"),
+        Commands::Remote { category } => {
+            let root = Config::load()?.with_vault_overrides()?.vault_path;
+            return match category {
+                Some(name) => remote::category_wizard(&root, &name),
+                None => remote::wizard(&root),
+            };
+        }
+        Commands::PromptSegment { style } => {
+            println!("{}", status::prompt_segment(&Config::load()?.with_vault_overrides()?.vault_path, style));
+            return Ok(());
+        }
+        Commands::Completions { shell } => {
+            print!("{}", completions::script(shell));
+            return Ok(());
+        }
+        Commands::Complete { words } => {
+            let want = completions::complete(&mut Cli::command(), &words);
+            if let Some(vault) = &want.vault { std::env::set_var(config::VAULT_ENV, vault); }
+            let items = match &want.candidates {
+                completions::Candidates::Files => vec![completions::FILES.to_string()],
+                completions::Candidates::Words(words) => words.clone(),
+                vault_list => {
+                    // A broken config just means nothing to offer.
+                    let Ok(config) = Config::load().and_then(Config::with_vault_overrides) else { return Ok(()) };
+                    let ignore = Ignore::load(&config.vault_path, &config.index.attachment_dirs, &config.index.ignore);
+                    completions::vault_items(&config.vault_path, &ignore, vault_list)
+                }
+            };
+            for item in items.iter().filter(|i| i.starts_with(&want.prefix) || *i == completions::FILES) { println!("{}", item); }
+            return Ok(());
+        }
+    };
+    let dir = Config::path().and_then(|p| p.parent().map(Path::to_path_buf)).context("config dir not found")?;
+    let config = Config::load()?.with_vault_overrides()?;
+    let (vault, templates) = (&config.vault_path, &config.templates_dir);
+    match action {
+        ConfigAction::Export { file: Some(file) } => fs::write(&file, bundle::export(&dir, vault, templates)?)?,
+        ConfigAction::Export { file: None } => print!("{}", bundle::export(&dir, vault, templates)?),
+        ConfigAction::Import { file } => {
+            let text = fs::read_to_string(&file).with_context(|| format!("reading {}", file.display()))?;
+            let written = bundle::import(&dir, vault, templates, &text)?;
+            if written.is_empty() { println!("Config already up to date"); }
+            for p in written { println!("wrote {}", p.display()); }
+        }
+    }
+    Ok(())
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Focus { Categories, Subfolders, Files }
+
+#[derive(PartialEq)]
+enum InputMode { Normal, NewCat, NewFolder, NewNote, Append, Rename, Search, Tag, ReadLater, CommitMessage, Credential, Unlock, AltText, Outline, Gallery, ConfirmDelete, ConfirmGc, ConfirmQuit, Conflicts, EditClash, Share, Edit, Picker, Comment }
+
+/// What the passphrase typed in `Unlock` mode is for.
+enum LockRequest {
+    /// Read a locked note for the rest of the session.
+    Open(PathBuf),
+    /// Lock a note; `first` holds the passphrase while it's typed again to confirm.
+    Lock { path: PathBuf, first: Option<String> },
+    /// Turn a locked note back into plain text.
+    Remove(PathBuf),
+}
+
+/// What the preview pane shows for the selected note.
+#[derive(PartialEq, Clone, Copy)]
+enum PreviewMode { Note, Blame, Diff, TimeTravel, Answers, WordDiff }
+
+/// Notes-list filter on modification time, cycled with `f`.
+#[derive(PartialEq, Clone, Copy)]
+enum AgeFilter { All, Today, Week, Stale }
+
+impl AgeFilter {
+    const ALL: [Self; 4] = [Self::All, Self::Today, Self::Week, Self::Stale];
+
+    fn next(self) -> Self {
+        match self { Self::All => Self::Today, Self::Today => Self::Week, Self::Week => Self::Stale, Self::Stale => Self::All }
+    }
+
+    fn label(self) -> &'static str {
+        match self { Self::All => "", Self::Today => "today", Self::Week => "this week", Self::Stale => "older than 6 months" }
+    }
+
+    /// The filter a context names: `all`, `today`, `week` or `stale`.
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "all" => Some(Self::All),
+            "today" => Some(Self::Today),
+            "week" => Some(Self::Week),
+            "stale" => Some(Self::Stale),
+            _ => None,
+        }
+    }
+
+    fn matches(self, modified: SystemTime) -> bool {
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        match self {
+            Self::All => true,
+            Self::Today => chrono::DateTime::<Local>::from(modified).date_naive() == Local::now().date_naive(),
+            Self::Week => age < Duration::from_secs(7 * 86_400),
+            Self::Stale => age > Duration::from_secs(182 * 86_400),
+        }
+    }
+}
+
+/// Revisions of one note being stepped through in [`PreviewMode::TimeTravel`]; `pos` 0 is the newest.
+struct TimeTravel {
+    path: PathBuf,
+    revisions: Vec<git::Revision>,
+    pos: usize,
+}
+
+/// Rendered preview of the selected note, rebuilt only when the selection, the file's mtime
+/// or the preview mode changes.
+struct PreviewCache {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    mode: PreviewMode,
+    raw: bool,
+    text: Text<'static>,
+    /// Links drawn in `text`, in order, for following with `[` / `]`.
+    links: Vec<links::Target>,
+    /// Review comments on the note, for the margin.
+    comments: Vec<comments::Comment>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> { fs::metadata(path).and_then(|m| m.modified()).ok() }
+
+struct App {
+    vault_root: PathBuf,
+    categories: Vec<String>,
+    subfolders: Vec<String>,
+    files: Vec<PathBuf>,
+    selected_cat: String,
+    selected_sub: Option<String>,
+    sub_state: ListState,
+    file_state: ListState,
+    focus: Focus,
+    input_mode: InputMode,
+    input_buffer: String,
+    /// Highlighted entry in the `[[` link completion list.
+    link_choice: usize,
+    should_quit: bool,
+    last_sync: String,
+    status_msg: Option<String>,
+    config: Config,
+    last_refresh: Instant,
+    profiler: Profiler,
+    /// Reads and writes `.age` notes when `encryption.identity` is set.
+    crypt: Option<crypt::Crypt>,
+    /// Passphrases of the `.locked` notes unlocked this session, by path.
+    passphrases: HashMap<PathBuf, String>,
+    /// Locked notes already looked up in the keyring, so a miss isn't retried every frame.
+    keyring_checked: BTreeSet<PathBuf>,
+    /// What the passphrase typed in `Unlock` mode is for.
+    lock_request: Option<LockRequest>,
+    /// A version of the note just edited that was overwritten during the edit, awaiting a choice
+    /// in `EditClash` mode.
+    clash: Option<guard::Clash>,
+    /// The selected note's QR code while `Share` mode shows it.
+    share: Option<share::Share>,
+    /// Image dropped on the terminal, attached to the selected note once `AltText` is answered.
+    pending_image: Option<PathBuf>,
+    /// The guided tour started by `knot tutorial`, while it runs.
+    tutorial: Option<tutorial::Tutorial>,
+    preview_mode: PreviewMode,
+    /// Show notes as plain text instead of rendered markdown.
+    raw_preview: bool,
+    /// `?` lists every key in the footer, whatever `ui.hints` says.
+    hints_expanded: bool,
+    travel: Option<TimeTravel>,
+    /// How many days back [`PreviewMode::WordDiff`] compares with.
+    diff_days: u32,
+    /// `None` forces the next frame to rebuild the preview.
+    preview: Option<PreviewCache>,
+    /// Set whenever visible state changes; the event loop only redraws dirty frames.
+    dirty: bool,
+    ignore: Ignore,
+    index: NoteIndex,
+    picker: Option<Picker>,
+    /// Categories hidden from the tab strip; listed in `.knot/archived`.
+    archived: BTreeSet<String>,
+    /// Note marked with `M` to be absorbed into the next note `M` is pressed on.
+    merge_source: Option<PathBuf>,
+    /// Notes cut with `m`, moved into the browsed folder by `p` or to a picked one by `P`.
+    pending_move: Vec<PathBuf>,
+    /// Template chosen for the note being named (`None` for a blank note).
+    note_template: Option<PathBuf>,
+    /// Notes selected with Space / `V` for batch delete, move and tag; kept across folders.
+    marked: BTreeSet<PathBuf>,
+    /// First end of a `V` range selection.
+    range_anchor: Option<PathBuf>,
+    age_filter: AgeFilter,
+    sort: context::Sort,
+    layout: context::Layout,
+    /// Name of the context last switched to with `x`.
+    context: Option<String>,
+    /// Notes past their `expires:` date or folder policy, flagged in the Notes pane.
+    expired: BTreeSet<PathBuf>,
+    /// Tag picked with Ctrl-T; the Notes pane then lists every note carrying it, vault-wide.
+    tag_filter: Option<String>,
+    /// Author picked with `@`: the Notes pane keeps only the notes they last committed.
+    author_filter: Option<String>,
+    /// Last committer of each note, from git; built on first use and dropped after a sync.
+    authors: Option<BTreeMap<PathBuf, String>>,
+    /// Preview link selected with `[` / `]`, followed with Enter.
+    link_cursor: Option<usize>,
+    /// Note open in the outliner (`InputMode::Outline`), drawn in place of the preview.
+    outline: Option<outline::Outline>,
+    /// Note open in the built-in editor (`InputMode::Edit`), drawn in place of the preview.
+    textarea: Option<textarea::TextArea>,
+    /// Unreferenced attachments awaiting confirmation in `ConfirmGc`.
+    orphans: Vec<Orphan>,
+    /// Background git sync, while one is running.
+    sync: Option<SyncJob>,
+    /// Quit as soon as the running sync succeeds ("sync and quit").
+    quit_after_sync: bool,
+    /// When the pending automatic sync fires; pushed back by every further change.
+    autosync_at: Option<Instant>,
+    /// When the `sync.interval_mins` timer fires next.
+    periodic_sync_at: Option<Instant>,
+    /// Whether the last sync (automatic or manual) succeeded; `None` before the first one.
+    last_sync_ok: Option<bool>,
+    /// Files a stopped rebase or merge left conflicted, listed on the conflict screen.
+    conflicts: Vec<String>,
+    conflict_cursor: usize,
+    /// The conflicts come from a rebase, which shows the remote's side first.
+    conflicts_rebase: bool,
+    /// The repository they're in: the vault's, or a category's own (see `nested`).
+    conflicts_repo: PathBuf,
+    /// Sync that failed for want of credentials, retried once they're entered in `Credential` mode.
+    auth_retry: Option<SyncKind>,
+    /// Username typed in `Credential` mode, while the token is being asked for.
+    credential_user: Option<String>,
+    /// The search typed in `Search` mode, also behind the `SearchResults` picker, whose items
+    /// are indexed like its hits.
+    search: Option<search::SearchJob>,
+    /// When to start the search for what's typed, once typing pauses.
+    search_due: Option<Instant>,
+    /// A count or the start of a key sequence typed in Normal mode, waiting for the rest.
+    pending: keymap::Pending,
+    /// Rows the Folders and Notes panes showed at the last draw, for half-page jumps.
+    list_rows: usize,
+    /// Tasks behind the open `Tasks` picker, indexed like its items.
+    tasks: Vec<tasks::Task>,
+    /// Images on the `Gallery` contact sheet, the highlighted one, and how many fit across
+    /// (set when drawing, for moving up and down).
+    gallery: Vec<gallery::Image>,
+    gallery_cursor: usize,
+    gallery_cols: usize,
+    /// Entries behind the open `Trash` picker, indexed like its items.
+    trash_entries: Vec<trash::Trashed>,
+    /// Commands behind the rows of the palette, with the pane each acts on.
+    commands: Vec<(Action, Focus)>,
+    /// Notes behind the rows of the what's-new picker, as queued.
+    incoming: Vec<incoming::Change>,
+    /// Notes behind the rows of the read-later picker.
+    reading_items: Vec<PathBuf>,
+    /// Files behind the rows of the audio picker.
+    audio_files: Vec<PathBuf>,
+    /// Events behind the rows of the agenda picker.
+    agenda: Vec<calendar::Event>,
+}
+
+impl App {
+    fn new(config: Config, mut profiler: Profiler) -> Result<Self> {
+        let vault_root = config.vault_path.clone();
+        if !vault_root.exists() { fs::create_dir_all(&vault_root)?; }
+        // Browsing works without git; only sync and the history views need it.
+        let git_status = profiler.time("git", || git::init(&vault_root)).err().map(|e| format!("⚠ {}", e));
+
+        let ignore = Ignore::load(&vault_root, &config.index.attachment_dirs, &config.index.ignore);
+        let periodic_sync_at = config.sync.interval.map(|i| Instant::now() + i);
+        let mut app = Self {
+            vault_root,
+            categories: Vec::new(),
+            subfolders: Vec::new(),
+            files: Vec::new(),
+            selected_cat: "[Root]".to_string(),
+            selected_sub: None,
+            sub_state: ListState::default(),
+            file_state: ListState::default(),
+            focus: Focus::Categories,
+            input_mode: InputMode::Normal,
+            input_buffer: String::new(),
+            link_choice: 0,
+            should_quit: false,
+            last_sync: status::last_sync(&config.vault_path).unwrap_or_else(|| "Manual".into()),
+            status_msg: git_status,
+            config,
+            last_refresh: Instant::now(),
+            profiler,
+            crypt: None,
+            passphrases: HashMap::new(),
+            keyring_checked: BTreeSet::new(),
+            lock_request: None,
+            clash: None,
+            share: None,
+            pending_image: None,
+            tutorial: None,
+            preview_mode: PreviewMode::Note,
+            raw_preview: false,
+            hints_expanded: false,
+            travel: None,
+            diff_days: 1,
+            preview: None,
+            dirty: true,
+            ignore,
+            index: NoteIndex::default(),
+            picker: None,
+            archived: BTreeSet::new(),
+            merge_source: None,
+            pending_move: Vec::new(),
+            note_template: None,
+            marked: BTreeSet::new(),
+            range_anchor: None,
+            age_filter: AgeFilter::All,
+            sort: context::Sort::default(),
+            layout: context::Layout::default(),
+            context: None,
+            tag_filter: None,
+            author_filter: None,
+            authors: None,
+            expired: BTreeSet::new(),
+            link_cursor: None,
+            outline: None,
+            textarea: None,
+            orphans: Vec::new(),
+            sync: None,
+            quit_after_sync: false,
+            autosync_at: None,
+            periodic_sync_at,
+            last_sync_ok: None,
+            conflicts: Vec::new(),
+            conflict_cursor: 0,
+            conflicts_rebase: false,
+            conflicts_repo: PathBuf::new(),
+            auth_retry: None,
+            credential_user: None,
+            search: None,
+            search_due: None,
+            pending: keymap::Pending::default(),
+            list_rows: 0,
+            trash_entries: Vec::new(),
+            commands: Vec::new(),
+            incoming: Vec::new(),
+            tasks: Vec::new(),
+            gallery: Vec::new(),
+            gallery_cursor: 0,
+            gallery_cols: 1,
+            reading_items: Vec::new(),
+            audio_files: Vec::new(),
+            agenda: Vec::new(),
+        };
+        app.archived = fs::read_to_string(app.state_dir().join("archived"))
+            .map(|s| s.lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+        if let Some(age) = app.config.trash_retention {
+            let purged = trash::purge_older_than(&app.vault_root, age);
+            if purged > 0 { app.status_msg = Some(format!("Purged {} old trash entries", purged)); }
+        }
+        app.expiry_pass()?;
+        app.index_pass();
+        app.restore_session()?;
+        // A sync (or git outside knot) may have stopped on conflicts last time.
+        let repos = std::iter::once(app.vault_root.clone())
+            .chain(nested::categories(&app.vault_root, &app.config.sync.categories).into_iter().filter(|n| n.is_repo()).map(|n| n.dir));
+        let conflicts = repos.map(|repo| { let files = git::conflicted(&repo); (repo, files) }).find(|(_, files)| !files.is_empty());
+        if let Some((repo, files)) = conflicts {
+            app.show_conflicts(repo, files);
+        } else if app.config.sync.pull_on_start {
+            app.sync = Some(SyncJob::pull(app.vault_root.clone(), &app.config.sync));
+        }
+        Ok(app)
+    }
+
+    /// Puts the browser back where the last session in this vault left it. Anything that no longer
+    /// exists falls back to the defaults.
+    fn restore_session(&mut self) -> Result<()> {
+        let session = session::load(&self.vault_root);
+        if let Some(cat) = session.get("category") { self.selected_cat = cat.clone(); }
+        self.selected_sub = session.get("folder").cloned();
+        self.tag_filter = session.get("tag").cloned();
+        self.author_filter = session.get("author").cloned();
+        // Only a theme switched to differs from the config's, which keeps its own adjustments.
+        if let Some(theme) = session.get("theme").filter(|t| **t != self.config.theme.name).and_then(|t| config::Theme::named(t)) { self.config.theme = theme; }
+        self.age_filter = AgeFilter::ALL.into_iter().find(|f| session.get("age").is_some_and(|a| a == f.label())).unwrap_or(AgeFilter::All);
+        self.raw_preview = session.get("raw").is_some_and(|r| r == "true");
+        self.context = session.get("context").cloned();
+        self.sort = session.get("sort").and_then(|s| context::Sort::parse(s)).unwrap_or_default();
+        self.layout = session.get("layout").and_then(|l| context::Layout::parse(l)).unwrap_or_default();
+        self.focus = match session.get("focus").map(String::as_str) {
+            Some("folders") => Focus::Subfolders,
+            Some("notes") => Focus::Files,
+            _ => Focus::Categories,
+        };
+        self.hard_refresh()?;
+        if let Some(i) = session.get("note").and_then(|n| self.files.iter().position(|p| vault::relative(&self.vault_root, p) == *n)) {
+            self.file_state.select(Some(i));
+        }
+        Ok(())
+    }
+
+    fn save_session(&self) -> std::io::Result<()> {
+        let mut session = BTreeMap::new();
+        session.insert("category", self.selected_cat.clone());
+        session.insert("folder", self.selected_sub.clone().unwrap_or_default());
+        session.insert("note", self.selected_file().map(|p| vault::relative(&self.vault_root, p)).unwrap_or_default());
+        session.insert("focus", match self.focus { Focus::Categories => "categories", Focus::Subfolders => "folders", Focus::Files => "notes" }.into());
+        session.insert("age", self.age_filter.label().into());
+        session.insert("tag", self.tag_filter.clone().unwrap_or_default());
+        session.insert("author", self.author_filter.clone().unwrap_or_default());
+        session.insert("theme", self.config.theme.name.clone());
+        session.insert("raw", self.raw_preview.to_string());
+        session.insert("context", self.context.clone().unwrap_or_default());
+        session.insert("sort", self.sort.name().into());
+        session.insert("layout", self.layout.name().into());
+        session::save(&self.vault_root, &session)
+    }
+
+    /// Startup maintenance: flags expired notes, or with `expiry.auto_archive` moves them into the
+    /// archive folder (repointing links like any other move).
+    fn expiry_pass(&mut self) -> Result<()> {
+        let started = Instant::now();
+        let (root, notes) = (self.vault_root.clone(), self.notes().to_vec());
+        let expired = expiry::expired(&root, &notes, &self.config.expiry);
+        self.profiler.record("expiry", started.elapsed());
+        if expired.is_empty() { return Ok(()); }
+        if !self.config.expiry.auto_archive {
+            self.status_msg = Some(format!("{} notes have expired (⌛)", expired.len()));
+            self.expired = expired.into_iter().collect();
+            return Ok(());
+        }
+        let mut archived = 0;
+        for note in expired {
+            let dest = expiry::archive_path(&root, &note, &self.config.expiry);
+            if self.move_note(&note, &dest).is_ok() { archived += 1; } else { self.expired.insert(note); }
+        }
+        self.status_msg = Some(format!("Archived {} expired notes to {}", archived, self.config.expiry.archive_dir));
+        Ok(())
+    }
+
+    /// Startup maintenance: regenerates the category `_index.md` notes that `knot index` created.
+    fn index_pass(&mut self) {
+        let started = Instant::now();
+        let (root, notes) = (self.vault_root.clone(), self.notes().to_vec());
+        match catalog::refresh(&root, &notes) {
+            Ok(written) if written.is_empty() => {}
+            Ok(written) => { self.index.invalidate(); self.status_msg.get_or_insert(format!("Updated {} category indexes", written.len())); }
+            Err(e) => self.status_msg = Some(format!("⚠ Category indexes: {}", e)),
+        }
+        self.profiler.record("catalog", started.elapsed());
+    }
+
+    /// Real subfolders of the category followed by the configured virtual folders.
+    fn folder_count(&self) -> usize { self.subfolders.len() + self.config.virtual_folders.len() }
+
+    fn folder_name(&self, i: usize) -> &str {
+        match self.subfolders.get(i) {
+            Some(name) => name,
+            None => &self.config.virtual_folders[i - self.subfolders.len()].0,
+        }
+    }
+
+    /// The selected Folders entry if it is a virtual (glob) folder, as (name, glob).
+    fn selected_virtual(&self) -> Option<&(String, String)> {
+        self.sub_state.selected().and_then(|i| i.checked_sub(self.subfolders.len())).and_then(|i| self.config.virtual_folders.get(i))
+    }
+
+    /// The selected subfolder name, unless a virtual folder is selected.
+    fn selected_real_sub(&self) -> Option<&String> {
+        self.selected_sub.as_ref().filter(|_| self.selected_virtual().is_none())
+    }
+
+    /// The directory the Folders pane is showing: the category plus the selected real subfolder.
+    fn browsed_dir(&self) -> PathBuf {
+        let mut dir = if self.selected_cat == "[Root]" { self.vault_root.clone() } else { self.vault_root.join(&self.selected_cat) };
+        if let Some(sub) = self.selected_real_sub() { dir.push(sub); }
+        dir
+    }
+
+    /// Bracketed paste: HTML (a browser copy) is converted to markdown first. In the browser it
+    /// becomes a new note in the current folder, named after its first heading; in a text prompt it
+    /// is typed into the prompt. The path of an image (a file dropped on the terminal) is attached
+    /// to the selected note instead.
+    fn paste(&mut self, text: &str) -> Result<()> {
+        if self.input_mode == InputMode::Normal {
+            if let Some(image) = attachments::pasted_file(text).filter(|p| gallery::is_image(p)) {
+                if !self.selected_file().is_some_and(|p| vault::is_markdown(p)) {
+                    self.status_msg = Some("Select a note to attach the image to".into());
+                    return Ok(());
+                }
+                self.pending_image = Some(image);
+                self.input_buffer.clear();
+                self.input_mode = InputMode::AltText;
+                return Ok(());
+            }
+        }
+        let mut markdown = html::convert_if_html(text);
+        if self.config.unfurl_links && matches!(self.input_mode, InputMode::Normal | InputMode::Append | InputMode::Edit) {
+            markdown = self.profiler.time("unfurl", || reading::unfurl(&markdown, self.config.unfurl_timeout));
+        }
+        let one_line = markdown.split_whitespace().collect::<Vec<_>>().join(" ");
+        match self.input_mode {
+            InputMode::Normal => {
+                let heading = markdown.lines().find_map(|l| l.strip_prefix("# ")).map(|h| h.replace(['/', '\\', ':'], "-").trim().to_string());
+                let name = heading.filter(|h| !h.is_empty()).unwrap_or_else(|| format!("Pasted {}", Local::now().format("%Y-%m-%d %H%M")));
+                let dir = self.browsed_dir();
+                let ext = if crypt::encrypts_folder(&self.config.encryption, &vault::relative(&self.vault_root, &dir)) { format!("md.{}", crypt::EXTENSION) } else { "md".to_string() };
+                let mut path = dir.join(format!("{}.{}", name, ext));
+                for n in 2.. {
+                    if !path.exists() { break; }
+                    path = dir.join(format!("{} {}.{}", name, n, ext));
+                }
+                if let Err(e) = self.write_note(&path, &(markdown.trim_end().to_string() + "\n")) {
+                    self.status_msg = Some(format!("❌ Not pasted: {:#}", e));
+                    return Ok(());
+                }
+                self.status_msg = Some(format!("Pasted into {}", vault::relative(&self.vault_root, &path)));
+                self.vault_changed();
+                self.reveal(&path)
+            }
+            InputMode::Picker => {
+                if let Some(p) = self.picker.as_mut() { one_line.chars().for_each(|c| p.push(c)); }
+                Ok(())
+            }
+            InputMode::Append => { self.input_buffer.push_str(markdown.trim_end()); Ok(()) }
+            InputMode::Edit => { if let Some(t) = self.textarea.as_mut() { t.insert(&markdown); } Ok(()) }
+            InputMode::Credential | InputMode::Unlock => { self.input_buffer.push_str(text.trim()); Ok(()) }
+            InputMode::Outline | InputMode::ConfirmDelete | InputMode::ConfirmGc | InputMode::ConfirmQuit | InputMode::EditClash | InputMode::Share => Ok(()),
+            _ => { self.input_buffer.push_str(&one_line); Ok(()) }
+        }
+    }
+
+    /// Vault-level knot state (archived categories, per-vault settings), synced along with the notes.
+    fn state_dir(&self) -> PathBuf { self.vault_root.join(".knot") }
+
+    fn save_archived(&mut self) -> Result<()> {
+        fs::create_dir_all(self.state_dir())?;
+        let list: Vec<&str> = self.archived.iter().map(String::as_str).collect();
+        fs::write(self.state_dir().join("archived"), list.join("\n") + "\n")?;
+        self.vault_changed();
+        Ok(())
+    }
+
+    /// Hides the selected category from the tab strip. Its notes stay on disk and in search.
+    fn archive_selected_category(&mut self) -> Result<()> {
+        if self.selected_cat == "[Root]" { return Ok(()); }
+        let name = std::mem::replace(&mut self.selected_cat, "[Root]".to_string());
+        self.archived.insert(name.clone());
+        self.save_archived()?;
+        self.status_msg = Some(format!("Archived {} · [U] to restore", name));
+        self.hard_refresh()
+    }
+
+    /// Runs the action of the picker that was just confirmed on `items[idx]`.
+    fn pick(&mut self, picker: Picker, idx: usize) -> Result<()> {
+        let item = picker.items[idx].clone();
+        match picker.kind {
+            PickerKind::Unarchive => {
+                self.archived.remove(&item);
+                self.save_archived()?;
+                self.status_msg = Some(format!("Restored {}", item));
+                self.selected_cat = item;
+                self.selected_sub = None;
+            }
+            PickerKind::Incoming => {
+                let _ = incoming::dismiss(&self.vault_root, &self.incoming[idx].path);
+                if let Some((path, _)) = self.picked_note(&picker, idx) { return self.reveal(&path); }
+            }
+            PickerKind::SearchResults | PickerKind::QuickSwitch | PickerKind::Reading | PickerKind::Tasks => {
+                if let Some((path, _)) = self.picked_note(&picker, idx) { return self.reveal(&path); }
+            }
+            PickerKind::Trash => {
+                let entry = self.trash_entries.swap_remove(idx);
+                match trash::restore(&self.vault_root, &entry) {
+                    Ok(path) => {
+                        self.status_msg = Some(format!("Restored {}", entry.original.display()));
+                        self.vault_changed();
+                        if path.is_file() { return self.reveal(&path); }
+                    }
+                    Err(e) => self.status_msg = Some(format!("Restore failed: {}", e)),
+                }
+            }
+            PickerKind::Tags => {
+                self.tag_filter = self.tags().keys().nth(idx).cloned();
+                self.focus = Focus::Files;
+                self.file_state.select(Some(0));
+            }
+            PickerKind::Themes => {
+                if let Some(theme) = config::Theme::named(config::THEMES[idx]) {
+                    self.status_msg = Some(format!("Theme: {}", theme.name));
+                    self.config.theme = theme;
+                }
+                return Ok(());
+            }
+            PickerKind::Comments if idx == 0 => {
+                self.input_mode = InputMode::Comment;
+                self.input_buffer.clear();
+                return Ok(());
+            }
+            PickerKind::Comments => {
+                let Some(note) = self.selected_file().cloned() else { return Ok(()) };
+                self.status_msg = Some(match comments::toggle(&note, idx - 1) {
+                    Ok(true) => "Comment resolved".into(),
+                    Ok(false) => "Comment reopened".into(),
+                    Err(e) => format!("Update failed: {}", e),
+                });
+                self.preview = None;
+                self.vault_changed();
+                self.open_comments();
+                if let Some(p) = self.picker.as_mut() { p.cursor = idx; }
+                return Ok(());
+            }
+            PickerKind::Authors => {
+                self.author_filter = self.author_counts().into_iter().nth(idx).map(|(name, _)| name);
+                self.focus = Focus::Files;
+                self.file_state.select(Some(0));
+            }
+            PickerKind::Agenda => {
+                let event = self.agenda[idx].clone();
+                return self.meeting_note(&event);
+            }
+            PickerKind::Template => {
+                // The first row is the blank note.
+                self.note_template = idx.checked_sub(1).and_then(|i| template::list(&self.vault_root.join(&self.config.templates_dir)).into_iter().nth(i));
+                self.input_mode = InputMode::NewNote;
+                self.input_buffer.clear();
+                return Ok(());
+            }
+            PickerKind::MoveTo => {
+                let dir = if item == "[Root]" { self.vault_root.clone() } else { self.vault_root.join(&item) };
+                return self.paste_move(&dir);
+            }
+            PickerKind::Contexts => {
+                let ctx = self.config.contexts[idx].clone();
+                return self.switch_context(&ctx);
+            }
+            PickerKind::Audio => {
+                if let Some(path) = self.audio_files.get(idx).cloned() { self.play(&path); }
+                return Ok(());
+            }
+            PickerKind::History => {
+                if let Some(travel) = self.travel.as_mut() { travel.pos = idx; }
+                self.preview_mode = PreviewMode::TimeTravel;
+                self.preview = None;
+                return Ok(());
+            }
+            // Run by the event loop instead, which has the terminal some commands need.
+            PickerKind::Commands => return Ok(()),
+        }
+        self.hard_refresh()
+    }
+
+    /// The note (and line) behind a picker entry, for pickers that list notes.
+    fn picked_note(&self, picker: &Picker, idx: usize) -> Option<(PathBuf, usize)> {
+        match picker.kind {
+            PickerKind::SearchResults => self.search_hit(idx).map(|h| (h.path.clone(), h.line)),
+            PickerKind::QuickSwitch => Some((self.vault_root.join(&picker.items[idx]), 1)),
+            PickerKind::Reading => self.reading_items.get(idx).map(|p| (p.clone(), 1)),
+            PickerKind::Tasks => self.tasks.get(idx).map(|t| (t.path.clone(), t.line)),
+            PickerKind::Incoming => self.incoming.get(idx).map(|c| (self.vault_root.join(&c.path), 1)),
+            PickerKind::Unarchive | PickerKind::MoveTo | PickerKind::Trash | PickerKind::Tags | PickerKind::Authors | PickerKind::Themes | PickerKind::Comments | PickerKind::Template | PickerKind::Agenda | PickerKind::History | PickerKind::Audio | PickerKind::Contexts | PickerKind::Commands => None,
+        }
+    }
+
+    /// `N`: asks for a template first when the vault has any, then for the note's name.
+    fn new_note(&mut self) {
+        self.note_template = None;
+        let templates = template::list(&self.vault_root.join(&self.config.templates_dir));
+        if templates.is_empty() {
+            self.input_mode = InputMode::NewNote;
+            self.input_buffer.clear();
+            return;
+        }
+        let items = std::iter::once("Blank note".to_string()).chain(templates.iter().map(|t| links::link_name(t))).collect();
+        self.picker = Some(Picker::new(PickerKind::Template, " New note from template ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// `c`: upcoming events from `calendar.source`, to create (or open) their meeting notes.
+    fn open_agenda(&mut self) {
+        if self.config.calendar.source.is_none() {
+            self.status_msg = Some("Set calendar.source to an .ics file or URL to see your agenda".into());
+            return;
+        }
+        self.agenda = match self.profiler.time("calendar", || calendar::upcoming(&self.config.calendar)) {
+            Ok(events) => events,
+            Err(e) => { self.status_msg = Some(format!("Calendar: {}", e)); return; }
+        };
+        if self.agenda.is_empty() {
+            self.status_msg = Some(format!("No events in the next {} days", self.config.calendar.days_ahead));
+            return;
+        }
+        let folder = self.vault_root.join(&self.config.calendar.folder);
+        let items = self.agenda.iter().map(|e| {
+            let exists = folder.join(e.note_name()).exists();
+            format!("{} {}", if exists { "📝" } else { "  " }, e.label())
+        }).collect();
+        self.picker = Some(Picker::new(PickerKind::Agenda, " Create meeting note for… · [ENTER] Create/Open ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Opens the meeting note for `event`, creating it pre-filled from the event first.
+    fn meeting_note(&mut self, event: &calendar::Event) -> Result<()> {
+        let folder = self.vault_root.join(&self.config.calendar.folder);
+        let path = folder.join(event.note_name());
+        if !path.exists() {
+            fs::create_dir_all(&folder)?;
+            fs::write(&path, event.note(&self.vault_root))?;
+            self.vault_changed();
+            self.status_msg = Some(format!("Created {}", vault::relative(&self.vault_root, &path)));
+        }
+        self.reveal(&path)
+    }
+
+    /// Ctrl-P: fuzzy-find any note in the vault by path.
+    fn quick_switch(&mut self) {
+        let root = self.vault_root.clone();
+        let items = self.notes().iter().map(|p| vault::relative(&root, p)).collect();
+        self.picker = Some(Picker::new(PickerKind::QuickSwitch, " Go to note · [ENTER] Go to  [^E] Edit ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Search-as-you-type: restarts the search once typing pauses.
+    fn schedule_search(&mut self) {
+        self.search = None;
+        self.search_due = Some(Instant::now() + search::DEBOUNCE);
+    }
+
+    fn start_search(&mut self, query: &str) {
+        self.search_due = None;
+        self.search = (!query.trim().is_empty()).then(|| search::SearchJob::start(self.vault_root.clone(), self.notes().to_vec(), query, 500));
+    }
+
+    /// Enter in `Search` mode: lists the matching lines in a picker, which fills as the search
+    /// goes on.
+    fn open_search_results(&mut self) {
+        self.input_mode = InputMode::Normal;
+        let query = std::mem::take(&mut self.input_buffer);
+        if query.trim().is_empty() { return; }
+        if self.search_due.is_some() || self.search.as_ref().is_none_or(|j| j.query != query) { self.start_search(&query); }
+        self.picker = Some(Picker::new(PickerKind::SearchResults, "", Vec::new()));
+        self.input_mode = InputMode::Picker;
+        self.poll_search();
+    }
+
+    /// Starts a due search and takes in what the running one found, into the results picker
+    /// when it's open. Searches nobody is looking at any more are dropped, which stops them.
+    fn poll_search(&mut self) {
+        let listing = self.picker.as_ref().is_some_and(|p| p.kind == PickerKind::SearchResults);
+        if self.input_mode != InputMode::Search && !listing {
+            self.search = None;
+            self.search_due = None;
+            return;
+        }
+        if self.search_due.is_some_and(|at| at <= Instant::now()) {
+            let query = self.input_buffer.clone();
+            self.start_search(&query);
+            self.dirty = true;
+        }
+        let Some(job) = self.search.as_mut() else { return };
+        let was_done = job.done;
+        if job.poll() > 0 || job.done != was_done { self.dirty = true; }
+        if job.done && !was_done { self.profiler.record("search", job.started.elapsed()); }
+        let Some(picker) = self.picker.as_mut().filter(|_| listing) else { return };
+        if job.done && job.hits.is_empty() {
+            self.status_msg = Some(format!("No matches for \"{}\"", job.query));
+            self.picker = None;
+            self.input_mode = InputMode::Normal;
+            return;
+        }
+        let new: Vec<String> = job.hits[picker.items.len()..].iter().map(|h| search::label(&self.vault_root, h)).collect();
+        picker.items.extend(new);
+        let more = if job.done { "" } else { "…" };
+        picker.title = format!(" {} matches{} for \"{}\" · [ENTER] Go to  [^E] Edit  [^T] Tag all  [^O] Move all ", job.hits.len(), more, job.query);
+    }
+
+    /// Result `i` of the search behind the `SearchResults` picker.
+    fn search_hit(&self, i: usize) -> Option<&search::Hit> {
+        self.search.as_ref().and_then(|j| j.hits.get(i))
+    }
+
+    /// Points the browser at `path`: its category, folder and row in the Files pane.
+    fn reveal(&mut self, path: &Path) -> Result<()> {
+        let rel = path.strip_prefix(&self.vault_root).unwrap_or(path);
+        let dirs: Vec<String> = rel.parent().map(|p| p.iter().map(|c| c.to_string_lossy().to_string()).collect()).unwrap_or_default();
+        self.selected_cat = dirs.first().cloned().unwrap_or_else(|| "[Root]".to_string());
+        self.selected_sub = dirs.get(1).cloned();
+        self.sub_state.select(None);
+        self.hard_refresh()?;
+        match self.files.iter().position(|p| p == path) {
+            Some(i) => { self.file_state.select(Some(i)); self.focus = Focus::Files; }
+            None => self.status_msg = Some(format!("{} is nested deeper than the Folders pane shows", vault::relative(&self.vault_root, path))),
+        }
+        Ok(())
+    }
+
+    /// Lists every category and folder as a destination for the marked (or else selected) note.
+    fn choose_move_target(&mut self) {
+        if self.pending_move.is_empty() { self.pending_move = self.batch_targets(); }
+        if self.pending_move.is_empty() { return; }
+        let root = self.vault_root.clone();
+        let mut dirs = vec!["[Root]".to_string()];
+        dirs.extend(vault::walk_dirs(&root, &self.ignore).iter().map(|d| vault::relative(&root, d)));
+        let title = format!(" Move {} to · [ENTER] Move ", describe(&self.pending_move));
+        self.picker = Some(Picker::new(PickerKind::MoveTo, title, dirs));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Moves the cut notes into `dir`, repointing links, and follows them there.
+    fn paste_move(&mut self, dir: &Path) -> Result<()> {
+        let notes: Vec<PathBuf> = std::mem::take(&mut self.pending_move).into_iter().filter(|n| n.parent() != Some(dir)).collect();
+        if notes.is_empty() {
+            self.status_msg = Some("Already in this folder".into());
+            return Ok(());
+        }
+        if let Err(e) = self.checkpoint(&notes, "moving") {
+            self.status_msg = Some(format!("Nothing moved · couldn't commit the vault first: {}", e));
+            return Ok(());
+        }
+        let (mut moved, mut refs, mut last) = (Vec::new(), 0, None);
+        for note in &notes {
+            let new = dir.join(note.file_name().unwrap_or_default());
+            match self.move_note(note, &new) {
+                Ok((r, _)) => { moved.push(note.clone()); refs += r; last = Some(new); }
+                Err(e) => {
+                    self.status_msg = Some(format!("Moved {} of {} · {} failed: {}", moved.len(), notes.len(), vault::relative(&self.vault_root, note), e));
+                    return self.hard_refresh();
+                }
+            }
+        }
+        self.status_msg = Some(format!("Moved {} to {} · {} links updated", describe(&moved), vault::relative(&self.vault_root, dir), refs));
+        match last {
+            Some(path) => self.reveal(&path),
+            None => self.hard_refresh(),
+        }
+    }
+
+    /// What batch actions apply to: the marked notes, or else the highlighted one.
+    fn batch_targets(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() { self.selected_file().cloned().into_iter().collect() } else { self.marked.iter().cloned().collect() }
+    }
+
+    /// `V`: the first press anchors a range, the second marks every note between it and the cursor.
+    fn mark_range(&mut self) {
+        let Some(cursor) = self.file_state.selected() else { return };
+        let anchor = self.range_anchor.take().and_then(|a| self.files.iter().position(|p| *p == a));
+        match anchor {
+            Some(a) => {
+                self.marked.extend(self.files[a.min(cursor)..=a.max(cursor)].iter().cloned());
+                self.status_msg = Some(format!("{} marked", self.marked.len()));
+            }
+            None => {
+                self.range_anchor = Some(self.files[cursor].clone());
+                self.marked.insert(self.files[cursor].clone());
+                self.status_msg = Some(format!("Range started · move to the other end and press [{}]", self.config.keys.key(Action::MarkRange)));
+            }
+        }
+    }
+
+    /// Before a change to several `notes` at once, commits the vault as it is, so the change can
+    /// be undone in one step.
+    fn checkpoint(&self, notes: &[PathBuf], verb: &str) -> Result<(), git::GitError> {
+        if notes.len() < 2 { return Ok(()); }
+        git::checkpoint(&self.vault_root, &format!("Before {} {} notes", verb, notes.len())).map(drop)
+    }
+
+    /// `*`, or ^T / ^O on search results: marks `notes` for a batch action.
+    fn mark_all(&mut self, notes: Vec<PathBuf>) {
+        let before = self.marked.len();
+        self.marked.extend(notes.into_iter().filter(|p| vault::is_note(p)));
+        self.range_anchor = None;
+        self.status_msg = Some(format!("{} marked ({} new)", self.marked.len(), self.marked.len() - before));
+    }
+
+    /// Adds frontmatter tags to every batch target; returns how many notes changed.
+    fn tag_notes(&mut self, tags: &[String]) -> Result<usize> {
+        let mut changed = 0;
+        let targets = self.batch_targets();
+        self.checkpoint(&targets, "tagging")?;
+        for note in targets.into_iter().filter(|n| !pdf::is_pdf(n)) {
+            let text = self.read_note(&note)?;
+            let (fm, body) = frontmatter::split(&text);
+            let mut list = fm.map(|y| frontmatter::list(y, "tags")).unwrap_or_default();
+            let before = list.len();
+            for tag in tags { if !list.contains(tag) { list.push(tag.clone()); } }
+            if list.len() == before { continue; }
+            self.write_note(&note, &frontmatter::join(Some(&frontmatter::set_list(fm.unwrap_or(""), "tags", &list)), body))?;
+            changed += 1;
+        }
+        self.vault_changed();
+        Ok(changed)
+    }
+
+    /// Lists the trash for restoring ([ENTER]) or purging ([^X]).
+    fn open_trash(&mut self) {
+        self.trash_entries = trash::list(&self.vault_root);
+        if self.trash_entries.is_empty() {
+            self.picker = None;
+            self.input_mode = InputMode::Normal;
+            self.status_msg = Some("Trash is empty".into());
+            return;
+        }
+        let items = self.trash_entries.iter().map(|e| match e.deleted {
+            Some(d) => format!("{} · deleted {}", e.original.display(), d.format("%Y-%m-%d %H:%M")),
+            None => e.original.display().to_string(),
+        }).collect();
+        self.picker = Some(Picker::new(PickerKind::Trash, format!(" Trash ({}) · [ENTER] Restore  [^X] Purge ", self.trash_entries.len()), items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// `H`: commits touching the selected note, newest first. Choosing one shows the note as of
+    /// that commit in time travel, where [←/→] keeps stepping.
+    fn open_history(&mut self) {
+        let Some(path) = self.selected_file().cloned() else { return };
+        let revisions = match self.profiler.time("git", || git::file_history(&self.vault_root, &path)) {
+            Ok(revisions) if !revisions.is_empty() => revisions,
+            Ok(_) => { self.status_msg = Some("No committed revisions of this note yet".into()); return; }
+            Err(e) => { self.status_msg = Some(format!("History unavailable: {}", e)); return; }
+        };
+        let items = revisions.iter().map(|r| format!("{}  {}  {}", ui::format_time(r.time), r.summary, &r.hash[..7.min(r.hash.len())])).collect();
+        let title = format!(" History of {} · [ENTER] View at this commit  [^R] Restore this version ", path.file_name().unwrap_or_default().to_string_lossy());
+        self.travel = Some(TimeTravel { path, revisions, pos: 0 });
+        self.picker = Some(Picker::new(PickerKind::History, title, items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// `w`: open tasks from every note, markdown checkboxes and org TODOs alike, dated ones first.
+    fn open_tasks(&mut self) {
+        let notes = self.notes().to_vec();
+        self.tasks = self.profiler.time("tasks", || tasks::collect(&notes));
+        if self.tasks.is_empty() {
+            self.status_msg = Some("No open tasks".into());
+            return;
+        }
+        let items = self.tasks.iter().map(tasks::Task::label).collect();
+        self.picker = Some(Picker::new(PickerKind::Tasks, format!(" Open tasks ({}) · [ENTER] Go to  [^E] Edit ", self.tasks.len()), items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// `O`: the audio files the selected note links to, with their length, to play.
+    fn open_audio(&mut self) {
+        let Some(note) = self.selected_file().cloned() else { return };
+        self.audio_files = audio::attachments(&self.vault_root, &note);
+        if self.audio_files.is_empty() {
+            self.status_msg = Some("No audio attachments in this note".into());
+            return;
+        }
+        let items = self.audio_files.iter().map(|p| {
+            let length = audio::duration(p).map(audio::format_duration).unwrap_or_else(|| "?:??".into());
+            format!("🔊 {:>7}  {}", length, vault::relative(&self.vault_root, p))
+        }).collect();
+        self.picker = Some(Picker::new(PickerKind::Audio, " Audio attachments · [ENTER] Play ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    fn play(&mut self, path: &Path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.status_msg = Some(match audio::play(&self.config.audio_player, path) {
+            Ok(()) => format!("▶ Playing {}", name),
+            Err(e) => format!("Couldn't play {}: {:#}", name, e),
+        });
+    }
+
+    /// `i`: a contact sheet of the images under the browsed folder.
+    fn open_gallery(&mut self) {
+        let dir = self.browsed_dir();
+        self.gallery = self.profiler.time("gallery", || gallery::images(&dir));
+        if self.gallery.is_empty() {
+            self.status_msg = Some("No images in this folder".into());
+            return;
+        }
+        self.gallery_cursor = 0;
+        self.input_mode = InputMode::Gallery;
+    }
+
+    /// `K`: the comments on the selected note, after a row for adding one. [ENTER] on a comment
+    /// resolves it, or reopens it.
+    fn open_comments(&mut self) {
+        let Some(note) = self.selected_file().cloned() else { return };
+        let mut items = vec!["✚ Add a comment".to_string()];
+        items.extend(comments::load(&note).iter().map(|c| {
+            let first = c.text.lines().next().unwrap_or_default();
+            format!("{} {} · {} · {}", if c.resolved { "✓" } else { "○" }, c.by, c.at, first)
+        }));
+        self.picker = Some(Picker::new(PickerKind::Comments, format!(" Comments on {} · [ENTER] Resolve/Reopen ", links::link_name(&note)), items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    fn add_comment(&mut self, text: &str) {
+        let Some(note) = self.selected_file().cloned() else { return };
+        let by = git::user_name(&self.vault_root).or_else(|| std::env::var("USER").ok()).unwrap_or_else(|| "me".into());
+        self.status_msg = Some(match comments::add(&note, &by, text) {
+            Ok(()) => format!("Commented on {}", links::link_name(&note)),
+            Err(e) => format!("Comment failed: {}", e),
+        });
+        self.preview = None;
+        self.vault_changed();
+    }
+
+    /// `L`: the read-later queue, unread first. [^R] flips the highlighted page between read and unread.
+    fn open_reading(&mut self) {
+        let queue = reading::queue(&self.vault_root);
+        if queue.is_empty() {
+            self.status_msg = Some("Reading queue is empty · [u] to add a URL".into());
+            return;
+        }
+        let unread = queue.iter().filter(|i| !i.read).count();
+        let items = queue.iter().map(|i| format!("{} {}", if i.read { "✓" } else { "●" }, i.title)).collect();
+        self.reading_items = queue.into_iter().map(|i| i.path).collect();
+        self.picker = Some(Picker::new(PickerKind::Reading, format!(" Read later ({} unread) · [ENTER] Open  [^R] Read/Unread ", unread), items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    fn toggle_read_selected(&mut self) {
+        let Some(idx) = self.picker.as_ref().filter(|p| p.kind == PickerKind::Reading).and_then(Picker::selected) else { return };
+        let path = self.reading_items[idx].clone();
+        let query = self.picker.as_ref().map(|p| p.query.clone()).unwrap_or_default();
+        self.status_msg = Some(match reading::toggle_read(&path) {
+            Ok(read) => format!("Marked {} as {}", links::link_name(&path), if read { "read" } else { "unread" }),
+            Err(e) => format!("Update failed: {}", e),
+        });
+        self.vault_changed();
+        self.open_reading();
+        if let Some(p) = self.picker.as_mut().filter(|p| p.kind == PickerKind::Reading) { p.query = query; }
+    }
+
+    /// Restores the note in the `History` picker to the highlighted revision, as a commit of its own.
+    fn restore_selected(&mut self) -> Result<()> {
+        let Some(idx) = self.picker.as_ref().filter(|p| p.kind == PickerKind::History).and_then(Picker::selected) else { return Ok(()) };
+        let Some(travel) = self.travel.as_ref() else { return Ok(()) };
+        let (path, rev) = (travel.path.clone(), &travel.revisions[idx]);
+        let short = &rev.hash[..7.min(rev.hash.len())];
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        self.status_msg = Some(match self.profiler.time("git", || git::restore(&self.vault_root, &path, rev)) {
+            Ok(true) => format!("Restored {} to {}", name, short),
+            Ok(false) => format!("{} already matches {}", name, short),
+            Err(e) => format!("Restore failed: {}", e),
+        });
+        self.picker = None;
+        self.input_mode = InputMode::Normal;
+        self.preview_mode = PreviewMode::Note;
+        self.preview = None;
+        self.vault_changed();
+        // The restored note moves to the top of the list; keep it selected.
+        self.reveal(&path)
+    }
+
+    /// `n`: the notes syncs brought in that haven't been looked at yet, newest first.
+    fn open_incoming(&mut self) {
+        let root = &self.vault_root;
+        // Notes gone since, by a later move here, are no longer anything to catch up on.
+        self.incoming = incoming::load(root).into_iter().filter(|c| root.join(&c.path).exists()).collect();
+        if self.incoming.is_empty() {
+            self.status_msg = Some("Nothing new from the remote".into());
+            return;
+        }
+        let items = self.incoming.iter().map(|c| format!("{} {}  · {}", c.kind.icon(), c.path, c.by)).collect();
+        self.picker = Some(Picker::new(PickerKind::Incoming, " What's new · [ENTER] Go to  [^E] Edit  [^X] Mark all seen ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Takes the note highlighted in the what's-new picker off the queue, as `Enter` does.
+    fn dismiss_incoming(&self) {
+        let Some(idx) = self.picker.as_ref().filter(|p| p.kind == PickerKind::Incoming).and_then(Picker::selected) else { return };
+        let _ = incoming::dismiss(&self.vault_root, &self.incoming[idx].path);
+    }
+
+    /// Deletes the trash entry behind the highlighted picker row for good.
+    fn purge_selected(&mut self) {
+        let Some(idx) = self.picker.as_ref().filter(|p| p.kind == PickerKind::Trash).and_then(Picker::selected) else { return };
+        let entry = &self.trash_entries[idx];
+        self.status_msg = Some(match trash::purge(entry) {
+            Ok(()) => format!("Purged {}", entry.original.display()),
+            Err(e) => format!("Purge failed: {}", e),
+        });
+        self.open_trash();
+    }
+
+    /// Scans attachment folders for files no note links to and asks before trashing them.
+    /// Encrypted and locked notes are read too; while one can't be, nothing is collected, since
+    /// its links are unknown.
+    fn collect_garbage(&mut self) {
+        let (root, dirs) = (self.vault_root.clone(), self.config.index.attachment_dirs.clone());
+        let mut notes: Vec<(PathBuf, String)> = self.notes().iter()
+            .filter_map(|n| Some((n.clone(), fs::read_to_string(n).ok()?)))
+            .collect();
+        let protected: Vec<PathBuf> = walkdir::WalkDir::new(&root).min_depth(1).into_iter()
+            .filter_entry(|e| !self.ignore.is_ignored(&vault::relative(&root, e.path()), e.file_type().is_dir()))
+            .flatten()
+            .map(|e| e.into_path())
+            .filter(|p| (crypt::is_encrypted(p) && vault::is_note(&crypt::plain_name(p))) || (lock::is_locked(p) && vault::is_note(&lock::plain_name(p))))
+            .collect();
+        let mut unreadable = Vec::new();
+        for note in protected {
+            if lock::is_locked(&note) { self.recall_passphrase(&note); }
+            match self.read_note(&note) {
+                Ok(text) => notes.push((note, text)),
+                Err(e) => unreadable.push((note, e)),
+            }
+        }
+        if let Some((note, e)) = unreadable.first() {
+            let others = match unreadable.len() { 1 => String::new(), n => format!(" (+{} more)", n - 1) };
+            let why = if lock::is_locked(note) { "is locked; unlock it first".to_string() } else { format!("can't be read: {:#}", e) };
+            self.status_msg = Some(format!("❌ Not collecting, links unknown: {}{} {}", vault::relative(&root, note), others, why));
+            return;
+        }
+        self.orphans = attachments::orphans(&root, &dirs, &notes);
+        if self.orphans.is_empty() {
+            self.status_msg = Some("No orphaned attachments".into());
+        } else {
+            self.input_mode = InputMode::ConfirmGc;
+        }
+    }
+
+    /// Settles an edit clash: merges the overwritten version back in, restores it, or keeps the
+    /// note as it is. Returns the note and the line of its first conflict marker when the merge
+    /// left some.
+    fn settle_clash(&mut self, choice: char) -> Result<Option<(PathBuf, usize)>> {
+        let Some(clash) = self.clash.take() else { return Ok(None) };
+        self.input_mode = InputMode::Normal;
+        let name = clash.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let mut marker = None;
+        match choice {
+            'm' => {
+                let current = fs::read_to_string(&clash.path)?;
+                match guard::merge(&current, &clash.base, &clash.lost) {
+                    Ok((merged, conflicts)) => {
+                        fs::write(&clash.path, &merged)?;
+                        if conflicts { marker = merged.lines().position(|l| l.starts_with("<<<<<<< ")).map(|i| (clash.path.clone(), i + 1)); }
+                        self.status_msg = Some(if conflicts { format!("Merged {} with conflicts marked; resolve them in the editor", name) } else { format!("Merged both versions of {}", name) });
+                    }
+                    Err(e) => { self.status_msg = Some(format!("❌ Couldn't merge {}: {:#}", name, e)); self.clash = Some(clash); self.input_mode = InputMode::EditClash; return Ok(None); }
+                }
+            }
+            'r' => {
+                fs::write(&clash.path, &clash.lost)?;
+                self.status_msg = Some(format!("Restored the overwritten version of {}", name));
+            }
+            _ => self.status_msg = Some(format!("Kept {} as it is", name)),
+        }
+        self.vault_changed();
+        Ok(marker)
+    }
+
+    fn trash_orphans(&mut self) -> Result<()> {
+        let orphans = std::mem::take(&mut self.orphans);
+        let (mut count, mut bytes) = (0, 0);
+        for orphan in &orphans {
+            if trash::trash(&self.vault_root, &orphan.path).is_ok() { count += 1; bytes += orphan.size; }
+        }
+        self.status_msg = Some(format!("Trashed {} attachments ({})", count, attachments::human_size(bytes)));
+        self.vault_changed();
+        self.hard_refresh()
+    }
+
+    fn hard_refresh(&mut self) -> Result<()> {
+        let started = Instant::now();
+        self.marked.retain(|p| p.exists());
+        let mut cats = vec!["[Root]".to_string()];
+        if let Ok(entries) = fs::read_dir(&self.vault_root) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && !name.starts_with('.') && !self.archived.contains(&name) {
+                    cats.push(name);
+                }
+            }
+        }
+        cats.sort();
+        if cats != self.categories { self.dirty = true; }
+        self.categories = cats;
+
+        if !self.categories.contains(&self.selected_cat) {
+            self.selected_cat = "[Root]".to_string();
+        }
+
+        let cat_path = if self.selected_cat == "[Root]" { self.vault_root.clone() } else { self.vault_root.join(&self.selected_cat) };
+        let mut subs = Vec::new();
+        if let Ok(entries) = fs::read_dir(&cat_path) {
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    subs.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+        subs.sort();
+        if subs != self.subfolders { self.dirty = true; }
+        self.subfolders = subs;
+
+        if let Some(ref sub_name) = self.selected_sub {
+            if let Some(pos) = (0..self.folder_count()).position(|i| self.folder_name(i) == sub_name) {
+                self.sub_state.select(Some(pos));
+            } else {
+                self.selected_sub = None;
+                self.sub_state.select(if self.folder_count() == 0 { None } else { Some(0) });
+            }
+        }
+
+        let mut file_path = cat_path;
+        if let Some(si) = self.sub_state.selected() {
+            if si < self.subfolders.len() {
+                file_path.push(&self.subfolders[si]);
+            }
+        }
+
+        let mut files = Vec::new();
+        if let Some(tag) = self.tag_filter.clone() {
+            files = self.tags().get(&tag).cloned().unwrap_or_default();
+        } else if let Some(glob) = self.selected_virtual().map(|(_, g)| g.clone()) {
+            let root = self.vault_root.clone();
+            files = self.notes().iter().filter(|p| glob::matches(&glob, &vault::relative(&root, p))).cloned().collect();
+        } else if let Ok(entries) = fs::read_dir(&file_path) {
+            for entry in entries.flatten() {
+                let p = entry.path();
+                if p.is_file() && !p.file_name().unwrap().to_string_lossy().starts_with('.') {
+                    files.push(p);
+                }
+            }
+        }
+        if let Some(author) = self.author_filter.clone() {
+            let authors = self.authors();
+            files.retain(|p| authors.get(p) == Some(&author));
+        }
+        let mut dated: Vec<(SystemTime, PathBuf)> = files.into_iter()
+            .map(|p| (modified(&p).unwrap_or(SystemTime::UNIX_EPOCH), p))
+            .filter(|(m, _)| self.age_filter.matches(*m))
+            .collect();
+        match self.sort {
+            context::Sort::Modified => dated.sort_by_key(|(m, _)| std::cmp::Reverse(*m)),
+            context::Sort::Name => dated.sort_by_key(|(_, p)| p.file_name().unwrap_or_default().to_string_lossy().to_lowercase()),
+        }
+        let files: Vec<PathBuf> = dated.into_iter().map(|(_, p)| p).collect();
+        if files != self.files { self.dirty = true; }
+        self.files = files;
+        
+        #[allow(clippy::unnecessary_map_or)]
+        if self.file_state.selected().map_or(true, |i| i >= self.files.len()) {
+            self.file_state.select(if self.files.is_empty() { None } else { Some(0) });
+        }
+        if self.preview_stale() { self.dirty = true; }
+        self.last_refresh = Instant::now();
+        self.profiler.record("refresh", started.elapsed());
+        Ok(())
+    }
+
+    /// A note's text, decrypted first when it's an `.age` or unlocked `.locked` one; a PDF's
+    /// first page.
+    fn read_note(&self, path: &Path) -> Result<String> {
+        if pdf::is_pdf(path) { return pdf::preview(path); }
+        if lock::is_locked(path) { return lock::read(path, self.passphrases.get(path).context("locked")?); }
+        if !crypt::is_encrypted(path) { return Ok(fs::read_to_string(path)?); }
+        self.crypt.as_ref().context("set encryption.identity to read encrypted notes")?.decrypt(path)
+    }
+
+    /// Writes a note's text, encrypting it again when it's an `.age` or unlocked `.locked` one.
+    fn write_note(&self, path: &Path, text: &str) -> Result<()> {
+        if pdf::is_pdf(path) { anyhow::bail!("PDFs can't be changed from knot"); }
+        if lock::is_locked(path) { return lock::write(path, self.passphrases.get(path).context("unlock the note first")?, text); }
+        if !crypt::is_encrypted(path) { return Ok(fs::write(path, text)?); }
+        self.crypt.as_ref().context("set encryption.identity to write encrypted notes")?.encrypt(text, path)
+    }
+
+    fn selected_file(&self) -> Option<&PathBuf> { self.file_state.selected().and_then(|i| self.files.get(i)) }
+
+    fn preview_stale(&self) -> bool {
+        let Some(cache) = &self.preview else { return true };
+        let path = self.selected_file();
+        path != cache.path.as_ref() || path.and_then(|p| modified(p)) != cache.modified || cache.mode != self.preview_mode || cache.raw != self.raw_preview
+    }
+
+    /// Re-reads and re-renders the preview if the selected note changed since the last frame.
+    fn refresh_preview(&mut self) {
+        if !self.preview_stale() { return; }
+        let started = Instant::now();
+        let path = self.selected_file().cloned();
+        if self.preview.as_ref().is_some_and(|c| c.path != path) { self.link_cursor = None; }
+        if let Some(p) = path.as_ref().filter(|p| lock::is_locked(p)) { self.recall_passphrase(p); }
+        let mut links = Vec::new();
+        let comments = path.as_deref().map(comments::load).unwrap_or_default();
+        let text = match (&path, self.preview_mode) {
+            (None, _) => Text::from("---"),
+            (Some(p), PreviewMode::Note) => match self.read_note(p) {
+                Ok(t) if self.raw_preview || !vault::is_markdown(&lock::plain_name(&crypt::plain_name(p))) => Text::from(emoji::render(&t)),
+                Ok(t) => {
+                    let (text, found) = render::render_with_links(&emoji::render(&t));
+                    links = found;
+                    text
+                }
+                Err(e) if crypt::is_encrypted(p) => Text::from(format!("🔒 {:#}", e)),
+                Err(_) if lock::is_locked(p) => Text::from("🔒 Locked note · [ENTER] Unlock"),
+                Err(e) if pdf::is_pdf(p) => Text::from(format!("📄 {:#}", e)),
+                Err(_) => Text::from("Error reading file"),
+            },
+            (Some(p), PreviewMode::Blame) => {
+                let blame = self.profiler.time("git", || git::blame(&self.vault_root, p));
+                match blame {
+                    Ok(lines) => ui::blame_text(&lines),
+                    Err(e) => Text::from(format!("Blame unavailable: {}", e)),
+                }
+            }
+            (Some(p), PreviewMode::Diff) => {
+                match self.profiler.time("git", || git::diff_head(&self.vault_root, p)) {
+                    Ok(diff) => ui::diff_text(diff.as_deref(), &self.read_note(p).unwrap_or_default()),
+                    Err(e) => Text::from(format!("Diff unavailable: {}", e)),
+                }
+            }
+            (Some(p), PreviewMode::Answers) => {
+                let prompts = journal::prompts(&self.vault_root);
+                let text = self.read_note(p).unwrap_or_default();
+                match journal::prompt_in(&text, &prompts) {
+                    Some(prompt) => render::render(&emoji::render(&journal::past_answers(&self.vault_root, &self.config.journal_path, prompt))),
+                    None => Text::from(format!("This note doesn't answer any of the prompts in {}.", journal::PROMPTS)),
+                }
+            }
+            (Some(p), PreviewMode::WordDiff) => {
+                let root = &self.vault_root;
+                let base = self.profiler.time("git", || git::commit_before(root, p, self.diff_days));
+                let content = self.read_note(p).unwrap_or_default();
+                match base {
+                    Ok(None) => ui::word_diff_text(self.diff_days, None, "", &content),
+                    Ok(Some((hash, time))) => match self.profiler.time("git", || git::word_diff(root, p, &hash)) {
+                        Ok(diff) => ui::word_diff_text(self.diff_days, Some(time), &diff, &content),
+                        Err(e) => Text::from(format!("Word diff unavailable: {}", e)),
+                    },
+                    Err(e) => Text::from(format!("Word diff unavailable: {}", e)),
+                }
+            }
+            (Some(p), PreviewMode::TimeTravel) => {
+                if self.travel.as_ref().is_none_or(|t| &t.path != p) {
+                    let revisions = self.profiler.time("git", || git::file_history(&self.vault_root, p)).unwrap_or_default();
+                    self.travel = Some(TimeTravel { path: p.clone(), revisions, pos: 0 });
+                }
+                let travel = self.travel.as_ref().expect("loaded above");
+                match travel.revisions.get(travel.pos) {
+                    None => Text::from("No committed revisions of this note yet."),
+                    Some(rev) => {
+                        let content = self.profiler.time("git", || git::show_file(&self.vault_root, &rev.hash, &rev.path));
+                        let content = content.map(|c| emoji::render(&c)).unwrap_or_else(|e| e.to_string());
+                        ui::time_travel_text(rev, travel.pos, travel.revisions.len(), content)
+                    }
+                }
+            }
+        };
+        self.preview = Some(PreviewCache { modified: path.as_deref().and_then(modified), path, mode: self.preview_mode, raw: self.raw_preview, text, links, comments });
+        self.profiler.record("preview", started.elapsed());
+    }
+
+    /// All indexable notes in the vault, rebuilding the index if something invalidated it.
+    fn notes(&mut self) -> &[PathBuf] {
+        if self.index.is_stale() {
+            let started = Instant::now();
+            self.index.rebuild(&self.vault_root, &self.ignore);
+            self.profiler.record("index", started.elapsed());
+        }
+        self.index.notes()
+    }
+
+    fn tags(&mut self) -> &BTreeMap<String, Vec<PathBuf>> {
+        self.notes();
+        let started = Instant::now();
+        let tags = self.index.tags();
+        self.profiler.record("tags", started.elapsed());
+        tags
+    }
+
+    /// Who last committed each note: from the vault's history, and from their own for categories
+    /// with their own repository.
+    fn authors(&mut self) -> &BTreeMap<PathBuf, String> {
+        if self.authors.is_none() {
+            let started = Instant::now();
+            let nested = nested::categories(&self.vault_root, &self.config.sync.categories);
+            let repos = std::iter::once(self.vault_root.clone()).chain(nested.into_iter().filter(|n| n.is_repo()).map(|n| n.dir));
+            let mut authors = BTreeMap::new();
+            for repo in repos {
+                let last = git::last_authors(&repo).unwrap_or_default();
+                authors.extend(last.into_iter().map(|(rel, name)| (repo.join(rel), name)));
+            }
+            self.profiler.record("authors", started.elapsed());
+            self.authors = Some(authors);
+        }
+        self.authors.as_ref().expect("built above")
+    }
+
+    /// Whether more than one person committed notes here, which is when the Notes pane names
+    /// the last author of each.
+    fn shared_vault(&mut self) -> bool {
+        let mut names = self.authors().values();
+        let first = names.next();
+        names.any(|n| Some(n) != first)
+    }
+
+    /// Each author with how many notes they were last to commit, most first.
+    fn author_counts(&mut self) -> Vec<(String, usize)> {
+        let notes = self.notes().to_vec();
+        let authors = self.authors();
+        let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+        for name in notes.iter().filter_map(|n| authors.get(n)) { *counts.entry(name).or_default() += 1; }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().map(|(name, n)| (name.clone(), n)).collect();
+        counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+        counts
+    }
+
+    /// The built-in color schemes to switch to, for this session and the next ones in this vault.
+    fn choose_theme(&mut self) {
+        let items = config::THEMES.iter().map(|t| if *t == self.config.theme.name { format!("{} · current", t) } else { t.to_string() }).collect();
+        let mut picker = Picker::new(PickerKind::Themes, " Color theme · [ENTER] Switch ", items);
+        picker.cursor = config::THEMES.iter().position(|t| *t == self.config.theme.name).unwrap_or(0);
+        self.picker = Some(picker);
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// `@`: everyone who last committed a note, to filter the Notes pane by.
+    fn choose_author(&mut self) {
+        let counts = self.author_counts();
+        if counts.is_empty() {
+            self.status_msg = Some("No committed notes yet".into());
+            return;
+        }
+        let items = counts.iter().map(|(name, n)| format!("@{} · {}", name, n)).collect();
+        self.picker = Some(Picker::new(PickerKind::Authors, " Filter notes by last author ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Ctrl-T: every tag in the vault, to filter the Notes pane by.
+    fn choose_tag(&mut self) {
+        let items: Vec<String> = self.tags().iter().map(|(tag, notes)| format!("#{} · {}", tag, notes.len())).collect();
+        if items.is_empty() {
+            self.status_msg = Some("No tags in this vault yet".into());
+            return;
+        }
+        self.picker = Some(Picker::new(PickerKind::Tags, " Filter notes by tag ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Renames or moves a note and rewrites every wikilink and relative markdown link pointing at it.
+    /// Returns how many links were updated and in how many notes.
+    fn move_note(&mut self, old: &Path, new: &Path) -> Result<(usize, usize)> {
+        if new.exists() { anyhow::bail!("{} already exists", new.display()); }
+        if let Some(parent) = new.parent() { fs::create_dir_all(parent)?; }
+        fs::rename(old, new)?;
+        comments::follow(old, new)?;
+        let (old_rel, new_rel) = (old.strip_prefix(&self.vault_root)?.to_path_buf(), new.strip_prefix(&self.vault_root)?.to_path_buf());
+        let (old_dir, new_dir) = (old_rel.parent().unwrap_or(Path::new("")), new_rel.parent().unwrap_or(Path::new("")));
+        if old_dir != new_dir {
+            let (text, n) = links::rebase_own_links(&fs::read_to_string(new)?, old_dir, new_dir);
+            if n > 0 { fs::write(new, text)?; }
+        }
+        let counts = self.repoint_links(&old_rel, &new_rel, new)?;
+        self.vault_changed();
+        Ok(counts)
+    }
+
+    /// Renames a category or folder and repoints links to every note inside it.
+    fn move_dir(&mut self, old: &Path, new: &Path) -> Result<(usize, usize)> {
+        if new.exists() { anyhow::bail!("{} already exists", new.display()); }
+        let inside: Vec<PathBuf> = self.notes().iter().filter(|p| p.starts_with(old)).cloned().collect();
+        fs::rename(old, new)?;
+        let (mut refs, mut notes) = (0, 0);
+        for note in inside {
+            let old_rel = note.strip_prefix(&self.vault_root)?.to_path_buf();
+            let new_rel = new.join(note.strip_prefix(old)?).strip_prefix(&self.vault_root)?.to_path_buf();
+            let (r, n) = self.repoint_links(&old_rel, &new_rel, Path::new(""))?;
+            refs += r;
+            notes += n;
+        }
+        self.vault_changed();
+        Ok((refs, notes))
+    }
+
+    /// The item `r` renames and `D` deletes for the current focus; `[Root]` and virtual folders
+    /// are neither.
+    fn focused_item(&self) -> Option<PathBuf> { self.item_in(self.focus) }
+
+    fn item_in(&self, focus: Focus) -> Option<PathBuf> {
+        match focus {
+            Focus::Categories if self.selected_cat != "[Root]" => Some(self.vault_root.join(&self.selected_cat)),
+            Focus::Categories => None,
+            Focus::Subfolders => {
+                let base = if self.selected_cat == "[Root]" { self.vault_root.clone() } else { self.vault_root.join(&self.selected_cat) };
+                self.sub_state.selected().and_then(|i| self.subfolders.get(i)).map(|s| base.join(s))
+            }
+            Focus::Files => self.selected_file().cloned(),
+        }
+    }
+
+    /// Points every link at vault-relative `old_rel` to `new_rel` instead, in all notes except `skip`.
+    fn repoint_links(&self, old_rel: &Path, new_rel: &Path, skip: &Path) -> Result<(usize, usize)> {
+        let mv = links::Move { old: old_rel, new: new_rel };
+        let (mut refs, mut notes) = (0, 0);
+        for note in vault::walk_notes(&self.vault_root, &self.ignore) {
+            if note == skip { continue; }
+            let Ok(text) = fs::read_to_string(&note) else { continue };
+            let (rewritten, n) = links::rewrite_links(&text, note.strip_prefix(&self.vault_root)?, &mv);
+            if n > 0 { fs::write(&note, rewritten)?; refs += n; notes += 1; }
+        }
+        Ok((refs, notes))
+    }
+
+    /// Appends `absorbed` to `target` under source annotations, unions their frontmatter tags,
+    /// repoints links from the absorbed note to the target and moves the absorbed note to the trash.
+    fn merge_notes(&mut self, absorbed: &Path, target: &Path) -> Result<usize> {
+        let protected = |p: &Path| crypt::is_encrypted(p) || lock::is_locked(p);
+        if protected(absorbed) && !protected(target) { anyhow::bail!("its text would end up unencrypted in the target"); }
+        let (target_text, absorbed_text) = (self.read_note(target)?, self.read_note(absorbed)?);
+        let (target_fm, target_body) = frontmatter::split(&target_text);
+        let (absorbed_fm, absorbed_body) = frontmatter::split(&absorbed_text);
+        let mut tags = target_fm.map(|y| frontmatter::list(y, "tags")).unwrap_or_default();
+        for tag in absorbed_fm.map(|y| frontmatter::list(y, "tags")).unwrap_or_default() {
+            if !tags.contains(&tag) { tags.push(tag); }
+        }
+        let fm = match (target_fm, tags.is_empty()) {
+            (Some(y), false) => Some(frontmatter::set_list(y, "tags", &tags)),
+            (None, false) => Some(frontmatter::set_list("", "tags", &tags)),
+            (y, true) => y.map(String::from),
+        };
+        let (target_rel, absorbed_rel) = (target.strip_prefix(&self.vault_root)?.to_path_buf(), absorbed.strip_prefix(&self.vault_root)?.to_path_buf());
+        let today = Local::now().format("%Y-%m-%d");
+        let body = format!(
+            "{}\n\n---\n\n<!-- merged from {} on {} -->\n\n{}\n",
+            target_body.trim_end(), absorbed_rel.display(), today, absorbed_body.trim()
+        );
+        self.write_note(target, &frontmatter::join(fm.as_deref(), &body))?;
+        let (refs, _) = self.repoint_links(&absorbed_rel, &target_rel, absorbed)?;
+        trash::trash(&self.vault_root, absorbed)?;
+        self.vault_changed();
+        Ok(refs)
+    }
+
+    /// Moves the time-travel cursor `delta` revisions back in time (negative steps forward).
+    fn travel_step(&mut self, delta: isize) {
+        if let Some(t) = self.travel.as_mut() {
+            let last = t.revisions.len().saturating_sub(1);
+            t.pos = t.pos.saturating_add_signed(delta).min(last);
+            self.preview = None;
+        }
+    }
+
+    /// Notes matching the `[[query` being typed in the input popup or the built-in editor, as
+    /// (link name, vault-relative path).
+    fn link_candidates(&mut self) -> Vec<(String, String)> {
+        let typed = match self.textarea.as_ref().filter(|_| self.input_mode == InputMode::Edit) {
+            Some(t) => t.before_cursor(),
+            None => &self.input_buffer,
+        };
+        let Some(query) = links::pending_wikilink(typed).map(str::to_string) else { return Vec::new() };
+        let root = self.vault_root.clone();
+        let rels: Vec<String> = self.notes().iter().map(|p| vault::relative(&root, p)).collect();
+        fuzzy::rank(&query, &rels, 8).into_iter().map(|i| (links::link_name(Path::new(&rels[i])), rels[i].clone())).collect()
+    }
+
+    /// Accepts the highlighted `[[` completion; returns false when no link is being typed.
+    fn accept_link(&mut self) -> bool {
+        let candidates = self.link_candidates();
+        let Some((name, _)) = candidates.get(self.link_choice.min(candidates.len().saturating_sub(1))) else { return false };
+        match self.textarea.as_mut().filter(|_| self.input_mode == InputMode::Edit) {
+            Some(t) => t.complete_link(name),
+            None => links::complete_wikilink(&mut self.input_buffer, name),
+        }
+        self.link_choice = 0;
+        true
+    }
+
+    /// Moves the preview link selection; starts at the first (or last) link.
+    fn step_link(&mut self, delta: isize) {
+        let count = self.preview.as_ref().map_or(0, |c| c.links.len());
+        if count == 0 || self.preview_mode != PreviewMode::Note {
+            self.status_msg = Some("No links in this preview".into());
+            return;
+        }
+        self.link_cursor = Some(match self.link_cursor {
+            Some(i) => (i as isize + delta).rem_euclid(count as isize) as usize,
+            None if delta < 0 => count - 1,
+            None => 0,
+        });
+    }
+
+    /// Jumps to the note behind the selected preview link, first creating it from the stub
+    /// template when it doesn't exist yet.
+    fn follow_link(&mut self) -> Result<()> {
+        let Some(note) = self.selected_file().cloned() else { return Ok(()) };
+        let Some(target) = self.link_cursor.take().and_then(|i| self.preview.as_ref()?.links.get(i).cloned()) else { return Ok(()) };
+        let root = self.vault_root.clone();
+        let notes: Vec<PathBuf> = self.notes().iter().filter_map(|p| p.strip_prefix(&root).ok().map(Path::to_path_buf)).collect();
+        let Some(rel) = links::resolve(&target, note.strip_prefix(&root)?, &notes) else {
+            self.status_msg = Some("External links open outside knot".into());
+            return Ok(());
+        };
+        let path = root.join(&rel);
+        if audio::is_audio(&path) && path.is_file() {
+            self.play(&path);
+            return Ok(());
+        }
+        if !vault::is_note(&path) {
+            self.status_msg = Some(format!("{} isn't a note", rel.display()));
+            return Ok(());
+        }
+        if !path.exists() {
+            if let Some(parent) = path.parent() { fs::create_dir_all(parent)?; }
+            fs::write(&path, links::stub(&root, &path))?;
+            self.vault_changed();
+            self.status_msg = Some(format!("Created {}", rel.display()));
+        }
+        self.reveal(&path)
+    }
+
+    /// How long the event loop may block before the next polling refresh, sync tick or autosync is due.
+    fn poll_timeout(&self) -> Option<Duration> {
+        let interval = self.config.refresh_interval;
+        let refresh = (!interval.is_zero()).then(|| interval.saturating_sub(self.last_refresh.elapsed()));
+        let autosync = self.autosync_at.map(|at| at.saturating_duration_since(Instant::now()));
+        let periodic = self.periodic_sync_at.map(|at| at.saturating_duration_since(Instant::now()));
+        let tick = self.sync.is_some().then_some(sync::TICK);
+        let search = self.search.as_ref().is_some_and(|j| !j.done).then_some(search::TICK);
+        let typing = self.search_due.map(|at| at.saturating_duration_since(Instant::now()));
+        let sequence = self.pending.deadline().map(|at| at.saturating_duration_since(Instant::now()));
+        // Once a second for the countdown in the header.
+        let countdown = self.periodic_sync_at.map(|_| Duration::from_secs(1));
+        [refresh, autosync, periodic, tick, search, typing, sequence, countdown].into_iter().flatten().min()
+    }
+
+    /// Something in the vault changed: reindex, and schedule an automatic sync if enabled.
+    fn vault_changed(&mut self) {
+        self.index.invalidate();
+        if self.config.sync.auto { self.autosync_at = Some(Instant::now() + self.config.sync.debounce); }
+    }
+
+    /// Starts the scheduled automatic sync once the quiet period is over and no sync is running,
+    /// and the timed one when its interval is up and there is anything to commit or push.
+    fn tick_autosync(&mut self) {
+        if self.autosync_at.is_some_and(|at| at <= Instant::now()) && self.sync.is_none() {
+            self.autosync_at = None;
+            self.manual_sync();
+        }
+        if self.periodic_sync_at.is_some_and(|at| at <= Instant::now()) && self.sync.is_none() {
+            self.periodic_sync_at = self.config.sync.interval.map(|i| Instant::now() + i);
+            if self.profiler.time("git", || git::has_unsynced(&self.vault_root)) { self.manual_sync(); }
+        }
+    }
+
+    /// Time left until the timed sync, for the header.
+    fn sync_countdown(&self) -> Option<Duration> {
+        self.periodic_sync_at.map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    fn refresh_due(&self) -> bool {
+        let interval = self.config.refresh_interval;
+        !interval.is_zero() && self.last_refresh.elapsed() >= interval
+    }
 
-#[derive(PartialEq)]
-enum InputMode { Normal, NewCat, NewFolder, NewNote, ConfirmDelete }
+    /// Rewrites a markdown note through the formatter, touching the file only if something changed.
+    fn format_note(&mut self, path: &Path) -> Result<()> {
+        if path.extension().is_none_or(|e| e != "md") { return Ok(()); }
+        let original = fs::read_to_string(path)?;
+        let formatted = markdown::format(&original, self.config.format.wrap_width);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if formatted != original {
+            fs::write(path, formatted)?;
+            self.status_msg = Some(format!("Formatted {}", name));
+            self.vault_changed();
+        } else {
+            self.status_msg = Some(format!("{} already formatted", name));
+        }
+        Ok(())
+    }
 
-struct App {
-    vault_root: PathBuf,
-    categories: Vec<String>,
-    subfolders: Vec<String>,
-    files: Vec<PathBuf>,
-    selected_cat: String,
-    selected_sub: Option<String>,
-    sub_state: ListState,
-    file_state: ListState,
-    focus: Focus,
-    input_mode: InputMode,
-    input_buffer: String,
-    should_quit: bool,
-    last_sync: String,
-}
+    /// Moves the selection in the focused Folders or Notes pane to the row `to` picks, given the
+    /// selected row and the number of rows; past the end means the last row.
+    fn select_row(&mut self, to: impl FnOnce(usize, usize) -> usize) -> Result<()> {
+        match self.focus {
+            Focus::Subfolders if self.folder_count() > 0 => {
+                let i = to(self.sub_state.selected().unwrap_or(0), self.folder_count()).min(self.folder_count() - 1);
+                self.sub_state.select(Some(i));
+                self.selected_sub = Some(self.folder_name(i).to_string());
+            }
+            Focus::Files if !self.files.is_empty() => {
+                let i = to(self.file_state.selected().unwrap_or(0), self.files.len()).min(self.files.len() - 1);
+                self.file_state.select(Some(i));
+            }
+            _ => {}
+        }
+        self.hard_refresh()
+    }
 
-impl App {
-    fn new() -> Result<Self> {
-        let mut vault_root = dirs::home_dir().context("Home dir not found")?;
-        vault_root.push(".knot_vault");
-        if !vault_root.exists() { fs::create_dir_all(&vault_root)?; }
-        
-        // Initial init if not exists
-        if !vault_root.join(".git").exists() {
-            let _ = Command::new("git").arg("init").current_dir(&vault_root).status();
+    /// Rows a half-page jump moves, from the panes' height at the last draw.
+    fn half_page(&self) -> usize { (self.list_rows / 2).max(1) }
+
+    /// Starts add/commit/push in the background; progress shows in the TUI until it finishes.
+    fn manual_sync(&mut self) {
+        self.start_sync(None);
+    }
+
+    /// `S`: syncs straight away, or first asks for the commit message when `sync.ask_message` is set.
+    fn sync_key(&mut self) {
+        if !self.config.sync.ask_message || self.sync.is_some() || !self.conflicts.is_empty() { return self.manual_sync(); }
+        let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.input_buffer = self.config.sync.message.replace("{{now}}", &now);
+        self.input_mode = InputMode::CommitMessage;
+    }
+
+    /// Enter in `AltText` mode: copies the pasted image into the note's attachment folder and
+    /// embeds it at the end of the note with the description as alt text, where search finds it.
+    fn attach_image(&mut self) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        let alt = std::mem::take(&mut self.input_buffer).replace(['[', ']'], "").trim().to_string();
+        let (Some(image), Some(note)) = (self.pending_image.take(), self.selected_file().cloned()) else { return Ok(()) };
+        let dir_name = self.config.index.attachment_dirs.first().map_or("attachments", String::as_str);
+        let link = attachments::attach(&note, &image, dir_name)?;
+        let alt = if alt.is_empty() { links::link_name(&image) } else { alt };
+        let text = fs::read_to_string(&note)?;
+        let sep = if text.is_empty() || text.ends_with("\n\n") { "" } else if text.ends_with('\n') { "\n" } else { "\n\n" };
+        fs::write(&note, format!("{}{}![{}]({})\n", text, sep, alt, link))?;
+        self.status_msg = Some(format!("🖼 Attached {}", link.replace("%20", " ")));
+        self.vault_changed();
+        Ok(())
+    }
+
+    /// `knot tutorial`: writes the lesson notes and shows them with the first step.
+    fn start_tutorial(&mut self) -> Result<()> {
+        self.tutorial = Some(tutorial::Tutorial::start(&self.vault_root)?);
+        self.vault_changed();
+        self.selected_cat = tutorial::DIR.to_string();
+        self.selected_sub = None;
+        self.focus = Focus::Categories;
+        self.hard_refresh()
+    }
+
+    /// Opens `Unlock` mode to ask for a passphrase for `request`.
+    fn ask_passphrase(&mut self, request: LockRequest) {
+        self.lock_request = Some(request);
+        self.input_buffer.clear();
+        self.input_mode = InputMode::Unlock;
+    }
+
+    /// Q: shows the selected note as a QR code for a phone to scan: its text if it fits on
+    /// screen, otherwise the address of a server on this machine that lasts until it's closed.
+    fn share_note(&mut self) {
+        let Some(path) = self.selected_file().cloned() else { return };
+        if !vault::is_note(&path) {
+            self.status_msg = Some(if lock::is_locked(&path) || crypt::is_encrypted(&path) { "Locked and encrypted notes aren't shared".into() } else { "Only notes can be shared".into() });
+            return;
+        }
+        // Each line of the popup shows two rows of modules, inside a border and a caption.
+        let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+        let max_size = (cols as usize).saturating_sub(2).min((rows as usize).saturating_sub(4) * 2).saturating_sub(2 * share::QUIET);
+        match share::Share::new(&path, max_size) {
+            Ok(share) => { self.share = Some(share); self.input_mode = InputMode::Share; }
+            Err(e) => self.status_msg = Some(format!("❌ Can't share: {:#}", e)),
         }
+    }
 
-        let mut app = Self {
-            vault_root,
-            categories: Vec::new(),
-            subfolders: Vec::new(),
-            files: Vec::new(),
-            selected_cat: "[Root]".to_string(),
-            selected_sub: None,
-            sub_state: ListState::default(),
-            file_state: ListState::default(),
-            focus: Focus::Categories,
-            input_mode: InputMode::Normal,
-            input_buffer: String::new(),
-            should_quit: false,
-            last_sync: "Manual".into(),
-        };
-        app.hard_refresh()?;
-        Ok(app)
+    /// `x`: lists the contexts from the config to switch to.
+    fn choose_context(&mut self) {
+        if self.config.contexts.is_empty() {
+            self.status_msg = Some("No contexts yet · define them as [contexts.<name>] in the config".into());
+            return;
+        }
+        let items = self.config.contexts.iter().map(context::describe).collect();
+        self.picker = Some(Picker::new(PickerKind::Contexts, " Contexts · [ENTER] Switch ", items));
+        self.input_mode = InputMode::Picker;
     }
 
-    fn hard_refresh(&mut self) -> Result<()> {
-        let mut cats = vec!["[Root]".to_string()];
-        if let Ok(entries) = fs::read_dir(&self.vault_root) {
-            for entry in entries.flatten() {
-                if entry.path().is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
-                    cats.push(entry.file_name().to_string_lossy().to_string());
-                }
+    /// `:`: every command there is a key for, to find by name and run. Those for a note, folder
+    /// or category are only listed while there's one to act on.
+    fn open_palette(&mut self) {
+        self.commands.clear();
+        let mut items = Vec::new();
+        for &(action, text, scope) in keymap::PALETTE {
+            let focus = match scope {
+                keymap::Scope::Notes => Focus::Files,
+                keymap::Scope::Folders => Focus::Subfolders,
+                keymap::Scope::Categories => Focus::Categories,
+                _ => self.focus,
+            };
+            if scope != keymap::Scope::Any && self.item_in(focus).is_none() { continue; }
+            let keys = self.config.keys.label(action).unwrap_or_default();
+            items.push(format!("{:<32} {}", text, keys));
+            self.commands.push((action, focus));
+        }
+        self.picker = Some(Picker::new(PickerKind::Commands, " Commands · [ENTER] Run ", items));
+        self.input_mode = InputMode::Picker;
+    }
+
+    /// Sets up everything `ctx` names, opening its vault first if it's another one. What it
+    /// leaves out goes back to the defaults, except where the browser is.
+    fn switch_context(&mut self, ctx: &context::Context) -> Result<()> {
+        let vault = ctx.vault.as_ref().map(std::path::absolute).transpose()?;
+        if let Some(vault) = vault.filter(|v| *v != self.vault_root) {
+            if self.sync.is_some() {
+                self.status_msg = Some("Wait for the sync to finish before switching vaults".into());
+                return Ok(());
             }
+            // Everything for the new vault is ready before anything of this one goes, so a
+            // failure leaves this one as it was.
+            let mut config = Config::load()?;
+            config.vault_path = vault.clone();
+            let config = config.with_vault_overrides()?;
+            let crypt = crypt::Crypt::load_without_asking(&config.encryption, config.keyring);
+            let mut app = App::new(config, Profiler::new(false))?;
+            let _ = self.save_session();
+            // No worker thread may read the environment while it changes.
+            if let Some(job) = self.search.take() { job.finish(); }
+            self.share = None;
+            // Through the environment, like --vault, so git's credential helper calls agree.
+            std::env::set_var(config::VAULT_ENV, &vault);
+            std::mem::swap(&mut app.profiler, &mut self.profiler);
+            match crypt {
+                Ok(crypt) => app.crypt = crypt,
+                Err(e) => app.status_msg = Some(format!("⚠ Encrypted notes unavailable: {:#}", e)),
+            }
+            *self = app;
         }
-        cats.sort();
-        self.categories = cats;
+        if let Some(category) = &ctx.category {
+            self.selected_cat = category.clone();
+            self.selected_sub = ctx.folder.clone();
+            self.sub_state.select(None);
+        }
+        self.tag_filter = ctx.tag.clone();
+        self.author_filter = ctx.author.clone();
+        self.age_filter = ctx.age.as_deref().and_then(AgeFilter::parse).unwrap_or(AgeFilter::All);
+        self.sort = ctx.sort;
+        self.layout = ctx.layout;
+        self.context = Some(ctx.name.clone());
+        self.focus = Focus::Files;
+        self.file_state.select(None);
+        self.status_msg = Some(format!("Context: {}", ctx.name));
+        self.hard_refresh()
+    }
 
-        if !self.categories.contains(&self.selected_cat) {
-            self.selected_cat = "[Root]".to_string();
+    /// `e`: opens the selected note in the built-in editor.
+    fn quick_edit(&mut self) {
+        let Some(path) = self.selected_file().cloned() else { return };
+        if !vault::is_note(&path) {
+            self.status_msg = Some(if lock::is_locked(&path) || crypt::is_encrypted(&path) { "Locked and encrypted notes open in the external editor [ENTER]".into() } else { "Only notes can be edited here".into() });
+            return;
+        }
+        match textarea::TextArea::open(&path) {
+            Ok(t) => { self.textarea = Some(t); self.input_mode = InputMode::Edit; }
+            Err(e) => self.status_msg = Some(format!("❌ Can't open the note: {}", e)),
         }
+    }
 
-        let cat_path = if self.selected_cat == "[Root]" { self.vault_root.clone() } else { self.vault_root.join(&self.selected_cat) };
-        let mut subs = Vec::new();
-        if let Ok(entries) = fs::read_dir(&cat_path) {
-            for entry in entries.flatten() {
-                if entry.path().is_dir() {
-                    subs.push(entry.file_name().to_string_lossy().to_string());
-                }
-            }
+    /// Leaves the built-in editor, formatting the note if it was saved and `format.on_save` asks.
+    fn close_textarea(&mut self) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        let Some(t) = self.textarea.take() else { return Ok(()) };
+        if t.written && self.config.format.on_save { self.format_note(&t.path)?; }
+        self.vault_changed();
+        self.hard_refresh()
+    }
+
+    /// X: locks the selected note behind a new passphrase, or removes the lock from a locked one.
+    fn toggle_lock(&mut self) -> Result<()> {
+        let Some(path) = self.selected_file().cloned() else { return Ok(()) };
+        if !lock::is_locked(&path) {
+            if !vault::is_note(&path) { self.status_msg = Some("Only notes can be locked".into()); return Ok(()); }
+            self.ask_passphrase(LockRequest::Lock { path, first: None });
+            return Ok(());
         }
-        subs.sort();
-        self.subfolders = subs;
+        self.recall_passphrase(&path);
+        match self.passphrases.get(&path).cloned() {
+            Some(passphrase) => self.remove_lock(&path, &passphrase),
+            None => { self.ask_passphrase(LockRequest::Remove(path)); Ok(()) }
+        }
+    }
 
-        if let Some(ref sub_name) = self.selected_sub {
-            if let Some(pos) = self.subfolders.iter().position(|s| s == sub_name) {
-                self.sub_state.select(Some(pos));
-            } else {
-                self.selected_sub = None;
-                self.sub_state.select(if self.subfolders.is_empty() { None } else { Some(0) });
+    /// Keeps a locked note's passphrase for the session and, with `secrets.keyring` on, in the
+    /// keyring for later ones.
+    fn remember_passphrase(&mut self, path: PathBuf, passphrase: String) {
+        if self.config.keyring {
+            if let Err(e) = secrets::set(&lock::keyring_account(&path), &passphrase) {
+                self.status_msg = Some(format!("Passphrase kept for this session only; the keyring refused it: {:#}", e));
             }
         }
+        self.passphrases.insert(path, passphrase);
+    }
 
-        let mut file_path = cat_path;
-        if let Some(si) = self.sub_state.selected() {
-            if si < self.subfolders.len() {
-                file_path.push(&self.subfolders[si]);
+    /// Fills in a locked note's passphrase from the keyring, if it's there and still right.
+    fn recall_passphrase(&mut self, path: &Path) {
+        if !self.config.keyring || self.passphrases.contains_key(path) || !self.keyring_checked.insert(path.to_path_buf()) { return; }
+        if let Ok(Some(passphrase)) = secrets::get(&lock::keyring_account(path)) {
+            if lock::read(path, &passphrase).is_ok() { self.passphrases.insert(path.to_path_buf(), passphrase); }
+        }
+    }
+
+    fn remove_lock(&mut self, path: &Path, passphrase: &str) -> Result<()> {
+        match lock::unlock(path, passphrase) {
+            Ok(plain) => {
+                self.passphrases.remove(path);
+                if self.config.keyring { let _ = secrets::delete(&lock::keyring_account(path)); }
+                self.vault_changed();
+                self.status_msg = Some(format!("🔓 Removed the lock from {}", vault::relative(&self.vault_root, &plain)));
+                self.reveal(&plain)
             }
+            Err(e) => { self.status_msg = Some(format!("❌ {:#}", e)); Ok(()) }
         }
+    }
 
-        let mut files = Vec::new();
-        if let Ok(entries) = fs::read_dir(&file_path) {
-            for entry in entries.flatten() {
-                let p = entry.path();
-                if p.is_file() && !p.file_name().unwrap().to_string_lossy().starts_with('.') {
-                    files.push(p);
+    /// Enter in `Unlock` mode. A wrong passphrase keeps the prompt open for another try.
+    fn submit_passphrase(&mut self) -> Result<()> {
+        let input = std::mem::take(&mut self.input_buffer);
+        let Some(request) = self.lock_request.take() else { self.input_mode = InputMode::Normal; return Ok(()) };
+        match request {
+            LockRequest::Open(path) => match lock::read(&path, &input) {
+                Ok(_) => {
+                    self.input_mode = InputMode::Normal;
+                    self.remember_passphrase(path, input);
+                    self.preview = None;
+                    self.status_msg = Some("🔓 Unlocked until knot exits · [ENTER] Edit".into());
+                }
+                Err(e) => {
+                    self.status_msg = Some(format!("❌ {:#}", e));
+                    self.lock_request = Some(LockRequest::Open(path));
                 }
+            },
+            LockRequest::Lock { path, first: None } => {
+                if input.is_empty() { self.status_msg = Some("The passphrase can't be empty".into()); }
+                let first = Some(input).filter(|i| !i.is_empty());
+                self.lock_request = Some(LockRequest::Lock { path, first });
+            }
+            LockRequest::Lock { path, first: Some(first) } => {
+                self.input_mode = InputMode::Normal;
+                if first != input { self.status_msg = Some("❌ The passphrases didn't match; the note is unchanged".into()); return Ok(()); }
+                match lock::lock(&path, &input) {
+                    Ok(locked) => {
+                        self.vault_changed();
+                        self.status_msg = Some(format!("🔒 Locked {}", vault::relative(&self.vault_root, &locked)));
+                        self.remember_passphrase(locked.clone(), input);
+                        return self.reveal(&locked);
+                    }
+                    Err(e) => self.status_msg = Some(format!("❌ {:#}", e)),
+                }
+            }
+            LockRequest::Remove(path) => {
+                self.input_mode = InputMode::Normal;
+                return self.remove_lock(&path, &input);
             }
-        }
-        files.sort_by_key(|p| std::cmp::Reverse(fs::metadata(p).and_then(|m| m.modified()).unwrap_or(std::time::SystemTime::UNIX_EPOCH)));
-        self.files = files;
-        
-        if self.file_state.selected().map_or(true, |i| i >= self.files.len()) {
-            self.file_state.select(if self.files.is_empty() { None } else { Some(0) });
         }
         Ok(())
     }
 
-    fn manual_sync(&mut self) -> Result<()> {
+    /// Asks for a username and token for the HTTP(S) remote, stores them in the keyring like
+    /// `knot credential setup` and retries the sync that needed them.
+    fn ask_credentials(&mut self, retry: SyncKind) {
+        let (protocol, host) = credentials::http_remote(&self.vault_root).unwrap_or_default();
+        self.status_msg = Some(format!("🔑 {}://{} wants a username and token", protocol, host));
+        self.auth_retry = Some(retry);
+        self.credential_user = None;
+        self.input_buffer.clear();
+        self.input_mode = InputMode::Credential;
+    }
+
+    /// Enter in `Credential` mode: takes the username, then the token, then retries the sync.
+    fn submit_credential(&mut self) {
+        let input = std::mem::take(&mut self.input_buffer);
+        let Some(user) = self.credential_user.take() else {
+            self.credential_user = Some(input.trim().to_string());
+            return;
+        };
+        self.input_mode = InputMode::Normal;
+        if let Err(e) = credentials::store(&self.vault_root, &user, &input) {
+            self.status_msg = Some(format!("❌ Couldn't save the credentials: {}", e));
+            return;
+        }
+        self.status_msg = Some("🔑 Credentials saved to the keyring".into());
+        match self.auth_retry.take() {
+            Some(SyncKind::Push) => self.manual_sync(),
+            Some(SyncKind::Pull) => self.sync = Some(SyncJob::pull(self.vault_root.clone(), &self.config.sync)),
+            None => {}
+        }
+    }
+
+    fn start_sync(&mut self, message: Option<String>) {
+        if self.sync.is_some() { self.status_msg = Some("Sync already running".into()); return; }
+        // Committing now would commit the conflict markers.
+        if !self.conflicts.is_empty() { self.status_msg = Some("Resolve the conflicts first".into()); return; }
         let now = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        
-        // Temporarily leave TUI to show Git output
-        execute!(io::stdout(), LeaveAlternateScreen)?;
-        disable_raw_mode()?;
+        self.sync = Some(SyncJob::push(self.vault_root.clone(), &self.config.sync, now, message));
+    }
 
-        println!("\n--- STARTING GIT SYNC ---");
-        let _ = Command::new("git").arg("add").arg(".").current_dir(&self.vault_root).status();
-        let _ = Command::new("git").arg("commit").arg("-m").arg(format!("Manual Sync: {}", now)).current_dir(&self.vault_root).status();
-        
-        println!("Pushing to remote...");
-        let status = Command::new("git").arg("push").current_dir(&self.vault_root).status();
-        
-        if let Ok(s) = status {
-            if s.success() { println!("\n✅ Sync Successful!"); }
-            else { println!("\n❌ Sync Failed. Check your network or remote settings."); }
+    /// Quits straight away when everything is pushed, otherwise asks first.
+    fn request_quit(&mut self) {
+        let unsynced = |root: &Path| git::has_unsynced(root)
+            || nested::categories(root, &self.config.sync.categories).iter().any(|n| n.is_repo() && git::has_unsynced(&n.dir));
+        if self.sync.is_some() || self.profiler.time("git", || unsynced(&self.vault_root)) {
+            self.input_mode = InputMode::ConfirmQuit;
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    /// Picks up output from a running sync and wraps it up once the worker is done.
+    fn poll_sync(&mut self) {
+        let Some(job) = self.sync.as_mut() else { return };
+        job.poll();
+        // Keep the spinner moving even when git is quiet.
+        self.dirty = true;
+        let Some(result) = job.finished.take() else { return };
+        let job = self.sync.take().expect("checked above");
+        self.profiler.record("sync", job.started.elapsed());
+        self.last_sync_ok = Some(result.is_ok());
+        // What came in stays in even when a later step failed.
+        if let Err(e) = incoming::record(&self.vault_root, &job.incoming) { self.status_msg = Some(format!("Could not queue what's new: {}", e)); }
+        if job.kind == SyncKind::Push {
+            // Any finished sync restarts the timer, so a manual one isn't followed by a redundant one.
+            self.periodic_sync_at = self.config.sync.interval.map(|i| Instant::now() + i);
+        }
+        if let Err(git::GitError::Conflict(files)) = result {
+            self.quit_after_sync = false;
+            self.status_msg = Some(format!("⚠ Sync stopped: {} file{} conflict with the remote", files.len(), if files.len() == 1 { "" } else { "s" }));
+            self.show_conflicts(job.repo.clone(), files);
+        } else if matches!(result, Err(git::GitError::Auth(_))) && job.category.is_none() && self.input_mode == InputMode::Normal && self.config.keyring && credentials::http_remote(&self.vault_root).is_some() {
+            self.quit_after_sync = false;
+            self.ask_credentials(job.kind);
+        } else if let Err(err) = result {
+            self.quit_after_sync = false;
+            let place = job.category.as_ref().map(|c| format!(" in {}", c)).unwrap_or_default();
+            self.status_msg = Some(match job.kind {
+                SyncKind::Push => format!("❌ Sync failed{}: {}", place, err),
+                SyncKind::Pull => format!("❌ Pull failed{}: {}", place, err),
+            });
+        } else if job.kind == SyncKind::Pull {
+            self.status_msg = Some("✅ Vault up to date".into());
+            if self.quit_after_sync { self.manual_sync(); }
+        } else {
+            let _ = status::record_sync(&self.vault_root, &job.stamp);
+            self.last_sync = job.stamp;
+            self.status_msg = Some(match &job.integrated {
+                Some(summary) => format!("✅ Sync successful · {}", summary),
+                None => "✅ Sync successful".into(),
+            });
+            if self.quit_after_sync { self.should_quit = true; }
         }
+        if let Some(msg) = &self.status_msg { self.notify_finished(job.started, "knot sync", msg); }
+        // New commits change what blame shows even though the file itself is untouched.
+        self.preview = None;
+        self.index.invalidate();
+        self.authors = None;
+        if let Err(err) = self.hard_refresh() { self.status_msg = Some(err.to_string()); }
+        if !job.incoming.is_empty() && self.input_mode == InputMode::Normal && !self.should_quit { self.open_incoming(); }
+    }
 
-        print!("\nPress [ENTER] to return to KNOT...");
-        io::stdout().flush()?;
-        let mut temp = String::new();
-        io::stdin().read_line(&mut temp)?;
+    fn show_conflicts(&mut self, repo: PathBuf, files: Vec<String>) {
+        self.conflicts = files;
+        self.conflict_cursor = 0;
+        self.conflicts_rebase = git::rebasing(&repo);
+        self.conflicts_repo = repo;
+        self.input_mode = InputMode::Conflicts;
+    }
 
-        enable_raw_mode()?;
-        execute!(io::stdout(), EnterAlternateScreen)?;
-        self.last_sync = now;
-        Ok(())
+    /// Resolves the selected file by keeping one side, or (`None`) checks it after hand-editing.
+    /// Once nothing is left, the rebase or merge carries on and the sync is pushed.
+    fn resolve_conflict(&mut self, side: Option<git::Side>) {
+        let Some(rel) = self.conflicts.get(self.conflict_cursor).cloned() else { return };
+        let resolved = match side {
+            Some(side) => git::take_side(&self.conflicts_repo, &rel, side).map(|_| true),
+            None => git::mark_resolved(&self.conflicts_repo, &rel),
+        };
+        match resolved {
+            Ok(true) => self.status_msg = Some(format!("Resolved {}", rel)),
+            Ok(false) => self.status_msg = Some(format!("{} still has conflict markers", rel)),
+            Err(e) => self.status_msg = Some(format!("Can't resolve {}: {}", rel, e)),
+        }
+        self.conflicts = git::conflicted(&self.conflicts_repo);
+        if self.conflicts.is_empty() {
+            match git::continue_after_conflicts(&self.conflicts_repo) {
+                // The rebase stopped again on the next commit.
+                Ok(next) if !next.is_empty() => {
+                    self.status_msg = Some(format!("⚠ {} more conflicted file{}", next.len(), if next.len() == 1 { "" } else { "s" }));
+                    self.show_conflicts(self.conflicts_repo.clone(), next);
+                    return;
+                }
+                Ok(_) => {
+                    self.input_mode = InputMode::Normal;
+                    self.manual_sync();
+                }
+                Err(e) => {
+                    self.input_mode = InputMode::Normal;
+                    self.status_msg = Some(format!("❌ Sync failed: {}", e));
+                }
+            }
+        }
+        self.conflict_cursor = self.conflict_cursor.min(self.conflicts.len().saturating_sub(1));
+        self.vault_changed();
+        if let Err(err) = self.hard_refresh() { self.status_msg = Some(err.to_string()); }
+    }
+
+    /// Undoes the stopped rebase or merge; local changes stay committed, nothing is pushed.
+    fn abort_conflicts(&mut self) {
+        self.status_msg = Some(match git::abort_after_conflicts(&self.conflicts_repo) {
+            Ok(()) => "Sync abandoned · your changes are kept locally, nothing was pushed".into(),
+            Err(e) => format!("Can't abandon the sync: {}", e),
+        });
+        self.conflicts.clear();
+        self.input_mode = InputMode::Normal;
+        self.vault_changed();
+        if let Err(err) = self.hard_refresh() { self.status_msg = Some(err.to_string()); }
+    }
+
+    /// Desktop notification for finished background work, skipped when it was too quick to miss.
+    fn notify_finished(&self, started: Instant, title: &str, body: &str) {
+        if started.elapsed() >= self.config.notify.min_duration { notify::send(&self.config.notify, title, body); }
+    }
+}
+
+/// A note named on the command line: a vault-relative path (`.md` optional) or any markdown file.
+fn read_note_arg(note: &str) -> Result<String> {
+    let path = if Path::new(note).is_file() { PathBuf::from(note) } else {
+        Config::load()?.with_vault_overrides()?.vault_path.join(format!("{}.md", note.trim_end_matches(".md")))
+    };
+    fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))
+}
+
+/// Export output goes to `-o FILE` when given, stdout otherwise.
+fn write_export(output: Option<PathBuf>, contents: &str) -> Result<()> {
+    match output {
+        Some(file) => fs::write(&file, contents).with_context(|| format!("writing {}", file.display())),
+        None => { print!("{}", contents); Ok(()) }
+    }
+}
+
+/// A note's metadata for `--json` output: modification time, word count (frontmatter aside) and
+/// size in bytes.
+fn note_fields(path: &Path) -> [(&'static str, json::Json); 3] {
+    let meta = fs::metadata(path).ok();
+    let modified = meta.as_ref().and_then(|m| m.modified().ok()).map(|t| chrono::DateTime::<Local>::from(t).to_rfc3339_opts(chrono::SecondsFormat::Secs, false));
+    let words = fs::read_to_string(path).ok().map(|text| frontmatter::split(&text).1.split_whitespace().count());
+    [("modified", modified.into()), ("words", words.into()), ("bytes", meta.map(|m| m.len() as i64).into())]
+}
+
+/// "note.md" for one note, "3 notes" for several.
+fn describe(notes: &[PathBuf]) -> String {
+    match notes {
+        [one] => one.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        many => format!("{} notes", many.len()),
+    }
+}
+
+/// Runs a Normal-mode action, with the count typed before it for the motions that take one.
+fn normal_action(app: &mut App, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, action: Action, count: Option<usize>) -> Result<()> {
+    match action {
+        Action::Quit => app.request_quit(),
+        Action::Sync => app.sync_key(),
+        Action::Focus => app.focus = match app.focus { 
+            Focus::Categories => Focus::Subfolders, 
+            Focus::Subfolders => Focus::Files, 
+            Focus::Files => Focus::Categories 
+        },
+        Action::Left if app.preview_mode == PreviewMode::TimeTravel => app.travel_step(1),
+        Action::Right if app.preview_mode == PreviewMode::TimeTravel => app.travel_step(-1),
+        Action::Left if app.preview_mode == PreviewMode::WordDiff => { app.diff_days += 1; app.preview = None; }
+        Action::Right if app.preview_mode == PreviewMode::WordDiff => { app.diff_days = app.diff_days.saturating_sub(1).max(1); app.preview = None; }
+        Action::Back if app.preview_mode != PreviewMode::Note => app.preview_mode = PreviewMode::Note,
+        Action::Back if app.link_cursor.is_some() => app.link_cursor = None,
+        Action::Back if app.merge_source.is_some() => { app.merge_source = None; app.status_msg = Some("Merge cancelled".into()); }
+        Action::Back if !app.pending_move.is_empty() => { app.pending_move.clear(); app.status_msg = Some("Move cancelled".into()); }
+        Action::Back if app.tag_filter.is_some() || app.author_filter.is_some() => {
+            app.tag_filter = None;
+            app.author_filter = None;
+            app.hard_refresh()?;
+        }
+        Action::Back if !app.marked.is_empty() => { app.marked.clear(); app.range_anchor = None; app.status_msg = Some("Selection cleared".into()); }
+        Action::Left => {
+            let cur_idx = app.categories.iter().position(|c| c == &app.selected_cat).unwrap_or(0);
+            let new_idx = if cur_idx == 0 { app.categories.len() - 1 } else { cur_idx - 1 };
+            app.selected_cat = app.categories[new_idx].clone();
+            app.hard_refresh()?;
+        }
+        Action::Right => {
+            let cur_idx = app.categories.iter().position(|c| c == &app.selected_cat).unwrap_or(0);
+            let new_idx = (cur_idx + 1) % app.categories.len();
+            app.selected_cat = app.categories[new_idx].clone();
+            app.hard_refresh()?;
+        }
+        Action::Down => match count {
+            // A single step wraps around the list; counted moves stop at its end, as in vim.
+            None => app.select_row(|i, len| (i + 1) % len)?,
+            Some(n) => app.select_row(|i, _| i + n)?,
+        },
+        Action::Up => match count {
+            None => app.select_row(|i, len| if i == 0 { len - 1 } else { i - 1 })?,
+            Some(n) => app.select_row(|i, _| i.saturating_sub(n))?,
+        },
+        // With a count, that row instead, like vim's `5G`.
+        Action::Top => app.select_row(|_, _| count.map_or(0, |n| n - 1))?,
+        Action::Bottom => app.select_row(|_, len| count.map_or(len - 1, |n| n - 1))?,
+        Action::HalfPageDown => { let rows = app.half_page() * count.unwrap_or(1); app.select_row(|i, _| i + rows)?; }
+        Action::HalfPageUp => { let rows = app.half_page() * count.unwrap_or(1); app.select_row(|i, _| i.saturating_sub(rows))?; }
+        Action::NewCategory => { app.input_mode = InputMode::NewCat; app.input_buffer.clear(); }
+        Action::NewFolder => { app.input_mode = InputMode::NewFolder; app.input_buffer.clear(); }
+        Action::NewNote => app.new_note(),
+        Action::RawPreview => app.raw_preview = !app.raw_preview,
+        Action::MoreKeys => app.hints_expanded = !app.hints_expanded,
+        Action::AgeFilter => { app.age_filter = app.age_filter.next(); app.hard_refresh()?; }
+        Action::Blame if app.focus == Focus::Files => {
+            app.preview_mode = if app.preview_mode == PreviewMode::Blame { PreviewMode::Note } else { PreviewMode::Blame };
+        }
+        Action::Changes if app.focus == Focus::Files => {
+            app.preview_mode = if app.preview_mode == PreviewMode::Diff { PreviewMode::Note } else { PreviewMode::Diff };
+        }
+        Action::TimeTravel if app.focus == Focus::Files => {
+            app.preview_mode = if app.preview_mode == PreviewMode::TimeTravel { PreviewMode::Note } else { PreviewMode::TimeTravel };
+            app.travel = None;
+        }
+        Action::Rename => {
+            if let Some(p) = app.focused_item() {
+                app.input_buffer = if p.is_dir() { p.file_name().unwrap_or_default().to_string_lossy().to_string() } else { links::link_name(&p) };
+                app.input_mode = InputMode::Rename;
+            }
+        }
+        Action::Share if app.focus == Focus::Files => app.share_note(),
+        Action::QuickEdit if app.focus == Focus::Files => app.quick_edit(),
+        Action::Contexts => app.choose_context(),
+        Action::Palette => app.open_palette(),
+        Action::Incoming => app.open_incoming(),
+        Action::WordChanges if app.focus == Focus::Files => {
+            app.preview_mode = if app.preview_mode == PreviewMode::WordDiff { PreviewMode::Note } else { PreviewMode::WordDiff };
+        }
+        Action::Answers if app.focus == Focus::Files => {
+            app.preview_mode = if app.preview_mode == PreviewMode::Answers { PreviewMode::Note } else { PreviewMode::Answers };
+        }
+        Action::Journal => {
+            match journal::today(&app.vault_root, &app.config.journal_path) {
+                Ok(p) => {
+                    app.vault_changed();
+                    app.reveal(&p)?;
+                    edit_note(app, terminal, &p, 1)?;
+                }
+                Err(e) => app.status_msg = Some(format!("Couldn't create today's journal: {}", e)),
+            }
+        }
+        Action::Lock if app.focus == Focus::Files => app.toggle_lock()?,
+        Action::Archive if app.focus == Focus::Categories => app.archive_selected_category()?,
+        Action::Unarchive => {
+            if app.archived.is_empty() {
+                app.status_msg = Some("No archived categories".into());
+            } else {
+                app.picker = Some(Picker::new(PickerKind::Unarchive, " Archived categories ", app.archived.iter().cloned().collect()));
+                app.input_mode = InputMode::Picker;
+            }
+        }
+        Action::Merge if app.focus == Focus::Files => {
+            if let Some(target) = app.selected_file().cloned() {
+                match app.merge_source.take() {
+                    Some(source) if source != target => {
+                        let name = |p: &Path| p.file_name().unwrap_or_default().to_string_lossy().to_string();
+                        app.status_msg = Some(match app.merge_notes(&source, &target) {
+                            Ok(refs) => format!("Merged {} into {} · {} links repointed", name(&source), name(&target), refs),
+                            Err(e) => format!("Merge failed: {}", e),
+                        });
+                        app.hard_refresh()?;
+                    }
+                    Some(_) => app.status_msg = Some("Merge cancelled".into()),
+                    None => {
+                        app.status_msg = Some(format!("Merging {} · select the note to merge into and press [{}]", target.file_name().unwrap_or_default().to_string_lossy(), app.config.keys.key(Action::Merge)));
+                        app.merge_source = Some(target);
+                    }
+                }
+            }
+        }
+        Action::Jump => app.quick_switch(),
+        Action::TagFilter => app.choose_tag(),
+        Action::AuthorFilter => app.choose_author(),
+        Action::Themes => app.choose_theme(),
+        Action::Move if app.focus == Focus::Files => {
+            let targets = app.batch_targets();
+            if !targets.is_empty() && targets == app.pending_move {
+                app.pending_move.clear();
+                app.status_msg = Some("Move cancelled".into());
+            } else if !targets.is_empty() {
+                app.status_msg = Some(format!("Moving {} · go to the destination and press [{}], or [{}] to pick one", describe(&targets), app.config.keys.key(Action::Paste), app.config.keys.key(Action::MoveTo)));
+                app.pending_move = targets;
+                app.marked.clear();
+            }
+        }
+        Action::Paste if !app.pending_move.is_empty() => { let dir = app.browsed_dir(); app.paste_move(&dir)?; }
+        Action::Mark if app.focus == Focus::Files => {
+            if let Some(p) = app.selected_file().cloned() {
+                if !app.marked.remove(&p) { app.marked.insert(p); }
+            }
+        }
+        Action::MarkRange if app.focus == Focus::Files => app.mark_range(),
+        Action::MarkAll if app.focus == Focus::Files => { let listed = app.files.clone(); app.mark_all(listed); }
+        Action::Outline if app.focus == Focus::Files => {
+            if let Some(p) = app.selected_file().cloned() {
+                match outline::Outline::open(&p) {
+                    Ok(o) => { app.outline = Some(o); app.input_mode = InputMode::Outline; }
+                    Err(e) => app.status_msg = Some(format!("Can't open outline: {}", e)),
+                }
+            }
+        }
+        Action::Tag if app.focus == Focus::Files && !app.batch_targets().is_empty() => { app.input_mode = InputMode::Tag; app.input_buffer.clear(); }
+        Action::MoveTo => app.choose_move_target(),
+        Action::Search => { app.input_mode = InputMode::Search; app.input_buffer.clear(); }
+        Action::Append if app.focus == Focus::Files && app.file_state.selected().is_some() => { app.input_mode = InputMode::Append; app.input_buffer.clear(); }
+        Action::Comments if app.focus == Focus::Files => app.open_comments(),
+        Action::Delete => { app.input_mode = InputMode::ConfirmDelete; }
+        Action::Orphans => app.collect_garbage(),
+        Action::Trash => app.open_trash(),
+        Action::ReadingList => app.open_reading(),
+        Action::Tasks => app.open_tasks(),
+        Action::Images => app.open_gallery(),
+        Action::Audio => app.open_audio(),
+        Action::History if app.focus == Focus::Files => app.open_history(),
+        Action::Agenda => app.open_agenda(),
+        Action::ReadLater => { app.input_mode = InputMode::ReadLater; app.input_buffer.clear(); }
+        Action::Format if app.focus == Focus::Files => {
+            if let Some(i) = app.file_state.selected() {
+                let p = app.files[i].clone();
+                app.format_note(&p)?;
+                app.hard_refresh()?;
+            }
+        }
+        Action::NextLink => app.step_link(1),
+        Action::PrevLink => app.step_link(-1),
+        Action::Open if app.link_cursor.is_some() => app.follow_link()?,
+        Action::Open if app.focus == Focus::Files => {
+            if let Some(p) = app.selected_file().cloned() { edit_note(app, terminal, &p, 1)?; }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Hands the terminal to the editor for `path` at `line`, then picks up whatever changed.
+fn edit_note(app: &mut App, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, path: &Path, line: usize) -> Result<()> {
+    if lock::is_locked(path) { return edit_locked(app, terminal, path, line); }
+    // Encrypted notes are edited as a decrypted copy that is encrypted back afterwards.
+    let copy = match (crypt::is_encrypted(path), app.crypt.as_ref()) {
+        (false, _) => None,
+        (true, None) => { app.status_msg = Some("Set encryption.identity to edit encrypted notes".into()); return Ok(()); }
+        (true, Some(crypt)) => match crypt.open_for_editing(path) {
+            Ok(copy) => Some(copy),
+            Err(e) => { app.status_msg = Some(format!("❌ {:#}", e)); return Ok(()); }
+        },
+    };
+    // Decrypted copies are private to this edit; the note itself may change underneath it.
+    let guard = copy.is_none().then(|| guard::watch(path));
+    execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?; disable_raw_mode()?;
+    let cmdline = editor::resolve(app.config.editor.as_deref());
+    let _ = editor::command(&cmdline, copy.as_deref().unwrap_or(path), line).status();
+    enable_raw_mode()?; execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    if let Some(clash) = guard.and_then(guard::Guard::finish) {
+        app.status_msg = Some(format!("⚠ {} changed on disk while you were editing it", path.file_name().unwrap_or_default().to_string_lossy()));
+        app.clash = Some(clash);
+        app.input_mode = InputMode::EditClash;
+    }
+    if app.config.format.on_save { app.format_note(copy.as_deref().unwrap_or(path))?; }
+    if let (Some(copy), Some(crypt)) = (&copy, &app.crypt) {
+        if let Err(e) = crypt.finish_editing(copy, path) {
+            app.status_msg = Some(format!("❌ Couldn't encrypt the note; your edit is in {} until knot exits: {:#}", copy.display(), e));
+        }
     }
+    app.vault_changed();
+    app.hard_refresh()?;
+    terminal.clear()?;
+    Ok(())
+}
+
+/// Edits a `.locked` note as a copy in a private temporary folder, asking for its passphrase
+/// first if it hasn't been unlocked this session.
+fn edit_locked(app: &mut App, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, path: &Path, line: usize) -> Result<()> {
+    app.recall_passphrase(path);
+    let Some(passphrase) = app.passphrases.get(path).cloned() else {
+        app.ask_passphrase(LockRequest::Open(path.to_path_buf()));
+        return Ok(());
+    };
+    let text = match lock::read(path, &passphrase) {
+        Ok(text) => text,
+        Err(e) => { app.status_msg = Some(format!("❌ {:#}", e)); return Ok(()); }
+    };
+    let scratch = std::env::temp_dir().join(format!("knot-{}-locked", std::process::id()));
+    crypt::create_private_dir(&scratch)?;
+    let copy = scratch.join(lock::plain_name(path).file_name().context("note has no name")?);
+    fs::write(&copy, &text)?;
+    execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?; disable_raw_mode()?;
+    let cmdline = editor::resolve(app.config.editor.as_deref());
+    let _ = editor::command(&cmdline, &copy, line).status();
+    enable_raw_mode()?; execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    if app.config.format.on_save { app.format_note(&copy)?; }
+    let edited = fs::read_to_string(&copy)?;
+    if edited != text {
+        if let Err(e) = lock::write(path, &passphrase, &edited) {
+            app.status_msg = Some(format!("❌ Couldn't lock the note; your edit is in {}: {:#}", copy.display(), e));
+            terminal.clear()?;
+            return Ok(());
+        }
+    }
+    fs::remove_file(&copy)?;
+    let _ = fs::remove_dir(&scratch);
+    app.vault_changed();
+    app.hard_refresh()?;
+    terminal.clear()?;
+    Ok(())
 }
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // Through the environment, so every Config::load and the knot processes git starts as a
+    // credential helper agree on the vault.
+    if let Some(vault) = &cli.vault { std::env::set_var(config::VAULT_ENV, vault); }
+    let tour = matches!(cli.command, Some(Commands::Tutorial));
+    if let Some(command) = cli.command.filter(|_| !tour) { return run_command(command); }
+    let config = Config::load()?.with_vault_overrides()?;
+    if config.vault_path.exists() && !config.vault_path.is_dir() { anyhow::bail!("the vault {} is not a directory", config.vault_path.display()); }
+    if remote::needs_setup(&config.vault_path) {
+        // A failed setup shouldn't keep the notes out of reach.
+        if let Err(e) = remote::wizard(&config.vault_path) { eprintln!("Remote setup: {:#}", e); }
+    }
+    // Unlocking a passphrase-protected identity asks on the terminal, so it comes first.
+    let crypt = crypt::Crypt::load(&config.encryption, config.keyring);
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
 
-    let mut app = App::new()?;
-    let colors = [Color::Cyan, Color::Magenta, Color::Green, Color::Yellow, Color::Blue];
+    let startup = Instant::now();
+    let mut app = App::new(config, Profiler::new(cli.profile))?;
+    app.profiler.record("startup", startup.elapsed());
+    match crypt {
+        Ok(crypt) => app.crypt = crypt,
+        Err(e) => app.status_msg = Some(format!("⚠ Encrypted notes unavailable: {:#}", e)),
+    }
+    if let Some(name) = &cli.context {
+        match app.config.contexts.iter().find(|c| c.name.eq_ignore_ascii_case(name)).cloned() {
+            Some(ctx) => app.switch_context(&ctx)?,
+            None => app.status_msg = Some(format!("No context named {}", name)),
+        }
+    }
+    let problems = app.config.keys.problems();
+    if !problems.is_empty() { app.status_msg = Some(format!("⚠ [keys] in the config: {}", problems.join(" · "))); }
+    if tour { app.start_tutorial()?; }
 
     while !app.should_quit {
-        terminal.draw(|f| {
-            let area = f.size();
-            let chunks = Layout::default().direction(Direction::Vertical).constraints([
-                Constraint::Length(3), 
-                Constraint::Length(3), 
-                Constraint::Min(0),    
-                Constraint::Length(3), 
-            ]).split(area);
-
-            f.render_widget(Paragraph::new(format!(" 🚀 KNOT v2 | Last Sync: {} ", app.last_sync))
-                .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::DarkGray))), chunks[0]);
-
-            let cat_idx = app.categories.iter().position(|c| c == &app.selected_cat).unwrap_or(0);
-            let tabs = Tabs::new(app.categories.iter().enumerate().map(|(i, c)| {
-                let color = colors[i % colors.len()];
-                if i == cat_idx { Line::from(vec![Span::styled(format!(" {} ", c), Style::default().bg(color).fg(Color::Black).add_modifier(Modifier::BOLD))]) }
-                else { Line::from(vec![Span::styled(format!(" {} ", c), Style::default().fg(color))]) }
-            }).collect())
-            .block(Block::default().borders(Borders::ALL).title(" Categories "))
-            .select(cat_idx);
-            f.render_widget(tabs, chunks[1]);
-
-            let main_chunks = Layout::default().direction(Direction::Horizontal).constraints([
-                Constraint::Percentage(20),
-                Constraint::Percentage(30),
-                Constraint::Percentage(50),
-            ]).split(chunks[2]);
-
-            let sub_list = List::new(app.subfolders.iter().map(|s| ListItem::new(format!("  {} ", s))).collect::<Vec<_>>())
-                .block(Block::default().borders(Borders::ALL).title(" Folders ")
-                .border_style(if app.focus == Focus::Subfolders { Style::default().fg(Color::Yellow) } else { Style::default() }))
-                .highlight_style(Style::default().bg(Color::Rgb(40,40,40)));
-            f.render_stateful_widget(sub_list, main_chunks[0], &mut app.sub_state);
-
-            let file_list = List::new(app.files.iter().map(|p| ListItem::new(format!(" 📄 {} ", p.file_name().unwrap().to_string_lossy()))).collect::<Vec<_>>())
-                .block(Block::default().borders(Borders::ALL).title(" Notes ")
-                .border_style(if app.focus == Focus::Files { Style::default().fg(Color::Yellow) } else { Style::default() }))
-                .highlight_style(Style::default().bg(Color::Rgb(40,40,40)));
-            f.render_stateful_widget(file_list, main_chunks[1], &mut app.file_state);
-
-            let preview = if let Some(i) = app.file_state.selected() {
-                fs::read_to_string(&app.files[i]).unwrap_or_else(|_| "Error reading file".into())
-            } else { "---".into() };
-            f.render_widget(Paragraph::new(preview).block(Block::default().borders(Borders::ALL).title(" Preview ")).wrap(Wrap{trim:true}), main_chunks[2]);
-
-            let footer = match app.input_mode {
-                InputMode::Normal => " [TAB] Focus | [S] Sync to Cloud | [C/F/N] New | [D] Delete | [Enter] Edit ",
-                InputMode::ConfirmDelete => " !!! PERMANENT DELETE? [y/n] !!! ",
-                _ => " Name: [ENTER] Save | [ESC] Cancel ",
-            };
-            f.render_widget(Paragraph::new(footer).block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::DarkGray))), chunks[3]);
+        app.poll_sync();
+        app.poll_search();
+        app.tick_autosync();
+        if app.should_quit { break; }
+        if app.dirty {
+            let frame_started = Instant::now();
+            terminal.draw(|f| ui::draw(f, &mut app))?;
+            app.profiler.record("draw", frame_started.elapsed());
+            app.dirty = false;
+        }
 
-            if app.input_mode != InputMode::Normal && app.input_mode != InputMode::ConfirmDelete {
-                let box_area = centered_rect(50, 15, area);
-                f.render_widget(Clear, box_area);
-                f.render_widget(Paragraph::new(app.input_buffer.as_str()).block(Block::default().borders(Borders::ALL).title(" Input ")), box_area);
+        if let Some(wait) = app.poll_timeout() {
+            // Nothing pressed before the interval ran out: pick up external changes and redraw.
+            if !event::poll(wait)? {
+                // A key that could have started a longer sequence counts on its own after all.
+                if let Some((action, count)) = app.pending.expire(&app.config.keys).filter(|_| app.input_mode == InputMode::Normal) {
+                    app.dirty = true;
+                    normal_action(&mut app, &mut terminal, action, count)?;
+                }
+                if app.refresh_due() { app.index.invalidate(); app.hard_refresh()?; }
+                if app.periodic_sync_at.is_some() { app.dirty = true; }
+                continue;
             }
-        })?;
-
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
+        }
+        let query = (app.input_mode == InputMode::Search).then(|| app.input_buffer.clone());
+        match event::read()? {
+            Event::Resize(..) => app.dirty = true,
+            Event::Paste(text) => { app.dirty = true; app.paste(&text)?; }
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                app.dirty = true;
+                if let Some(tour) = app.tutorial.as_mut() {
+                    if !tour.observe(&key, &app.input_mode) {
+                        app.tutorial = None;
+                        app.status_msg = Some("🎓 Tutorial over · `knot tutorial` runs it again".into());
+                    }
+                }
                 match app.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('q') => app.should_quit = true,
-                        KeyCode::Char('S') => { app.manual_sync()?; terminal.clear()?; }
-                        KeyCode::Tab => app.focus = match app.focus { 
-                            Focus::Categories => Focus::Subfolders, 
-                            Focus::Subfolders => Focus::Files, 
-                            Focus::Files => Focus::Categories 
-                        },
-                        KeyCode::Char('h') | KeyCode::Left => {
-                            let cur_idx = app.categories.iter().position(|c| c == &app.selected_cat).unwrap_or(0);
-                            let new_idx = if cur_idx == 0 { app.categories.len() - 1 } else { cur_idx - 1 };
-                            app.selected_cat = app.categories[new_idx].clone();
-                            app.hard_refresh()?;
+                    InputMode::Normal => {
+                        for (action, count) in app.pending.feed(&app.config.keys, &key) {
+                            normal_action(&mut app, &mut terminal, action, count)?;
+                        }
+                    }
+                    InputMode::Picker => match key.code {
+                        KeyCode::Esc => { app.picker = None; app.input_mode = InputMode::Normal; }
+                        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            let target = app.picker.as_ref().and_then(|p| app.picked_note(p, p.selected()?));
+                            if let Some((path, line)) = target {
+                                app.dismiss_incoming();
+                                app.picker = None; app.input_mode = InputMode::Normal;
+                                app.reveal(&path)?;
+                                edit_note(&mut app, &mut terminal, &path, line)?;
+                            }
                         }
-                        KeyCode::Char('l') | KeyCode::Right => {
-                            let cur_idx = app.categories.iter().position(|c| c == &app.selected_cat).unwrap_or(0);
-                            let new_idx = (cur_idx + 1) % app.categories.len();
-                            app.selected_cat = app.categories[new_idx].clone();
-                            app.hard_refresh()?;
+                        KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) && app.picker.as_ref().is_some_and(|p| p.kind == PickerKind::Incoming) => {
+                            app.picker = None; app.input_mode = InputMode::Normal;
+                            app.status_msg = Some(match incoming::clear(&app.vault_root) {
+                                Ok(()) => "Marked everything new as seen".into(),
+                                Err(e) => format!("Update failed: {}", e),
+                            });
                         }
-                        KeyCode::Char('j') | KeyCode::Down => {
-                            match app.focus {
-                                Focus::Subfolders if !app.subfolders.is_empty() => {
-                                    let i = (app.sub_state.selected().unwrap_or(0) + 1) % app.subfolders.len();
-                                    app.sub_state.select(Some(i));
-                                    app.selected_sub = Some(app.subfolders[i].clone());
-                                }
-                                Focus::Files if !app.files.is_empty() => {
-                                    let i = (app.file_state.selected().unwrap_or(0) + 1) % app.files.len();
-                                    app.file_state.select(Some(i));
+                        KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => app.purge_selected(),
+                        KeyCode::Char(c @ ('t' | 'o')) if key.modifiers.contains(KeyModifiers::CONTROL) && app.picker.as_ref().is_some_and(|p| p.kind == PickerKind::SearchResults) => {
+                            let Some(picker) = app.picker.take() else { continue };
+                            let mut notes: Vec<PathBuf> = picker.visible().iter().filter_map(|&i| app.search_hit(i)).map(|h| h.path.clone()).collect();
+                            notes.dedup();
+                            app.input_mode = InputMode::Normal;
+                            app.mark_all(notes);
+                            if c == 't' { app.input_mode = InputMode::Tag; app.input_buffer.clear(); } else { app.choose_move_target(); }
+                        }
+                        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) && app.picker.as_ref().is_some_and(|p| p.kind == PickerKind::History) => app.restore_selected()?,
+                        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => app.toggle_read_selected(),
+                        KeyCode::Enter => {
+                            app.input_mode = InputMode::Normal;
+                            if let Some(picker) = app.picker.take() {
+                                match picker.selected() {
+                                    Some(idx) if picker.kind == PickerKind::Commands => {
+                                        let (action, focus) = app.commands[idx];
+                                        app.focus = focus;
+                                        normal_action(&mut app, &mut terminal, action, None)?;
+                                    }
+                                    Some(idx) => app.pick(picker, idx)?,
+                                    None => {}
                                 }
-                                _ => {}
                             }
-                            app.hard_refresh()?;
                         }
-                        KeyCode::Char('k') | KeyCode::Up => {
-                            match app.focus {
-                                Focus::Subfolders if !app.subfolders.is_empty() => {
-                                    let i = if app.sub_state.selected().unwrap_or(0) == 0 { app.subfolders.len()-1 } else { app.sub_state.selected().unwrap()-1 };
-                                    app.sub_state.select(Some(i));
-                                    app.selected_sub = Some(app.subfolders[i].clone());
-                                }
-                                Focus::Files if !app.files.is_empty() => {
-                                    let i = if app.file_state.selected().unwrap_or(0) == 0 { app.files.len()-1 } else { app.file_state.selected().unwrap()-1 };
-                                    app.file_state.select(Some(i));
+                        KeyCode::Up => { if let Some(p) = app.picker.as_mut() { p.move_by(-1); } }
+                        KeyCode::Down => { if let Some(p) = app.picker.as_mut() { p.move_by(1); } }
+                        KeyCode::Backspace => { if let Some(p) = app.picker.as_mut() { p.pop(); } }
+                        KeyCode::Char(c) => { if let Some(p) = app.picker.as_mut() { p.push(c); } }
+                        _ => {}
+                    },
+                    InputMode::Outline => {
+                        let Some(o) = app.outline.as_mut() else { app.input_mode = InputMode::Normal; continue };
+                        let saved = match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => {
+                                app.outline = None; app.input_mode = InputMode::Normal;
+                                app.vault_changed(); app.hard_refresh()?;
+                                Ok(())
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => { o.move_cursor(1); Ok(()) }
+                            KeyCode::Char('k') | KeyCode::Up => { o.move_cursor(-1); Ok(()) }
+                            KeyCode::Char('J') => o.move_item(false),
+                            KeyCode::Char('K') => o.move_item(true),
+                            KeyCode::Tab | KeyCode::Char('l') => o.shift(true),
+                            KeyCode::BackTab | KeyCode::Char('h') => o.shift(false),
+                            KeyCode::Char(' ') | KeyCode::Enter => { o.toggle_fold(); Ok(()) }
+                            KeyCode::Char('z') => { o.fold_all(true); Ok(()) }
+                            KeyCode::Char('Z') => { o.fold_all(false); Ok(()) }
+                            _ => Ok(()),
+                        };
+                        if let Err(e) = saved { app.status_msg = Some(format!("Outline not saved: {}", e)); }
+                    }
+                    InputMode::ConfirmQuit => {
+                        app.input_mode = InputMode::Normal;
+                        match key.code {
+                            KeyCode::Char('y' | 's') => { app.quit_after_sync = true; if app.sync.is_none() { app.manual_sync(); } }
+                            KeyCode::Char('n' | 'q') => app.should_quit = true,
+                            // `c`, Esc or anything else cancels.
+                            _ => {}
+                        }
+                    }
+                    InputMode::Edit => {
+                        // While a `[[link` is typed these pick from its completions, as in the input popup.
+                        if matches!(key.code, KeyCode::Enter | KeyCode::Tab | KeyCode::Up | KeyCode::Down) && !app.link_candidates().is_empty() {
+                            match key.code {
+                                KeyCode::Up => app.link_choice = app.link_choice.saturating_sub(1),
+                                KeyCode::Down => app.link_choice = (app.link_choice + 1).min(app.link_candidates().len() - 1),
+                                _ => { app.accept_link(); }
+                            }
+                            continue;
+                        }
+                        let Some(t) = app.textarea.as_mut() else { app.input_mode = InputMode::Normal; continue };
+                        let warning = t.warning.take();
+                        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                        match key.code {
+                            KeyCode::Esc if t.is_modified() && warning != Some(textarea::Warning::Discard) => {
+                                t.warning = Some(textarea::Warning::Discard);
+                                app.status_msg = Some("Unsaved changes · [ESC] again discards them, [^S] saves".into());
+                            }
+                            KeyCode::Esc => app.close_textarea()?,
+                            KeyCode::Char('s') if ctrl && t.changed_on_disk() && warning != Some(textarea::Warning::Overwrite) => {
+                                t.warning = Some(textarea::Warning::Overwrite);
+                                app.status_msg = Some("⚠ The note changed on disk since you opened it · [^S] again overwrites it".into());
+                            }
+                            KeyCode::Char('s') if ctrl => match t.save() {
+                                Ok(()) => app.status_msg = Some(format!("Saved {}", t.path.file_name().unwrap_or_default().to_string_lossy())),
+                                Err(e) => app.status_msg = Some(format!("❌ Not saved: {}", e)),
+                            },
+                            KeyCode::Char('e') if ctrl && t.is_modified() => app.status_msg = Some("Save [^S] or discard the changes before switching editors".into()),
+                            KeyCode::Char('e') if ctrl => {
+                                let (path, line) = (t.path.clone(), t.line_number());
+                                app.close_textarea()?;
+                                edit_note(&mut app, &mut terminal, &path, line)?;
+                            }
+                            KeyCode::Left if ctrl => t.word(false),
+                            KeyCode::Right if ctrl => t.word(true),
+                            KeyCode::Left => t.left(),
+                            KeyCode::Right => t.right(),
+                            KeyCode::Up => t.vertical(-1),
+                            KeyCode::Down => t.vertical(1),
+                            KeyCode::PageUp => t.vertical(-20),
+                            KeyCode::PageDown => t.vertical(20),
+                            KeyCode::Home => t.home(),
+                            KeyCode::End => t.end(),
+                            KeyCode::Enter => t.newline(true),
+                            KeyCode::Backspace => t.backspace(),
+                            KeyCode::Delete => t.delete(),
+                            KeyCode::Tab => t.insert("  "),
+                            KeyCode::Char(c) if !ctrl => { app.link_choice = 0; t.insert(c.encode_utf8(&mut [0; 4])); }
+                            _ => {}
+                        }
+                    }
+                    InputMode::Share => {
+                        // Dropping the share stops its server.
+                        app.share = None;
+                        app.input_mode = InputMode::Normal;
+                    }
+                    InputMode::Gallery => {
+                        let (last, cols) = (app.gallery.len().saturating_sub(1), app.gallery_cols.max(1));
+                        match key.code {
+                            KeyCode::Char('l') | KeyCode::Right => app.gallery_cursor = (app.gallery_cursor + 1).min(last),
+                            KeyCode::Char('h') | KeyCode::Left => app.gallery_cursor = app.gallery_cursor.saturating_sub(1),
+                            KeyCode::Char('j') | KeyCode::Down => app.gallery_cursor = (app.gallery_cursor + cols).min(last),
+                            KeyCode::Char('k') | KeyCode::Up => app.gallery_cursor = app.gallery_cursor.saturating_sub(cols),
+                            KeyCode::Home | KeyCode::Char('g') => app.gallery_cursor = 0,
+                            KeyCode::End | KeyCode::Char('G') => app.gallery_cursor = last,
+                            KeyCode::Enter => {
+                                if let Some(image) = app.gallery.get(app.gallery_cursor) {
+                                    app.status_msg = Some(match gallery::open(&image.path) {
+                                        Ok(()) => format!("Opened {}", image.path.file_name().unwrap_or_default().to_string_lossy()),
+                                        Err(e) => format!("Couldn't open the image: {}", e),
+                                    });
                                 }
-                                _ => {}
                             }
-                            app.hard_refresh()?;
+                            KeyCode::Esc | KeyCode::Char('q') => app.input_mode = InputMode::Normal,
+                            _ => {}
                         }
-                        KeyCode::Char('C') => { app.input_mode = InputMode::NewCat; app.input_buffer.clear(); }
-                        KeyCode::Char('F') => { app.input_mode = InputMode::NewFolder; app.input_buffer.clear(); }
-                        KeyCode::Char('N') => { app.input_mode = InputMode::NewNote; app.input_buffer.clear(); }
-                        KeyCode::Char('D') => { app.input_mode = InputMode::ConfirmDelete; }
-                        KeyCode::Enter if app.focus == Focus::Files => {
-                            if let Some(i) = app.file_state.selected() {
-                                execute!(io::stdout(), LeaveAlternateScreen)?; disable_raw_mode()?;
-                                let _ = Command::new("helix").arg(&app.files[i]).status();
-                                enable_raw_mode()?; execute!(io::stdout(), EnterAlternateScreen)?;
-                                app.hard_refresh()?;
-                                terminal.clear()?;
+                    }
+                    InputMode::Conflicts => match key.code {
+                        KeyCode::Char('j') | KeyCode::Down => app.conflict_cursor = (app.conflict_cursor + 1).min(app.conflicts.len().saturating_sub(1)),
+                        KeyCode::Char('k') | KeyCode::Up => app.conflict_cursor = app.conflict_cursor.saturating_sub(1),
+                        KeyCode::Char('m') => app.resolve_conflict(Some(git::Side::Mine)),
+                        KeyCode::Char('t') => app.resolve_conflict(Some(git::Side::Theirs)),
+                        KeyCode::Char('e') | KeyCode::Enter => {
+                            if let Some(rel) = app.conflicts.get(app.conflict_cursor).cloned() {
+                                let path = app.conflicts_repo.join(&rel);
+                                let line = fs::read_to_string(&path).ok()
+                                    .and_then(|t| t.lines().position(|l| l.starts_with("<<<<<<< "))).map_or(1, |i| i + 1);
+                                edit_note(&mut app, &mut terminal, &path, line)?;
+                                app.resolve_conflict(None);
                             }
                         }
+                        KeyCode::Char('a') => app.abort_conflicts(),
+                        // Leaves the conflicts for next time; the screen comes back on startup.
+                        KeyCode::Char('q') => app.should_quit = true,
                         _ => {}
                     },
+                    InputMode::EditClash => {
+                        let choice = match key.code { KeyCode::Char(c @ ('m' | 'r' | 'k')) => Some(c), KeyCode::Esc => Some('k'), _ => None };
+                        if let Some((path, line)) = choice.map(|c| app.settle_clash(c)).transpose()?.flatten() {
+                            edit_note(&mut app, &mut terminal, &path, line)?;
+                        }
+                    }
+                    InputMode::ConfirmGc => {
+                        app.input_mode = InputMode::Normal;
+                        if key.code == KeyCode::Char('y') { app.trash_orphans()?; } else { app.orphans.clear(); }
+                    }
                     InputMode::ConfirmDelete => match key.code {
                         KeyCode::Char('y') => {
-                            let path = match app.focus {
-                                Focus::Categories if app.selected_cat != "[Root]" => Some(app.vault_root.join(&app.selected_cat)),
-                                Focus::Subfolders => app.sub_state.selected().map(|i| app.vault_root.join(&app.selected_cat).join(&app.subfolders[i])),
-                                Focus::Files => app.file_state.selected().map(|i| app.files[i].clone()),
-                                _ => None,
-                            };
-                            if let Some(p) = path {
-                                if p.is_dir() { let _ = fs::remove_dir_all(p); } else { let _ = fs::remove_file(p); }
+                            if app.focus == Focus::Files && !app.marked.is_empty() {
+                                let marked = std::mem::take(&mut app.marked);
+                                let trashed = marked.iter().filter(|p| trash::trash(&app.vault_root, p).is_ok()).count();
+                                app.status_msg = Some(format!("Moved {} of {} notes to trash · [T] to restore", trashed, marked.len()));
+                            } else if let Some(p) = app.focused_item() {
+                                match trash::trash(&app.vault_root, &p) {
+                                    Ok(_) => app.status_msg = Some(format!("Moved {} to trash · [T] to restore", vault::relative(&app.vault_root, &p))),
+                                    Err(e) => app.status_msg = Some(format!("Delete failed: {}", e)),
+                                }
                                 if app.focus == Focus::Categories { app.selected_cat = "[Root]".to_string(); }
                             }
-                            app.input_mode = InputMode::Normal; app.hard_refresh()?;
-                            terminal.clear()?;
+                            app.input_mode = InputMode::Normal; app.vault_changed(); app.hard_refresh()?;
                         },
                         _ => app.input_mode = InputMode::Normal,
                     },
                     _ => match key.code {
+                        KeyCode::Enter if app.input_mode == InputMode::Search => app.open_search_results(),
+                        KeyCode::Enter if app.input_mode == InputMode::Credential => app.submit_credential(),
+                        KeyCode::Enter if app.input_mode == InputMode::Unlock => app.submit_passphrase()?,
+                        KeyCode::Enter if app.input_mode == InputMode::AltText => app.attach_image()?,
+                        // Tokens are typed as they are: no snippets, emoji or link completion.
+                        KeyCode::Char(c) if matches!(app.input_mode, InputMode::Credential | InputMode::Unlock) => app.input_buffer.push(c),
+                        KeyCode::Enter if app.input_mode == InputMode::CommitMessage => {
+                            app.input_mode = InputMode::Normal;
+                            let message = std::mem::take(&mut app.input_buffer);
+                            app.start_sync(Some(message).filter(|m| !m.trim().is_empty()));
+                        }
+                        KeyCode::Enter | KeyCode::Tab if app.accept_link() => {}
+                        KeyCode::Up => app.link_choice = app.link_choice.saturating_sub(1),
+                        KeyCode::Down => app.link_choice = (app.link_choice + 1).min(app.link_candidates().len().saturating_sub(1)),
                         KeyCode::Enter => {
                             let buf = app.input_buffer.clone();
                             if !buf.is_empty() {
@@ -322,30 +3399,101 @@ fn main() -> Result<()> {
                                     InputMode::NewFolder => { let _ = fs::create_dir_all(base.join(&buf)); app.selected_sub = Some(buf); }
                                     InputMode::NewNote => {
                                         let mut p = base;
-                                        if let Some(ref s) = app.selected_sub { p.push(s); }
-                                        let _ = fs::write(p.join(format!("{}.md", buf)), "# New Note");
+                                        if let Some(s) = app.selected_real_sub() { p.push(s); }
+                                        let rel = vault::relative(&app.vault_root, &p);
+                                        let stem = match app.config.numbered_folders.iter().find(|(folder, _)| *folder == rel) {
+                                            Some((_, digits)) => numbering::stem(&p, &buf, *digits),
+                                            None => buf.clone(),
+                                        };
+                                        let encrypted = crypt::encrypts_folder(&app.config.encryption, &rel);
+                                        let file = if encrypted { format!("{}.md.{}", stem, crypt::EXTENSION) } else { format!("{}.md", stem) };
+                                        let p = p.join(&file);
+                                        if p.exists() {
+                                            app.status_msg = Some(format!("{} already exists", file));
+                                        } else {
+                                            let category = if app.selected_cat == "[Root]" { "" } else { app.selected_cat.as_str() };
+                                            let body = template::render(app.note_template.take().as_deref(), &buf, category);
+                                            match app.crypt.as_ref().filter(|_| encrypted) {
+                                                Some(crypt) => if let Err(e) = crypt.encrypt(&body, &p) { app.status_msg = Some(format!("❌ {:#}", e)); },
+                                                None => { let _ = fs::write(&p, body); }
+                                            }
+                                        }
+                                    }
+                                    InputMode::Rename if buf.contains(['/', '\\']) => app.status_msg = Some("Names can't contain path separators".into()),
+                                    InputMode::Rename => {
+                                        if let Some(old) = app.focused_item() {
+                                            let result = if old.is_dir() {
+                                                let new = old.with_file_name(&buf);
+                                                let moved = app.move_dir(&old, &new);
+                                                if moved.is_ok() {
+                                                    if app.focus == Focus::Categories { app.selected_cat = buf.clone(); } else { app.selected_sub = Some(buf.clone()); }
+                                                }
+                                                moved
+                                            } else {
+                                                let mut new = old.with_file_name(&buf);
+                                                if new.extension().is_none() { if let Some(ext) = old.extension() { new.set_extension(ext); } }
+                                                app.move_note(&old, &new)
+                                            };
+                                            app.status_msg = Some(match result {
+                                                Ok((refs, notes)) => format!("Renamed to {} · {} links updated in {} notes", buf, refs, notes),
+                                                Err(e) => format!("Rename failed: {}", e),
+                                            });
+                                        }
+                                    }
+                                    InputMode::Tag => {
+                                        let tags: Vec<String> = buf.split([' ', ',']).map(|t| t.trim_start_matches('#').to_string()).filter(|t| !t.is_empty()).collect();
+                                        app.status_msg = Some(match app.tag_notes(&tags) {
+                                            Ok(n) => format!("Tagged {} notes with {}", n, tags.join(", ")),
+                                            Err(e) => format!("Tagging failed: {}", e),
+                                        });
+                                    }
+                                    InputMode::ReadLater => {
+                                        app.status_msg = Some(match reading::capture(&app.vault_root, buf.trim(), None, app.config.reading_snapshot) {
+                                            Ok(path) => format!("Queued {} · [L] Read later", links::link_name(&path)),
+                                            Err(e) => format!("Capture failed: {}", e),
+                                        });
+                                    }
+                                    InputMode::Comment => app.add_comment(&buf),
+                                    InputMode::Append => {
+                                        if let Some(path) = app.selected_file().cloned() {
+                                            let buf = if app.config.unfurl_links { reading::unfurl(&buf, app.config.unfurl_timeout) } else { buf };
+                                            let appended = app.read_note(&path).and_then(|mut note| {
+                                                if !note.is_empty() && !note.ends_with('\n') { note.push('\n'); }
+                                                note.push_str(&buf);
+                                                note.push('\n');
+                                                app.write_note(&path, &note)
+                                            });
+                                            if let Err(e) = appended { app.status_msg = Some(format!("❌ Not appended: {:#}", e)); }
+                                        }
                                     }
                                     _ => {}
                                 }
                             }
-                            app.input_mode = InputMode::Normal; app.hard_refresh()?;
-                            terminal.clear()?;
+                            app.input_mode = InputMode::Normal; app.vault_changed(); app.hard_refresh()?;
                         }
                         KeyCode::Esc => app.input_mode = InputMode::Normal,
-                        KeyCode::Char(c) => app.input_buffer.push(c),
+                        KeyCode::Char(c) => {
+                            app.link_choice = 0;
+                            app.input_buffer.push(c);
+                            // Only into note text: an expansion in a name would end up on disk.
+                            if matches!(app.input_mode, InputMode::Append | InputMode::Comment) {
+                                app.config.snippets.expand(&mut app.input_buffer);
+                                emoji::expand_closed(&mut app.input_buffer);
+                            }
+                        }
+                        KeyCode::Tab if matches!(app.input_mode, InputMode::Append | InputMode::Comment) => emoji::complete_pending(&mut app.input_buffer),
                         KeyCode::Backspace => { app.input_buffer.pop(); }
                         _ => {}
                     }
                 }
             }
+            _ => {}
         }
+        if app.input_mode == InputMode::Search && query.as_ref() != Some(&app.input_buffer) { app.schedule_search(); }
     }
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    let saved = app.save_session();
+    execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen)?;
     disable_raw_mode()?;
+    if let Err(e) = saved { eprintln!("knot: couldn't save the session: {}", e); }
     Ok(())
 }
-
-fn centered_rect(px: u16, py: u16, r: Rect) -> Rect {
-    let v = Layout::default().direction(Direction::Vertical).constraints([Constraint::Percentage((100-py)/2), Constraint::Percentage(py), Constraint::Percentage((100-py)/2)]).split(r);
-    Layout::default().direction(Direction::Horizontal).constraints([Constraint::Percentage((100-px)/2), Constraint::Percentage(px), Constraint::Percentage((100-px)/2)]).split(v[1])[1]
-}
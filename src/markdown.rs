@@ -0,0 +1,212 @@
+//! Markdown helpers shared by the preview and the note formatter.
+
+/// Normalizes a markdown document so notes produce small, predictable git diffs:
+/// `#Heading` spacing and surrounding blank lines, `-` as the only bullet marker,
+/// aligned pipe tables, collapsed blank runs and (when `wrap_width > 0`) wrapped prose.
+/// Fenced and indented code blocks and YAML frontmatter are left untouched.
+pub fn format(src: &str, wrap_width: usize) -> String {
+    let lines: Vec<&str> = src.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    if lines.first().map(|l| l.trim_end()) == Some("---") {
+        if let Some(end) = lines.iter().skip(1).position(|l| l.trim_end() == "---") {
+            out.extend(lines[..end + 2].iter().map(|l| l.trim_end().to_string()));
+            i = end + 2;
+        }
+    }
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            // Only a run of the same character at least as long closes it, so a ```` fence can
+            // hold ``` lines.
+            let mark = trimmed.as_bytes()[0] as char;
+            let run = trimmed.chars().take_while(|&c| c == mark).count();
+            out.push(line.trim_end().to_string());
+            i += 1;
+            while i < lines.len() {
+                out.push(lines[i].to_string());
+                i += 1;
+                let close = lines[i - 1].trim();
+                if close.chars().take_while(|&c| c == mark).count() >= run && close.trim_start_matches(mark).is_empty() { break; }
+            }
+            continue;
+        }
+
+        if is_indented_code(line) && starts_block(&lines[..i]) {
+            while i < lines.len() && (lines[i].trim().is_empty() || is_indented_code(lines[i])) {
+                out.push(lines[i].trim_end().to_string());
+                i += 1;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            let start = i;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') { i += 1; }
+            out.extend(align_table(&lines[start..i]));
+            continue;
+        }
+
+        if let Some(heading) = normalize_heading(trimmed) {
+            if out.last().is_some_and(|l| !l.is_empty()) { out.push(String::new()); }
+            out.push(heading);
+            if lines.get(i + 1).is_some_and(|l| !l.trim().is_empty()) { out.push(String::new()); }
+            i += 1;
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            if out.last().is_some_and(|l| !l.is_empty()) { out.push(String::new()); }
+            i += 1;
+            continue;
+        }
+
+        let hard_break = line.ends_with("  ") && lines.get(i + 1).is_some_and(|l| !l.trim().is_empty());
+        let mut normalized = normalize_bullet(line.trim_end());
+        if hard_break { normalized.push_str("  "); }
+        out.extend(wrap_line(&normalized, wrap_width));
+        i += 1;
+    }
+
+    while out.last().is_some_and(|l| l.is_empty()) { out.pop(); }
+    let mut result = out.join("\n");
+    result.push('\n');
+    result
+}
+
+fn is_indented_code(line: &str) -> bool {
+    line.starts_with('\t') || line.starts_with("    ")
+}
+
+/// Whether a line indented as code after `before` starts a code block: it must follow a blank
+/// line, and not one inside a list, where the indent continues an item instead.
+fn starts_block(before: &[&str]) -> bool {
+    if before.last().is_some_and(|l| !l.trim().is_empty()) { return false; }
+    match before.iter().rev().find(|l| !l.trim().is_empty()) {
+        Some(prev) => !prev.starts_with([' ', '\t']) && !starts_list_item(prev),
+        None => true,
+    }
+}
+
+/// Whether `word` at the start of a line would make it a list item, quote or heading.
+fn is_marker(word: &str) -> bool {
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    matches!(word, "-" | "*" | "+" | ">")
+        || (word.chars().all(|c| c == '#') && word.len() <= 6)
+        || (digits > 0 && digits <= 9 && matches!(&word[digits..], "." | ")"))
+}
+
+fn starts_list_item(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next().is_some_and(|w| is_marker(w) && !w.starts_with(['#', '>'])) && words.next().is_some()
+}
+
+fn normalize_heading(trimmed: &str) -> Option<String> {
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 { return None; }
+    let text = trimmed[level..].trim();
+    // `#tag` at the start of a line is a hashtag, not a heading missing its space.
+    if text.is_empty() || (!trimmed[level..].starts_with([' ', '\t']) && crate::tags::starts_with_tag(trimmed)) { return None; }
+    // A closing run of `#` only counts after a space: `# C#` keeps its `#`.
+    let text = match text.trim_end_matches('#') {
+        open if open.len() < text.len() && open.ends_with([' ', '\t']) && !open.trim().is_empty() => open.trim_end(),
+        _ => text,
+    };
+    Some(format!("{} {}", "#".repeat(level), text))
+}
+
+fn normalize_bullet(line: &str) -> String {
+    let indent = line.len() - line.trim_start().len();
+    let body = &line[indent..];
+    // `* * *` and friends are thematic breaks, not bullets.
+    if body.chars().filter(|c| !c.is_whitespace()).count() >= 3 && body.chars().all(|c| matches!(c, '*' | '-' | '_' | ' ')) {
+        return line.to_string();
+    }
+    match body.as_bytes() {
+        [b'*' | b'+' | b'-', b' ', ..] => format!("{}- {}", &line[..indent], body[2..].trim_start()),
+        _ => line.to_string(),
+    }
+}
+
+/// Number of columns a wrapped continuation line must be indented by to line up with the text.
+fn hanging_indent(line: &str) -> usize {
+    let indent = line.len() - line.trim_start().len();
+    let body = &line[indent..];
+    if body.starts_with("- ") { return indent + 2; }
+    let digits = body.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && body[digits..].starts_with(". ") { return indent + digits + 2; }
+    if body.starts_with("> ") { return indent; }
+    indent
+}
+
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if width == 0 || line.chars().count() <= width || line.trim_start().starts_with('>') {
+        return vec![line.to_string()];
+    }
+    let hang = " ".repeat(hanging_indent(line));
+    let indent = line.len() - line.trim_start().len();
+    let mut lines = Vec::new();
+    let mut cur = line[..indent].to_string();
+    let mut cur_has_word = false;
+    for word in line[indent..].split(' ').filter(|w| !w.is_empty()) {
+        // Breaking before a marker would turn the rest of the line into a list item or a quote.
+        if cur_has_word && cur.chars().count() + 1 + word.chars().count() > width && !is_marker(word) {
+            lines.push(std::mem::replace(&mut cur, hang.clone()));
+            cur_has_word = false;
+        }
+        if cur_has_word { cur.push(' '); }
+        cur.push_str(word);
+        cur_has_word = true;
+    }
+    lines.push(cur);
+    lines
+}
+
+/// The cells of a table row, split on its pipes but not on escaped `\|` ones.
+pub fn split_cells(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = if row.ends_with('|') && !row.ends_with("\\|") { &row[..row.len() - 1] } else { row };
+    let mut cells = vec![String::new()];
+    let mut escaped = false;
+    for c in row.chars() {
+        if c == '|' && !escaped {
+            cells.push(String::new());
+        } else {
+            cells.last_mut().expect("never empty").push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.iter().map(|c| c.trim().to_string()).collect()
+}
+
+pub fn is_separator(cells: &[String]) -> bool {
+    !cells.is_empty() && cells.iter().all(|c| !c.is_empty() && c.chars().all(|ch| ch == '-' || ch == ':'))
+}
+
+fn align_table(rows: &[&str]) -> Vec<String> {
+    let cells: Vec<Vec<String>> = rows.iter().map(|r| split_cells(r)).collect();
+    let cols = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![3; cols];
+    for row in cells.iter().filter(|r| !is_separator(r)) {
+        for (c, cell) in row.iter().enumerate() { widths[c] = widths[c].max(cell.chars().count()); }
+    }
+    cells.iter().map(|row| {
+        let sep = is_separator(row);
+        let parts: Vec<String> = (0..cols).map(|c| {
+            let cell = row.get(c).map(String::as_str).unwrap_or("");
+            if sep {
+                let (left, right) = (cell.starts_with(':'), cell.len() > 1 && cell.ends_with(':'));
+                let dashes = widths[c] - left as usize - right as usize;
+                format!("{}{}{}", if left { ":" } else { "" }, "-".repeat(dashes), if right { ":" } else { "" })
+            } else {
+                format!("{}{}", cell, " ".repeat(widths[c] - cell.chars().count()))
+            }
+        }).collect();
+        format!("| {} |", parts.join(" | "))
+    }).collect()
+}
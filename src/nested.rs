@@ -0,0 +1,68 @@
+//! Categories with their own repository, so a shared `Team/` can sync with a work remote while
+//! the rest of the vault stays personal. Such a category is a git repository nested in the
+//! vault's, which ignores it through `.git/info/exclude` (a rule for this machine only, not
+//! something to commit); a sync handles each one after the vault itself.
+//!
+//! ```toml
+//! [category_remotes]
+//! Team = "git@git.example.com:team/notes.git"
+//! ```
+//!
+//! A category listed there is cloned by the first sync when it doesn't exist yet. `knot remote
+//! --category Team` connects one that already has notes.
+
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::git::{self, GitError};
+
+pub struct Nested {
+    pub name: String,
+    pub dir: PathBuf,
+    /// From `[category_remotes]`, for cloning it where it's missing.
+    pub url: Option<String>,
+}
+
+impl Nested {
+    pub fn is_repo(&self) -> bool { self.dir.join(".git").exists() }
+
+    /// Whether there is nothing here yet that cloning would have to make room for.
+    pub fn is_empty(&self) -> bool {
+        fs::read_dir(&self.dir).map_or(true, |mut entries| entries.next().is_none())
+    }
+}
+
+/// The categories of the vault at `root` with their own repository: those in `remotes` and any
+/// other top-level folder that is a repository.
+pub fn categories(root: &Path, remotes: &[(String, String)]) -> Vec<Nested> {
+    let mut found: Vec<Nested> = remotes.iter()
+        .map(|(name, url)| Nested { name: name.clone(), dir: root.join(name), url: Some(url.clone()) })
+        .collect();
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join(".git").exists())
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !found.iter().any(|n| n.name == name) { found.push(Nested { name, dir, url: None }); }
+    }
+    found
+}
+
+/// Keeps category `name` out of the vault's repository: ignored from now on, and no longer
+/// tracked if it was, its files staying where they are. The next sync commits their removal.
+pub fn detach(root: &Path, name: &str) -> Result<(), GitError> {
+    if !root.join(".git").exists() { return Ok(()); }
+    let exclude = root.join(git::run(root, &["rev-parse", "--git-path", "info/exclude"])?.trim());
+    let rule = format!("/{}/", name);
+    let rules = fs::read_to_string(&exclude).unwrap_or_default();
+    if !rules.lines().any(|l| l.trim() == rule) {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = exclude.parent() { fs::create_dir_all(dir)?; }
+            let sep = if rules.is_empty() || rules.ends_with('\n') { "" } else { "\n" };
+            fs::write(&exclude, format!("{}{}{}\n", rules, sep, rule))
+        };
+        write().map_err(|e| GitError::Failed { command: "exclude".into(), message: e.to_string() })?;
+    }
+    git::run(root, &["rm", "-r", "--cached", "--quiet", "--ignore-unmatch", "--", name]).map(drop)
+}
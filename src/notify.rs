@@ -0,0 +1,45 @@
+//! Desktop notifications through whatever the platform ships: `notify-send` (libnotify) on
+//! Linux/BSD, `osascript` on macOS and a PowerShell toast on Windows.
+//!
+//! Notifications are best-effort: a missing tool or a headless session just means nothing shows.
+
+use std::{process::{Command, Stdio}, thread};
+
+use crate::config::NotifyConfig;
+
+pub fn send(config: &NotifyConfig, title: &str, body: &str) {
+    if !config.enabled { return; }
+    let mut cmd = command(title, body);
+    cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    // Reap the child off the UI thread; some backends take a moment to return.
+    if let Ok(mut child) = cmd.spawn() { thread::spawn(move || child.wait()); }
+}
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Command {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut cmd = Command::new("osascript");
+    cmd.arg("-e").arg(format!("display notification {} with title {}", quote(body), quote(title)));
+    cmd
+}
+
+#[cfg(windows)]
+fn command(title: &str, body: &str) -> Command {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let script = format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $t = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $x = $t.GetElementsByTagName('text'); $x.Item(0).AppendChild($t.CreateTextNode({})) > $null; $x.Item(1).AppendChild($t.CreateTextNode({})) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('knot').Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+        quote(title), quote(body));
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-Command", &script]);
+    cmd
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn command(title: &str, body: &str) -> Command {
+    let mut cmd = Command::new("notify-send");
+    cmd.args(["--app-name=knot", title, body]);
+    cmd
+}
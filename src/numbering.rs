@@ -0,0 +1,23 @@
+//! Sequential note numbering for decision-record style folders (`[numbered_folders]`): a note
+//! created in one gets the next free number as a prefix, e.g. `0007-use-postgres.md`.
+
+use std::{fs, path::Path};
+
+/// The file stem for a new note titled `title` in `dir`, numbered one past the highest prefix
+/// already there. A title that already starts with a number is kept as typed.
+pub fn stem(dir: &Path, title: &str, digits: usize) -> String {
+    if number(title).is_some() { return title.to_string(); }
+    let next = fs::read_dir(dir).into_iter().flatten().flatten()
+        .filter_map(|e| number(&e.file_name().to_string_lossy()))
+        .max()
+        .map_or(1, |n| n + 1);
+    let words: Vec<String> = title.split_whitespace().map(str::to_lowercase).collect();
+    format!("{:0width$}-{}", next, words.join("-"), width = digits)
+}
+
+/// `7` for `0007-title.md` (or `0007 title`, `0007_title`).
+pub fn number(name: &str) -> Option<u64> {
+    let digits = name.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || !name[digits..].starts_with(['-', '_', ' ', '.']) { return None; }
+    name[..digits].parse().ok()
+}
@@ -0,0 +1,205 @@
+//! OPML interop with outliner tools (Workflowy, Dynalist, OmniOutliner, Logseq…).
+//!
+//! Import turns an outline into one note of nested bullets, with `_note` text kept as an indented
+//! line under its item. Export writes a folder as an outline: subfolders and notes become items,
+//! and each note's headings and bullets become the items below it, nested the way the outliner
+//! (`o`) nests them: headings above body text, body text by indentation.
+
+use anyhow::{bail, Context, Result};
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{html, links, vault};
+
+struct Node {
+    text: String,
+    /// OPML `_note`: free text attached to an item.
+    note: Option<String>,
+    /// A vault folder rather than a note or a line of one (export only).
+    folder: bool,
+    children: Vec<Node>,
+}
+
+/// Writes the outline in `file` as a note in `dir`, named after the OPML title or the file.
+pub fn import(file: &Path, dir: &Path) -> Result<PathBuf> {
+    let xml = fs::read_to_string(file).with_context(|| format!("reading {}", file.display()))?;
+    let (title, nodes) = parse(&xml);
+    if nodes.is_empty() { bail!("{} has no outline items", file.display()); }
+    let title = title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| links::link_name(file));
+    let name: String = title.chars().map(|c| if matches!(c, '/' | '\\' | ':') { '-' } else { c }).collect();
+    fs::create_dir_all(dir)?;
+    let mut path = dir.join(format!("{}.md", name.trim()));
+    for n in 2.. {
+        if !path.exists() { break; }
+        path = dir.join(format!("{} {}.md", name.trim(), n));
+    }
+    let mut out = format!("# {}\n\n", title);
+    write_bullets(&nodes, 0, &mut out);
+    fs::write(&path, out)?;
+    Ok(path)
+}
+
+fn write_bullets(nodes: &[Node], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    for node in nodes {
+        out.push_str(&format!("{}- {}\n", indent, node.text));
+        if let Some(note) = &node.note {
+            for line in note.lines().filter(|l| !l.trim().is_empty()) { out.push_str(&format!("{}  {}\n", indent, line.trim())); }
+        }
+        write_bullets(&node.children, depth + 1, out);
+    }
+}
+
+/// The OPML `<title>` and the `<outline>` tree inside `<body>`.
+fn parse(xml: &str) -> (Option<String>, Vec<Node>) {
+    let title = xml.find("<title>").and_then(|s| {
+        let rest = &xml[s + 7..];
+        rest.find("</title>").map(|e| html::unescape(rest[..e].trim()))
+    });
+    let mut stack: Vec<Node> = vec![Node { text: String::new(), note: None, folder: false, children: Vec::new() }];
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(end) = tag_end(rest) else { break };
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        if let Some(attrs) = tag.strip_prefix("outline").filter(|a| a.is_empty() || a.starts_with(char::is_whitespace) || a.starts_with('/')) {
+            let text = attr(attrs, "text").or_else(|| attr(attrs, "title")).unwrap_or_default();
+            let node = Node { text: text.replace('\n', " "), note: attr(attrs, "_note"), folder: false, children: Vec::new() };
+            if attrs.trim_end().ends_with('/') {
+                stack.last_mut().expect("root").children.push(node);
+            } else {
+                stack.push(node);
+            }
+        } else if tag.trim() == "/outline" && stack.len() > 1 {
+            let node = stack.pop().expect("checked");
+            stack.last_mut().expect("root").children.push(node);
+        }
+    }
+    // Unclosed items still count.
+    while stack.len() > 1 {
+        let node = stack.pop().expect("checked");
+        stack.last_mut().expect("root").children.push(node);
+    }
+    (title, stack.pop().map(|root| root.children).unwrap_or_default())
+}
+
+/// Position of the `>` closing a tag, skipping any inside quoted attribute values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let mut from = 0;
+    while let Some(pos) = attrs[from..].find(name) {
+        let at = from + pos;
+        from = at + name.len();
+        if !attrs[..at].ends_with(char::is_whitespace) { continue; }
+        let Some(rest) = attrs[from..].trim_start().strip_prefix('=') else { continue };
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let value = &rest[1..];
+        return value.find(quote).map(|e| html::unescape(&value[..e]));
+    }
+    None
+}
+
+/// The notes under `dir` (vault-relative, empty for the whole vault) as an OPML document.
+pub fn export(root: &Path, dir: &str, ignore: &vault::Ignore) -> Result<String> {
+    let base = root.join(dir);
+    if !base.is_dir() { bail!("{} is not a folder in the vault", dir); }
+    let notes: Vec<PathBuf> = vault::walk_notes(root, ignore).into_iter()
+        .filter(|n| n.starts_with(&base) && vault::is_markdown(n))
+        .collect();
+    let mut top = Vec::new();
+    for note in &notes {
+        let rel = note.strip_prefix(&base).unwrap_or(note);
+        let folders: Vec<String> = rel.parent().map(|p| p.iter().map(|c| c.to_string_lossy().to_string()).collect()).unwrap_or_default();
+        let mut level = &mut top;
+        for folder in folders {
+            let pos = match level.iter().position(|n: &Node| n.folder && n.text == folder) {
+                Some(pos) => pos,
+                None => { level.push(Node { text: folder, note: None, folder: true, children: Vec::new() }); level.len() - 1 }
+            };
+            level = &mut level[pos].children;
+        }
+        let name = links::link_name(note);
+        let mut children = note_outline(&fs::read_to_string(note).unwrap_or_default());
+        // A single title heading repeating the note's name would just nest everything one level deeper.
+        if children.len() == 1 && children[0].text.eq_ignore_ascii_case(&name) { children = children.remove(0).children; }
+        level.push(Node { text: name, note: None, folder: false, children });
+    }
+    let title = if dir.is_empty() { links::link_name(root) } else { dir.to_string() };
+    let mut out = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>{}</title>\n  </head>\n  <body>\n", escape(&title));
+    write_outlines(&top, 2, &mut out);
+    out.push_str("  </body>\n</opml>\n");
+    Ok(out)
+}
+
+fn write_outlines(nodes: &[Node], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    for node in nodes {
+        if node.children.is_empty() {
+            out.push_str(&format!("{}<outline text=\"{}\"/>\n", indent, escape(&node.text)));
+        } else {
+            out.push_str(&format!("{}<outline text=\"{}\">\n", indent, escape(&node.text)));
+            write_outlines(&node.children, depth + 1, out);
+            out.push_str(&format!("{}</outline>\n", indent));
+        }
+    }
+}
+
+/// A note's headings and body lines as a tree, without frontmatter, code blocks, rules or comments.
+fn note_outline(text: &str) -> Vec<Node> {
+    let body = crate::frontmatter::split(text).1;
+    let mut items: Vec<(isize, String)> = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") { in_fence = !in_fence; continue; }
+        let trimmed = line.trim();
+        if in_fence || trimmed.is_empty() || trimmed == "---" || (trimmed.starts_with("<!--") && trimmed.ends_with("-->")) { continue; }
+        let hashes = line.len() - line.trim_start_matches('#').len();
+        let (depth, text) = if hashes > 0 && line[hashes..].starts_with(' ') {
+            (hashes as isize - 10, line[hashes..].trim())
+        } else {
+            let ws = &line[..line.len() - line.trim_start().len()];
+            (ws.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum(), strip_marker(line.trim_start()))
+        };
+        items.push((depth, text.to_string()));
+    }
+    let mut pos = 0;
+    build(&items, &mut pos, isize::MIN)
+}
+
+/// Items from `pos` deeper than `parent`, each owning the deeper items after it.
+fn build(items: &[(isize, String)], pos: &mut usize, parent: isize) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    while let Some((depth, text)) = items.get(*pos) {
+        if *depth <= parent { break; }
+        *pos += 1;
+        let children = build(items, pos, *depth);
+        nodes.push(Node { text: text.clone(), note: None, folder: false, children });
+    }
+    nodes
+}
+
+fn strip_marker(line: &str) -> &str {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) { return rest; }
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 { if let Some(rest) = line[digits..].strip_prefix(". ") { return rest; } }
+    line
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\n', "&#10;")
+}
@@ -0,0 +1,158 @@
+//! Outliner for notes made of nested bullet lists: fold branches, reorder items and change their
+//! depth without leaving knot. Every structural edit is written straight back to the markdown.
+//!
+//! An item owns the lines after it that are nested deeper; headings own everything up to the
+//! next heading of the same or a higher level, so whole sections move and fold too.
+
+use anyhow::Result;
+use std::{fs, path::{Path, PathBuf}};
+
+pub struct Outline {
+    pub path: PathBuf,
+    lines: Vec<String>,
+    /// Parallel to `lines`, so fold state travels with an item when it moves.
+    folded: Vec<bool>,
+    /// Index into `lines`; always a visible, non-blank line.
+    pub cursor: usize,
+    /// One level of indentation, as the note already writes it.
+    unit: String,
+    trailing_newline: bool,
+}
+
+pub enum Marker { Leaf, Open, Folded }
+
+impl Outline {
+    pub fn open(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let unit = if lines.iter().any(|l| l.starts_with('\t')) {
+            "\t".to_string()
+        } else {
+            let min = lines.iter().filter(|l| !l.trim().is_empty()).map(|l| l.len() - l.trim_start_matches(' ').len()).filter(|&n| n > 0).min();
+            " ".repeat(min.unwrap_or(2))
+        };
+        let mut outline = Self { path: path.to_path_buf(), folded: vec![false; lines.len()], lines, cursor: 0, unit, trailing_newline: text.ends_with('\n') };
+        outline.cursor = outline.visible().first().copied().unwrap_or(0);
+        Ok(outline)
+    }
+
+    fn is_blank(&self, i: usize) -> bool { self.lines[i].trim().is_empty() }
+
+    fn heading_level(&self, i: usize) -> Option<usize> {
+        let line = &self.lines[i];
+        let level = line.len() - line.trim_start_matches('#').len();
+        (level > 0 && line[level..].starts_with(' ')).then_some(level)
+    }
+
+    /// Nesting depth: headings sit above all body text (`#` shallowest), body text goes by indent.
+    fn depth(&self, i: usize) -> isize {
+        if let Some(level) = self.heading_level(i) { return level as isize - 10; }
+        let ws = &self.lines[i][..self.lines[i].len() - self.lines[i].trim_start().len()];
+        ws.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum::<isize>()
+    }
+
+    /// One past the last line owned by `i`. Blank lines inside the branch belong to it; trailing ones don't.
+    fn subtree_end(&self, i: usize) -> usize {
+        let mut end = i + 1;
+        for j in i + 1..self.lines.len() {
+            if self.is_blank(j) { continue; }
+            if self.depth(j) <= self.depth(i) { break; }
+            end = j + 1;
+        }
+        end
+    }
+
+    /// Non-blank lines not hidden inside a folded branch.
+    pub fn visible(&self) -> Vec<usize> {
+        let mut rows = Vec::new();
+        let mut i = 0;
+        while i < self.lines.len() {
+            if self.is_blank(i) { i += 1; continue; }
+            rows.push(i);
+            i = if self.folded[i] { self.subtree_end(i) } else { i + 1 };
+        }
+        rows
+    }
+
+    /// Visible rows with their fold marker, for drawing.
+    pub fn rows(&self) -> Vec<(usize, Marker, &str)> {
+        self.visible().into_iter().map(|i| {
+            let marker = match (self.subtree_end(i) > i + 1, self.folded[i]) {
+                (false, _) => Marker::Leaf,
+                (true, false) => Marker::Open,
+                (true, true) => Marker::Folded,
+            };
+            (i, marker, self.lines[i].as_str())
+        }).collect()
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let rows = self.visible();
+        let Some(pos) = rows.iter().position(|&i| i == self.cursor) else { return };
+        self.cursor = rows[pos.saturating_add_signed(delta).min(rows.len().saturating_sub(1))];
+    }
+
+    pub fn toggle_fold(&mut self) {
+        if self.subtree_end(self.cursor) > self.cursor + 1 { self.folded[self.cursor] ^= true; }
+    }
+
+    /// Sets the fold state of every branch at once.
+    pub fn fold_all(&mut self, folded: bool) {
+        for i in 0..self.lines.len() { self.folded[i] = folded && self.subtree_end(i) > i + 1; }
+        // The cursor may now be hidden; the nearest visible line above it is the branch hiding it.
+        let rows = self.visible();
+        self.cursor = rows.iter().rev().find(|&&i| i <= self.cursor).or(rows.first()).copied().unwrap_or(0);
+    }
+
+    fn prev_sibling(&self, i: usize) -> Option<usize> {
+        let j = (0..i).rev().find(|&j| !self.is_blank(j) && self.depth(j) <= self.depth(i))?;
+        (self.depth(j) == self.depth(i)).then_some(j)
+    }
+
+    fn next_sibling(&self, i: usize) -> Option<usize> {
+        let k = (self.subtree_end(i)..self.lines.len()).find(|&k| !self.is_blank(k))?;
+        (self.depth(k) == self.depth(i)).then_some(k)
+    }
+
+    /// Swaps the item under the cursor, with everything it owns, with its previous (`up`) or next sibling.
+    pub fn move_item(&mut self, up: bool) -> Result<()> {
+        let (start, split, end) = if up {
+            let Some(prev) = self.prev_sibling(self.cursor) else { return Ok(()) };
+            (prev, self.cursor, self.subtree_end(self.cursor))
+        } else {
+            let Some(next) = self.next_sibling(self.cursor) else { return Ok(()) };
+            (self.cursor, next, self.subtree_end(next))
+        };
+        self.lines[start..end].rotate_left(split - start);
+        self.folded[start..end].rotate_left(split - start);
+        self.cursor = if up { start } else { start + end - split };
+        self.save()
+    }
+
+    /// Indents (or outdents) the item under the cursor and its children by one level.
+    /// Indenting needs a previous sibling to become the new parent; headings keep their level.
+    pub fn shift(&mut self, deeper: bool) -> Result<()> {
+        let i = self.cursor;
+        if self.heading_level(i).is_some() { return Ok(()); }
+        if deeper && self.prev_sibling(i).is_none() { return Ok(()); }
+        if !deeper && self.depth(i) == 0 { return Ok(()); }
+        for j in i..self.subtree_end(i) {
+            if self.is_blank(j) { continue; }
+            let line = &mut self.lines[j];
+            if deeper {
+                line.insert_str(0, &self.unit);
+            } else {
+                let ws = line.len() - line.trim_start().len();
+                line.drain(..ws.min(self.unit.len()));
+            }
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let mut text = self.lines.join("\n");
+        if self.trailing_newline { text.push('\n'); }
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
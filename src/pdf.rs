@@ -0,0 +1,67 @@
+//! Text from PDF attachments, so stored papers show in the preview and turn up in search.
+//!
+//! Extraction shells out to poppler's `pdftotext` and is cached under the user's cache directory,
+//! keyed by path, size and modification time, so each PDF is only read once until it changes.
+
+use anyhow::{bail, Result};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::UNIX_EPOCH,
+};
+use walkdir::WalkDir;
+
+use crate::trash::TRASH_DIR;
+
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+/// Every PDF in the vault, attachment folders included, sorted by path.
+pub fn walk(root: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = WalkDir::new(root).min_depth(1).into_iter()
+        .filter_entry(|e| { let name = e.file_name().to_string_lossy(); !name.starts_with('.') && name != TRASH_DIR })
+        .flatten()
+        .filter(|e| e.file_type().is_file() && is_pdf(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    found.sort();
+    found
+}
+
+/// The PDF's text, pages separated by form feeds.
+pub fn text(path: &Path) -> Result<String> {
+    let cached = cache_path(path);
+    if let Some(text) = cached.as_ref().and_then(|c| fs::read_to_string(c).ok()) { return Ok(text); }
+    let out = Command::new("pdftotext").args(["-layout", "-enc", "UTF-8"]).arg(path).arg("-")
+        .stdin(Stdio::null()).output()
+        .map_err(|e| if e.kind() == std::io::ErrorKind::NotFound { anyhow::anyhow!("pdftotext (poppler) is not installed or not on PATH") } else { e.into() })?;
+    if !out.status.success() { bail!("pdftotext: {}", String::from_utf8_lossy(&out.stderr).trim()); }
+    let text = String::from_utf8_lossy(&out.stdout).into_owned();
+    if let Some(cached) = cached {
+        if let Some(dir) = cached.parent() { let _ = fs::create_dir_all(dir); }
+        let _ = fs::write(cached, &text);
+    }
+    Ok(text)
+}
+
+/// The first page of [`text`] with a header giving the page count, for the preview.
+pub fn preview(path: &Path) -> Result<String> {
+    let text = text(path)?;
+    let pages = text.trim_end_matches('\x0c').split('\x0c').count();
+    let first = text.split('\x0c').next().unwrap_or_default().trim_end();
+    let body = if first.trim().is_empty() { "(no text on the first page; scanned PDFs need OCR)" } else { first };
+    Ok(format!("📄 Page 1 of {}\n\n{}\n", pages, body))
+}
+
+fn cache_path(path: &Path) -> Option<PathBuf> {
+    let meta = fs::metadata(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).hash(&mut hasher);
+    meta.len().hash(&mut hasher);
+    meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos().hash(&mut hasher);
+    Some(dirs::cache_dir()?.join("knot").join("pdf").join(format!("{:016x}.txt", hasher.finish())))
+}
@@ -0,0 +1,42 @@
+//! Generic filterable selection popup. `kind` tells the event loop what to do with the choice.
+
+use crate::fuzzy;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PickerKind { Unarchive, SearchResults, QuickSwitch, MoveTo, Trash, Tags, Reading, Template, Agenda, History, Tasks, Audio, Contexts, Commands, Incoming, Authors, Themes, Comments }
+
+pub struct Picker {
+    pub kind: PickerKind,
+    pub title: String,
+    pub items: Vec<String>,
+    pub query: String,
+    /// Position within the filtered list.
+    pub cursor: usize,
+}
+
+impl Picker {
+    pub fn new(kind: PickerKind, title: impl Into<String>, items: Vec<String>) -> Self {
+        Self { kind, title: title.into(), items, query: String::new(), cursor: 0 }
+    }
+
+    /// Indices into `items` that match the query, best first (original order when the query is empty).
+    pub fn visible(&self) -> Vec<usize> {
+        if self.query.is_empty() { return (0..self.items.len()).collect(); }
+        fuzzy::rank(&self.query, &self.items, self.items.len())
+    }
+
+    /// Index into `items` of the highlighted entry.
+    pub fn selected(&self) -> Option<usize> {
+        let visible = self.visible();
+        visible.get(self.cursor.min(visible.len().saturating_sub(1))).copied()
+    }
+
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.visible().len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn push(&mut self, c: char) { self.query.push(c); self.cursor = 0; }
+
+    pub fn pop(&mut self) { self.query.pop(); self.cursor = 0; }
+}
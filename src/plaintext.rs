@@ -0,0 +1,318 @@
+//! Plain-text rendering of a note for pasting into email: prose wrapped to a width, links and
+//! footnotes turned into numbered references listed at the end, tables laid out as aligned
+//! columns, code indented. Like the formatter it works line by line, and anything it doesn't
+//! recognise passes through as written.
+
+use std::collections::HashMap;
+
+use crate::{frontmatter, markdown};
+
+/// Numbered references collected while rendering, in order of first use.
+#[derive(Default)]
+struct Notes {
+    /// `[^id]: text` definitions, by id.
+    defs: HashMap<String, String>,
+    /// Number already given to a URL or footnote id.
+    numbers: HashMap<String, usize>,
+    texts: Vec<String>,
+}
+
+impl Notes {
+    fn number(&mut self, key: String, text: impl FnOnce() -> String) -> usize {
+        if let Some(&n) = self.numbers.get(&key) { return n; }
+        self.texts.push(text());
+        self.numbers.insert(key, self.texts.len());
+        self.texts.len()
+    }
+}
+
+/// A paragraph, list item or quote being collected until it ends.
+struct Block {
+    first: String,
+    rest: String,
+    text: String,
+}
+
+/// `src` as plain text wrapped at `width` columns (0: no wrapping).
+pub fn render(src: &str, width: usize) -> String {
+    let mut notes = Notes::default();
+    let mut lines: Vec<&str> = Vec::new();
+    // Footnote definitions go to the end, so take them out first; continuation lines are indented.
+    let mut current: Option<String> = None;
+    for line in frontmatter::split(src).1.lines() {
+        if let Some((id, text)) = line.strip_prefix("[^").and_then(|r| r.split_once("]:")) {
+            current = Some(id.to_string());
+            notes.defs.insert(id.to_string(), text.trim().to_string());
+        } else if let (Some(id), true) = (&current, line.starts_with("    ") || line.starts_with('\t')) {
+            let def = notes.defs.entry(id.clone()).or_default();
+            def.push(' ');
+            def.push_str(line.trim());
+        } else {
+            current = None;
+            lines.push(line);
+        }
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut block: Option<Block> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        i += 1;
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush(&mut block, &mut out, width, &mut notes);
+            gap(&mut out);
+            let fence = &trimmed[..3];
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                out.push(format!("    {}", lines[i]).trim_end().to_string());
+                i += 1;
+            }
+            i += 1;
+            gap(&mut out);
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut block, &mut out, width, &mut notes);
+            gap(&mut out);
+            continue;
+        }
+        if trimmed.starts_with("<!--") && trimmed.trim_end().ends_with("-->") { continue; }
+        if trimmed.starts_with('|') {
+            flush(&mut block, &mut out, width, &mut notes);
+            let start = i - 1;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') { i += 1; }
+            gap(&mut out);
+            out.extend(table(&lines[start..i], &mut notes));
+            gap(&mut out);
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            flush(&mut block, &mut out, width, &mut notes);
+            gap(&mut out);
+            let text = inline(trimmed[level..].trim().trim_end_matches('#').trim_end(), &mut notes, true);
+            let len = text.chars().count();
+            out.push(text);
+            match level {
+                1 => out.push("=".repeat(len)),
+                2 => out.push("-".repeat(len)),
+                _ => {}
+            }
+            gap(&mut out);
+            continue;
+        }
+        let compact: String = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+        if compact.len() >= 3 && ["*", "-", "_"].iter().any(|m| compact.chars().all(|c| m.starts_with(c))) {
+            flush(&mut block, &mut out, width, &mut notes);
+            gap(&mut out);
+            out.push("* * *".to_string());
+            gap(&mut out);
+            continue;
+        }
+        if trimmed.starts_with('>') {
+            let depth = trimmed.chars().take_while(|&c| c == '>' || c == ' ').filter(|&c| c == '>').count();
+            let text = trimmed.trim_start_matches(['>', ' ']);
+            let prefix = "> ".repeat(depth);
+            if block.as_ref().is_some_and(|b| b.first != prefix) { flush(&mut block, &mut out, width, &mut notes); }
+            let b = block.get_or_insert_with(|| Block { first: prefix.clone(), rest: prefix, text: String::new() });
+            append(b, text, line);
+            continue;
+        }
+        if let Some(marker) = list_marker(trimmed) {
+            flush(&mut block, &mut out, width, &mut notes);
+            let pad = " ".repeat(indent);
+            block = Some(Block { first: format!("{}{}", pad, marker), rest: format!("{}{}", pad, " ".repeat(marker.chars().count())), text: String::new() });
+            append(block.as_mut().expect("just set"), &trimmed[marker.len()..], line);
+            continue;
+        }
+        // Anything else continues the open paragraph or item, or starts a paragraph.
+        let b = block.get_or_insert_with(|| Block { first: String::new(), rest: String::new(), text: String::new() });
+        append(b, trimmed, line);
+    }
+    flush(&mut block, &mut out, width, &mut notes);
+
+    if !notes.texts.is_empty() {
+        gap(&mut out);
+        let texts = std::mem::take(&mut notes.texts);
+        let label_width = format!("[{}] ", texts.len()).len();
+        for (n, text) in texts.iter().enumerate() {
+            let label = format!("[{}] ", n + 1);
+            out.extend(wrap(text, width, &format!("{:<w$}", label, w = label_width), &" ".repeat(label_width)));
+        }
+    }
+    while out.first().is_some_and(|l| l.is_empty()) { out.remove(0); }
+    while out.last().is_some_and(|l| l.is_empty()) { out.pop(); }
+    out.join("\n") + "\n"
+}
+
+/// Adds a source line to the block, keeping a hard break (two trailing spaces or `\`) as a newline.
+fn append(block: &mut Block, text: &str, line: &str) {
+    if !block.text.is_empty() && !block.text.ends_with('\n') { block.text.push(' '); }
+    let hard = line.ends_with("  ") || line.ends_with('\\');
+    block.text.push_str(text.trim_end().trim_end_matches('\\'));
+    if hard { block.text.push('\n'); }
+}
+
+fn flush(block: &mut Option<Block>, out: &mut Vec<String>, width: usize, notes: &mut Notes) {
+    let Some(b) = block.take() else { return };
+    for (n, part) in b.text.trim_end().split('\n').enumerate() {
+        let first = if n == 0 { &b.first } else { &b.rest };
+        out.extend(wrap(&inline(part, notes, true), width, first, &b.rest));
+    }
+}
+
+fn gap(out: &mut Vec<String>) {
+    if out.last().is_some_and(|l| !l.is_empty()) { out.push(String::new()); }
+}
+
+/// `- `, `* `, `+ `, `1. ` or `1) ` at the start of an item, with task boxes kept as text.
+fn list_marker(line: &str) -> Option<&str> {
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") { return Some(&line[..2]); }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    (digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") "))).then(|| &line[..digits + 2])
+}
+
+/// Words of `text` filled to `width`, the first line after `first` and the others after `rest`.
+/// Words longer than a line (URLs, mostly) are never broken.
+fn wrap(text: &str, width: usize, first: &str, rest: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut cur = first.to_string();
+    let mut has_word = false;
+    for word in text.split(' ').filter(|w| !w.is_empty()) {
+        if width > 0 && has_word && cur.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::replace(&mut cur, rest.to_string()));
+            has_word = false;
+        }
+        if has_word { cur.push(' '); }
+        cur.push_str(word);
+        has_word = true;
+    }
+    lines.push(cur.trim_end().to_string());
+    lines
+}
+
+/// A pipe table as columns padded to line up, the header underlined; cells aligned right when
+/// the separator says so (`--:`).
+fn table(rows: &[&str], notes: &mut Notes) -> Vec<String> {
+    let cells: Vec<Vec<String>> = rows.iter().map(|r| markdown::split_cells(r)).collect();
+    let right: Vec<bool> = cells.iter().find(|r| markdown::is_separator(r))
+        .map(|sep| sep.iter().map(|c| c.ends_with(':') && !c.starts_with(':')).collect()).unwrap_or_default();
+    let body: Vec<Vec<String>> = cells.iter().filter(|r| !markdown::is_separator(r))
+        .map(|r| r.iter().map(|c| inline(c, notes, true)).collect()).collect();
+    let cols = body.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![0; cols];
+    for row in &body {
+        for (c, cell) in row.iter().enumerate() { widths[c] = widths[c].max(cell.chars().count()); }
+    }
+    let line = |row: &[String]| -> String {
+        let parts: Vec<String> = (0..cols).map(|c| {
+            let cell = row.get(c).map(String::as_str).unwrap_or("");
+            if right.get(c).copied().unwrap_or(false) { format!("{:>w$}", cell, w = widths[c]) } else { format!("{:<w$}", cell, w = widths[c]) }
+        }).collect();
+        parts.join("  ").trim_end().to_string()
+    };
+    let mut out = Vec::new();
+    for (n, row) in body.iter().enumerate() {
+        out.push(line(row));
+        // Only underline a real header row, one followed by the separator.
+        if n == 0 && cells.get(1).is_some_and(|r| markdown::is_separator(r)) {
+            out.push(widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("  "));
+        }
+    }
+    out
+}
+
+/// Inline markdown as plain text: emphasis as `*bold*` and `_italic_`, links and footnotes as
+/// `[n]` references (plain `label (url)` when `refs` is off, inside footnote text itself).
+fn inline(text: &str, notes: &mut Notes, refs: bool) -> String {
+    let mut out = String::new();
+    let (mut bold, mut italic) = (false, false);
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let prev_alnum = out.chars().last().is_some_and(char::is_alphanumeric);
+        if c == '\\' {
+            if let Some(next) = rest[1..].chars().next().filter(|n| n.is_ascii_punctuation()) {
+                out.push(next);
+                rest = &rest[1 + next.len_utf8()..];
+                continue;
+            }
+        }
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                out.push_str(&rest[..end + 2]);
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if let Some(after) = rest.strip_prefix("[^") {
+            if let Some(end) = after.find(']') {
+                let id = &after[..end];
+                if let Some(def) = notes.defs.get(id).cloned() {
+                    if refs {
+                        let body = inline(&def, notes, false);
+                        out.push_str(&format!("[{}]", notes.number(format!("^{}", id), || body)));
+                    }
+                    rest = &after[end + 1..];
+                    continue;
+                }
+            }
+        }
+        if let Some(after) = rest.strip_prefix("[[") {
+            if let Some(end) = after.find("]]") {
+                let inner = &after[..end];
+                out.push_str(inner.split_once('|').map_or(inner, |(_, alias)| alias));
+                rest = &after[end + 2..];
+                continue;
+            }
+        }
+        if c == '<' {
+            if let Some(end) = rest.find('>').filter(|&e| rest[1..e].contains("://") && !rest[1..e].contains(' ')) {
+                out.push_str(&rest[1..end]);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        let image = rest.starts_with("![");
+        if c == '[' || image {
+            let open = if image { 2 } else { 1 };
+            if let Some((label, after)) = rest[open..].split_once("](") {
+                if let Some(close) = after.find(')').filter(|_| !label.contains(']')) {
+                    let target = after[..close].split_whitespace().next().unwrap_or("").trim_matches(['<', '>']);
+                    let label = inline(label, notes, false);
+                    let label = if label.is_empty() && image { "image".to_string() } else { label };
+                    out.push_str(&link(&label, target, notes, refs));
+                    rest = &after[close + 1..];
+                    continue;
+                }
+            }
+        }
+        let toggle = |marker: &str, on: bool| rest.starts_with(marker) && (on || rest[marker.len()..].contains(marker));
+        if toggle("**", bold) || toggle("__", bold) && !prev_alnum {
+            out.push('*');
+            bold = !bold;
+            rest = &rest[2..];
+            continue;
+        }
+        if toggle("*", italic) || (toggle("_", italic) && (italic || !prev_alnum)) {
+            out.push('_');
+            italic = !italic;
+            rest = &rest[1..];
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// A link's label followed by its reference. Links to other notes or files in the vault mean
+/// nothing to the reader, so only the label is kept; so is a label that is the URL itself.
+fn link(label: &str, target: &str, notes: &mut Notes, refs: bool) -> String {
+    let shown = target.strip_prefix("mailto:").unwrap_or(target);
+    if !(target.contains("://") || target.starts_with("mailto:")) || label == shown || label == target { return label.to_string(); }
+    if !refs { return format!("{} ({})", label, shown); }
+    format!("{} [{}]", label, notes.number(target.to_string(), || shown.to_string()))
+}
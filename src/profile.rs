@@ -0,0 +1,57 @@
+//! Timing collection behind `--profile`: per-operation stats for the debug HUD plus a log file.
+
+use chrono::Local;
+use std::{collections::BTreeMap, fs::{self, File, OpenOptions}, io::Write, path::PathBuf, time::{Duration, Instant}};
+
+#[derive(Default, Clone, Copy)]
+struct Stats { count: u32, total: Duration, last: Duration, max: Duration }
+
+pub struct Profiler {
+    enabled: bool,
+    stats: BTreeMap<&'static str, Stats>,
+    log: Option<File>,
+}
+
+impl Profiler {
+    pub fn new(enabled: bool) -> Self {
+        let log = if enabled { Self::log_path().and_then(|p| {
+            fs::create_dir_all(p.parent()?).ok()?;
+            OpenOptions::new().create(true).append(true).open(p).ok()
+        }) } else { None };
+        Self { enabled, stats: BTreeMap::new(), log }
+    }
+
+    pub fn log_path() -> Option<PathBuf> { dirs::cache_dir().map(|d| d.join("knot").join("profile.log")) }
+
+    pub fn enabled(&self) -> bool { self.enabled }
+
+    pub fn record(&mut self, label: &'static str, elapsed: Duration) {
+        if !self.enabled { return; }
+        let s = self.stats.entry(label).or_default();
+        s.count += 1;
+        s.total += elapsed;
+        s.last = elapsed;
+        s.max = s.max.max(elapsed);
+        if let Some(log) = self.log.as_mut() {
+            let _ = writeln!(log, "{} {:<12} {:>10.3}ms", Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"), label, ms(elapsed));
+        }
+    }
+
+    /// Runs `f`, recording how long it took under `label`.
+    pub fn time<T>(&mut self, label: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.record(label, start.elapsed());
+        out
+    }
+
+    /// One line per operation for the HUD: count, last, average and worst time.
+    pub fn hud_lines(&self) -> Vec<String> {
+        self.stats.iter().map(|(label, s)| {
+            let avg = s.total / s.count.max(1);
+            format!("{:<10} n={:<5} last={:>8.2}ms avg={:>8.2}ms max={:>8.2}ms", label, s.count, ms(s.last), ms(avg), ms(s.max))
+        }).collect()
+    }
+}
+
+fn ms(d: Duration) -> f64 { d.as_secs_f64() * 1000.0 }
@@ -0,0 +1,50 @@
+//! `knot new-project`: a category (or folder) pre-filled from the vault's project template set.
+//!
+//! A vault can supply its own set in `.knot/templates/project/`; every file there is copied with
+//! `{{name}}`, `{{date}}` and `{{time}}` filled in. Without one the built-in set below is used.
+
+use anyhow::{bail, Result};
+use std::{fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+use crate::snippets;
+
+pub const TEMPLATE_DIR: &str = ".knot/templates/project";
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("overview.md", "# {{name}}\n\nStarted {{date}}.\n\n## Goal\n\n## Scope\n\n## Links\n"),
+    ("tasks.md", "# {{name}}: Tasks\n\n- [ ] \n"),
+    ("log.md", "# {{name}}: Log\n\n## {{date}}\n\n- Project created\n"),
+    ("decisions.md", "# {{name}}: Decisions\n\n<!-- One entry per decision: date, context, decision, consequences. -->\n"),
+];
+
+/// Creates `<root>/<name>` (or `<root>/<category>/<name>`) and writes the template set into it.
+/// Returns the files written.
+pub fn scaffold(root: &Path, name: &str, category: Option<&str>) -> Result<Vec<PathBuf>> {
+    if name.trim().is_empty() || name.contains(['/', '\\']) { bail!("invalid project name \"{}\"", name); }
+    let dir = match category {
+        Some(cat) => root.join(cat).join(name),
+        None => root.join(name),
+    };
+    if dir.exists() { bail!("{} already exists", dir.display()); }
+    let fill = |body: &str| snippets::render(&body.replace("{{name}}", name));
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    let custom = root.join(TEMPLATE_DIR);
+    if custom.is_dir() {
+        for entry in WalkDir::new(&custom).min_depth(1).into_iter().flatten().filter(|e| e.file_type().is_file()) {
+            let rel = entry.path().strip_prefix(&custom)?.to_path_buf();
+            files.push((rel, fill(&fs::read_to_string(entry.path())?)));
+        }
+    } else {
+        files.extend(BUILTIN.iter().map(|(file, body)| (PathBuf::from(file), fill(body))));
+    }
+    let mut written = Vec::new();
+    for (rel, text) in files {
+        let path = dir.join(rel);
+        if let Some(parent) = path.parent() { fs::create_dir_all(parent)?; }
+        fs::write(&path, text)?;
+        written.push(path);
+    }
+    written.sort();
+    Ok(written)
+}
@@ -0,0 +1,300 @@
+//! QR codes for sharing a note to a phone, drawn with half-block characters.
+//!
+//! A byte-mode encoder after the specification (ISO/IEC 18004), at error correction level L, which
+//! packs the most text into a code small enough for a terminal.
+
+/// Error correction codewords per block, by version (level L).
+const ECC_PER_BLOCK: [usize; 41] = [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30];
+/// Error correction blocks, by version (level L).
+const BLOCKS: [usize; 41] = [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25];
+/// Level L's two format bits.
+const LEVEL_L: u32 = 1;
+
+pub struct QrCode {
+    pub size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// The smallest code holding `data`, if one of at most `max_size` modules a side does.
+    pub fn encode(data: &[u8], max_size: usize) -> Option<Self> {
+        let version = (1..=40).take_while(|v| v * 4 + 17 <= max_size).find(|&v| data_bits(v, data.len()) <= data_codewords(v) * 8)?;
+        let capacity = data_codewords(version) * 8;
+        let mut bits = Bits::default();
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, if version < 10 { 8 } else { 16 });
+        for &b in data { bits.push(b as u32, 8); }
+        bits.push(0, (capacity - bits.0.len()).min(4));
+        bits.push(0, (8 - bits.0.len() % 8) % 8);
+        let mut codewords: Vec<u8> = bits.0.chunks(8).map(|c| c.iter().fold(0, |acc, &b| acc << 1 | b as u8)).collect();
+        for pad in [0xEC, 0x11].into_iter().cycle() {
+            if codewords.len() * 8 >= capacity { break; }
+            codewords.push(pad);
+        }
+        let mut qr = Self::blank(version);
+        qr.draw_codewords(&interleave(version, &codewords));
+        // Any mask reads back; the least penalised one scans most reliably.
+        let mask = (0..8).min_by_key(|&m| {
+            qr.apply_mask(m);
+            qr.draw_format(m);
+            let score = qr.penalty();
+            qr.apply_mask(m);
+            score
+        }).unwrap_or(0);
+        qr.apply_mask(mask);
+        qr.draw_format(mask);
+        Some(qr)
+    }
+
+    pub fn dark(&self, x: usize, y: usize) -> bool { self.modules[y * self.size + x] }
+
+    /// The code as lines of half blocks, two rows of modules per line, with a quiet zone of
+    /// `quiet` modules. `true` in the returned pairs means dark, for the caller to colour.
+    pub fn half_blocks(&self, quiet: usize) -> Vec<Vec<(bool, bool)>> {
+        let span = self.size + 2 * quiet;
+        let at = |x: usize, y: usize| x >= quiet && y >= quiet && x < quiet + self.size && y < quiet + self.size && self.dark(x - quiet, y - quiet);
+        (0..span.div_ceil(2)).map(|row| (0..span).map(|x| (at(x, row * 2), at(x, row * 2 + 1))).collect()).collect()
+    }
+
+    /// Finder, timing and alignment patterns, with the format and version areas reserved.
+    fn blank(version: usize) -> Self {
+        let size = version * 4 + 17;
+        let mut qr = Self { size, modules: vec![false; size * size], function: vec![false; size * size] };
+        for i in 0..size {
+            qr.set_function(6, i, i % 2 == 0);
+            qr.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                    if (0..size as i32).contains(&xx) && (0..size as i32).contains(&yy) {
+                        let dist = dx.abs().max(dy.abs());
+                        qr.set_function(xx as usize, yy as usize, dist != 2 && dist != 4);
+                    }
+                }
+            }
+        }
+        let positions = alignment_positions(version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The corners are taken by finder patterns.
+                if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) { continue; }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        qr.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dx.abs().max(dy.abs()) != 1);
+                    }
+                }
+            }
+        }
+        qr.draw_format(0);
+        if version >= 7 {
+            let mut rem = version as u32;
+            for _ in 0..12 { rem = (rem << 1) ^ ((rem >> 11) * 0x1F25); }
+            let bits = (version as u32) << 12 | rem;
+            for i in 0..18 {
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                qr.set_function(a, b, bits >> i & 1 == 1);
+                qr.set_function(b, a, bits >> i & 1 == 1);
+            }
+        }
+        qr
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_format(&mut self, mask: u32) {
+        let data = LEVEL_L << 3 | mask;
+        let mut rem = data;
+        for _ in 0..10 { rem = (rem << 1) ^ ((rem >> 9) * 0x537); }
+        let bits = (data << 10 | rem) ^ 0x5412;
+        let bit = |i: u32| bits >> i & 1 == 1;
+        let size = self.size;
+        for i in 0..=5 { self.set_function(8, i, bit(i as u32)); }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 { self.set_function(14 - i, 8, bit(i as u32)); }
+        for i in 0..8 { self.set_function(size - 1 - i, 8, bit(i as u32)); }
+        for i in 8..15 { self.set_function(8, size - 15 + i, bit(i as u32)); }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Fills the non-function modules in the standard zigzag, two columns at a time from the
+    /// bottom right.
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            if right == 6 { right = 5; }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !self.function[y * size + x] && i < data.len() * 8 {
+                        self.modules[y * size + x] = data[i >> 3] >> (7 - (i & 7)) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 { break; }
+            right -= 2;
+        }
+    }
+
+    /// Inverts the data modules the mask selects; applying it twice undoes it.
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.function[y * self.size + x] { self.modules[y * self.size + x] ^= true; }
+            }
+        }
+    }
+
+    /// The specification's penalty for runs, 2×2 blocks and imbalance; finder-like patterns
+    /// aren't scored.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut score = 0;
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let mut run = 0;
+                let mut last = None;
+                for b in 0..size {
+                    let dark = if horizontal { self.dark(b, a) } else { self.dark(a, b) };
+                    if Some(dark) == last { run += 1; } else { run = 1; last = Some(dark); }
+                    if run == 5 { score += 3; } else if run > 5 { score += 1; }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = self.dark(x, y);
+                if c == self.dark(x + 1, y) && c == self.dark(x, y + 1) && c == self.dark(x + 1, y + 1) { score += 3; }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&d| d).count();
+        let total = size * size;
+        score + (dark * 20).abs_diff(total * 10).div_ceil(total) * 10
+    }
+}
+
+#[derive(Default)]
+struct Bits(Vec<bool>);
+
+impl Bits {
+    fn push(&mut self, value: u32, len: usize) {
+        for i in (0..len).rev() { self.0.push(value >> i & 1 == 1); }
+    }
+}
+
+fn data_bits(version: usize, len: usize) -> usize {
+    4 + if version < 10 { 8 } else { 16 } + len * 8
+}
+
+/// Modules left for data and error correction once the function patterns are placed.
+fn raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let align = version / 7 + 2;
+        result -= (25 * align - 10) * align - 55;
+        if version >= 7 { result -= 36; }
+    }
+    result
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 { return Vec::new(); }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut positions = vec![6];
+    let mut pos = version * 4 + 17 - 7;
+    for _ in 0..count - 1 {
+        positions.insert(1, pos);
+        pos -= step;
+    }
+    positions
+}
+
+/// Splits the data into blocks, appends each block's Reed-Solomon codewords and interleaves them.
+fn interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let (blocks, ecc_len) = (BLOCKS[version], ECC_PER_BLOCK[version]);
+    let raw = raw_data_modules(version) / 8;
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks;
+    let divisor = rs_divisor(ecc_len);
+    let mut all = Vec::new();
+    let mut k = 0;
+    for i in 0..blocks {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let ecc = rs_remainder(&block, &divisor);
+        // Short blocks get a placeholder so every block lines up column by column.
+        if i < short_blocks { block.push(0); }
+        block.extend(ecc);
+        all.push(block);
+    }
+    let mut out = Vec::with_capacity(raw);
+    for i in 0..all[0].len() {
+        for (j, block) in all.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks { out.push(block[i]); }
+        }
+    }
+    out
+}
+
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree { result[j] ^= result[j + 1]; }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) { *r ^= gf_mul(d, factor); }
+    }
+    result
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
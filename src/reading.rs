@@ -0,0 +1,138 @@
+//! Read-later queue: captured URLs become notes in the `Reading` category with the page title,
+//! an `unread`/`read` status in their frontmatter and, optionally, a plain-markdown snapshot of
+//! the page's main text.
+//!
+//! Pages are fetched with `curl` (present on Linux, macOS and Windows 10+). Snapshots are a
+//! readability-style approximation: the `<article>` (or `<main>`, or `<body>`) converted to
+//! markdown with scripts, navigation and other page chrome dropped.
+//!
+//! The same fetch unfurls bare URLs in captured text into `[Title](url)` links.
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use std::{fs, path::{Path, PathBuf}, process::{Command, Stdio}, time::Duration};
+
+use crate::{frontmatter, html};
+
+pub const DIR: &str = "Reading";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub struct Item {
+    pub path: PathBuf,
+    pub title: String,
+    pub read: bool,
+}
+
+/// Saves `url` to the queue and returns the new note. Without a title the page's `<title>` is
+/// used; a page that can't be fetched is still queued, titled by its URL.
+pub fn capture(root: &Path, url: &str, title: Option<&str>, snapshot: bool) -> Result<PathBuf> {
+    if !url.contains("://") { bail!("\"{}\" is not a URL", url); }
+    let html = if title.is_none() || snapshot { fetch(url, FETCH_TIMEOUT).ok() } else { None };
+    let title = title.map(String::from)
+        .or_else(|| html.as_deref().and_then(html::title))
+        .unwrap_or_else(|| url.split("://").nth(1).unwrap_or(url).trim_end_matches('/').to_string());
+    let mut body = format!(
+        "---\nurl: {}\ntitle: \"{}\"\nadded: {}\nstatus: unread\n---\n# {}\n\n<{}>\n",
+        url, title.replace('"', "'"), Local::now().format("%Y-%m-%d"), title, url
+    );
+    if let Some(text) = html.as_deref().filter(|_| snapshot).map(|h| html::to_markdown(html::main_content(h))).filter(|t| !t.is_empty()) {
+        body.push_str(&format!("\n## Snapshot\n\n{}\n", text));
+    }
+    let dir = root.join(DIR);
+    fs::create_dir_all(&dir)?;
+    let stem = slug(&title);
+    let mut path = dir.join(format!("{}.md", stem));
+    for n in 2.. {
+        if !path.exists() { break; }
+        path = dir.join(format!("{}-{}.md", stem, n));
+    }
+    fs::write(&path, body)?;
+    Ok(path)
+}
+
+/// Everything in the queue, unread first, newest first within each group.
+pub fn queue(root: &Path) -> Vec<Item> {
+    let mut items: Vec<(String, Item)> = fs::read_dir(root.join(DIR)).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "md"))
+        .filter_map(|path| {
+            let text = fs::read_to_string(&path).ok()?;
+            let yaml = frontmatter::split(&text).0?;
+            frontmatter::value(yaml, "url")?;
+            let title = frontmatter::value(yaml, "title").unwrap_or_else(|| crate::links::link_name(&path));
+            let added = frontmatter::value(yaml, "added").unwrap_or_default();
+            let read = frontmatter::value(yaml, "status").is_some_and(|s| s == "read");
+            Some((added, Item { path, title, read }))
+        })
+        .collect();
+    items.sort_by(|(a_added, a), (b_added, b)| a.read.cmp(&b.read).then(b_added.cmp(a_added)));
+    items.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Flips a queued note between `unread` and `read`; returns the new state.
+pub fn toggle_read(path: &Path) -> Result<bool> {
+    let text = fs::read_to_string(path)?;
+    let (Some(yaml), body) = frontmatter::split(&text) else { bail!("{} has no frontmatter", path.display()) };
+    let read = frontmatter::value(yaml, "status").is_none_or(|s| s != "read");
+    let yaml = frontmatter::set_value(yaml, "status", if read { "read" } else { "unread" });
+    fs::write(path, frontmatter::join(Some(&yaml), body))?;
+    Ok(read)
+}
+
+/// Turns every line of `text` that is only a bare URL (optionally a list item) into a
+/// `[Title](url)` link titled from the page. Pages without a title keep their bare URL; once a
+/// fetch can't connect or times out, the rest are left alone rather than waited on too.
+pub fn unfurl(text: &str, timeout: Duration) -> String {
+    let mut offline = false;
+    let lines: Vec<String> = text.lines().map(|line| {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let body = line.trim();
+        let (marker, url) = ["- ", "* ", "+ "].iter().find_map(|m| body.strip_prefix(m).map(|u| (*m, u.trim()))).unwrap_or(("", body));
+        let url = url.trim_start_matches('<').trim_end_matches('>');
+        let bare = (url.starts_with("http://") || url.starts_with("https://")) && !url.contains(char::is_whitespace);
+        if !bare || offline { return line.to_string(); }
+        match fetch(url, timeout) {
+            Ok(html) => match html::title(&html).filter(|t| !t.trim().is_empty()) {
+                Some(title) => format!("{}{}[{}]({})", indent, marker, title.trim().replace('[', "(").replace(']', ")"), url),
+                None => line.to_string(),
+            },
+            Err(e) => {
+                offline = e.downcast_ref::<Unreachable>().is_some();
+                line.to_string()
+            }
+        }
+    }).collect();
+    let mut out = lines.join("\n");
+    if text.ends_with('\n') { out.push('\n'); }
+    out
+}
+
+/// The fetch failed for lack of a connection rather than because of the page.
+#[derive(Debug)]
+struct Unreachable;
+
+impl std::fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { write!(f, "no connection") }
+}
+
+impl std::error::Error for Unreachable {}
+
+fn fetch(url: &str, timeout: Duration) -> Result<String> {
+    let out = Command::new("curl").args(["-fsSL", "--max-time", &timeout.as_secs().max(1).to_string(), "--", url])
+        .stdin(Stdio::null()).output().context("curl is not installed")?;
+    // curl's exit codes for "couldn't resolve host", "couldn't connect" and "timed out".
+    if matches!(out.status.code(), Some(6 | 7 | 28)) { return Err(Unreachable.into()); }
+    if !out.status.success() { bail!("fetching {} failed: {}", url, String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// File-name-safe version of a title: letters, digits and dashes, at most 60 characters.
+fn slug(title: &str) -> String {
+    let mut out = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() { out.extend(c.to_lowercase()); } else if !out.ends_with('-') { out.push('-'); }
+    }
+    let out: String = out.trim_matches('-').chars().take(60).collect();
+    if out.is_empty() { "link".into() } else { out.trim_end_matches('-').to_string() }
+}
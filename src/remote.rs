@@ -0,0 +1,126 @@
+//! First-run remote setup: connects a vault without an `origin` to one, either by cloning an
+//! existing vault repository into it or by adding the URL of a (usually empty) repository its
+//! notes will be pushed to. Runs in the terminal before the TUI starts, and as `knot remote`;
+//! `knot remote --category` does the same for a category with its own repository (see `nested`).
+//!
+//! Cloning into the vault directory is done as fetch + checkout rather than `git clone`, which
+//! refuses non-empty directories, so knot's own `.knot` files can already be there.
+
+use anyhow::{bail, Result};
+use std::{io::IsTerminal, path::Path};
+
+use crate::{credentials, git::{self, GitError}, nested, status};
+
+/// Written to `.knot/` when the user skips the wizard, so it isn't offered again.
+const SKIPPED: &str = "remote_setup_skipped";
+
+/// Whether to offer the wizard on launch: an interactive terminal, a vault without `origin`, and
+/// not skipped before.
+pub fn needs_setup(root: &Path) -> bool {
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+        && !root.join(".knot").join(SKIPPED).exists()
+        && git::run(root, &["remote", "get-url", "origin"]).is_err()
+}
+
+pub fn wizard(root: &Path) -> Result<()> {
+    setup(root, "vault", true)
+}
+
+/// Connects category `name` of the vault at `vault` to a repository of its own, and takes it out
+/// of the vault's. Left as it was when nothing gets connected.
+pub fn category_wizard(vault: &Path, name: &str) -> Result<()> {
+    let name = name.trim_matches('/');
+    if name.is_empty() || name.contains('/') { bail!("`{}` is not a category", name); }
+    let dir = vault.join(name);
+    let fresh = !dir.join(".git").exists();
+    let result = setup(&dir, &format!("category {}", name), false);
+    if git::run(&dir, &["remote", "get-url", "origin"]).is_err() {
+        if fresh { let _ = std::fs::remove_dir_all(dir.join(".git")); }
+        return result;
+    }
+    result?;
+    nested::detach(vault, name)?;
+    println!("{} now syncs with its own remote; the vault's repository stops tracking it with the next sync.", name);
+    Ok(())
+}
+
+/// The wizard for `root`, called `what` in its questions. Only the vault's can be skipped for good.
+fn setup(root: &Path, what: &str, skippable: bool) -> Result<()> {
+    std::fs::create_dir_all(root)?;
+    git::init(root)?;
+    if let Ok(url) = git::run(root, &["remote", "get-url", "origin"]) {
+        println!("This {} already syncs with {}.", what, url.trim());
+        if !credentials::prompt("Replace it? [y/N] ", false)?.eq_ignore_ascii_case("y") { return Ok(()); }
+        git::run(root, &["remote", "remove", "origin"])?;
+    }
+    println!("knot syncs your {} ({}) through a git remote.", what, root.display());
+    println!("  [1] Clone an existing repository");
+    println!("  [2] Add a remote for this {} (e.g. a new, empty repository)", what);
+    println!("  [s] Skip (run `knot remote` any time to set it up)");
+    let clone = loop {
+        match credentials::prompt("Choice: ", false)?.as_str() {
+            "1" => break true,
+            "2" => break false,
+            "s" | "S" | "" => {
+                if skippable { status::write_local(root, SKIPPED, "")?; }
+                println!("Skipped.");
+                return Ok(());
+            }
+            _ => {}
+        }
+    };
+    if clone && has_commits(root) {
+        bail!("this {} already has its own history; choose [2] to sync it with the remote instead", what);
+    }
+    let url = credentials::prompt("Repository URL: ", false)?;
+    if url.is_empty() { bail!("no URL given"); }
+    git::run(root, &["remote", "add", "origin", &url])?;
+    match connect(root, clone, what) {
+        Ok(summary) => {
+            println!("{}", summary);
+            Ok(())
+        }
+        Err(e) => {
+            // Leave the vault as it was so the wizard can simply be run again.
+            let _ = git::run(root, &["remote", "remove", "origin"]);
+            Err(e)
+        }
+    }
+}
+
+/// Tests the connection, then points the current branch at the remote's default branch.
+fn connect(root: &Path, clone: bool, what: &str) -> Result<String> {
+    println!("Testing the connection…");
+    let head = match git::run(root, &["ls-remote", "--symref", "origin", "HEAD"]) {
+        Ok(out) => out,
+        Err(GitError::Auth(msg)) => {
+            println!("The remote wants credentials ({}).", msg);
+            credentials::setup(root)?;
+            git::run(root, &["ls-remote", "--symref", "origin", "HEAD"])?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    // `ref: refs/heads/main\tHEAD`; nothing at all for an empty repository.
+    let remote_branch = head.lines().find_map(|l| l.strip_prefix("ref: refs/heads/")?.split('\t').next().map(String::from));
+    let local_branch = git::run(root, &["symbolic-ref", "--short", "HEAD"])?.trim().to_string();
+    let Some(branch) = remote_branch else {
+        if clone { bail!("the repository is empty; there is nothing to clone (choose [2] to push this {} to it)", what); }
+        git::run(root, &["config", &format!("branch.{}.remote", local_branch), "origin"])?;
+        git::run(root, &["config", &format!("branch.{}.merge", local_branch), &format!("refs/heads/{}", local_branch)])?;
+        return Ok(format!("Connected. The next sync pushes this {} to origin/{}.", what, local_branch));
+    };
+    println!("Fetching origin/{}…", branch);
+    git::run(root, &["fetch", "origin", &branch])?;
+    if !has_commits(root) {
+        git::run(root, &["checkout", "-B", &branch, "--track", &format!("origin/{}", branch)])?;
+        return Ok(format!("Cloned origin/{} into the {}.", branch, what));
+    }
+    // Sync pushes to the branch of the same name, so follow the remote's naming.
+    if local_branch != branch { git::run(root, &["branch", "-m", &branch])?; }
+    git::run(root, &["branch", "--set-upstream-to", &format!("origin/{}", branch)])?;
+    Ok(format!("Connected to origin/{}. The next sync brings in its notes and pushes yours.", branch))
+}
+
+fn has_commits(root: &Path) -> bool {
+    git::run(root, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok()
+}
@@ -0,0 +1,191 @@
+//! Styled preview of a markdown note: headings, emphasis, code, lists, quotes and links.
+//!
+//! Like the formatter this works line by line rather than building a full CommonMark tree; it
+//! only has to look right in a terminal, and anything it doesn't recognise is shown verbatim.
+
+use ratatui::{prelude::*, text::{Line, Span, Text}};
+
+use crate::links::Target;
+
+const HEADING_COLORS: [Color; 6] = [Color::Magenta, Color::Cyan, Color::Green, Color::Yellow, Color::Blue, Color::Red];
+
+pub fn render(src: &str) -> Text<'static> { render_with_links(src).0 }
+
+/// Renders like [`render`] and also returns every link it drew, in reading order, for
+/// [`highlight_link`] to pick from.
+pub fn render_with_links(src: &str) -> (Text<'static>, Vec<Target>) {
+    let mut links = Vec::new();
+    let mut lines = Vec::new();
+    let mut in_fence: Option<String> = None;
+    let dim = Style::default().fg(Color::DarkGray);
+    let code = Style::default().fg(Color::Green).bg(Color::Rgb(30, 30, 30));
+
+    let (frontmatter, body) = crate::frontmatter::split(src);
+    if let Some(yaml) = frontmatter {
+        lines.extend(yaml.lines().map(|l| Line::styled(l.to_string(), dim)));
+        lines.push(Line::styled("─".repeat(20), dim));
+    }
+
+    for raw in body.lines() {
+        let trimmed = raw.trim_start();
+        if let Some(fence) = in_fence.as_deref() {
+            if trimmed.starts_with(fence) { in_fence = None; } else { lines.push(Line::styled(format!(" {} ", raw), code)); }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let fence = &trimmed[..3];
+            let lang = trimmed.trim_start_matches(fence).trim();
+            in_fence = Some(fence.to_string());
+            lines.push(Line::styled(if lang.is_empty() { "┄┄┄".to_string() } else { format!("┄┄┄ {}", lang) }, dim));
+            continue;
+        }
+        lines.push(block(raw, &mut links));
+    }
+    (Text::from(lines), links)
+}
+
+/// Marks the `n`th link drawn by [`render_with_links`] as selected; returns its line.
+pub fn highlight_link(text: &mut Text, n: usize) -> Option<usize> {
+    let mut seen = 0;
+    for (i, line) in text.lines.iter_mut().enumerate() {
+        for span in line.spans.iter_mut().filter(|s| is_link(s.style)) {
+            if seen == n {
+                span.style = span.style.add_modifier(Modifier::REVERSED);
+                return Some(i);
+            }
+            seen += 1;
+        }
+    }
+    None
+}
+
+/// Link spans are the only underlined text in Cyan (wikilinks) or Blue (markdown links); H1
+/// underlines are Magenta.
+fn is_link(style: Style) -> bool {
+    style.add_modifier.contains(Modifier::UNDERLINED) && matches!(style.fg, Some(Color::Cyan | Color::Blue))
+}
+
+/// One line outside code fences.
+fn block(raw: &str, links: &mut Vec<Target>) -> Line<'static> {
+    let indent = raw.len() - raw.trim_start().len();
+    let trimmed = raw.trim_start();
+    let pad = " ".repeat(indent);
+
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+        let mut style = Style::default().fg(HEADING_COLORS[level - 1]).add_modifier(Modifier::BOLD);
+        if level == 1 { style = style.add_modifier(Modifier::UNDERLINED); }
+        return Line::from(inline(trimmed[level..].trim(), style, links));
+    }
+    if ["---", "***", "___"].iter().any(|hr| trimmed.replace(' ', "") == *hr) {
+        return Line::styled("─".repeat(40), Style::default().fg(Color::DarkGray));
+    }
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        let mut spans = vec![Span::styled(format!("{}▎ ", pad), Style::default().fg(Color::DarkGray))];
+        spans.extend(inline(quote.trim_start(), Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC), links));
+        return Line::from(spans);
+    }
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|b| trimmed.strip_prefix(b)) {
+        let (marker, item) = if let Some(rest) = item.strip_prefix("[ ] ") { ("☐ ", rest) }
+            else if let Some(rest) = item.strip_prefix("[x] ").or_else(|| item.strip_prefix("[X] ")) { ("☑ ", rest) }
+            else { ("• ", item) };
+        let mut spans = vec![Span::styled(format!("{}{}", pad, marker), Style::default().fg(Color::Yellow))];
+        spans.extend(inline(item, Style::default(), links));
+        return Line::from(spans);
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && (trimmed[digits..].starts_with(". ") || trimmed[digits..].starts_with(") ")) {
+        let mut spans = vec![Span::styled(format!("{}{} ", pad, &trimmed[..digits + 1]), Style::default().fg(Color::Yellow))];
+        spans.extend(inline(&trimmed[digits + 2..], Style::default(), links));
+        return Line::from(spans);
+    }
+    let mut spans = vec![Span::raw(pad)];
+    spans.extend(inline(trimmed, Style::default(), links));
+    Line::from(spans)
+}
+
+/// Inline markup within one line. Emphasis markers only open when a matching closer follows,
+/// so a stray `*` or `snake_case` renders as written.
+fn inline(text: &str, base: Style, links: &mut Vec<Target>) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut buf = String::new();
+    let (mut bold, mut italic, mut strike) = (false, false, false);
+    let style = |bold: bool, italic: bool, strike: bool| {
+        let mut s = base;
+        if bold { s = s.add_modifier(Modifier::BOLD); }
+        if italic { s = s.add_modifier(Modifier::ITALIC); }
+        if strike { s = s.add_modifier(Modifier::CROSSED_OUT); }
+        s
+    };
+    let flush = |buf: &mut String, spans: &mut Vec<Span<'static>>, s: Style| {
+        if !buf.is_empty() { spans.push(Span::styled(std::mem::take(buf), s)); }
+    };
+
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let current = style(bold, italic, strike);
+        let prev_alnum = buf.chars().last().is_some_and(char::is_alphanumeric);
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                flush(&mut buf, &mut spans, current);
+                spans.push(Span::styled(rest[1..end + 1].to_string(), Style::default().fg(Color::Green).bg(Color::Rgb(30, 30, 30))));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if rest.starts_with("[[") {
+            if let Some(end) = rest.find("]]") {
+                flush(&mut buf, &mut spans, current);
+                let inner = &rest[2..end];
+                let shown = inner.split_once('|').map_or(inner, |(_, alias)| alias);
+                links.push(Target::Wiki(inner[..inner.find(['|', '#']).unwrap_or(inner.len())].trim().to_string()));
+                spans.push(Span::styled(shown.to_string(), current.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED)));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        let image = rest.starts_with("![");
+        if c == '[' || image {
+            let open = if image { 2 } else { 1 };
+            if let Some((label, after)) = rest[open..].split_once("](") {
+                if let Some(close) = after.find(')').filter(|_| !label.contains(']')) {
+                    flush(&mut buf, &mut spans, current);
+                    let shown = if crate::audio::is_audio(std::path::Path::new(&after[..close])) {
+                        format!("🔊 {}", label)
+                    } else if image {
+                        format!("🖼 {}", label)
+                    } else {
+                        label.to_string()
+                    };
+                    links.push(Target::Path(after[..close].to_string()));
+                    spans.push(Span::styled(shown, current.fg(Color::Blue).add_modifier(Modifier::UNDERLINED)));
+                    rest = &after[close + 1..];
+                    continue;
+                }
+            }
+        }
+        let toggle = |marker: &str, on: bool| rest.starts_with(marker) && (on || rest[marker.len()..].contains(marker));
+        if toggle("**", bold) || toggle("__", bold) && !prev_alnum {
+            flush(&mut buf, &mut spans, current);
+            bold = !bold;
+            rest = &rest[2..];
+            continue;
+        }
+        if toggle("~~", strike) {
+            flush(&mut buf, &mut spans, current);
+            strike = !strike;
+            rest = &rest[2..];
+            continue;
+        }
+        if toggle("*", italic) || (toggle("_", italic) && (italic || !prev_alnum)) {
+            flush(&mut buf, &mut spans, current);
+            italic = !italic;
+            rest = &rest[1..];
+            continue;
+        }
+        buf.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    flush(&mut buf, &mut spans, style(bold, italic, strike));
+    spans
+}
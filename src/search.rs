@@ -0,0 +1,134 @@
+//! Full-text search over every indexed note, plus the extracted text of the vault's PDFs.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, TryRecvError}, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub struct Hit {
+    pub path: PathBuf,
+    /// 1-based line number of the match.
+    pub line: usize,
+    pub snippet: String,
+}
+
+/// Characters of context kept around a match in the snippet.
+const CONTEXT: usize = 60;
+
+/// Lines of `notes` containing `query`, in path then line order. Smart case: the match is
+/// case-insensitive unless the query has an uppercase letter.
+pub fn search(notes: &[PathBuf], query: &str, limit: usize) -> Vec<Hit> {
+    let Some(needle) = Needle::new(query) else { return Vec::new() };
+    let mut hits = Vec::new();
+    for path in notes {
+        hits.extend(needle.find_in(path));
+        if hits.len() >= limit { hits.truncate(limit); break; }
+    }
+    hits
+}
+
+/// The query as matched against lines: lowercased unless it has an uppercase letter.
+struct Needle { text: String, fold: bool }
+
+impl Needle {
+    fn new(query: &str) -> Option<Self> {
+        let query = query.trim();
+        if query.is_empty() { return None; }
+        let fold = !query.chars().any(char::is_uppercase);
+        Some(Self { text: if fold { query.to_lowercase() } else { query.to_string() }, fold })
+    }
+
+    fn find_in(&self, path: &Path) -> Vec<Hit> {
+        let text = if crate::pdf::is_pdf(path) { crate::pdf::text(path).ok() } else { fs::read_to_string(path).ok() };
+        let Some(text) = text else { return Vec::new() };
+        text.lines().enumerate().filter_map(|(n, line)| {
+            let hay = if self.fold { line.to_lowercase() } else { line.to_string() };
+            hay.find(&self.text).map(|pos| Hit { path: path.to_path_buf(), line: n + 1, snippet: snippet(line, pos) })
+        }).collect()
+    }
+}
+
+/// Pause in typing after which the search starts.
+pub const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// How often the UI picks up hits from a running [`SearchJob`].
+pub const TICK: Duration = Duration::from_millis(50);
+
+/// A search on a worker thread, for search-as-you-type: hits come back note by note, so the
+/// first ones show while the rest of the vault is still being read. Dropping the job stops it.
+pub struct SearchJob {
+    pub query: String,
+    pub hits: Vec<Hit>,
+    pub started: Instant,
+    /// Set once every note has been searched or the limit was reached.
+    pub done: bool,
+    rx: Receiver<Vec<Hit>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl SearchJob {
+    /// Searches `notes`, then the vault's PDFs, whose text is slow to extract.
+    pub fn start(root: PathBuf, notes: Vec<PathBuf>, query: &str, limit: usize) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let stop = cancel.clone();
+        let needle = Needle::new(query);
+        let worker = thread::spawn(move || {
+            let Some(needle) = needle else { return };
+            let mut found = 0;
+            for path in notes.into_iter().chain(crate::pdf::walk(&root)) {
+                if stop.load(Ordering::Relaxed) { return; }
+                let mut hits = needle.find_in(&path);
+                if hits.is_empty() { continue; }
+                hits.truncate(limit - found);
+                found += hits.len();
+                if tx.send(hits).is_err() || found >= limit { return; }
+            }
+        });
+        Self { query: query.to_string(), hits: Vec::new(), started: Instant::now(), done: false, rx, cancel, worker: Some(worker) }
+    }
+
+    /// Takes in the hits found since the last call; returns how many.
+    pub fn poll(&mut self) -> usize {
+        let before = self.hits.len();
+        loop {
+            match self.rx.try_recv() {
+                Ok(hits) => self.hits.extend(hits),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => { self.done = true; break; }
+            }
+        }
+        self.hits.len() - before
+    }
+
+    /// Stops the search and waits for its thread to end, which dropping the job doesn't.
+    pub fn finish(mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() { let _ = worker.join(); }
+    }
+}
+
+impl Drop for SearchJob {
+    fn drop(&mut self) { self.cancel.store(true, Ordering::Relaxed); }
+}
+
+/// The line trimmed to a window around byte offset `pos` (an offset into the lowercased line,
+/// which only drifts for the rare characters whose lowercase form changes length).
+fn snippet(line: &str, pos: usize) -> String {
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    let at = line.char_indices().take_while(|(i, _)| *i < pos).count().min(chars.len());
+    let start = at.saturating_sub(CONTEXT / 2);
+    let end = (start + CONTEXT).min(chars.len());
+    let body: String = chars[start..end].iter().collect();
+    let body = if start == 0 { body.trim_start().to_string() } else { format!("…{}", body) };
+    if end < chars.len() { format!("{}…", body) } else { body }
+}
+
+/// `rel:line  snippet`, the form results are listed in.
+pub fn label(root: &Path, hit: &Hit) -> String {
+    format!("{}:{}  {}", crate::vault::relative(root, &hit.path), hit.line, hit.snippet)
+}
@@ -0,0 +1,74 @@
+//! Secrets in the OS keyring instead of plaintext files: the Secret Service (`secret-tool`) on
+//! Linux/BSD and the login keychain (`security`) on macOS. Entries live under the service name
+//! `knot`, keyed by an account string chosen by the caller.
+
+use anyhow::{bail, Context, Result};
+use std::{io::Write, process::{Command, Stdio}};
+
+const SERVICE: &str = "knot";
+
+pub fn get(account: &str) -> Result<Option<String>> {
+    let out = lookup_command(account).stderr(Stdio::null()).output().context("no keyring tool available")?;
+    // Both tools exit non-zero when the entry doesn't exist.
+    if !out.status.success() { return Ok(None); }
+    let secret = String::from_utf8_lossy(&out.stdout).trim_end_matches('\n').to_string();
+    Ok((!secret.is_empty()).then_some(secret))
+}
+
+pub fn set(account: &str, secret: &str) -> Result<()> {
+    let (mut cmd, via_stdin) = store_command(account, secret);
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn().context("no keyring tool available")?;
+    if via_stdin { child.stdin.take().expect("piped").write_all(secret.as_bytes())?; }
+    let out = child.wait_with_output()?;
+    if !out.status.success() { bail!("keyring refused the secret: {}", String::from_utf8_lossy(&out.stderr).trim()); }
+    Ok(())
+}
+
+pub fn delete(account: &str) -> Result<()> {
+    delete_command(account).stdout(Stdio::null()).stderr(Stdio::null()).status().context("no keyring tool available")?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn lookup_command(account: &str) -> Command {
+    let mut cmd = Command::new("security");
+    cmd.args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"]);
+    cmd
+}
+
+/// The command storing `secret`, and whether it expects the secret on stdin.
+#[cfg(target_os = "macos")]
+fn store_command(account: &str, secret: &str) -> (Command, bool) {
+    // `security` only takes the password as an argument; -U updates an existing entry.
+    let mut cmd = Command::new("security");
+    cmd.args(["add-generic-password", "-U", "-s", SERVICE, "-a", account, "-w", secret]);
+    (cmd, false)
+}
+
+#[cfg(target_os = "macos")]
+fn delete_command(account: &str) -> Command {
+    let mut cmd = Command::new("security");
+    cmd.args(["delete-generic-password", "-s", SERVICE, "-a", account]);
+    cmd
+}
+
+#[cfg(not(target_os = "macos"))]
+fn lookup_command(account: &str) -> Command {
+    let mut cmd = Command::new("secret-tool");
+    cmd.args(["lookup", "service", SERVICE, "account", account]);
+    cmd
+}
+
+#[cfg(not(target_os = "macos"))]
+fn store_command(account: &str, _secret: &str) -> (Command, bool) {
+    let mut cmd = Command::new("secret-tool");
+    cmd.args(["store", &format!("--label=knot: {}", account), "service", SERVICE, "account", account]);
+    (cmd, true)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn delete_command(account: &str) -> Command {
+    let mut cmd = Command::new("secret-tool");
+    cmd.args(["clear", "service", SERVICE, "account", account]);
+    cmd
+}
@@ -0,0 +1,23 @@
+//! Per-vault UI session: where the browser was (category, folder, note, focus) and which filters
+//! were on, saved on exit and restored on the next start. Each vault keeps its own in
+//! `.knot/session`, git-ignored like `last_sync`, so switching vaults never mixes them up.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::status;
+
+const FILE: &str = "session";
+
+/// `key = value` lines; unknown keys are ignored so older sessions still load.
+pub fn load(root: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(root.join(".knot").join(FILE)).unwrap_or_default().lines()
+        .filter_map(|l| l.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .filter(|(_, v)| !v.is_empty())
+        .collect()
+}
+
+pub fn save(root: &Path, session: &BTreeMap<&str, String>) -> std::io::Result<()> {
+    let text: String = session.iter().filter(|(_, v)| !v.is_empty()).map(|(k, v)| format!("{} = {}\n", k, v)).collect();
+    status::write_local(root, FILE, &text)
+}
@@ -0,0 +1,89 @@
+//! Sharing a note to a phone on the same network: short notes go into the QR code itself; longer
+//! ones are served over HTTP from this machine, at an unguessable address, for as long as the
+//! QR code stays on screen.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::{Read, Write},
+    net::{IpAddr, TcpListener, UdpSocket},
+    path::Path,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    thread,
+    time::Duration,
+};
+
+use crate::qr::QrCode;
+
+/// Modules of light margin around the code, which scanners need.
+pub const QUIET: usize = 2;
+
+/// A note's QR code on screen, and the server behind it for a long note.
+pub struct Share {
+    pub name: String,
+    pub code: QrCode,
+    pub server: Option<Server>,
+}
+
+impl Share {
+    /// Puts the note at `path` into a code at most `max_size` modules a side, or failing that
+    /// serves it and encodes the address.
+    pub fn new(path: &Path, max_size: usize) -> Result<Self> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let text = std::fs::read_to_string(path)?;
+        if let Some(code) = QrCode::encode(text.as_bytes(), max_size) { return Ok(Self { name, code, server: None }); }
+        let server = serve(text)?;
+        let code = QrCode::encode(server.url.as_bytes(), max_size).context("the window is too small for a QR code")?;
+        Ok(Self { name, code, server: Some(server) })
+    }
+}
+
+/// A note being served; dropping it stops the server.
+pub struct Server {
+    pub url: String,
+    stop: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for Server {
+    /// Waits for the server thread to notice, at most a request's read timeout, so nothing of
+    /// it outlives the QR code.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() { let _ = worker.join(); }
+    }
+}
+
+/// Serves `text` as plain text at a random path on this machine's network address.
+pub fn serve(text: String) -> Result<Server> {
+    let ip = lan_address().context("no network address to serve from; is this machine on a network?")?;
+    let listener = TcpListener::bind((ip, 0))?;
+    listener.set_nonblocking(true)?;
+    let token = format!("{:016x}{:016x}", RandomState::new().build_hasher().finish(), RandomState::new().build_hasher().finish());
+    let url = format!("http://{}/{}", listener.local_addr()?, token);
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    let worker = thread::spawn(move || {
+        while !flag.load(Ordering::Relaxed) {
+            let Ok((mut stream, _)) = listener.accept() else { thread::sleep(Duration::from_millis(100)); continue };
+            let _ = stream.set_nonblocking(false);
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            let mut request = [0u8; 2048];
+            let n = stream.read(&mut request).unwrap_or(0);
+            let head = String::from_utf8_lossy(&request[..n]);
+            let wanted = head.split_whitespace().nth(1) == Some(&format!("/{}", token));
+            let (status, body) = if wanted { ("200 OK", text.as_str()) } else { ("404 Not Found", "Not found\n") };
+            let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+        }
+    });
+    Ok(Server { url, stop, worker: Some(worker) })
+}
+
+/// The address other machines on the network reach this one at: the local end of a route
+/// towards a public address. Connecting a UDP socket sends nothing.
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
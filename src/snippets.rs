@@ -0,0 +1,47 @@
+//! Abbreviation expansion for the input popups (`;d` → today's date, `;mtg` → meeting skeleton).
+
+use chrono::Local;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct Snippets(BTreeMap<String, String>);
+
+impl Default for Snippets {
+    fn default() -> Self {
+        let mut map = BTreeMap::new();
+        map.insert(";d".to_string(), "{{date}}".to_string());
+        map.insert(";t".to_string(), "{{time}}".to_string());
+        map.insert(";mtg".to_string(), "## Meeting {{date}}\nAttendees: \nAgenda:\n- \nActions:\n- [ ] ".to_string());
+        Self(map)
+    }
+}
+
+impl Snippets {
+    /// Adds or overrides a trigger from the `[snippets]` config section.
+    pub fn insert(&mut self, trigger: &str, body: &str) { self.0.insert(trigger.to_string(), body.to_string()); }
+
+    /// Expands the trigger the user just finished typing at the end of `buf`.
+    ///
+    /// A trigger fires as soon as it is typed unless a longer trigger shares its prefix
+    /// (`;d` vs `;dt`); in that case it fires once a space is typed after it.
+    pub fn expand(&self, buf: &mut String) {
+        let boundary = buf.ends_with(char::is_whitespace);
+        let body = buf.trim_end_matches(char::is_whitespace);
+        let start = body.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let token = &body[start..];
+        let Some(expansion) = self.0.get(token) else { return };
+        let ambiguous = self.0.keys().any(|k| k.len() > token.len() && k.starts_with(token));
+        if ambiguous && !boundary { return; }
+        let tail = buf[body.len()..].to_string();
+        let rendered = render(expansion);
+        buf.truncate(start);
+        buf.push_str(&rendered);
+        buf.push_str(&tail);
+    }
+}
+
+/// Fills the `{{date}}` / `{{time}}` placeholders shared by snippets.
+pub fn render(body: &str) -> String {
+    let now = Local::now();
+    body.replace("{{date}}", &now.format("%Y-%m-%d").to_string()).replace("{{time}}", &now.format("%H:%M").to_string())
+}
@@ -0,0 +1,90 @@
+//! `knot init --template`: scaffolds a new vault for a note-taking methodology, with its
+//! categories, starter notes, note templates and a vault `.knot/config.toml` tuned to match.
+//! Note templates, listed under `.templates/`, go into the configured `templates.dir`.
+//!
+//! Files that already exist are left alone, so running it over a vault only fills in what's
+//! missing.
+
+use anyhow::Result;
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{git, snippets};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Starter {
+    /// PARA: Projects, Areas, Resources and Archive, with an Inbox and a daily journal
+    Pkm,
+    /// Getting Things Done: Inbox, Next Actions, Projects, Waiting For, Someday and weekly reviews
+    Gtd,
+    /// Fleeting, literature and numbered permanent notes linked with wikilinks
+    Zettelkasten,
+    /// Just an Inbox
+    Blank,
+}
+
+const PKM: &[(&str, &str)] = &[
+    ("Inbox/Start here.md", "# Start here\n\nCapture anything into Inbox, then file it:\n\n- **Projects**: efforts with a goal and a deadline\n- **Areas**: responsibilities you keep up over time\n- **Resources**: topics and reference material\n- **Archive**: anything inactive from the other three\n\nPress `d` for today's journal note.\n"),
+    ("Projects/README.md", "# Projects\n\nOne folder per project. `knot new-project` creates one from `.knot/templates/project/`.\n"),
+    ("Areas/README.md", "# Areas\n\nOngoing responsibilities: health, finances, a team, a home.\n"),
+    ("Resources/README.md", "# Resources\n\nTopics of interest and reference material.\n"),
+    ("Archive/README.md", "# Archive\n\nInactive projects, areas and resources.\n"),
+    (".knot/templates/daily.md", "# {{date}}\n\n## Focus\n\n- \n\n## Notes\n\n## Done\n\n- [ ] \n"),
+    (".templates/resource.md", "---\ntags: [resource]\nsource: \n---\n# {{title}}\n\n## Summary\n\n## Highlights\n"),
+    (".templates/area.md", "# {{title}}\n\n## Standard to keep\n\n## Recurring\n\n- [ ] \n"),
+    (".knot/templates/project/overview.md", "# {{name}}\n\nStarted {{date}}.\n\n## Outcome\n\n## Deadline\n\n## Links\n"),
+    (".knot/templates/project/tasks.md", "# {{name}}: Tasks\n\n- [ ] \n"),
+    (".knot/config.toml", "# Tuned for PARA by `knot init --template pkm`.\n\n[journal]\npath = \"Journal/%Y-%m-%d.md\"\n\n[expiry]\narchive_dir = \"Archive\"\n\n[expire_after_days]\nInbox = 14\n"),
+];
+
+const GTD: &[(&str, &str)] = &[
+    ("Inbox/Start here.md", "# Start here\n\nCapture everything into Inbox, then process it:\n\n- Takes under two minutes? Do it now.\n- A single next step? Add it to **Next Actions** with a context tag like `#calls`.\n- Several steps? Make it a note in **Projects**.\n- Waiting on someone? **Waiting For**, with the date.\n- Not now? **Someday**.\n- Worth keeping? **Reference**.\n\nEvery week, create a note from the `weekly-review` template in Reviews.\n"),
+    ("Next Actions/Next actions.md", "# Next actions\n\n- [ ] #computer \n- [ ] #calls \n- [ ] #errands \n"),
+    ("Projects/README.md", "# Projects\n\nOne note per outcome that needs more than one action. Each has at least one next action.\n"),
+    ("Waiting For/Waiting for.md", "# Waiting for\n\n- [ ] Who, what, since when\n"),
+    ("Someday/Someday maybe.md", "# Someday / maybe\n\n- \n"),
+    ("Reference/README.md", "# Reference\n\nMaterial worth keeping that needs no action.\n"),
+    ("Reviews/README.md", "# Reviews\n\nWeekly reviews, created from the `weekly-review` template.\n"),
+    (".templates/weekly-review.md", "---\ntags: [review]\n---\n# Weekly review {{date}}\n\n## Get clear\n\n- [ ] Empty Inbox\n- [ ] Process loose notes\n\n## Get current\n\n- [ ] Review Next Actions\n- [ ] Review Waiting For\n- [ ] Review Projects: each has a next action\n- [ ] Review the calendar, past and coming weeks\n\n## Get creative\n\n- [ ] Review Someday\n- [ ] New ideas\n"),
+    (".templates/project.md", "---\ntags: [project]\n---\n# {{title}}\n\n## Outcome\n\n## Next action\n\n- [ ] \n\n## Support material\n"),
+    (".knot/config.toml", "# Tuned for GTD by `knot init --template gtd`.\n\n[journal]\npath = \"Reviews/%Y-%m-%d.md\"\n\n[expire_after_days]\nInbox = 7\n\"Waiting For\" = 30\n\n[virtual_folders]\n\"All projects\" = \"Projects/**\"\n"),
+];
+
+const ZETTELKASTEN: &[(&str, &str)] = &[
+    ("Fleeting/Start here.md", "# Start here\n\n- **Fleeting**: quick thoughts, processed within a day or two and then deleted.\n- **Literature**: notes on one source each, in your own words.\n- **Permanent**: one idea per note, numbered, written to stand alone and linked to related ideas with `[[wikilinks]]`.\n\nNew notes in Permanent get the next number automatically. Start from an index note and link outwards.\n"),
+    ("Literature/README.md", "# Literature\n\nOne note per source, from the `literature` template.\n"),
+    ("Permanent/0001-index.md", "# Index\n\nEntry points into the permanent notes.\n\n- \n"),
+    (".templates/literature.md", "---\nsource: \nauthor: \ntags: [literature]\n---\n# {{title}}\n\n## Key ideas, in my own words\n\n## Quotes\n\n## Leads to\n\n- [[]]\n"),
+    (".templates/permanent.md", "# {{title}}\n\n\n\n---\nLinks: [[]]\nSource: \n"),
+    (".knot/templates/stub.md", "# {{title}}\n\n\n\n---\nLinks: \n"),
+    (".knot/config.toml", "# Tuned for a Zettelkasten by `knot init --template zettelkasten`.\n\n[numbered_folders]\nPermanent = 4\n\n[expire_after_days]\nFleeting = 3\n"),
+];
+
+const BLANK: &[(&str, &str)] = &[
+    ("Inbox/Start here.md", "# Start here\n\nPress `C` to add a category, `F` a folder and `N` a note.\n"),
+];
+
+impl Starter {
+    fn files(self) -> &'static [(&'static str, &'static str)] {
+        match self { Self::Pkm => PKM, Self::Gtd => GTD, Self::Zettelkasten => ZETTELKASTEN, Self::Blank => BLANK }
+    }
+}
+
+/// Creates the vault at `root` if needed and writes the starter's files into it, its note
+/// templates into `templates_dir`. Returns the files written and those skipped because they
+/// already existed.
+pub fn init(root: &Path, templates_dir: &str, starter: Starter) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    fs::create_dir_all(root)?;
+    git::init(root)?;
+    let (mut written, mut kept) = (Vec::new(), Vec::new());
+    for (rel, body) in starter.files() {
+        let note_template = rel.strip_prefix(".templates/");
+        let path = match note_template { Some(name) => root.join(templates_dir).join(name), None => root.join(rel) };
+        if path.exists() { kept.push(path); continue; }
+        if let Some(parent) = path.parent() { fs::create_dir_all(parent)?; }
+        // Templates keep their placeholders for when they're used; starter notes are filled now.
+        let text = if rel.starts_with(".knot/") || note_template.is_some() { body.to_string() } else { snippets::render(body) };
+        fs::write(&path, text)?;
+        written.push(path);
+    }
+    Ok((written, kept))
+}
@@ -0,0 +1,106 @@
+//! `knot status`: a one-shot summary of the vault for scripts and status lines.
+
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{config::Config, git, json::Json, vault::{self, Ignore}};
+
+pub struct VaultStatus {
+    pub vault: PathBuf,
+    pub notes: usize,
+    /// Files with uncommitted changes; `None` when the vault is not a git repository.
+    pub dirty: Option<usize>,
+    /// Commits (ahead, behind) relative to the upstream branch, if there is one.
+    pub ahead_behind: Option<(usize, usize)>,
+    pub last_sync: Option<String>,
+}
+
+/// Where the time of the last successful sync is kept. It describes this machine only, so it is
+/// git-ignored; committing it would leave the vault dirty after every sync.
+const LAST_SYNC_FILE: &str = ".knot/last_sync";
+
+pub fn last_sync(root: &Path) -> Option<String> {
+    fs::read_to_string(root.join(LAST_SYNC_FILE)).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+pub fn record_sync(root: &Path, stamp: &str) -> std::io::Result<()> {
+    write_local(root, "last_sync", stamp)
+}
+
+/// Writes `.knot/<name>`, git-ignoring it first: state that describes this machine, not the notes.
+pub fn write_local(root: &Path, name: &str, contents: &str) -> std::io::Result<()> {
+    let dir = root.join(".knot");
+    fs::create_dir_all(&dir)?;
+    let gitignore = dir.join(".gitignore");
+    let ignored = fs::read_to_string(&gitignore).unwrap_or_default();
+    if !ignored.lines().any(|l| l.trim() == name) {
+        fs::write(&gitignore, format!("{}{}\n", ignored, name))?;
+    }
+    fs::write(dir.join(name), contents)
+}
+
+pub fn gather(config: &Config) -> VaultStatus {
+    let root = &config.vault_path;
+    let ignore = Ignore::load(root, &config.index.attachment_dirs, &config.index.ignore);
+    VaultStatus {
+        vault: root.clone(),
+        notes: vault::walk_notes(root, &ignore).len(),
+        dirty: git::dirty_count(root).ok(),
+        ahead_behind: git::ahead_behind(root),
+        last_sync: last_sync(root),
+    }
+}
+
+impl VaultStatus {
+    pub fn human(&self) -> String {
+        let mut out = format!("vault:      {}\nnotes:      {}\n", self.vault.display(), self.notes);
+        match self.dirty {
+            Some(n) => out.push_str(&format!("changed:    {}\n", n)),
+            None => out.push_str("changed:    (not a git repository)\n"),
+        }
+        match self.ahead_behind {
+            Some((ahead, behind)) => out.push_str(&format!("ahead:      {}\nbehind:     {}\n", ahead, behind)),
+            None => out.push_str("upstream:   none\n"),
+        }
+        out.push_str(&format!("last sync:  {}\n", self.last_sync.as_deref().unwrap_or("never")));
+        out
+    }
+
+    pub fn json(&self) -> Json {
+        Json::object([
+            ("vault", Json::from(self.vault.to_string_lossy().to_string())),
+            ("notes", self.notes.into()),
+            ("dirty", self.dirty.into()),
+            ("ahead", self.ahead_behind.map(|(a, _)| a).into()),
+            ("behind", self.ahead_behind.map(|(_, b)| b).into()),
+            ("last_sync", self.last_sync.clone().into()),
+        ])
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum SegmentStyle {
+    /// ANSI escape colors, for shell prompts and starship custom modules
+    Ansi,
+    /// `#[fg=...]` markup for tmux's status-left/status-right
+    Tmux,
+    Plain,
+}
+
+/// `knot ✚3 ⇡1 ⇣2`: changed files, commits to push, commits to pull; `knot ✔` when in sync.
+/// Only asks git, so it stays fast enough to run on every prompt.
+pub fn prompt_segment(root: &Path, style: SegmentStyle) -> String {
+    let paint = |text: String, color: (&str, &str)| match style {
+        SegmentStyle::Ansi => format!("\x1b[{}m{}\x1b[0m", color.0, text),
+        SegmentStyle::Tmux => format!("#[fg={}]{}#[default]", color.1, text),
+        SegmentStyle::Plain => text,
+    };
+    let (yellow, green, red) = (("33", "yellow"), ("32", "green"), ("31", "red"));
+    let mut parts = vec!["knot".to_string()];
+    let Ok(dirty) = git::dirty_count(root) else { return parts.remove(0) };
+    let (ahead, behind) = git::ahead_behind(root).unwrap_or((0, 0));
+    if dirty > 0 { parts.push(paint(format!("✚{}", dirty), yellow)); }
+    if ahead > 0 { parts.push(paint(format!("⇡{}", ahead), green)); }
+    if behind > 0 { parts.push(paint(format!("⇣{}", behind), red)); }
+    if parts.len() == 1 { parts.push(paint("✔".into(), green)); }
+    parts.join(" ")
+}
@@ -0,0 +1,308 @@
+//! Background git sync (add, commit, bring in remote commits, push; or a pull on its own) so the
+//! TUI stays usable while it runs. Categories with their own repository (see `nested`) go through
+//! the same steps after the vault.
+//!
+//! The worker thread streams git's output back over a channel; the event loop calls `poll`
+//! between frames to collect it.
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{config::SyncConfig, git::{self, GitError}, incoming::{self, Change}, nested::{self, Nested}};
+
+/// How often the event loop wakes up while a sync runs, to animate the spinner.
+pub const TICK: Duration = Duration::from_millis(100);
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+/// Output lines kept for the progress widget.
+const LOG_LINES: usize = 5;
+
+enum Event { Output(String), Integrated(String), Incoming(Vec<Change>), Category(String, PathBuf), Done(Result<(), GitError>) }
+
+enum Step {
+    Git(Vec<String>),
+    /// Fetch, then fast-forward or rebase onto the remote branch so the push isn't rejected.
+    Integrate { remote: Option<String> },
+    /// Fast-forwards to the upstream branch.
+    Pull,
+    /// Runs the steps that follow in a category's own repository.
+    Enter(Nested),
+    /// Keeps a category out of the vault's repository.
+    Detach(String),
+    Clone { url: String, dir: PathBuf },
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum SyncKind { Push, Pull }
+
+pub struct SyncJob {
+    rx: Receiver<Event>,
+    pub kind: SyncKind,
+    pub started: Instant,
+    /// Timestamp a push commits with; becomes `last_sync` on success.
+    pub stamp: String,
+    /// The last few lines git printed.
+    pub log: Vec<String>,
+    /// What bringing in remote commits did before the push ("fast-forwarded 2 commits", ...).
+    pub integrated: Option<String>,
+    /// Notes the remote's commits touched, vault-relative, for the `incoming` queue.
+    pub incoming: Vec<Change>,
+    /// The category with its own repository the sync has got to; `None` while in the vault's.
+    pub category: Option<String>,
+    /// The repository the sync has got to, where a conflict it stops on is resolved.
+    pub repo: PathBuf,
+    /// Set once the worker has finished.
+    pub finished: Option<Result<(), GitError>>,
+}
+
+impl SyncJob {
+    /// Commits everything, rebases it onto the remote's new commits and pushes it (only the commit
+    /// when `push = false`), in the vault and then in each category with its own repository.
+    /// Without a `message` the configured one is used.
+    pub fn push(root: PathBuf, config: &SyncConfig, stamp: String, message: Option<String>) -> Self {
+        let message = message.unwrap_or_else(|| config.message.replace("{{now}}", &stamp));
+        let categories = nested::categories(&root, &config.categories);
+        let mut steps: Vec<Step> = categories.iter().map(|n| Step::Detach(n.name.clone())).collect();
+        steps.extend(Self::commit_steps(config.remote.clone(), config.push, &message));
+        for category in categories {
+            if !category.is_repo() {
+                steps.extend(Self::clone_step(category));
+                continue;
+            }
+            steps.push(Step::Enter(category));
+            // The category's own upstream, never the vault's remote.
+            steps.extend(Self::commit_steps(None, config.push, &message));
+        }
+        Self::start(SyncKind::Push, root, steps, stamp)
+    }
+
+    fn commit_steps(remote: Option<String>, push: bool, message: &str) -> Vec<Step> {
+        let mut push_args = vec!["push".to_string(), "--progress".to_string()];
+        push_args.extend(remote.clone());
+        [
+            Some(Step::Git(vec!["add".into(), "-A".into()])),
+            Some(Step::Git(vec!["commit".into(), "-m".into(), message.to_string()])),
+            push.then_some(Step::Integrate { remote }),
+            push.then_some(Step::Git(push_args)),
+        ].into_iter().flatten().collect()
+    }
+
+    /// Clones a category that has a remote in `[category_remotes]` but isn't there yet. One that
+    /// already has notes is left for `knot remote --category` to connect.
+    fn clone_step(category: Nested) -> Option<Step> {
+        if !category.is_empty() { return None; }
+        Some(Step::Clone { url: category.url?, dir: category.dir })
+    }
+
+    /// Fast-forwards the vault to its upstream, then each category with its own repository.
+    pub fn pull(root: PathBuf, config: &SyncConfig) -> Self {
+        let mut steps = vec![Step::Pull];
+        for category in nested::categories(&root, &config.categories) {
+            if !category.is_repo() {
+                steps.extend(Self::clone_step(category));
+                continue;
+            }
+            steps.push(Step::Enter(category));
+            steps.push(Step::Pull);
+        }
+        Self::start(SyncKind::Pull, root, steps, String::new())
+    }
+
+    fn start(kind: SyncKind, root: PathBuf, steps: Vec<Step>, stamp: String) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let vault = root.clone();
+        thread::spawn(move || {
+            let mut dir = vault.clone();
+            let result = steps.into_iter().try_for_each(|step| match step {
+                // Nothing staged: skip the commit rather than fail on "nothing to commit".
+                Step::Git(args) if args[0] == "commit" && nothing_staged(&dir) => Ok(()),
+                Step::Git(args) => run_streaming(&dir, &args, &tx),
+                Step::Integrate { remote } => integrate(&dir, remote.as_deref(), &tx),
+                Step::Pull => pull(&dir, &tx),
+                Step::Enter(category) => {
+                    let _ = tx.send(Event::Category(category.name, category.dir.clone()));
+                    dir = category.dir;
+                    Ok(())
+                }
+                Step::Detach(name) => nested::detach(&vault, &name),
+                Step::Clone { url, dir: target } => {
+                    let _ = tx.send(Event::Category(target.file_name().unwrap_or_default().to_string_lossy().to_string(), target.clone()));
+                    let args = ["clone".to_string(), "--progress".to_string(), url, target.to_string_lossy().to_string()];
+                    run_streaming(&vault, &args, &tx)
+                }
+            });
+            let _ = tx.send(Event::Done(result));
+        });
+        Self { rx, kind, started: Instant::now(), stamp, log: Vec::new(), integrated: None, incoming: Vec::new(), category: None, repo: root, finished: None }
+    }
+
+    /// Collects whatever the worker sent since the last call.
+    pub fn poll(&mut self) {
+        while let Ok(event) = self.rx.try_recv() {
+            match event {
+                Event::Output(line) => {
+                    self.log.push(line);
+                    if self.log.len() > LOG_LINES { self.log.remove(0); }
+                }
+                Event::Integrated(summary) => self.record_integrated(summary),
+                Event::Incoming(changes) => self.record_incoming(changes),
+                Event::Category(name, dir) => { self.category = Some(name); self.repo = dir; }
+                Event::Done(result) => self.finished = Some(result),
+            }
+        }
+    }
+
+    /// A category's summary joins the vault's, named: "remote unchanged · Team: fast-forwarded…".
+    fn record_integrated(&mut self, summary: String) {
+        self.integrated = Some(match (self.integrated.take(), &self.category) {
+            (Some(before), Some(name)) => format!("{} · {}: {}", before, name, summary),
+            (None, Some(name)) => format!("{}: {}", name, summary),
+            (_, None) => summary,
+        });
+    }
+
+    /// Changes in a category's repository are relative to it; the category folder puts them in the vault.
+    fn record_incoming(&mut self, changes: Vec<Change>) {
+        let place = |path: String| match &self.category {
+            Some(name) => format!("{}/{}", name, path),
+            None => path,
+        };
+        let changes: Vec<Change> = changes.into_iter().map(|c| Change { path: place(c.path), ..c }).collect();
+        self.incoming.extend(changes);
+    }
+
+    /// Blocks until the worker is done, handing each line git prints to `output`. For the CLI,
+    /// which has no event loop to poll from.
+    pub fn wait(mut self, mut output: impl FnMut(&str)) -> Self {
+        while self.finished.is_none() {
+            match self.rx.recv() {
+                Ok(Event::Output(line)) => output(&line),
+                Ok(Event::Integrated(summary)) => self.record_integrated(summary),
+                Ok(Event::Incoming(changes)) => self.record_incoming(changes),
+                Ok(Event::Category(name, dir)) => { self.category = Some(name); self.repo = dir; }
+                Ok(Event::Done(result)) => self.finished = Some(result),
+                Err(_) => break,
+            }
+        }
+        self
+    }
+
+    pub fn spinner(&self) -> char {
+        SPINNER[(self.started.elapsed().as_millis() / TICK.as_millis()) as usize % SPINNER.len()]
+    }
+}
+
+/// Runs one git command, forwarding stdout and stderr line by line. Progress meters redraw with
+/// `\r`, so those count as line breaks too.
+fn run_streaming(root: &PathBuf, args: &[String], tx: &Sender<Event>) -> Result<(), GitError> {
+    let _ = tx.send(Event::Output(format!("$ git {}", args[0])));
+    let mut command = Command::new("git");
+    command.args(args).current_dir(root)
+        // A credential prompt would hang invisibly behind the TUI.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    // ssh asks for passphrases and unknown host keys on the terminal itself, bypassing the
+    // above; batch mode fails instead, and keys held by ssh-agent still work. A user's own ssh
+    // command is left alone.
+    if std::env::var_os("GIT_SSH_COMMAND").is_none() && git::run(root, &["config", "core.sshCommand"]).is_err() {
+        command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    }
+    let mut child = command.spawn().map_err(|e| GitError::spawn(e, &args[0]))?;
+    let stdout = child.stdout.take().map(|out| {
+        let tx = tx.clone();
+        thread::spawn(move || { forward(out, &tx); })
+    });
+    let stderr = child.stderr.take().map(|err| forward(err, tx)).unwrap_or_default();
+    if let Some(t) = stdout { let _ = t.join(); }
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(_) => Err(GitError::classify(&args[0], &stderr)),
+        Err(e) => Err(GitError::spawn(e, &args[0])),
+    }
+}
+
+/// Brings in commits pushed from elsewhere: nothing to do when the remote hasn't moved, a
+/// fast-forward when only it has, otherwise a rebase of the local commits on top. A conflicting
+/// rebase stays stopped for the conflict screen to resolve; any other failure is aborted. Without
+/// an upstream (or before the remote branch exists) there is nothing to bring in and the push
+/// reports any problem itself.
+fn integrate(root: &PathBuf, remote: Option<&str>, tx: &Sender<Event>) -> Result<(), GitError> {
+    let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let upstream = match remote {
+        Some(r) => format!("{}/{}", r, git::run(root, &["rev-parse", "--abbrev-ref", "HEAD"])?.trim()),
+        None => match git::run(root, &["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{upstream}"]) {
+            Ok(u) => u.trim().to_string(),
+            Err(_) => return Ok(()),
+        },
+    };
+    let mut fetch = args(&["fetch", "--progress"]);
+    fetch.extend(remote.map(String::from));
+    run_streaming(root, &fetch, tx)?;
+    if git::run(root, &["rev-parse", "--verify", "--quiet", &upstream]).is_err() { return Ok(()); }
+    let counts = git::run(root, &["rev-list", "--left-right", "--count", &format!("HEAD...{}", upstream)])?;
+    let mut counts = counts.split_whitespace().map(|n| n.parse::<usize>().unwrap_or(0));
+    let (ahead, behind) = (counts.next().unwrap_or(0), counts.next().unwrap_or(0));
+    if behind > 0 { send_incoming(root, "HEAD", &upstream, tx); }
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let summary = match (ahead, behind) {
+        (_, 0) => "remote unchanged".to_string(),
+        (0, behind) => {
+            run_streaming(root, &args(&["merge", "--ff-only", &upstream]), tx)?;
+            format!("fast-forwarded {} remote commit{}", behind, plural(behind))
+        }
+        (ahead, behind) => {
+            if let Err(e) = run_streaming(root, &args(&["rebase", "--autostash", &upstream]), tx) {
+                let conflicts = git::conflicted(root);
+                if conflicts.is_empty() {
+                    let _ = git::run(root, &["rebase", "--abort"]);
+                    return Err(e);
+                }
+                return Err(GitError::Conflict(conflicts));
+            }
+            format!("rebased {} local commit{} onto {} remote commit{}", ahead, plural(ahead), behind, plural(behind))
+        }
+    };
+    let _ = tx.send(Event::Integrated(summary));
+    Ok(())
+}
+
+fn pull(root: &PathBuf, tx: &Sender<Event>) -> Result<(), GitError> {
+    let before = git::run(root, &["rev-parse", "--verify", "--quiet", "HEAD"]).map(|h| h.trim().to_string());
+    let args = ["pull", "--ff-only", "--progress"].map(String::from);
+    run_streaming(root, &args, tx)?;
+    if let Ok(before) = before { send_incoming(root, &before, "HEAD", tx); }
+    Ok(())
+}
+
+/// Reports the notes commits `from..to` touched. Not knowing is no reason to fail the sync.
+fn send_incoming(root: &Path, from: &str, to: &str, tx: &Sender<Event>) {
+    if let Some(changes) = incoming::between(root, from, to).ok().filter(|c| !c.is_empty()) {
+        let _ = tx.send(Event::Incoming(changes));
+    }
+}
+
+fn nothing_staged(root: &PathBuf) -> bool {
+    Command::new("git").args(["diff", "--cached", "--quiet"]).current_dir(root).status().is_ok_and(|s| s.success())
+}
+
+/// Sends each line to the UI and returns everything read, for error classification.
+fn forward(stream: impl Read, tx: &Sender<Event>) -> String {
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+    let mut all = String::new();
+    while reader.read_until(b'\n', &mut buf).is_ok_and(|n| n > 0) {
+        let chunk = String::from_utf8_lossy(&buf);
+        for part in chunk.split(['\r', '\n']) {
+            if !part.trim().is_empty() { let _ = tx.send(Event::Output(part.trim_end().to_string())); }
+        }
+        all.push_str(&chunk);
+        buf.clear();
+    }
+    all
+}
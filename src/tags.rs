@@ -0,0 +1,64 @@
+//! Tags from frontmatter `tags:` lists and inline `#tag` tokens.
+//!
+//! Inline tags follow Obsidian's rules closely enough for everyday notes: a `#` at the start of a
+//! word followed by letters, digits, `_`, `-` or `/`, not all digits (`#42` is an issue number),
+//! and not inside code.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use crate::frontmatter;
+
+/// Every tag in a note, frontmatter first, without the `#` and without duplicates.
+pub fn extract(text: &str) -> Vec<String> {
+    let (fm, body) = frontmatter::split(text);
+    let mut tags = fm.map(|y| frontmatter::list(y, "tags")).unwrap_or_default();
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") { in_fence = !in_fence; continue; }
+        if in_fence { continue; }
+        // Odd-numbered backtick segments are inline code.
+        for segment in line.split('`').step_by(2) {
+            tags.extend(inline(segment));
+        }
+    }
+    let mut unique = Vec::new();
+    for tag in tags {
+        if !unique.contains(&tag) { unique.push(tag); }
+    }
+    unique
+}
+
+fn inline(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        if c == '#' && (prev.is_whitespace() || prev == '(') {
+            if let Some(tag) = tag_after(&text[i + 1..]) { tags.push(tag); }
+        }
+        prev = c;
+    }
+    tags
+}
+
+/// Whether `line` opens with a `#tag`, as `#todo call Bob` does.
+pub fn starts_with_tag(line: &str) -> bool {
+    line.strip_prefix('#').and_then(tag_after).is_some()
+}
+
+/// The tag spelled by the text right after a `#`, if it is one.
+fn tag_after(rest: &str) -> Option<String> {
+    let is_tag_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '/');
+    let token: String = rest.chars().take_while(|&c| is_tag_char(c)).collect();
+    let token = token.trim_end_matches(['-', '/']);
+    (!token.is_empty() && !token.chars().all(|c| c.is_ascii_digit())).then(|| token.to_string())
+}
+
+/// Tag → notes carrying it, each list in the order of `notes`.
+pub fn build(notes: &[PathBuf]) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut index: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for note in notes {
+        let Ok(text) = fs::read_to_string(note) else { continue };
+        for tag in extract(&text) { index.entry(tag).or_default().push(note.clone()); }
+    }
+    index
+}
@@ -0,0 +1,145 @@
+//! Open tasks across the vault: markdown checkboxes (`- [ ] …`) and, in `.org` files, TODO
+//! headlines with their `SCHEDULED:`/`DEADLINE:` dates, so org files show up next to markdown ones.
+//!
+//! Org headlines count as open with `TODO`, `NEXT`, `STARTED`, `WAITING` or `HOLD` (anything
+//! else, `DONE` included, doesn't); a file's own `#+TODO: A B | C` line replaces that list.
+
+use chrono::{Local, NaiveDate};
+use std::{fs, path::{Path, PathBuf}};
+
+const OPEN: &[&str] = &["TODO", "NEXT", "STARTED", "WAITING", "WAIT", "HOLD"];
+
+pub struct Task {
+    pub path: PathBuf,
+    /// 1-based line of the checkbox or headline.
+    pub line: usize,
+    pub text: String,
+    /// Org keyword (`TODO`, `NEXT`…); `None` for checkboxes.
+    pub keyword: Option<String>,
+    pub scheduled: Option<NaiveDate>,
+    pub deadline: Option<NaiveDate>,
+}
+
+impl Task {
+    /// The date the task is due to be looked at: its deadline, else when it's scheduled.
+    pub fn date(&self) -> Option<NaiveDate> { self.deadline.or(self.scheduled) }
+
+    /// `⚠ 2024-09-03 DEADLINE  TODO Call the bank · Errands`, as listed on the dashboard.
+    pub fn label(&self) -> String {
+        let today = Local::now().date_naive();
+        let when = match (self.deadline, self.scheduled) {
+            (Some(d), _) => format!("{} {} DEADLINE  ", if d < today { "⚠" } else if d == today { "●" } else { " " }, d),
+            (None, Some(s)) => format!("{} {} scheduled ", if s <= today { "●" } else { " " }, s),
+            (None, None) => String::new(),
+        };
+        let keyword = self.keyword.as_ref().map(|k| format!("{} ", k)).unwrap_or_else(|| "☐ ".into());
+        let note = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        format!("{}{}{} · {}", when, keyword, self.text, note)
+    }
+}
+
+/// Every open task in `notes`: dated ones first, soonest first, then the rest by note.
+pub fn collect(notes: &[PathBuf]) -> Vec<Task> {
+    let mut tasks: Vec<Task> = notes.iter().flat_map(|note| {
+        let text = fs::read_to_string(note).unwrap_or_default();
+        if is_org(note) { org_tasks(note, &text) } else { checkboxes(note, &text) }
+    }).collect();
+    tasks.sort_by(|a, b| match (a.date(), b.date()) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.path.cmp(&b.path).then(a.line.cmp(&b.line)),
+    });
+    tasks
+}
+
+fn is_org(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("org"))
+}
+
+/// Unchecked `- [ ]`, `* [ ]`, `+ [ ]` and `1. [ ]` items outside code blocks.
+fn checkboxes(path: &Path, text: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") { in_fence = !in_fence; continue; }
+        if in_fence { continue; }
+        if let Some(item) = open_checkbox(trimmed) {
+            tasks.push(Task { path: path.to_path_buf(), line: i + 1, text: item.to_string(), keyword: None, scheduled: None, deadline: None });
+        }
+    }
+    tasks
+}
+
+fn open_checkbox(line: &str) -> Option<&str> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = if digits > 0 { line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))? } else { line.strip_prefix(['-', '*', '+'])?.strip_prefix(' ')? };
+    let item = rest.strip_prefix("[ ] ")?.trim();
+    (!item.is_empty()).then_some(item)
+}
+
+/// Org headlines with an open TODO keyword, plus org's own `- [ ]` checkboxes. The planning line
+/// under a headline carries its dates.
+fn org_tasks(path: &Path, text: &str) -> Vec<Task> {
+    let open = open_keywords(text);
+    let mut tasks = checkboxes(path, text);
+    let lines: Vec<&str> = text.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        let stars = line.chars().take_while(|&c| c == '*').count();
+        if stars == 0 || !line[stars..].starts_with(' ') { continue; }
+        let headline = line[stars..].trim();
+        let (keyword, rest) = headline.split_once(' ').unwrap_or((headline, ""));
+        if !open.iter().any(|k| k == keyword) { continue; }
+        let mut task = Task { path: path.to_path_buf(), line: i + 1, text: clean_headline(rest), keyword: Some(keyword.to_string()), scheduled: None, deadline: None };
+        // Planning info sits on the line right after the headline (sometimes the same line).
+        for planning in [*line, lines.get(i + 1).copied().unwrap_or("")] {
+            if let Some(d) = timestamp_after(planning, "SCHEDULED:") { task.scheduled = Some(d); }
+            if let Some(d) = timestamp_after(planning, "DEADLINE:") { task.deadline = Some(d); }
+        }
+        task.text = task.text.split("SCHEDULED:").next().unwrap_or("").split("DEADLINE:").next().unwrap_or("").trim().to_string();
+        tasks.push(task);
+    }
+    tasks.sort_by_key(|t| t.line);
+    tasks
+}
+
+/// Open keywords from `#+TODO:` (or `#+SEQ_TODO:`/`#+TYP_TODO:`) lines, or the defaults. Those
+/// after the `|` are closed; without one the last keyword is, as in org.
+fn open_keywords(text: &str) -> Vec<String> {
+    let mut open = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_start();
+        let prefix = ["#+TODO:", "#+SEQ_TODO:", "#+TYP_TODO:"].into_iter().find(|p| line.get(..p.len()).is_some_and(|l| l.eq_ignore_ascii_case(p)));
+        let Some(prefix) = prefix else { continue };
+        let spec = &line[prefix.len()..];
+        // `WAIT(w@/!)`: the fast-access key and logging options aren't part of the keyword.
+        let words = |s: &str| s.split_whitespace().map(|w| w.split('(').next().unwrap_or(w).to_string()).collect::<Vec<_>>();
+        match spec.split_once('|') {
+            Some((o, _)) => open.extend(words(o)),
+            None => {
+                let mut all = words(spec);
+                all.pop();
+                open.extend(all);
+            }
+        }
+    }
+    if open.is_empty() { OPEN.iter().map(|s| s.to_string()).collect() } else { open }
+}
+
+/// Headline text without its `[#A]` priority cookie and trailing `:tag:list:`.
+fn clean_headline(rest: &str) -> String {
+    let mut text = rest.trim();
+    if text.starts_with("[#") && text.get(3..4) == Some("]") { text = text[4..].trim_start(); }
+    if let Some(tags) = text.rsplit_once(' ').map(|(_, t)| t).filter(|t| t.len() > 2 && t.starts_with(':') && t.ends_with(':') && !t.contains(' ')) {
+        text = text[..text.len() - tags.len()].trim_end();
+    }
+    text.to_string()
+}
+
+/// The date in `<2024-09-03 Tue 10:00>` (or `[…]`) following `key` on `line`.
+fn timestamp_after(line: &str, key: &str) -> Option<NaiveDate> {
+    let rest = line[line.find(key)? + key.len()..].trim_start();
+    let date = rest.strip_prefix(['<', '['])?.get(..10)?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
@@ -0,0 +1,28 @@
+//! Templates for new notes: every `.md` file in the vault's `templates.dir` folder (`.templates/`
+//! unless set) is offered when creating a note with `N`. `{{title}}`, `{{date}}`, `{{time}}` and `{{category}}` are filled in.
+
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::snippets;
+
+/// `templates.dir` when the config doesn't set it.
+pub const DEFAULT_DIR: &str = ".templates";
+
+/// Contents of a note created without a template.
+const BLANK: &str = "# {{title}}\n\n";
+
+/// The note templates in `dir`, sorted by name.
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = fs::read_dir(dir).into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "md"))
+        .collect();
+    found.sort();
+    found
+}
+
+/// A new note's contents from `template` (or the blank one). `category` is empty at the vault root.
+pub fn render(template: Option<&Path>, title: &str, category: &str) -> String {
+    let body = template.and_then(|t| fs::read_to_string(t).ok()).unwrap_or_else(|| BLANK.to_string());
+    snippets::render(&body.replace("{{title}}", title).replace("{{category}}", category))
+}
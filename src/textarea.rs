@@ -0,0 +1,223 @@
+//! The built-in editor (`e`): a plain text area over the selected note for quick tweaks, saved
+//! with Ctrl-S. Anything bigger still goes to the external editor with Enter.
+
+use anyhow::Result;
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::links;
+
+/// Columns a tab takes on screen.
+const TAB: usize = 4;
+
+pub struct TextArea {
+    pub path: PathBuf,
+    lines: Vec<String>,
+    /// Cursor line, and position in it in characters.
+    row: usize,
+    col: usize,
+    /// Column the cursor returns to when moving up and down through shorter lines.
+    goal: usize,
+    /// First line and screen column shown, kept so the cursor stays in view.
+    top: usize,
+    left: usize,
+    /// The note as it was opened or last saved, to tell edits and changes on disk apart.
+    saved: String,
+    /// A warning shown for the last key, which the same key again confirms.
+    pub warning: Option<Warning>,
+    /// Saved at least once.
+    pub written: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// Esc with unsaved changes.
+    Discard,
+    /// Ctrl-S after the note changed on disk.
+    Overwrite,
+}
+
+impl TextArea {
+    pub fn open(path: &Path) -> Result<Self> {
+        let saved = fs::read_to_string(path)?;
+        let mut lines: Vec<String> = saved.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l).to_string()).collect();
+        // The final newline is kept on save rather than shown as an empty last line.
+        if saved.ends_with('\n') { lines.pop(); }
+        if lines.is_empty() { lines.push(String::new()); }
+        Ok(Self { path: path.to_path_buf(), lines, row: 0, col: 0, goal: 0, top: 0, left: 0, saved, warning: None, written: false })
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = self.lines.join("\n");
+        if self.saved.ends_with('\n') || (self.saved.is_empty() && !text.is_empty()) { text.push('\n'); }
+        text
+    }
+
+    pub fn is_modified(&self) -> bool { self.text() != self.saved }
+
+    /// Whether something else wrote the note since it was opened or saved here.
+    pub fn changed_on_disk(&self) -> bool {
+        fs::read_to_string(&self.path).map_or(true, |t| t != self.saved)
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        let text = self.text();
+        fs::write(&self.path, &text)?;
+        self.saved = text;
+        self.written = true;
+        Ok(())
+    }
+
+    /// Byte offset of character `col` in line `row`.
+    fn offset(&self, row: usize, col: usize) -> usize {
+        self.lines[row].char_indices().nth(col).map_or(self.lines[row].len(), |(i, _)| i)
+    }
+
+    fn line_len(&self, row: usize) -> usize { self.lines[row].chars().count() }
+
+    pub fn insert(&mut self, text: &str) {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 { self.newline(false); }
+            let part = part.strip_suffix('\r').unwrap_or(part);
+            let at = self.offset(self.row, self.col);
+            self.lines[self.row].insert_str(at, part);
+            self.col += part.chars().count();
+        }
+        self.goal = self.col;
+    }
+
+    /// Splits the line at the cursor; with `indent`, the new line starts with the old one's
+    /// indentation and list marker, as typing Enter in a list should.
+    pub fn newline(&mut self, indent: bool) {
+        let at = self.offset(self.row, self.col);
+        let line = &self.lines[self.row];
+        let lead = if indent { continuation(&line[..at]).or_else(|| (at < line.len()).then(String::new)) } else { Some(String::new()) };
+        // Enter on an empty list item ends the list instead, like in most editors.
+        let Some(lead) = lead else {
+            self.lines[self.row].clear();
+            self.col = 0;
+            self.goal = 0;
+            return;
+        };
+        let rest = self.lines[self.row].split_off(at);
+        self.col = lead.chars().count();
+        self.goal = self.col;
+        self.lines.insert(self.row + 1, lead + &rest);
+        self.row += 1;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.offset(self.row, self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+        self.goal = self.col;
+    }
+
+    pub fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let at = self.offset(self.row, self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if self.col > 0 { self.col -= 1; } else if self.row > 0 { self.row -= 1; self.col = self.line_len(self.row); }
+        self.goal = self.col;
+    }
+
+    pub fn right(&mut self) {
+        if self.col < self.line_len(self.row) { self.col += 1; } else if self.row + 1 < self.lines.len() { self.row += 1; self.col = 0; }
+        self.goal = self.col;
+    }
+
+    /// Moves `delta` lines up or down, keeping to the goal column where the line is long enough.
+    pub fn vertical(&mut self, delta: isize) {
+        self.row = self.row.saturating_add_signed(delta).min(self.lines.len() - 1);
+        self.col = self.goal.min(self.line_len(self.row));
+    }
+
+    pub fn home(&mut self) { self.col = 0; self.goal = 0; }
+
+    pub fn end(&mut self) { self.col = self.line_len(self.row); self.goal = self.col; }
+
+    /// Moves to the start of the previous or next word.
+    pub fn word(&mut self, forward: bool) {
+        let chars: Vec<char> = self.lines[self.row].chars().collect();
+        let mut col = self.col;
+        if forward {
+            if col == chars.len() { return self.right(); }
+            while col < chars.len() && !chars[col].is_whitespace() { col += 1; }
+            while col < chars.len() && chars[col].is_whitespace() { col += 1; }
+        } else {
+            if col == 0 { return self.left(); }
+            while col > 0 && chars[col - 1].is_whitespace() { col -= 1; }
+            while col > 0 && !chars[col - 1].is_whitespace() { col -= 1; }
+        }
+        self.col = col;
+        self.goal = col;
+    }
+
+    /// The lines to show in a `width` × `height` area, already scrolled, with tabs expanded, and
+    /// the cursor's position in it.
+    pub fn view(&mut self, width: usize, height: usize) -> (Vec<String>, (usize, usize)) {
+        let (width, height) = (width.max(1), height.max(1));
+        if self.row < self.top { self.top = self.row; }
+        if self.row >= self.top + height { self.top = self.row + 1 - height; }
+        let x = screen_col(&self.lines[self.row], self.col);
+        if x < self.left { self.left = x; }
+        if x >= self.left + width { self.left = x + 1 - width; }
+        let lines = self.lines.iter().skip(self.top).take(height)
+            .map(|l| expand_tabs(l).chars().skip(self.left).take(width).collect())
+            .collect();
+        (lines, (x - self.left, self.row - self.top))
+    }
+
+    /// The cursor line up to the cursor, where a `[[link` being typed ends.
+    pub fn before_cursor(&self) -> &str {
+        &self.lines[self.row][..self.offset(self.row, self.col)]
+    }
+
+    /// Replaces the unclosed `[[query` before the cursor with a complete link to `target`.
+    pub fn complete_link(&mut self, target: &str) {
+        let at = self.offset(self.row, self.col);
+        let mut head = self.lines[self.row][..at].to_string();
+        links::complete_wikilink(&mut head, target);
+        self.col = head.chars().count();
+        self.goal = self.col;
+        self.lines[self.row].replace_range(..at, &head);
+    }
+
+    /// 1-based cursor line, for the title.
+    pub fn line_number(&self) -> usize { self.row + 1 }
+}
+
+/// What a new line after `line` starts with: its indentation, and its bullet or checkbox if it
+/// is a list item. `None` when `line` is an empty item.
+fn continuation(line: &str) -> Option<String> {
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    let marker = ["- [ ] ", "- [x] ", "* [ ] ", "- ", "* ", "+ ", "> "].iter().find(|m| body.starts_with(*m));
+    match marker {
+        Some(m) if body.len() == m.len() => None,
+        Some(m) => Some(format!("{}{}", indent, m.replace("[x]", "[ ]"))),
+        None => Some(indent.to_string()),
+    }
+}
+
+fn expand_tabs(line: &str) -> String {
+    line.replace('\t', &" ".repeat(TAB))
+}
+
+/// Screen column of character `col` in `line`.
+fn screen_col(line: &str, col: usize) -> usize {
+    line.chars().take(col).map(|c| if c == '\t' { TAB } else { 1 }).sum()
+}
@@ -0,0 +1,94 @@
+//! Soft deletion into the vault's hidden `.trash/` directory.
+//!
+//! Each deletion lands in its own `.trash/<timestamp>/<original vault-relative path>`, next to an
+//! `.origin` file naming that path, so the original location can always be recovered.
+
+use anyhow::{bail, Result};
+use chrono::{Local, NaiveDateTime};
+use std::{fs, path::{Path, PathBuf}, time::Duration};
+
+pub const TRASH_DIR: &str = ".trash";
+const ORIGIN: &str = ".origin";
+const STAMP: &str = "%Y%m%d-%H%M%S";
+
+/// One deletion sitting in the trash.
+pub struct Trashed {
+    /// `.trash/<timestamp>`, removed as a whole on purge.
+    pub dir: PathBuf,
+    /// The trashed file or directory itself.
+    pub item: PathBuf,
+    /// Where it was, relative to the vault root.
+    pub original: PathBuf,
+    pub deleted: Option<NaiveDateTime>,
+}
+
+/// Moves `path` (file or directory) into the trash and returns where it ended up.
+pub fn trash(root: &Path, path: &Path) -> Result<PathBuf> {
+    let rel = path.strip_prefix(root)?;
+    let stamp = Local::now().format(STAMP).to_string();
+    let mut dir = root.join(TRASH_DIR).join(&stamp);
+    // Several deletions within a second each still get their own directory.
+    for n in 2.. {
+        if !dir.exists() { break; }
+        dir = root.join(TRASH_DIR).join(format!("{}-{}", stamp, n));
+    }
+    let dest = dir.join(rel);
+    if let Some(parent) = dest.parent() { fs::create_dir_all(parent)?; }
+    fs::rename(path, &dest)?;
+    fs::write(dir.join(ORIGIN), rel.to_string_lossy().as_bytes())?;
+    Ok(dest)
+}
+
+/// Everything in the trash, newest first.
+pub fn list(root: &Path) -> Vec<Trashed> {
+    let mut entries: Vec<Trashed> = fs::read_dir(root.join(TRASH_DIR)).into_iter().flatten().flatten()
+        .map(|e| e.path()).filter(|p| p.is_dir())
+        .filter_map(|dir| {
+            let original = match fs::read_to_string(dir.join(ORIGIN)) {
+                Ok(rel) => PathBuf::from(rel.trim()),
+                Err(_) => legacy_item(&dir)?.strip_prefix(&dir).ok()?.to_path_buf(),
+            };
+            let name = dir.file_name()?.to_string_lossy().to_string();
+            let deleted = NaiveDateTime::parse_from_str(name.get(..15)?, STAMP).ok();
+            Some(Trashed { item: dir.join(&original), dir, original, deleted })
+        })
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.dir.clone()));
+    entries
+}
+
+/// Entries trashed before `.origin` files existed: follow single-child directories down to the item.
+fn legacy_item(dir: &Path) -> Option<PathBuf> {
+    let mut path = dir.to_path_buf();
+    loop {
+        let children: Vec<PathBuf> = fs::read_dir(&path).ok()?.flatten().map(|e| e.path()).collect();
+        match children.as_slice() {
+            [only] if only.is_dir() => path = only.clone(),
+            [only] => return Some(only.clone()),
+            _ if path == dir => return None,
+            _ => return Some(path),
+        }
+    }
+}
+
+/// Moves an entry back to its original location, which must be free.
+pub fn restore(root: &Path, entry: &Trashed) -> Result<PathBuf> {
+    let dest = root.join(&entry.original);
+    if dest.exists() { bail!("{} already exists", entry.original.display()); }
+    if let Some(parent) = dest.parent() { fs::create_dir_all(parent)?; }
+    fs::rename(&entry.item, &dest)?;
+    fs::remove_dir_all(&entry.dir)?;
+    Ok(dest)
+}
+
+pub fn purge(entry: &Trashed) -> Result<()> {
+    fs::remove_dir_all(&entry.dir)?;
+    Ok(())
+}
+
+/// Permanently removes entries deleted longer than `age` ago; returns how many.
+pub fn purge_older_than(root: &Path, age: Duration) -> usize {
+    let Ok(age) = chrono::Duration::from_std(age) else { return 0 };
+    let cutoff = Local::now().naive_local() - age;
+    list(root).iter().filter(|e| e.deleted.is_some_and(|d| d < cutoff)).filter(|e| purge(e).is_ok()).count()
+}
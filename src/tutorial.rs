@@ -0,0 +1,134 @@
+//! `knot tutorial`: a guided tour of the keys. Lesson notes are written to a `Tutorial` category
+//! and an overlay asks for one keypress at a time, moving on when it sees it.
+//!
+//! Keys are only watched, never swallowed, so every step does what it says. F1 skips a step and
+//! F2 ends the tour.
+
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{fs, path::Path, time::{Duration, SystemTime}};
+
+use crate::InputMode;
+
+pub const DIR: &str = "Tutorial";
+
+pub struct Lesson {
+    pub title: &'static str,
+    /// The lesson note, written into [`DIR`].
+    note: &'static str,
+    pub steps: &'static [Step],
+}
+
+pub struct Step {
+    pub text: &'static str,
+    /// Any of these, named as [`key_name`] names them, completes the step.
+    keys: &'static [&'static str],
+    /// Only count the key while typing into a prompt or choosing from a popup.
+    typing: bool,
+}
+
+const fn step(text: &'static str, keys: &'static [&'static str]) -> Step { Step { text, keys, typing: false } }
+const fn typed(text: &'static str, keys: &'static [&'static str]) -> Step { Step { text, keys, typing: true } }
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Moving around",
+        note: "# 1 Moving around\n\nknot has three lists side by side: **Categories** along the top, **Folders** on the left and **Notes** in the middle, with a preview of the selected note on the right.\n\n- `Tab` moves the focus from one list to the next.\n- `←`/`→` or `h`/`l` switch categories.\n- `↑`/`↓` or `j`/`k` move within a list.\n",
+        steps: &[
+            step("Press Tab to move the focus from Categories to Folders.", &["Tab"]),
+            step("Press Tab again to reach the Notes list.", &["Tab"]),
+            step("Move through the notes with j/k or ↓/↑. The preview follows the selection.", &["j", "k", "Down", "Up"]),
+        ],
+    },
+    Lesson {
+        title: "Creating notes",
+        note: "# 2 Creating notes\n\n- `C` creates a category, `F` a folder and `N` a note in the current folder.\n- `Enter` opens the selected note in your editor; save and quit it to come back.\n- `a` appends a line to the selected note without leaving knot.\n- `d` opens today's journal note.\n",
+        steps: &[
+            step("Press N to create a note in the current folder.", &["N"]),
+            typed("Type a title and press Enter. (With note templates, pick one first.)", &["Enter"]),
+            step("Press a to append a line to the selected note.", &["a"]),
+            typed("Type anything and press Enter to add it to the end of the note.", &["Enter"]),
+        ],
+    },
+    Lesson {
+        title: "Finding notes",
+        note: "# 3 Finding notes\n\n- `/` searches the text of every note; choose a match and press `Enter` to go to it.\n- `Ctrl-P` jumps to any note by typing part of its path.\n- `Esc` closes any prompt or popup.\n",
+        steps: &[
+            step("Press / to search every note.", &["/"]),
+            typed("Type a word, for example \"knot\", and press Enter.", &["Enter"]),
+            typed("Choose a match and press Enter to go to it, or Esc to close.", &["Enter", "Esc"]),
+            step("Press Ctrl-P to jump to a note by name.", &["Ctrl-p"]),
+            typed("Type part of a name, then Enter to go there or Esc to close.", &["Enter", "Esc"]),
+        ],
+    },
+    Lesson {
+        title: "Syncing",
+        note: "# 4 Syncing\n\nThe vault is a git repository. `S` commits every change and pushes it to your remote, pulling other machines' changes first. Without a remote yet, run `knot remote` to connect one.\n\n`q` quits, offering to sync first if anything changed.\n",
+        steps: &[
+            step("Press S to commit your changes and sync them.", &["S"]),
+        ],
+    },
+];
+
+/// Progress through [`LESSONS`].
+pub struct Tutorial {
+    lesson: usize,
+    step: usize,
+}
+
+impl Tutorial {
+    /// Writes the lesson notes that don't exist yet and starts at the first step.
+    pub fn start(root: &Path) -> Result<Self> {
+        let dir = root.join(DIR);
+        fs::create_dir_all(&dir)?;
+        let now = SystemTime::now();
+        for (i, lesson) in LESSONS.iter().enumerate() {
+            let title = lesson.note.lines().next().unwrap_or_default().trim_start_matches("# ");
+            let path = dir.join(format!("{}.md", title));
+            if path.exists() { continue; }
+            fs::write(&path, lesson.note)?;
+            // Notes list newest first, so older times keep the lessons in order.
+            fs::File::options().write(true).open(&path)?.set_modified(now - Duration::from_secs(i as u64))?;
+        }
+        Ok(Self { lesson: 0, step: 0 })
+    }
+
+    /// The current lesson and step, with their 1-based numbers.
+    pub fn current(&self) -> (&'static Lesson, usize, &'static Step, usize) {
+        let lesson = &LESSONS[self.lesson];
+        (lesson, self.lesson + 1, &lesson.steps[self.step], self.step + 1)
+    }
+
+    /// Watches a keypress made in `mode`. Returns false once the last step is done or the tour
+    /// is ended.
+    pub fn observe(&mut self, key: &KeyEvent, mode: &InputMode) -> bool {
+        let name = key_name(key);
+        match name.as_str() {
+            "F2" => return false,
+            "F1" => return self.advance(),
+            _ => {}
+        }
+        let (_, _, step, _) = self.current();
+        let typing = *mode != InputMode::Normal;
+        if step.typing == typing && step.keys.contains(&name.as_str()) { self.advance() } else { true }
+    }
+
+    fn advance(&mut self) -> bool {
+        self.step += 1;
+        if self.step == LESSONS[self.lesson].steps.len() {
+            self.step = 0;
+            self.lesson += 1;
+        }
+        self.lesson < LESSONS.len()
+    }
+}
+
+/// `j`, `Ctrl-p`, `Enter`, `F1`…
+fn key_name(key: &KeyEvent) -> String {
+    match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => format!("Ctrl-{}", c),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,581 @@
+//! Frame rendering. Drawing reads `App` state only; the event loop decides when a redraw is needed.
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap, Tabs},
+};
+
+use chrono::{DateTime, Local};
+use std::path::Path;
+
+use crate::{attachments::{human_size, Orphan}, comments::Comment, config::Theme, emoji, keymap::{self, Action, Hints, Scope}, search, guard, share, vault, git::{BlameLine, Revision}, outline::{Marker, Outline}, picker::Picker, textarea::TextArea, render, sync::SyncKind, tutorial, App, Focus, InputMode, LockRequest, PreviewMode};
+
+pub fn draw(f: &mut Frame, app: &mut App) {
+    let area = f.size();
+    let theme = app.config.theme.clone();
+    let prompt = match app.input_mode {
+        InputMode::Normal => "",
+        InputMode::ConfirmDelete if app.focus == Focus::Files && !app.marked.is_empty() => " Move all marked notes to .trash? [y/n] (restore later with [T]) ",
+        InputMode::ConfirmDelete => " Move to .trash? [y/n] (restore later with [T]) ",
+        InputMode::ConfirmQuit if app.sync.is_some() => " A sync is still running. Wait for it before exit? [y] Quit when it finishes | [n] Quit anyway | [c/ESC] Cancel ",
+        InputMode::ConfirmQuit => " Sync before exit? [y] Sync and quit | [n] Quit without syncing | [c/ESC] Cancel ",
+        InputMode::ConfirmGc => " Move these orphaned attachments to .trash? [y/n] ",
+        InputMode::Gallery => " [h/j/k/l] Choose | [ENTER] Open in image viewer | [ESC] Close ",
+        InputMode::Conflicts => " [j/k] Choose | [m] Keep mine | [t] Keep theirs | [e] Edit | [a] Abandon sync | [q] Quit, resolve later ",
+        InputMode::EditClash => " [m] Merge both versions | [k/ESC] Keep the note as it is | [r] Restore the overwritten version ",
+        InputMode::Edit => " [^S] Save | [ESC] Close | [^E] External editor | [^←/^→] Word | [PGUP/PGDN] Page ",
+        InputMode::Share => " Scan with your phone's camera | [any key] Close ",
+        InputMode::Picker => " Type to filter | [↑/↓] Choose | [ENTER] Select | [ESC] Cancel ",
+        InputMode::Rename => " Rename (links are updated across the vault): [ENTER] Save | [ESC] Cancel ",
+        InputMode::Outline => " [j/k] Up/Down | [J/K] Reorder | [TAB/S-TAB] Indent/Outdent | [SPACE] Fold | [z/Z] Fold/Unfold all | [ESC] Close ",
+        InputMode::Tag => " Add tags (space-separated): [ENTER] Save | [ESC] Cancel ",
+        InputMode::ReadLater => " Read later (URL): [ENTER] Save | [ESC] Cancel ",
+        InputMode::CommitMessage => " Commit message: [ENTER] Sync | [ESC] Cancel ",
+        InputMode::Credential if app.credential_user.is_none() => " Username for the sync remote: [ENTER] Next | [ESC] Cancel ",
+        InputMode::Credential => " Token or password (saved to the system keyring): [ENTER] Sync | [ESC] Cancel ",
+        InputMode::Unlock => match &app.lock_request {
+            Some(LockRequest::Lock { first: None, .. }) => " New passphrase for this note: [ENTER] Next | [ESC] Cancel ",
+            Some(LockRequest::Lock { .. }) => " Repeat the passphrase: [ENTER] Lock | [ESC] Cancel ",
+            Some(LockRequest::Remove(_)) => " Passphrase (the note is saved unencrypted): [ENTER] Remove lock | [ESC] Cancel ",
+            _ => " Passphrase: [ENTER] Unlock | [ESC] Cancel ",
+        },
+        InputMode::AltText => " Describe the image (alt text, found by search): [ENTER] Attach | [ESC] Cancel ",
+        InputMode::Search => " Search all notes as you type: [ENTER] List the results | [ESC] Cancel ",
+        InputMode::Append => " Append to note: [ENTER] Save | [ESC] Cancel | ;d date ;t time ;mtg meeting ",
+        InputMode::Comment => " Comment on note (kept beside it, the note is untouched): [ENTER] Save | [ESC] Cancel ",
+        _ => " Name: [ENTER] Save | [ESC] Cancel ",
+    };
+    let footer = if app.input_mode == InputMode::Normal { normal_hints(app, area.width.saturating_sub(2) as usize) } else { vec![prompt.to_string()] };
+    let footer_height = if footer.is_empty() { 0 } else { (footer.len() as u16 + 2).min(area.height / 3).max(3) };
+
+    let chunks = Layout::default().direction(Direction::Vertical).constraints([
+        Constraint::Length(3), 
+        Constraint::Length(3), 
+        Constraint::Min(0),    
+        Constraint::Length(footer_height), 
+    ]).split(area);
+
+    let note_count = app.notes().len();
+    let result = match app.last_sync_ok { Some(true) => " ✅", Some(false) => " ❌", None => "" };
+    let next = match app.sync_countdown() {
+        Some(_) if app.sync.is_some() => " | Syncing…".to_string(),
+        Some(left) => format!(" | Next sync in {}:{:02}", left.as_secs() / 60, left.as_secs() % 60),
+        None => String::new(),
+    };
+    let context = app.context.as_ref().map(|c| format!(" · {}", c)).unwrap_or_default();
+    let header = match &app.status_msg {
+        Some(msg) => format!(" 🚀 KNOT v2{} | {} notes | Last Sync: {}{}{} | {} ", context, note_count, app.last_sync, result, next, msg),
+        None => format!(" 🚀 KNOT v2{} | {} notes | Last Sync: {}{}{} ", context, note_count, app.last_sync, result, next),
+    };
+    f.render_widget(Paragraph::new(header)
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.dim))), chunks[0]);
+
+    let cat_idx = app.categories.iter().position(|c| c == &app.selected_cat).unwrap_or(0);
+    let tabs = Tabs::new(app.categories.iter().enumerate().map(|(i, c)| {
+        let (color, label) = (app.config.category_color(c), app.config.category_label(c));
+        if i == cat_idx { Line::from(vec![Span::styled(format!(" {} ", label), Style::default().bg(color).fg(Color::Black).add_modifier(Modifier::BOLD))]) }
+        else { Line::from(vec![Span::styled(format!(" {} ", label), Style::default().fg(color))]) }
+    }).collect())
+    .block(Block::default().borders(Borders::ALL).title(" Categories "))
+    .select(cat_idx);
+    f.render_widget(tabs, chunks[1]);
+
+    let [folders, notes, preview] = app.layout.widths();
+    let main_chunks = Layout::default().direction(Direction::Horizontal).constraints([
+        Constraint::Percentage(folders),
+        Constraint::Percentage(notes),
+        Constraint::Percentage(preview),
+    ]).split(chunks[2]);
+
+    let virtual_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC);
+    let folders_title = Line::from(vec![
+        Span::raw(" Folders · "),
+        Span::styled(app.config.category_label(&app.selected_cat), Style::default().fg(app.config.category_color(&app.selected_cat))),
+        Span::raw(" "),
+    ]);
+    let sub_list = List::new(app.subfolders.iter().map(|s| ListItem::new(format!("  {} ", s)))
+        .chain(app.config.virtual_folders.iter().map(|(name, _)| ListItem::new(format!(" ◆ {} ", name)).style(virtual_style)))
+        .collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(folders_title)
+        .border_style(if app.focus == Focus::Subfolders { Style::default().fg(theme.accent) } else { Style::default() }))
+        .highlight_style(Style::default().bg(theme.highlight));
+    f.render_stateful_widget(sub_list, main_chunks[0], &mut app.sub_state);
+
+    // Virtual folders and tag filters gather notes from the whole vault, so show where each one lives.
+    let in_virtual = app.selected_virtual().is_some() || app.tag_filter.is_some();
+    let tag = match (&app.tag_filter, &app.author_filter) {
+        (Some(t), Some(a)) => format!(" · #{} @{} [Esc]", t, a),
+        (Some(t), None) => format!(" · #{} [Esc]", t),
+        (None, Some(a)) => format!(" · @{} [Esc]", a),
+        (None, None) => String::new(),
+    };
+    let notes_title = match app.age_filter.label() {
+        "" => format!(" Notes{} ", tag),
+        age => format!(" Notes{} · {} [f] ", tag, age),
+    };
+    let shared = app.shared_vault();
+    let authors = app.authors.as_ref().filter(|_| shared);
+    let file_list = List::new(app.files.iter().map(|p| {
+        let icon = if app.merge_source.as_ref() == Some(p) { "🔀" } else if app.pending_move.contains(p) { "✂" } else if app.marked.contains(p) { "☑" } else if app.expired.contains(p) { "⌛" } else { "📄" };
+        let name = if in_virtual { vault::relative(&app.vault_root, p) } else { p.file_name().unwrap().to_string_lossy().to_string() };
+        let mut row = vec![Span::raw(format!(" {} {} ", icon, name))];
+        // Who last touched it, when more than one person commits here.
+        if let Some(author) = authors.and_then(|a| a.get(p)) { row.push(Span::styled(format!("· {} ", author), Style::default().fg(theme.dim))); }
+        ListItem::new(Line::from(row))
+    }).collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(notes_title)
+        .border_style(if app.focus == Focus::Files { Style::default().fg(theme.accent) } else { Style::default() }))
+        .highlight_style(Style::default().bg(theme.highlight));
+    f.render_stateful_widget(file_list, main_chunks[1], &mut app.file_state);
+    app.list_rows = main_chunks[1].height.saturating_sub(2) as usize;
+
+    app.refresh_preview();
+    let preview_title = match app.preview_mode {
+        PreviewMode::Note if app.raw_preview => " Preview · raw [v] ",
+        PreviewMode::Note => " Preview ",
+        PreviewMode::Blame => " Blame [b] ",
+        PreviewMode::Diff => " Changes since last commit [g] ",
+        PreviewMode::TimeTravel => " Time Travel [←/→] [Esc] ",
+        PreviewMode::WordDiff => " Word changes [W] [Esc] ",
+        PreviewMode::Answers => " Past answers to this prompt [R] ",
+    };
+    if let Some(outline) = &app.outline {
+        draw_outline(f, outline, &theme, main_chunks[2]);
+    } else if let Some(textarea) = app.textarea.as_mut() {
+        let cursor = draw_textarea(f, textarea, &theme, main_chunks[2]);
+        let links = app.link_candidates();
+        if !links.is_empty() {
+            let pane = main_chunks[2];
+            let width = pane.width.min(50);
+            let anchor = Rect { x: cursor.0.min(pane.right() - width), y: cursor.1, width, height: 1 };
+            let items = links.iter().map(|(name, rel)| format!(" [[{}]]  {}", name, rel)).collect();
+            hint_popup(f, &theme, area, anchor, " [TAB/ENTER] Link  [↑/↓] Choose ", items, Some(app.link_choice.min(links.len() - 1)));
+        }
+    } else {
+        let mut preview = app.preview.as_ref().map(|c| c.text.clone()).unwrap_or_default();
+        let mut title = preview_title.to_string();
+        let mut scroll = 0;
+        if let (Some(n), Some(cache)) = (app.link_cursor, &app.preview) {
+            title = format!(" Preview · link {}/{} [ENTER] Follow [ESC] ", n + 1, cache.links.len());
+            // Keep the selected link roughly a third of the way down the pane.
+            let line = render::highlight_link(&mut preview, n).unwrap_or(0);
+            scroll = line.saturating_sub(main_chunks[2].height as usize / 3) as u16;
+        }
+        let mut pane = main_chunks[2];
+        let open: Vec<&Comment> = app.preview.as_ref().filter(|_| app.preview_mode == PreviewMode::Note)
+            .map(|c| c.comments.iter().filter(|c| !c.resolved).collect()).unwrap_or_default();
+        if !open.is_empty() {
+            let parts = Layout::default().direction(Direction::Horizontal).constraints([Constraint::Percentage(65), Constraint::Percentage(35)]).split(pane);
+            draw_comments(f, &open, &theme, app.config.keys.label(Action::Comments), parts[1]);
+            pane = parts[0];
+        }
+        f.render_widget(Paragraph::new(preview).block(Block::default().borders(Borders::ALL).title(title)).wrap(Wrap{trim:false}).scroll((scroll, 0)), pane);
+    }
+
+    if app.profiler.enabled() {
+        let lines = app.profiler.hud_lines();
+        let pane = main_chunks[2];
+        let height = (lines.len() as u16 + 2).min(pane.height);
+        let hud_area = Rect { y: pane.y + pane.height - height, height, ..pane };
+        f.render_widget(Clear, hud_area);
+        f.render_widget(Paragraph::new(lines.join("\n")).style(Style::default().fg(theme.dim))
+            .block(Block::default().borders(Borders::ALL).title(" Profile ").border_style(Style::default().fg(Color::Red))), hud_area);
+    }
+
+    if let Some(job) = &app.sync {
+        let pane = main_chunks[2];
+        let sync_area = Rect { height: (job.log.len() as u16 + 2).min(pane.height), ..pane };
+        let verb = if job.kind == SyncKind::Pull { "Pulling" } else { "Syncing" };
+        let title = format!(" {} {} · {}s ", job.spinner(), verb, job.started.elapsed().as_secs());
+        f.render_widget(Clear, sync_area);
+        f.render_widget(Paragraph::new(job.log.join("\n")).style(Style::default().fg(theme.dim))
+            .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(theme.accent))), sync_area);
+    }
+
+    // A count or an unfinished key sequence shows in the corner until it's complete, like vim's showcmd.
+    let typed = app.pending.typed();
+    let footer_block = Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.dim))
+        .title(if typed.is_empty() { String::new() } else { format!(" {} ", typed) }).title_alignment(Alignment::Right);
+    f.render_widget(Paragraph::new(footer.join("\n")).block(footer_block), chunks[3]);
+
+    if let Some(picker) = &app.picker {
+        draw_picker(f, picker, &theme, area);
+    } else if app.input_mode == InputMode::ConfirmGc {
+        draw_orphans(f, &app.orphans, &app.vault_root, area);
+    } else if app.input_mode == InputMode::Conflicts {
+        draw_conflicts(f, app, area);
+    } else if let Some(clash) = app.clash.as_ref().filter(|_| app.input_mode == InputMode::EditClash) {
+        draw_clash(f, clash, &theme, area);
+    } else if let Some(share) = app.share.as_ref().filter(|_| app.input_mode == InputMode::Share) {
+        draw_share(f, share, &theme, area);
+    } else if app.input_mode == InputMode::Gallery {
+        draw_gallery(f, app, area);
+    } else if !matches!(app.input_mode, InputMode::Normal | InputMode::ConfirmDelete | InputMode::ConfirmQuit | InputMode::Outline | InputMode::Edit) {
+        let box_area = centered_rect(50, 15, area);
+        f.render_widget(Clear, box_area);
+        let input = match app.input_mode {
+            InputMode::Credential if app.credential_user.is_some() => "•".repeat(app.input_buffer.chars().count()),
+            InputMode::Unlock => "•".repeat(app.input_buffer.chars().count()),
+            _ => app.input_buffer.clone(),
+        };
+        f.render_widget(Paragraph::new(input).block(Block::default().borders(Borders::ALL).title(" Input ")), box_area);
+        let links = app.link_candidates();
+        if let Some(job) = app.search.as_ref().filter(|_| app.input_mode == InputMode::Search) {
+            let more = if job.done { "" } else { "…" };
+            let title = format!(" {} matches{} [ENTER] List them ", job.hits.len(), more);
+            let items = if job.done && job.hits.is_empty() { vec![" No matches".to_string()] } else { job.hits.iter().take(20).map(|h| format!(" {}", search::label(&app.vault_root, h))).collect() };
+            hint_popup(f, &theme, area, box_area, &title, items, None);
+        } else if !links.is_empty() {
+            let items = links.iter().map(|(name, rel)| format!(" [[{}]]  {}", name, rel)).collect();
+            hint_popup(f, &theme, area, box_area, " [TAB/ENTER] Link  [↑/↓] Choose ", items, Some(app.link_choice.min(links.len() - 1)));
+        } else if let Some(code) = emoji::pending_shortcode(&app.input_buffer) {
+            let hits = emoji::complete(code, 5);
+            if !hits.is_empty() {
+                hint_popup(f, &theme, area, box_area, " [TAB] Emoji ", hits.iter().map(|(name, e)| format!(" {} :{}:", e, name)).collect(), None);
+            }
+        }
+    }
+    if let Some(tour) = &app.tutorial { draw_tutorial(f, tour, &theme, chunks[3]); }
+}
+
+/// The keys for the focused pane and the state the browser is in, packed into lines of `width`,
+/// as `ui.hints` and the `?` toggle ask; none when hints are off.
+fn normal_hints(app: &App, width: usize) -> Vec<String> {
+    let full = app.hints_expanded || app.config.hints == Hints::Full;
+    if app.config.hints == Hints::Off && !full { return Vec::new(); }
+    let mut active = vec![match app.focus { Focus::Categories => Scope::Categories, Focus::Subfolders => Scope::Folders, Focus::Files => Scope::Notes }];
+    if app.preview_mode != PreviewMode::Note { active.insert(0, Scope::Preview); }
+    if matches!(app.preview_mode, PreviewMode::TimeTravel | PreviewMode::WordDiff) { active.insert(0, Scope::Stepping); }
+    if app.link_cursor.is_some() { active.push(Scope::Link); }
+    if app.merge_source.is_some() { active.push(Scope::Merging); }
+    if !app.pending_move.is_empty() { active.push(Scope::Moving); }
+    if app.tag_filter.is_some() || app.author_filter.is_some() { active.push(Scope::Filtered); }
+    if !app.marked.is_empty() { active.push(Scope::Marked); }
+    let (title, bindings) = keymap::hints(&app.config.keys, &active, full);
+    let mut items: Vec<String> = bindings.iter().map(|(keys, text)| format!("[{}] {}", keys, text)).collect();
+    let toggle = match app.config.keys.label(Action::MoreKeys) {
+        Some(key) => format!("[{}] {}", key, if app.hints_expanded { "Fewer keys" } else { "More keys" }),
+        None => String::new(),
+    };
+    if !toggle.is_empty() { items.push(toggle.clone()); }
+    let mut lines = vec![title.map(|t| format!(" {}", t)).unwrap_or_default()];
+    let count = items.len();
+    for (i, item) in items.into_iter().enumerate() {
+        // Minimal hints stay on one line, always keeping room for the toggle.
+        let room = if full || i + 1 == count { width } else { width.saturating_sub(toggle.len() + 3) };
+        let fresh = |l: &str| l.trim().is_empty() || title.is_some_and(|t| l == format!(" {}", t));
+        let sep = if lines.last().is_some_and(|l| fresh(l)) { " " } else { " | " };
+        if lines.last().is_some_and(|l| l.chars().count() + sep.len() + item.chars().count() >= room) {
+            if !full { continue; }
+            lines.push(String::new());
+        }
+        let last = lines.last_mut().expect("starts with a line");
+        last.push_str(if fresh(last) { " " } else { " | " });
+        last.push_str(&item);
+    }
+    lines
+}
+
+/// The tutorial's current step, in a box sitting just above the footer on the right.
+fn draw_tutorial(f: &mut Frame, tour: &tutorial::Tutorial, theme: &Theme, footer: Rect) {
+    let (lesson, lesson_no, step, step_no) = tour.current();
+    let width = footer.width.min(60);
+    let height = 5.min(footer.y);
+    let area = Rect { x: footer.x + footer.width - width, y: footer.y - height, width, height };
+    let title = format!(" 🎓 {}/{} {} · step {}/{} ", lesson_no, tutorial::LESSONS.len(), lesson.title, step_no, lesson.steps.len());
+    let text = vec![
+        Line::from(step.text),
+        Line::from(""),
+        Line::from(Span::styled("[F1] Skip step  [F2] End tutorial", Style::default().fg(theme.dim))),
+    ];
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(theme.accent))), area);
+}
+
+fn draw_outline(f: &mut Frame, outline: &Outline, theme: &Theme, area: Rect) {
+    let rows = outline.rows();
+    let items: Vec<ListItem> = rows.iter().map(|(_, marker, line)| {
+        let marker = match marker { Marker::Leaf => "  ", Marker::Open => "▾ ", Marker::Folded => "▸ " };
+        ListItem::new(format!("{}{}", marker, line))
+    }).collect();
+    let mut state = ListState::default().with_selected(rows.iter().position(|(i, ..)| *i == outline.cursor));
+    let title = format!(" Outline · {} ", outline.path.file_name().unwrap_or_default().to_string_lossy());
+    f.render_stateful_widget(List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(theme.accent)))
+        .highlight_style(Style::default().bg(theme.highlight).add_modifier(Modifier::BOLD)), area, &mut state);
+}
+
+/// The open review comments on the previewed note, in a margin beside it.
+fn draw_comments(f: &mut Frame, open: &[&Comment], theme: &Theme, key: Option<String>, area: Rect) {
+    let mut lines = Vec::new();
+    for c in open {
+        if !lines.is_empty() { lines.push(Line::from("")); }
+        lines.push(Line::styled(format!("{} · {}", c.by, c.at), Style::default().fg(theme.dim)));
+        lines.extend(c.text.lines().map(|l| Line::from(l.to_string())));
+    }
+    let title = match key {
+        Some(key) => format!(" 💬 {} open [{}] ", open.len(), key),
+        None => format!(" 💬 {} open ", open.len()),
+    };
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(theme.dim))), area);
+}
+
+/// Returns where the cursor went on screen.
+fn draw_textarea(f: &mut Frame, textarea: &mut TextArea, theme: &Theme, area: Rect) -> (u16, u16) {
+    let (lines, (x, y)) = textarea.view(area.width.saturating_sub(2) as usize, area.height.saturating_sub(2) as usize);
+    let modified = if textarea.is_modified() { " [+]" } else { "" };
+    let title = format!(" Editing · {}{} · line {} ", textarea.path.file_name().unwrap_or_default().to_string_lossy(), modified, textarea.line_number());
+    f.render_widget(Paragraph::new(lines.join("\n"))
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(theme.accent))), area);
+    let cursor = (area.x + 1 + x as u16, area.y + 1 + y as u16);
+    f.set_cursor(cursor.0, cursor.1);
+    cursor
+}
+
+/// Groups blame output into blocks of consecutive lines from the same commit, each introduced by
+/// a dim header with the short hash, date and commit summary.
+pub fn blame_text(lines: &[BlameLine]) -> Text<'static> {
+    let mut out = Vec::new();
+    let mut prev_hash: Option<&str> = None;
+    for line in lines {
+        if prev_hash != Some(line.hash.as_str()) {
+            let header = if line.is_uncommitted() {
+                "── not committed yet".to_string()
+            } else {
+                format!("── {} · {} · {}", &line.hash[..7], format_time(line.time), line.summary)
+            };
+            out.push(Line::from(Span::styled(header, Style::default().fg(Color::DarkGray))));
+            prev_hash = Some(&line.hash);
+        }
+        out.push(Line::from(vec![Span::styled("│ ", Style::default().fg(Color::DarkGray)), Span::raw(line.text.clone())]));
+    }
+    Text::from(out)
+}
+
+/// A unified diff coloured line by line: additions green, removals red, hunk headers dim. `None`
+/// (a note not committed yet) shows `content` as all added.
+pub fn diff_text(diff: Option<&str>, content: &str) -> Text<'static> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let (added, removed) = (Style::default().fg(Color::Green), Style::default().fg(Color::Red));
+    let Some(diff) = diff else {
+        let mut out = vec![Line::styled("── new note, not committed yet", dim)];
+        out.extend(content.lines().map(|l| Line::styled(format!("+{}", l), added)));
+        return Text::from(out);
+    };
+    if diff.trim().is_empty() { return Text::styled("No changes since the last commit.", dim); }
+    // Skip the file header; one note means one file.
+    Text::from(diff.lines().skip_while(|l| !l.starts_with("@@")).map(|line| match line.chars().next() {
+        Some('@') => Line::styled(line.to_string(), Style::default().fg(Color::Cyan)),
+        Some('+') => Line::styled(line.to_string(), added),
+        Some('-') => Line::styled(line.to_string(), removed),
+        Some('\\') => Line::styled(line.to_string(), dim),
+        _ => Line::raw(line.to_string()),
+    }).collect::<Vec<_>>())
+}
+
+/// A word diff from [`git::word_diff`] laid out as the whole note, additions in green and
+/// deletions struck through in red, under a line saying what it's compared with.
+pub fn word_diff_text(days: u32, base: Option<i64>, diff: &str, content: &str) -> Text<'static> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let added = Style::default().fg(Color::Green);
+    let span = if days == 1 { "day".to_string() } else { format!("{} days", days) };
+    let Some(base) = base else {
+        let mut out = vec![Line::styled(format!("── new in the last {} · [←/→] Further back/nearer", span), dim)];
+        out.extend(content.lines().map(|l| Line::styled(l.to_string(), added)));
+        return Text::from(out);
+    };
+    let mut out = vec![Line::styled(format!("── changes over the last {}, since {} · [←/→] Further back/nearer", span, format_time(base)), dim)];
+    if diff.trim().is_empty() {
+        out.push(Line::styled("No changes.", dim));
+        out.extend(content.lines().map(|l| Line::raw(l.to_string())));
+        return Text::from(out);
+    }
+    out.extend(word_diff_lines(diff));
+    Text::from(out)
+}
+
+/// `git diff --word-diff=porcelain` output as lines, added words green and removed ones struck
+/// through in red.
+fn word_diff_lines(diff: &str) -> Vec<Line<'static>> {
+    let (added, removed) = (Style::default().fg(Color::Green), Style::default().fg(Color::Red).add_modifier(Modifier::CROSSED_OUT));
+    let (mut out, mut line): (Vec<Line>, Vec<Span>) = (Vec::new(), Vec::new());
+    for raw in diff.lines().skip_while(|l| !l.starts_with("@@")).skip(1) {
+        let (marker, words) = raw.split_at(raw.len().min(1));
+        match marker {
+            "~" => out.push(Line::from(std::mem::take(&mut line))),
+            "+" => line.push(Span::styled(words.to_string(), added)),
+            "-" => line.push(Span::styled(words.to_string(), removed)),
+            _ => line.push(Span::raw(words.to_string())),
+        }
+    }
+    if !line.is_empty() { out.push(Line::from(line)); }
+    out
+}
+
+/// A note as of one revision, under a slider line showing where in its history we are.
+pub fn time_travel_text(rev: &Revision, pos: usize, total: usize, content: String) -> Text<'static> {
+    let older = if pos + 1 < total { "◀ " } else { "  " };
+    let newer = if pos > 0 { " ▶" } else { "  " };
+    let slider = format!("{}{}/{} · {} · {} · {}{}", older, total - pos, total, format_time(rev.time), &rev.hash[..7], rev.summary, newer);
+    let mut text = Text::from(Line::from(Span::styled(slider, Style::default().fg(Color::Black).bg(Color::Cyan))));
+    text.extend(Text::from(content));
+    text
+}
+
+pub fn format_time(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0).map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default()
+}
+
+fn draw_picker(f: &mut Frame, picker: &Picker, theme: &Theme, area: Rect) {
+    let popup = centered_rect(50, 50, area);
+    f.render_widget(Clear, popup);
+    let parts = Layout::default().direction(Direction::Vertical).constraints([Constraint::Length(3), Constraint::Min(0)]).split(popup);
+    f.render_widget(Paragraph::new(format!("> {}", picker.query)).block(Block::default().borders(Borders::ALL).title(picker.title.as_str())), parts[0]);
+    let visible = picker.visible();
+    let items: Vec<ListItem> = visible.iter().map(|&i| ListItem::new(format!(" {} ", picker.items[i]))).collect();
+    let mut state = ListState::default().with_selected((!visible.is_empty()).then(|| picker.cursor.min(visible.len() - 1)));
+    f.render_stateful_widget(List::new(items).block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().bg(theme.highlight).add_modifier(Modifier::BOLD)), parts[1], &mut state);
+}
+
+/// Size report of the attachments garbage collection would trash, largest first.
+fn draw_orphans(f: &mut Frame, orphans: &[Orphan], root: &Path, area: Rect) {
+    let popup = centered_rect(60, 60, area);
+    let total: u64 = orphans.iter().map(|o| o.size).sum();
+    let mut sorted: Vec<&Orphan> = orphans.iter().collect();
+    sorted.sort_by_key(|o| std::cmp::Reverse(o.size));
+    let items: Vec<ListItem> = sorted.iter().map(|o| ListItem::new(format!(" {:>9}  {} ", human_size(o.size), vault::relative(root, &o.path)))).collect();
+    let title = format!(" {} orphaned attachments · {} ", orphans.len(), human_size(total));
+    f.render_widget(Clear, popup);
+    f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(Color::Red))), popup);
+}
+
+/// Contact sheet of `app.gallery`: a grid of cards with each image's name, pixel size and file
+/// size, scrolled to keep the highlighted one in view.
+fn draw_gallery(f: &mut Frame, app: &mut App, area: Rect) {
+    const CARD_W: u16 = 26;
+    const CARD_H: u16 = 4;
+    let popup = centered_rect(90, 85, area);
+    f.render_widget(Clear, popup);
+    let dir = app.browsed_dir();
+    let title = format!(" {} images · {} ", app.gallery.len(), vault::relative(&app.vault_root, &dir));
+    let block = Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(app.config.theme.accent));
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+    let cols = (inner.width / CARD_W).max(1) as usize;
+    let rows = (inner.height / CARD_H).max(1) as usize;
+    app.gallery_cols = cols;
+    let first_row = (app.gallery_cursor / cols).saturating_sub(rows - 1);
+    for (i, image) in app.gallery.iter().enumerate().skip(first_row * cols).take(rows * cols) {
+        let (row, col) = ((i / cols - first_row) as u16, (i % cols) as u16);
+        let card = Rect::new(inner.x + col * CARD_W, inner.y + row * CARD_H, CARD_W.min(inner.width), CARD_H.min(inner.height));
+        let name = image.path.file_name().unwrap_or_default().to_string_lossy();
+        let folder = image.path.parent().filter(|p| *p != dir).map(|p| vault::relative(&dir, p));
+        let size = image.size.map_or_else(|| "?".to_string(), |(w, h)| format!("{}×{}", w, h));
+        let lines = vec![
+            Line::from(format!("🖼 {}  {}", size, human_size(image.bytes))),
+            Line::from(Span::styled(folder.map(|f| format!("{}/", f)).unwrap_or_default(), Style::default().fg(app.config.theme.dim))),
+        ];
+        let style = if i == app.gallery_cursor { Style::default().fg(app.config.theme.accent).add_modifier(Modifier::BOLD) } else { Style::default().fg(app.config.theme.dim) };
+        f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!(" {} ", name)).border_style(style)), card);
+    }
+}
+
+/// Conflicted files, and the conflicting passages of the selected one with each side labelled.
+fn draw_conflicts(f: &mut Frame, app: &App, area: Rect) {
+    let popup = centered_rect(80, 80, area);
+    f.render_widget(Clear, popup);
+    let parts = Layout::default().direction(Direction::Vertical)
+        .constraints([Constraint::Length((app.conflicts.len() as u16 + 2).min(popup.height / 3)), Constraint::Min(0)]).split(popup);
+    let items: Vec<ListItem> = app.conflicts.iter().map(|rel| ListItem::new(format!(" ⚠ {} ", rel))).collect();
+    let place = app.conflicts_repo.strip_prefix(&app.vault_root).ok().filter(|p| !p.as_os_str().is_empty()).map(|p| format!(" in {}", p.display())).unwrap_or_default();
+    let title = format!(" {} conflicted file{}{} · sync is waiting ", app.conflicts.len(), if app.conflicts.len() == 1 { "" } else { "s" }, place);
+    let mut state = ListState::default().with_selected((!app.conflicts.is_empty()).then_some(app.conflict_cursor));
+    f.render_stateful_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title).border_style(Style::default().fg(Color::Red)))
+        .highlight_style(Style::default().bg(app.config.theme.highlight).add_modifier(Modifier::BOLD)), parts[0], &mut state);
+
+    // A rebase replays our commits onto the remote's, so its first side is theirs.
+    let (first, second) = if app.conflicts_rebase { ("theirs (remote)", "mine") } else { ("mine", "theirs (remote)") };
+    let (mine, theirs) = (Style::default().fg(Color::Green), Style::default().fg(Color::Magenta));
+    let (first_style, second_style) = if app.conflicts_rebase { (theirs, mine) } else { (mine, theirs) };
+    let text = app.conflicts.get(app.conflict_cursor).and_then(|rel| std::fs::read_to_string(app.conflicts_repo.join(rel)).ok()).unwrap_or_default();
+    let all: Vec<&str> = text.lines().collect();
+    let mut lines: Vec<Line> = Vec::new();
+    let mut side = None;
+    for (i, line) in all.iter().enumerate() {
+        if line.starts_with("<<<<<<< ") {
+            // A little context before each conflict.
+            if !lines.is_empty() { lines.push(Line::from("")); }
+            for ctx in &all[i.saturating_sub(2)..i] { lines.push(Line::styled(ctx.to_string(), Style::default().fg(app.config.theme.dim))); }
+            lines.push(Line::styled(format!("── {} ──", first), first_style.add_modifier(Modifier::BOLD)));
+            side = Some(first_style);
+        } else if *line == "=======" && side.is_some() {
+            lines.push(Line::styled(format!("── {} ──", second), second_style.add_modifier(Modifier::BOLD)));
+            side = Some(second_style);
+        } else if line.starts_with(">>>>>>> ") && side.is_some() {
+            lines.push(Line::styled("──".to_string(), Style::default().fg(app.config.theme.dim)));
+            side = None;
+        } else if let Some(style) = side {
+            lines.push(Line::styled(line.to_string(), style));
+        }
+    }
+    if lines.is_empty() { lines.push(Line::from(" No conflict markers: one side deleted or renamed this file. Keep mine or theirs.")); }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(" Conflicts ")), parts[1]);
+}
+
+/// The QR code in black on white whatever the terminal's colours, two rows of modules per line.
+fn draw_share(f: &mut Frame, share: &share::Share, theme: &Theme, area: Rect) {
+    let shade = |dark: bool| if dark { Color::Black } else { Color::White };
+    let mut lines: Vec<Line> = share.code.half_blocks(share::QUIET).into_iter()
+        .map(|row| Line::from(row.into_iter().map(|(top, bottom)| Span::styled("▀", Style::default().fg(shade(top)).bg(shade(bottom)))).collect::<Vec<_>>()))
+        .collect();
+    let caption = match &share.server {
+        Some(server) => format!("Serving at {} until closed", server.url),
+        None => "The note's text".to_string(),
+    };
+    lines.push(Line::styled(caption, Style::default().fg(theme.dim)));
+    let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect { x: area.x + (area.width - width) / 2, y: area.y + (area.height - height) / 2, width, height };
+    f.render_widget(Clear, popup);
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(Block::default().borders(Borders::ALL).title(format!(" 📱 {} ", share.name))), popup);
+}
+
+/// What an edit overwrote: the note as it is now against the version it replaced.
+fn draw_clash(f: &mut Frame, clash: &guard::Clash, theme: &Theme, area: Rect) {
+    let popup = centered_rect(80, 70, area);
+    f.render_widget(Clear, popup);
+    let name = clash.path.file_name().unwrap_or_default().to_string_lossy();
+    let seen = DateTime::<Local>::from(clash.seen).format("%H:%M:%S");
+    let dim = Style::default().fg(theme.dim);
+    let mut lines = vec![
+        Line::from(format!("While you were editing {}, the version on disk at {} was overwritten, and its changes aren't in the note now.", name, seen)),
+        Line::styled("Green: only in the overwritten version. Red: only in the note now.", dim),
+        Line::from(""),
+    ];
+    match &clash.diff {
+        Ok(diff) => lines.extend(word_diff_lines(diff)),
+        Err(e) => lines.push(Line::from(format!("Diff unavailable: {}", e))),
+    }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(format!(" ⚠ {} changed while you were editing it ", name)).border_style(Style::default().fg(Color::Red))), popup);
+}
+
+/// Completion list drawn directly below the input popup at `anchor`.
+fn hint_popup(f: &mut Frame, theme: &Theme, area: Rect, anchor: Rect, title: &str, items: Vec<String>, selected: Option<usize>) {
+    let top = anchor.y + anchor.height;
+    let hint_area = Rect { y: top, height: (items.len() as u16 + 2).min(area.height.saturating_sub(top)), ..anchor };
+    let mut state = ListState::default().with_selected(selected);
+    f.render_widget(Clear, hint_area);
+    f.render_stateful_widget(List::new(items.into_iter().map(ListItem::new).collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().bg(theme.highlight)), hint_area, &mut state);
+}
+
+pub fn centered_rect(px: u16, py: u16, r: Rect) -> Rect {
+    let v = Layout::default().direction(Direction::Vertical).constraints([Constraint::Percentage((100-py)/2), Constraint::Percentage(py), Constraint::Percentage((100-py)/2)]).split(r);
+    Layout::default().direction(Direction::Horizontal).constraints([Constraint::Percentage((100-px)/2), Constraint::Percentage(px), Constraint::Percentage((100-px)/2)]).split(v[1])[1]
+}
@@ -0,0 +1,122 @@
+//! Vault-wide file enumeration shared by search and indexing.
+//!
+//! Everything that scans the whole vault goes through [`walk_notes`], so `.git/`, `.trash/`,
+//! attachment folders, `.gitignore` entries and the user's configured ignore globs are excluded
+//! in one place.
+
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+use crate::{glob, tags};
+
+/// Extensions treated as notes; anything else is an attachment as far as indexing is concerned.
+pub const NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "txt", "org"];
+
+/// Directories that are never indexed regardless of configuration.
+const ALWAYS_SKIPPED: &[&str] = &[".git", ".trash"];
+
+struct Rule { pattern: String, negated: bool, dir_only: bool }
+
+/// Ignore rules in gitignore semantics: the last matching rule wins and `!` re-includes.
+pub struct Ignore { rules: Vec<Rule> }
+
+impl Ignore {
+    /// Combines the vault's root `.gitignore`, one `**/<dir>/` rule per attachment folder,
+    /// and the user's extra globs (applied last so they can override the others).
+    pub fn load(root: &Path, attachment_dirs: &[String], extra: &[String]) -> Self {
+        let mut rules = Vec::new();
+        if let Ok(gitignore) = fs::read_to_string(root.join(".gitignore")) {
+            rules.extend(gitignore.lines().filter_map(Rule::parse));
+        }
+        rules.extend(attachment_dirs.iter().filter_map(|d| Rule::parse(&format!("{}/", d.trim_matches('/')))));
+        rules.extend(extra.iter().filter_map(|g| Rule::parse(g)));
+        Self { rules }
+    }
+
+    /// `rel` is relative to the vault root, `/`-separated.
+    pub fn is_ignored(&self, rel: &str, is_dir: bool) -> bool {
+        if rel.split('/').any(|seg| ALWAYS_SKIPPED.contains(&seg)) { return true; }
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir { continue; }
+            if glob::matches(&rule.pattern, rel) { ignored = !rule.negated; }
+        }
+        ignored
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { return None; }
+        let (negated, line) = match line.strip_prefix('!') { Some(rest) => (true, rest), None => (false, line) };
+        let (dir_only, line) = match line.strip_suffix('/') { Some(rest) => (true, rest), None => (false, line) };
+        // Patterns without an inner slash match at any depth, like git does.
+        let pattern = match line.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if line.contains('/') => line.to_string(),
+            None => format!("**/{}", line),
+        };
+        Some(Self { pattern, negated, dir_only })
+    }
+}
+
+pub fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| NOTE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"))
+}
+
+/// Every note in the vault that survives the ignore rules, sorted by path.
+pub fn walk_notes(root: &Path, ignore: &Ignore) -> Vec<PathBuf> {
+    let mut notes: Vec<PathBuf> = WalkDir::new(root).min_depth(1).into_iter()
+        .filter_entry(|e| !ignore.is_ignored(&relative(root, e.path()), e.file_type().is_dir()))
+        .flatten()
+        .filter(|e| e.file_type().is_file() && is_note(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    notes.sort();
+    notes
+}
+
+/// Every visible directory below the root (categories and their folders), sorted by path.
+pub fn walk_dirs(root: &Path, ignore: &Ignore) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = WalkDir::new(root).min_depth(1).into_iter()
+        .filter_entry(|e| e.file_type().is_dir() && !e.file_name().to_string_lossy().starts_with('.')
+            && !ignore.is_ignored(&relative(root, e.path()), true))
+        .flatten()
+        .map(|e| e.into_path())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Cached list of every indexable note, rebuilt lazily after the vault changes.
+#[derive(Default)]
+pub struct NoteIndex {
+    notes: Option<Vec<PathBuf>>,
+    /// Tag → notes, built on first use since it reads every note.
+    tags: Option<BTreeMap<String, Vec<PathBuf>>>,
+}
+
+impl NoteIndex {
+    pub fn invalidate(&mut self) { self.notes = None; self.tags = None; }
+
+    pub fn is_stale(&self) -> bool { self.notes.is_none() }
+
+    pub fn rebuild(&mut self, root: &Path, ignore: &Ignore) { self.notes = Some(walk_notes(root, ignore)); }
+
+    pub fn notes(&self) -> &[PathBuf] { self.notes.as_deref().unwrap_or_default() }
+
+    /// Call after `rebuild` so the note list is current.
+    pub fn tags(&mut self) -> &BTreeMap<String, Vec<PathBuf>> {
+        let notes = self.notes.as_deref().unwrap_or_default();
+        self.tags.get_or_insert_with(|| tags::build(notes))
+    }
+}